@@ -0,0 +1,87 @@
+//! Measures how long parsing a large chunk stalls other tasks on a single-threaded runtime,
+//! parsing on the runtime thread and on the blocking thread pool.
+//!
+//! Run with `cargo bench --bench chunk`.
+
+use std::{
+    hint::black_box,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use snowflake_connector_rs::{__private, Result};
+
+const ROWS: usize = 200_000;
+const COLUMNS: usize = 20;
+const TICK: Duration = Duration::from_millis(1);
+
+fn main() -> Result<()> {
+    let body = chunk_body()?;
+    println!("chunk: {} rows, {} compressed bytes", ROWS, body.len());
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+    runtime.block_on(async {
+        let inline = body.clone();
+        measure("parse on runtime thread", || async move {
+            __private::parse_chunk(&inline)
+        })
+        .await?;
+        measure("parse on blocking pool", || {
+            __private::parse_chunk_blocking(body)
+        })
+        .await
+    })
+}
+
+/// Parses a chunk while a ticker task runs every millisecond, reporting the parse time and the
+/// longest the ticker was kept waiting.
+async fn measure<F, Fut>(label: &str, parse: F) -> Result<()>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<usize>>,
+{
+    let done = Arc::new(AtomicBool::new(false));
+    let ticker = tokio::spawn({
+        let done = Arc::clone(&done);
+        async move {
+            let mut worst = Duration::ZERO;
+            while !done.load(Ordering::Relaxed) {
+                let start = Instant::now();
+                tokio::time::sleep(TICK).await;
+                worst = worst.max(start.elapsed().saturating_sub(TICK));
+            }
+            worst
+        }
+    });
+    tokio::task::yield_now().await;
+
+    let start = Instant::now();
+    let rows = parse().await?;
+    let elapsed = start.elapsed();
+    done.store(true, Ordering::Relaxed);
+    let worst = ticker.await?;
+    black_box(rows);
+    println!("{label:<24} {elapsed:>10.2?} (longest stall of other tasks: {worst:.2?})");
+    Ok(())
+}
+
+fn chunk_body() -> Result<Vec<u8>> {
+    let mut gzip = GzEncoder::new(vec![], Compression::fast());
+    for row in 0..ROWS {
+        let cells = (0..COLUMNS)
+            .map(|column| format!("\"row {row:>8} column {column:>4}\""))
+            .collect::<Vec<_>>();
+        if row > 0 {
+            gzip.write_all(b",")?;
+        }
+        write!(gzip, "[{}]", cells.join(","))?;
+    }
+    Ok(gzip.finish()?)
+}
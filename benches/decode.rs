@@ -0,0 +1,105 @@
+//! Compares ways of decoding a wide result: owned and borrowed strings, and numbers read by
+//! column name, by a resolved column index and through a `RowAccessor`.
+//!
+//! Run with `cargo bench --bench decode`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use snowflake_connector_rs::{Result, RowsExt, SnowflakeRow};
+
+const ROWS: usize = 100_000;
+const COLUMNS: usize = 20;
+
+fn main() -> Result<()> {
+    let names = (0..COLUMNS).map(|i| format!("col_{i}")).collect::<Vec<_>>();
+    let upper = names.iter().map(|n| n.to_uppercase()).collect::<Vec<_>>();
+
+    let text = build(&upper, "text", |row, column| {
+        format!("row {row:>8} column {column:>4} some text")
+    });
+    bench("get::<String>", &text, |row| {
+        for name in &names {
+            black_box(row.get::<String>(name)?);
+        }
+        Ok(())
+    })?;
+    bench("get_ref::<&str>", &text, |row| {
+        for name in &names {
+            black_box(row.get_ref::<&str>(name)?);
+        }
+        Ok(())
+    })?;
+
+    let numbers = build(&upper, "fixed", |row, column| (row * column).to_string());
+    bench("get::<i64>", &numbers, |row| {
+        for name in &names {
+            black_box(row.get::<i64>(name)?);
+        }
+        Ok(())
+    })?;
+    let indices = names
+        .iter()
+        .map(|name| numbers[0].column_index(name))
+        .collect::<Result<Vec<_>>>()?;
+    bench("get_index::<i64>", &numbers, |row| {
+        for &index in &indices {
+            black_box(row.get_index::<i64>(index)?);
+        }
+        Ok(())
+    })?;
+    let accessor =
+        numbers.accessor::<(i64, i64, i64, i64)>(("col_0", "col_5", "col_10", "col_19"))?;
+    bench("accessor (4 columns)", &numbers, |row| {
+        black_box(accessor.get(row)?);
+        Ok(())
+    })?;
+    bench("get::<i64> (4 columns)", &numbers, |row| {
+        for name in ["col_0", "col_5", "col_10", "col_19"] {
+            black_box(row.get::<i64>(name)?);
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn build(
+    names: &[String],
+    snowflake_type: &str,
+    value: impl Fn(usize, usize) -> String,
+) -> Vec<SnowflakeRow> {
+    let columns = names
+        .iter()
+        .map(|name| (name.as_str(), snowflake_type))
+        .collect::<Vec<_>>();
+    let values = (0..ROWS)
+        .map(|row| {
+            (0..names.len())
+                .map(|column| Some(value(row, column)))
+                .collect()
+        })
+        .collect();
+    snowflake_connector_rs::__private::rows(&columns, values)
+}
+
+fn bench(
+    label: &str,
+    rows: &[SnowflakeRow],
+    decode: impl Fn(&SnowflakeRow) -> Result<()>,
+) -> Result<()> {
+    let mut best = Duration::MAX;
+    for _ in 0..5 {
+        let start = Instant::now();
+        for row in rows {
+            decode(row)?;
+        }
+        best = best.min(start.elapsed());
+    }
+    println!(
+        "{label:<24} {best:>10.2?} ({:.1} ns/row)",
+        best.as_nanos() as f64 / rows.len() as f64
+    );
+    Ok(())
+}
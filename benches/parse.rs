@@ -0,0 +1,84 @@
+//! Measures parsing a large query response with its rows inline, and result chunks as sent by
+//! the storage service, plain and gzip-compressed.
+//!
+//! Run with `cargo bench --bench parse`.
+
+use std::{
+    hint::black_box,
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use snowflake_connector_rs::{__private, Result};
+
+const ROWS: usize = 100_000;
+const COLUMNS: usize = 20;
+const RUNS: usize = 5;
+
+fn main() -> Result<()> {
+    let response = response_body();
+    println!("response: {ROWS} rows, {} bytes", response.len());
+    bench("query response", || {
+        __private::parse_query_response(&response)
+    })?;
+
+    let chunk = rows_json(", ");
+    println!("chunk: {ROWS} rows, {} bytes", chunk.len());
+    bench("chunk", || __private::parse_chunk(chunk.as_bytes()))?;
+
+    let mut gzip = GzEncoder::new(vec![], Compression::fast());
+    gzip.write_all(chunk.as_bytes())?;
+    let gzip = gzip.finish()?;
+    bench("chunk (gzip)", || __private::parse_chunk(&gzip))
+}
+
+/// Reports the fastest of several runs of `parse`.
+fn bench(label: &str, parse: impl Fn() -> Result<usize>) -> Result<()> {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let rows = parse()?;
+        best = best.min(start.elapsed());
+        assert_eq!(black_box(rows), ROWS);
+    }
+    println!("{label:<16} {best:>10.2?}");
+    Ok(())
+}
+
+/// Rows of text, a quarter of them with characters that have to be unescaped.
+fn rows_json(separator: &str) -> String {
+    (0..ROWS)
+        .map(|row| {
+            let cells = (0..COLUMNS)
+                .map(|column| match (row + column) % 4 {
+                    0 => format!(r#""row {row:>8} column {column:>4} \"quoted\"""#),
+                    1 => "null".to_string(),
+                    _ => format!(r#""row {row:>8} column {column:>4} some text""#),
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]", cells.join(","))
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// A query response shaped like the server's, with session parameters and column metadata.
+fn response_body() -> String {
+    let parameters = (0..60)
+        .map(|i| format!(r#"{{"name":"PARAMETER_{i}","value":{i}}}"#))
+        .collect::<Vec<_>>();
+    let row_types = (0..COLUMNS)
+        .map(|i| {
+            format!(
+                r#"{{"name":"COL_{i}","database":"DB","schema":"PUBLIC","table":"EVENTS","nullable":true,"type":"text","byteLength":16777216,"length":16777216,"scale":null,"precision":null,"collation":null}}"#
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        r#"{{"data":{{"parameters":[{}],"rowtype":[{}],"rowset":[{}],"total":{ROWS},"returned":{ROWS},"queryId":"01b0a1b2-0000-4c5d-0000-0001234f5678","queryResultFormat":"json"}},"code":null,"message":null,"success":true}}"#,
+        parameters.join(","),
+        row_types.join(","),
+        rows_json(","),
+    )
+}
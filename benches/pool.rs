@@ -0,0 +1,119 @@
+//! Measures how many connections downloading the chunks of several results opens with
+//! different pool settings, against a local server that delays each new connection as a TLS
+//! handshake would.
+//!
+//! Run with `cargo bench --bench pool`.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use snowflake_connector_rs::{__private, ConnectionPoolConfig, Result, SnowflakeClientConfig};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+};
+
+const QUERIES: usize = 5;
+const CHUNKS: usize = 40;
+const CONCURRENCY: usize = 4;
+const CHUNK_BYTES: usize = 64 * 1024;
+const HANDSHAKE: Duration = Duration::from_millis(20);
+const BETWEEN_QUERIES: Duration = Duration::from_millis(200);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/chunk", listener.local_addr()?);
+    let connections = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(serve(listener, Arc::clone(&connections)));
+    println!(
+        "{QUERIES} results of {CHUNKS} chunks of {CHUNK_BYTES} bytes, {CONCURRENCY} downloads at a time"
+    );
+
+    let pools = [
+        ("no idle connections", Some(0)),
+        ("1 idle connection per host", Some(1)),
+        ("default", None),
+        ("unlimited idle connections", Some(usize::MAX)),
+    ];
+    for (label, max_idle_per_host) in pools {
+        let config = SnowflakeClientConfig {
+            max_concurrent_chunk_downloads: Some(CONCURRENCY),
+            chunk_download_pool: ConnectionPoolConfig {
+                max_idle_per_host,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let client = __private::chunk_client(&config)?;
+        connections.store(0, Ordering::SeqCst);
+        let start = Instant::now();
+        for _ in 0..QUERIES {
+            download(&client, &url).await?;
+            tokio::time::sleep(BETWEEN_QUERIES).await;
+        }
+        let elapsed = start.elapsed() - BETWEEN_QUERIES * QUERIES as u32;
+        println!(
+            "{label:>28}: {:>3} connections, {elapsed:?}",
+            connections.load(Ordering::SeqCst)
+        );
+    }
+    Ok(())
+}
+
+/// Downloads the chunks of one result, at most `CONCURRENCY` at a time.
+async fn download(client: &reqwest::Client, url: &str) -> Result<()> {
+    let permits = Arc::new(Semaphore::new(CONCURRENCY));
+    let downloads = (0..CHUNKS).map(|_| {
+        let (client, url, permits) = (client.clone(), url.to_string(), Arc::clone(&permits));
+        tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await.unwrap();
+            client
+                .get(url)
+                .send()
+                .await?
+                .bytes()
+                .await
+                .map(|body| body.len())
+        })
+    });
+    for download in downloads.collect::<Vec<_>>() {
+        assert_eq!(download.await.unwrap()?, CHUNK_BYTES);
+    }
+    Ok(())
+}
+
+/// Serves chunks over keep-alive connections, counting the connections.
+async fn serve(listener: TcpListener, connections: Arc<AtomicUsize>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        connections.fetch_add(1, Ordering::SeqCst);
+        tokio::spawn(respond(stream));
+    }
+}
+
+async fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    tokio::time::sleep(HANDSHAKE).await;
+    let body = vec![b'x'; CHUNK_BYTES];
+    let mut request = vec![];
+    let mut buffer = [0; 4096];
+    loop {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+        if !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            continue;
+        }
+        request.clear();
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {CHUNK_BYTES}\r\n\r\n");
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&body).await?;
+    }
+}
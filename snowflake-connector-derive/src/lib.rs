@@ -0,0 +1,107 @@
+//! Derive macros for `snowflake-connector-rs`. Use them through the `derive` feature of the main
+//! crate rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Derives `snowflake_connector_rs::FromRow` for a struct with named fields.
+///
+/// Each field is decoded with `SnowflakeDecode` from the column of the same name (matched
+/// case-insensitively). Field attributes:
+///
+/// - `#[snowflake(rename = "COLUMN")]` reads the field from `COLUMN` instead.
+/// - `#[snowflake(default)]` uses `Default::default()` when the column is absent from the result.
+#[proc_macro_derive(FromRow, attributes(snowflake))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_row(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_from_row(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromRow can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut initializers = Vec::with_capacity(fields.len());
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let attrs = FieldAttrs::parse(field)?;
+        let column = attrs
+            .rename
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        let value = if attrs.default {
+            quote!(::snowflake_connector_rs::__private::get_or_default(row, #column)?)
+        } else {
+            quote!(row.get(#column)?)
+        };
+        initializers.push(quote!(#ident: #value));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::snowflake_connector_rs::FromRow for #name #ty_generics #where_clause {
+            fn from_row(
+                row: &::snowflake_connector_rs::SnowflakeRow,
+            ) -> ::snowflake_connector_rs::Result<Self> {
+                ::std::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    default: bool,
+}
+
+impl FieldAttrs {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut attrs = FieldAttrs::default();
+        for attr in &field.attrs {
+            if !attr.path().is_ident("snowflake") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    if attrs.rename.is_some() {
+                        return Err(meta.error("duplicate `rename` attribute"));
+                    }
+                    let value: LitStr = meta.value()?.parse()?;
+                    attrs.rename = Some(value.value());
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    if attrs.default {
+                        return Err(meta.error("duplicate `default` attribute"));
+                    }
+                    attrs.default = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown snowflake attribute, expected `rename` or `default`"))
+                }
+            })?;
+        }
+        Ok(attrs)
+    }
+}
@@ -0,0 +1,9 @@
+use snowflake_connector_rs::FromRow;
+
+#[derive(FromRow)]
+struct Example {
+    #[snowflake(rename = "A", rename = "B")]
+    id: i64,
+}
+
+fn main() {}
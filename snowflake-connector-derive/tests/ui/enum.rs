@@ -0,0 +1,8 @@
+use snowflake_connector_rs::FromRow;
+
+#[derive(FromRow)]
+enum Example {
+    A,
+}
+
+fn main() {}
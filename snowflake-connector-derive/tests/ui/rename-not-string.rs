@@ -0,0 +1,9 @@
+use snowflake_connector_rs::FromRow;
+
+#[derive(FromRow)]
+struct Example {
+    #[snowflake(rename = 1)]
+    id: i64,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use snowflake_connector_rs::FromRow;
+
+#[derive(FromRow)]
+struct Example(i64);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use snowflake_connector_rs::FromRow;
+
+#[derive(FromRow)]
+struct Example {
+    #[snowflake(column = "ID")]
+    id: i64,
+}
+
+fn main() {}
@@ -0,0 +1,428 @@
+//! Loading Arrow record batches into a table through a stage, as parquet files.
+
+use std::{fs::File, io, path::Path, sync::Arc};
+
+use arrow_array::RecordBatch;
+use arrow_schema::{DataType, Field, SchemaRef, TimeUnit};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+
+use crate::{
+    bulk_load::{create_load_directory, remove_load_directory, CopyFormat, LocalFile},
+    catalog::{parse_object_name, quote_object_parts},
+    insert::field_name,
+    spill::with_path,
+    BulkLoadOptions, BulkLoadReport, ColumnInfo, Error, Result, SnowflakeSession,
+};
+
+impl SnowflakeSession {
+    /// Loads Arrow record batches into a table through a temporary stage, as parquet files,
+    /// which Snowflake reads without converting the values to text.
+    ///
+    /// The columns of the batches are checked against the columns of the table before
+    /// anything is written: a load fails with [`Error::SchemaMismatch`], naming every column
+    /// that does not match, when a column is not in the table, when its type does not fit
+    /// the type of the table's column, e.g. a decimal with more fractional digits than the
+    /// column's scale or a timestamp with a time zone for a `TIMESTAMP_NTZ` column, or when a
+    /// `NOT NULL` column without a default is not in the batches.
+    ///
+    /// The batches are written to parquet files of about [`BulkLoadOptions::file_size`] bytes
+    /// each, uploaded with [`SnowflakeSession::put`] and loaded with one `COPY INTO` statement
+    /// that matches the columns by name, whatever their case. With
+    /// [`BulkLoadOptions::columns`], only the named columns of the batches are loaded. As the
+    /// lines of a parquet file do not tell its rows, the errors in the report have no
+    /// [`FileLoadError::row`](crate::FileLoadError::row).
+    ///
+    /// ```rust
+    /// # use arrow_array::RecordBatch;
+    /// # use snowflake_connector_rs::{BulkLoadOptions, Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession, batches: Vec<RecordBatch>) -> Result<()> {
+    /// let report = session
+    ///     .bulk_load_arrow("events", batches, &BulkLoadOptions::default())
+    ///     .await?;
+    /// println!("{} rows loaded", report.rows_loaded());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn bulk_load_arrow(
+        &self,
+        table: &str,
+        batches: impl IntoIterator<Item = RecordBatch>,
+        options: &BulkLoadOptions,
+    ) -> Result<BulkLoadReport> {
+        let table = parse_object_name(table)?;
+        let mut batches = batches.into_iter().peekable();
+        let Some(first) = batches.peek() else {
+            return Ok(BulkLoadReport { files: vec![] });
+        };
+        let projection = projection(&first.schema(), options.columns.as_deref())?;
+        let schema = Arc::new(first.schema().project(&projection).map_err(arrow_error)?);
+        let name = quote_object_parts(&table);
+        let columns = self.describe_table(&name).await?;
+        let mismatches = mismatches(&schema, &columns);
+        if !mismatches.is_empty() {
+            return Err(Error::SchemaMismatch {
+                table: name,
+                mismatches,
+            });
+        }
+
+        let directory = create_load_directory(options)?;
+        let result = match write_files(&directory, schema, &projection, batches, options) {
+            Ok(files) => {
+                let format = CopyFormat {
+                    file_format: "TYPE = PARQUET",
+                    pattern: "*.parquet",
+                    columns: None,
+                };
+                self.copy_through_stage(&table, &files, &format, options)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        remove_load_directory(&directory, result)
+    }
+}
+
+/// The positions of the fields to load: those named by `columns`, or all of them.
+fn projection(schema: &SchemaRef, columns: Option<&[String]>) -> Result<Vec<usize>> {
+    let Some(columns) = columns else {
+        return Ok((0..schema.fields().len()).collect());
+    };
+    if columns.is_empty() {
+        return Err(Error::Bind("a load needs at least one column".to_string()));
+    }
+    columns
+        .iter()
+        .map(|column| {
+            schema
+                .index_of(&field_name(column))
+                .map_err(|_| Error::Bind(format!("the batches have no field {column}")))
+        })
+        .collect()
+}
+
+/// Describes each field that does not match a column of the table, and each column that
+/// must be loaded but is not.
+fn mismatches(schema: &SchemaRef, columns: &[ColumnInfo]) -> Vec<String> {
+    let same_name = |field: &Field, column: &ColumnInfo| {
+        field.name().to_lowercase() == column.name.to_lowercase()
+    };
+    let mut mismatches = vec![];
+    for field in schema.fields() {
+        match columns.iter().find(|column| same_name(field, column)) {
+            Some(column) => {
+                if let Err(reason) = check_type(field.data_type(), column) {
+                    mismatches.push(format!("{}: {reason}", field.name()));
+                }
+            }
+            None => mismatches.push(format!("{}: not a column of the table", field.name())),
+        }
+    }
+    for column in columns {
+        let loaded = schema.fields().iter().any(|field| same_name(field, column));
+        if !loaded && !column.nullable && column.default.is_none() {
+            mismatches.push(format!(
+                "{}: NOT NULL without a default, and not in the batches",
+                column.name
+            ));
+        }
+    }
+    mismatches
+}
+
+/// Checks that values of an Arrow type load into a column without losing digits or their
+/// time zone.
+fn check_type(data_type: &DataType, column: &ColumnInfo) -> std::result::Result<(), String> {
+    let does_not_fit = || format!("{data_type} does not fit {}", column.data_type);
+    let is = |names: &[&str]| names.contains(&column.type_name.as_str());
+    let float = [
+        "FLOAT",
+        "FLOAT4",
+        "FLOAT8",
+        "DOUBLE",
+        "DOUBLE PRECISION",
+        "REAL",
+    ];
+    // The fractional-second digits of a time or timestamp column, 9 unless described.
+    let digits = |unit: &TimeUnit| {
+        let digits = match unit {
+            TimeUnit::Second => 0,
+            TimeUnit::Millisecond => 3,
+            TimeUnit::Microsecond => 6,
+            TimeUnit::Nanosecond => 9,
+        };
+        match digits > column.precision.unwrap_or(9) {
+            true => Err(format!(
+                "{data_type} has more fractional-second digits than {}",
+                column.data_type
+            )),
+            false => Ok(()),
+        }
+    };
+    let fits = match data_type {
+        DataType::Null => true,
+        DataType::Dictionary(_, values) => return check_type(values, column),
+        DataType::Boolean => is(&["BOOLEAN"]),
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => is(&["NUMBER", "DECIMAL", "NUMERIC"]) || is(&float),
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => is(&float),
+        DataType::Decimal128(precision, scale) | DataType::Decimal256(precision, scale) => {
+            if is(&float) {
+                true
+            } else if is(&["NUMBER", "DECIMAL", "NUMERIC"]) {
+                // A negative scale leaves no fractional digits, and as many more integer ones.
+                let column_scale = column.scale.unwrap_or(0) as i32;
+                let column_precision = column.precision.unwrap_or(38) as i32;
+                let (precision, scale) = (*precision as i32, *scale as i32);
+                scale.max(0) <= column_scale && precision - scale <= column_precision - column_scale
+            } else {
+                false
+            }
+        }
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+            is(&["VARCHAR", "CHAR", "CHARACTER", "STRING", "TEXT", "VARIANT"])
+        }
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => is(&["BINARY", "VARBINARY"]),
+        DataType::Date32 | DataType::Date64 => is(&["DATE"]),
+        DataType::Time32(unit) | DataType::Time64(unit) => {
+            if !is(&["TIME"]) {
+                return Err(does_not_fit());
+            }
+            return digits(unit);
+        }
+        DataType::Timestamp(unit, time_zone) => {
+            let types: &[&str] = match time_zone {
+                Some(_) => &["TIMESTAMP_TZ", "TIMESTAMP_LTZ"],
+                None => &["TIMESTAMP_NTZ", "DATETIME"],
+            };
+            if !is(types) {
+                return Err(does_not_fit());
+            }
+            return digits(unit);
+        }
+        DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(..) => {
+            is(&["ARRAY", "VARIANT"])
+        }
+        DataType::Struct(_) | DataType::Map(..) => is(&["OBJECT", "VARIANT"]),
+        _ => return Err(format!("{data_type} is not supported")),
+    };
+    match fits {
+        true => Ok(()),
+        false => Err(does_not_fit()),
+    }
+}
+
+/// Writes the batches into parquet files, starting a new file when one reaches the file size.
+fn write_files(
+    directory: &Path,
+    schema: SchemaRef,
+    projection: &[usize],
+    batches: impl Iterator<Item = RecordBatch>,
+    options: &BulkLoadOptions,
+) -> Result<Vec<LocalFile>> {
+    let file_size = options.file_size.max(1);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut files: Vec<LocalFile> = vec![];
+    let mut writer: Option<ArrowWriter<File>> = None;
+    let mut rows = 0;
+    for batch in batches {
+        let batch = batch.project(projection).map_err(arrow_error)?;
+        if batch.num_rows() == 0 {
+            continue;
+        }
+        if writer.is_none() {
+            let path = directory.join(format!("data_{}.parquet", files.len()));
+            let file = File::create(&path).map_err(|e| with_path(e, &path))?;
+            let properties = Some(properties.clone());
+            writer = Some(
+                ArrowWriter::try_new(file, Arc::clone(&schema), properties)
+                    .map_err(|e| parquet_error(e, &path))?,
+            );
+            // The lines of a parquet file do not tell its rows.
+            files.push(LocalFile {
+                path,
+                rows: rows..rows,
+                multiline: true,
+            });
+        }
+        let (Some(open), Some(file)) = (&mut writer, files.last_mut()) else {
+            unreachable!("a file is open");
+        };
+        open.write(&batch)
+            .map_err(|e| parquet_error(e, &file.path))?;
+        rows += batch.num_rows() as u64;
+        file.rows.end = rows;
+        if (open.bytes_written() + open.in_progress_size()) as u64 >= file_size {
+            close(writer.take(), &file.path)?;
+        }
+    }
+    if let Some(file) = files.last() {
+        close(writer.take(), &file.path)?;
+    }
+    Ok(files)
+}
+
+/// Writes the footer of an open parquet file.
+fn close(writer: Option<ArrowWriter<File>>, path: &Path) -> Result<()> {
+    if let Some(writer) = writer {
+        writer.close().map_err(|e| parquet_error(e, path))?;
+    }
+    Ok(())
+}
+
+/// The error of an Arrow operation on the batches, e.g. one with a column of another type.
+fn arrow_error(error: arrow_schema::ArrowError) -> Error {
+    Error::Bind(error.to_string())
+}
+
+/// The error of writing a parquet file, as an I/O error naming the file.
+fn parquet_error(error: parquet::errors::ParquetError, path: &Path) -> Error {
+    Error::IO(with_path(io::Error::other(error), path))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{Int64Array, StringArray};
+    use arrow_schema::Schema;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+
+    fn column(name: &str, data_type: &str, nullable: bool) -> ColumnInfo {
+        let (type_name, parameters) = data_type.split_once('(').unwrap_or((data_type, ""));
+        let parameters = parameters
+            .trim_end_matches(')')
+            .split(',')
+            .filter_map(|parameter| parameter.parse().ok())
+            .collect::<Vec<u32>>();
+        ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            type_name: type_name.to_string(),
+            precision: parameters.first().copied(),
+            scale: parameters.get(1).copied(),
+            length: None,
+            nullable,
+            default: None,
+            primary_key: false,
+            comment: None,
+        }
+    }
+
+    fn batch(ids: Vec<i64>) -> RecordBatch {
+        let notes = ids
+            .iter()
+            .map(|id| format!("note {id}"))
+            .collect::<Vec<_>>();
+        RecordBatch::try_from_iter([
+            ("id", Arc::new(Int64Array::from(ids)) as _),
+            ("note", Arc::new(StringArray::from(notes)) as _),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let columns = [
+            column("ID", "NUMBER(38,0)", false),
+            column("AMOUNT", "NUMBER(10,2)", true),
+            column("CREATED_AT", "TIMESTAMP_NTZ(3)", true),
+            column("UPDATED_AT", "TIMESTAMP_TZ(9)", true),
+            column("CODE", "NUMBER(10,2)", true),
+            column("NOTE", "VARCHAR(16777216)", true),
+            column("TENANT", "VARCHAR(16777216)", false),
+        ];
+        let micros = DataType::Timestamp(TimeUnit::Microsecond, None);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("amount", DataType::Decimal128(12, 4), true),
+            Field::new("created_at", micros.clone(), true),
+            Field::new("updated_at", micros, true),
+            Field::new("code", DataType::Decimal128(10, 2), true),
+            Field::new("note", DataType::Int32, true),
+            Field::new("extra", DataType::Utf8, true),
+        ]));
+        assert_eq!(
+            mismatches(&schema, &columns),
+            [
+                "amount: Decimal128(12, 4) does not fit NUMBER(10,2)",
+                "created_at: Timestamp(Microsecond, None) has more fractional-second digits \
+                 than TIMESTAMP_NTZ(3)",
+                "updated_at: Timestamp(Microsecond, None) does not fit TIMESTAMP_TZ(9)",
+                "note: Int32 does not fit VARCHAR(16777216)",
+                "extra: not a column of the table",
+                "TENANT: NOT NULL without a default, and not in the batches",
+            ]
+        );
+
+        let amount = |data_type| check_type(&data_type, &column("A", "NUMBER(10,2)", true));
+        assert!(amount(DataType::Decimal128(10, 2)).is_ok());
+        assert!(amount(DataType::Decimal128(5, -3)).is_ok());
+        assert!(amount(DataType::Decimal128(11, 2)).is_err());
+        assert!(amount(DataType::Duration(TimeUnit::Second))
+            .unwrap_err()
+            .ends_with("is not supported"));
+
+        let error = Error::SchemaMismatch {
+            table: "\"EVENTS\"".to_string(),
+            mismatches: vec!["a: x".to_string(), "b: y".to_string()],
+        };
+        assert_eq!(
+            error.to_string(),
+            "the data does not match table \"EVENTS\": a: x; b: y"
+        );
+    }
+
+    #[test]
+    fn test_write_files() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("snowflake-load-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory)?;
+        let options = BulkLoadOptions {
+            columns: Some(vec!["id".to_string()]),
+            file_size: 1,
+            ..Default::default()
+        };
+        let batches = vec![batch(vec![1, 2]), batch(vec![]), batch(vec![3])];
+        let fields = projection(&batches[0].schema(), options.columns.as_deref())?;
+        assert_eq!(fields, [0]);
+        let schema = Arc::new(batches[0].schema().project(&fields).unwrap());
+        let files = write_files(&directory, schema, &fields, batches.into_iter(), &options)?;
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| file.rows.clone())
+                .collect::<Vec<_>>(),
+            [0..2, 2..3]
+        );
+        assert!(files[0].path.ends_with("data_0.parquet") && files[0].multiline);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&files[0].path)?)
+            .unwrap()
+            .build()
+            .unwrap();
+        let read = reader.collect::<std::result::Result<Vec<_>, _>>().unwrap();
+        assert_eq!(read[0].num_columns(), 1);
+        let ids = read[0].column(0).as_any().downcast_ref::<Int64Array>();
+        assert_eq!(ids.unwrap().values(), &[1, 2]);
+
+        let error = projection(&batch(vec![]).schema(), Some(&["missing".to_string()]));
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "bind error: the batches have no field missing"
+        );
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
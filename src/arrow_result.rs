@@ -0,0 +1,1177 @@
+//! Results in Arrow format.
+//!
+//! Snowflake sends the rows of an Arrow result as Arrow IPC streams, base64 encoded in the
+//! response (`rowsetBase64`) and as the bodies of its chunks. The columns keep Snowflake's
+//! physical layout there: NUMBER values are integers scaled by the column scale, TIME and
+//! TIMESTAMP values integers of units of `10^-scale` seconds, or structs of an `epoch` in
+//! seconds and a `fraction` in nanoseconds, and TIMESTAMP_TZ values carry a `timezone`, the
+//! offset in minutes biased by 1440. The batches are converted here either to the text the row
+//! API reads, as sent in JSON results, or to batches of the Arrow types of
+//! [`arrow_data_type`], which are also built from the rows of JSON results.
+
+use std::{io::Cursor, sync::Arc};
+
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        Date32Type, Decimal128Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+        TimestampSecondType,
+    },
+    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float64Array,
+    Int64Array, PrimitiveArray, RecordBatch, StringArray, Time64NanosecondArray,
+};
+use arrow_ipc::reader::StreamReader;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+
+use crate::{
+    chunk::{decode_body, download_raw, parse_chunk},
+    numeric::parse_scaled,
+    query::{raw_columns, QueryRequest},
+    row::{parse_bool, parse_hex, Columns},
+    session::raw_chunk_set,
+    temporal::{parse_days, parse_zoned, ScaledSeconds},
+    types::SnowflakeColumnType,
+    values::RowValues,
+    Error, Result, SnowflakeSession,
+};
+
+/// The precision of the `Decimal128` arrays NUMBER columns with a scale are returned as, that of
+/// the widest NUMBER.
+const DECIMAL_PRECISION: u8 = 38;
+
+impl SnowflakeSession {
+    /// Runs a query in Arrow format and returns its rows as Arrow record batches: those sent
+    /// with the response, then those of each chunk, downloaded
+    /// [`max_concurrent_chunk_downloads`](crate::SnowflakeClientConfig::max_concurrent_chunk_downloads)
+    /// at a time.
+    ///
+    /// The columns have the Arrow types of their Snowflake types, whatever Snowflake sent:
+    ///
+    /// | Snowflake type | Arrow type |
+    /// |---|---|
+    /// | NUMBER with scale 0 | `Int64` |
+    /// | NUMBER with a scale | `Decimal128(38, scale)` |
+    /// | FLOAT | `Float64` |
+    /// | BOOLEAN | `Boolean` |
+    /// | DATE | `Date32` |
+    /// | TIME | `Time64(Nanosecond)` |
+    /// | TIMESTAMP_NTZ | `Timestamp(unit, None)` |
+    /// | TIMESTAMP_LTZ, TIMESTAMP_TZ | `Timestamp(unit, "UTC")` |
+    /// | BINARY | `Binary` |
+    /// | VARCHAR, VARIANT, OBJECT, ARRAY and others | `Utf8` |
+    ///
+    /// The unit of a timestamp is the coarsest that holds the column scale: seconds for scale
+    /// 0, then milli-, micro- and nanoseconds. TIMESTAMP_TZ values are returned as the instant,
+    /// without their offset. A NUMBER value with scale 0 out of the range of `i64` fails with
+    /// [`Error::Decode`].
+    ///
+    /// If Snowflake answers in JSON anyway, the batches are built from the rows as
+    /// [`SnowflakeSession::query_record_batches`] builds them. A result without rows is
+    /// returned as one empty batch, which still has the schema.
+    ///
+    /// The query runs as with [`SnowflakeSession::query_raw`], so this is not supported with
+    /// the SQL API, and chunk URLs are not refreshed once they expire.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let batches = session.query_arrow("SELECT * FROM events").await?;
+    /// let rows: usize = batches.iter().map(|batch| batch.num_rows()).sum();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_arrow<Q: Into<QueryRequest>>(&self, request: Q) -> Result<Vec<RecordBatch>> {
+        let data = self.query_raw(request.into().arrow_format()).await?;
+        let columns = Arc::new(raw_columns(&data)?);
+        let base64_rows = data["rowsetBase64"]
+            .as_str()
+            .filter(|rows| !rows.is_empty());
+        let arrow = data["queryResultFormat"] == "arrow" || base64_rows.is_some();
+        let mut batches = match base64_rows {
+            Some(rows) => arrow_batches(&decode_base64(rows)?, &columns)?,
+            None if arrow => vec![],
+            None => {
+                let rows = Option::<RowValues>::deserialize(&data["rowset"])
+                    .map_err(|e| Error::Json(e, data["rowset"].to_string()))?
+                    .unwrap_or_default();
+                json_batches(&rows, &columns, Error::with_row)?
+            }
+        };
+
+        let urls = data["chunks"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|chunk| Some(chunk["url"].as_str()?.to_string()))
+            .collect();
+        let chunks = Arc::new(raw_chunk_set(&data, urls)?);
+        let downloads = Arc::new(Semaphore::new(
+            self.chunk_download
+                .max_concurrent
+                .clamp(1, Semaphore::MAX_PERMITS),
+        ));
+        let mut pending = (0..chunks.urls.len())
+            .map(|index| {
+                let (config, chunks) = (self.chunk_download.clone(), Arc::clone(&chunks));
+                let (downloads, columns) = (Arc::clone(&downloads), Arc::clone(&columns));
+                tokio::spawn(async move {
+                    let body = {
+                        let _permit = downloads.acquire().await;
+                        download_raw(&config, &chunks, index).await?
+                    };
+                    tokio::task::spawn_blocking(move || match arrow {
+                        true => arrow_batches(&body, &columns),
+                        false => json_batches(&parse_chunk(&body, None)?, &columns, |e, row| {
+                            e.with_chunk(index, row)
+                        }),
+                    })
+                    .await?
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+        while let Some(handle) = pending.next() {
+            match handle.await.map_err(Error::from).and_then(|result| result) {
+                Ok(chunk) => batches.extend(chunk),
+                Err(e) => {
+                    pending.for_each(|handle| handle.abort());
+                    return Err(e);
+                }
+            }
+        }
+        if batches.is_empty() {
+            batches.push(RecordBatch::new_empty(arrow_schema(&columns)));
+        }
+        Ok(batches)
+    }
+
+    /// Runs a query and returns its rows as Arrow record batches, one for the rows sent with
+    /// the response and one for each chunk, built from the rows with the types of their
+    /// columns as [`SnowflakeSession::query_arrow`] describes.
+    ///
+    /// The result is read as [`SnowflakeSession::query_stream`] reads it, whatever its format,
+    /// so this works with the SQL API and with expired chunk URLs, which are refreshed. The
+    /// values are parsed as [`SnowflakeRow::get`](crate::SnowflakeRow::get) parses them: a
+    /// value that does not fit its Arrow type fails with [`Error::Decode`], naming its column
+    /// and row. A result without rows is returned as one empty batch, which still has the
+    /// schema.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let batches = session
+    ///     .query_record_batches("SELECT id, amount, created_at FROM payments")
+    ///     .await?;
+    /// for batch in &batches {
+    ///     println!("{} rows", batch.num_rows());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_record_batches<Q: Into<QueryRequest>>(
+        &self,
+        request: Q,
+    ) -> Result<Vec<RecordBatch>> {
+        let mut rows = self.query_lazy(request).await?.stream();
+        let columns = Arc::clone(rows.columns());
+        let mut batches = vec![];
+        let mut first_row = 0;
+        while let Some(values) = rows.next_values().await {
+            let values = values?;
+            let batch = json_batches(&values, &columns, |e, row| e.with_row(first_row + row))?;
+            first_row += values.len();
+            batches.extend(batch);
+        }
+        if batches.is_empty() {
+            batches.push(RecordBatch::new_empty(arrow_schema(&columns)));
+        }
+        Ok(batches)
+    }
+}
+
+/// The Arrow type the values of a column of `column_type` are returned as; see
+/// [`SnowflakeSession::query_arrow`].
+pub(crate) fn arrow_data_type(column_type: &SnowflakeColumnType) -> DataType {
+    match column_type.snowflake_type() {
+        "fixed" => match column_type.scale().unwrap_or(0) {
+            0 => DataType::Int64,
+            scale => DataType::Decimal128(DECIMAL_PRECISION, scale as i8),
+        },
+        "real" => DataType::Float64,
+        "boolean" => DataType::Boolean,
+        "date" => DataType::Date32,
+        "time" => DataType::Time64(TimeUnit::Nanosecond),
+        "timestamp_ntz" => DataType::Timestamp(time_unit(column_type), None),
+        "timestamp_ltz" | "timestamp_tz" => {
+            DataType::Timestamp(time_unit(column_type), Some("UTC".into()))
+        }
+        "binary" => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+/// The schema of batches of `columns`.
+pub(crate) fn arrow_schema(columns: &Columns) -> SchemaRef {
+    let fields = (0..columns.len())
+        .map(|i| {
+            let column_type = columns.column_type(i);
+            Field::new(
+                columns.name(i),
+                arrow_data_type(column_type),
+                column_type.nullable(),
+            )
+        })
+        .collect::<Vec<_>>();
+    Arc::new(Schema::new(fields))
+}
+
+/// The coarsest unit that holds the fractional seconds of a TIMESTAMP column.
+fn time_unit(column_type: &SnowflakeColumnType) -> TimeUnit {
+    match column_type.scale().unwrap_or(9) {
+        ..=0 => TimeUnit::Second,
+        1..=3 => TimeUnit::Millisecond,
+        4..=6 => TimeUnit::Microsecond,
+        _ => TimeUnit::Nanosecond,
+    }
+}
+
+/// The number of fractional digits of seconds in `unit`.
+fn unit_scale(unit: &TimeUnit) -> u32 {
+    match unit {
+        TimeUnit::Second => 0,
+        TimeUnit::Millisecond => 3,
+        TimeUnit::Microsecond => 6,
+        TimeUnit::Nanosecond => 9,
+    }
+}
+
+/// The number of fractional digits of the values of a column as Snowflake sends them.
+fn column_scale(column_type: &SnowflakeColumnType) -> u32 {
+    let default = match column_type.snowflake_type() {
+        "time" | "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => 9,
+        _ => 0,
+    };
+    column_type.scale().unwrap_or(default).clamp(0, 38) as u32
+}
+
+/// Reads the record batches of an Arrow IPC stream.
+fn read_ipc(body: &[u8]) -> Result<Vec<RecordBatch>> {
+    StreamReader::try_new(Cursor::new(body), None)
+        .and_then(|reader| reader.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| Error::Communication(format!("invalid Arrow result: {e}")))
+}
+
+/// Decodes a chunk body with its `Content-Encoding` and reads its Arrow batches as the rows of
+/// `columns`, for the row API.
+pub(crate) fn parse_arrow_chunk(
+    body: &[u8],
+    content_encoding: Option<&str>,
+    columns: &Columns,
+) -> Result<RowValues> {
+    let mut buf = Vec::with_capacity(body.len());
+    decode_body(body, content_encoding, &mut buf)?;
+    row_values(&read_ipc(&buf)?, columns)
+}
+
+/// Decodes the base64 `rowsetBase64` of a response into the rows of `columns`.
+pub(crate) fn parse_rowset_base64(rows: &str, columns: &Columns) -> Result<RowValues> {
+    row_values(&read_ipc(&decode_base64(rows)?)?, columns)
+}
+
+fn decode_base64(rows: &str) -> Result<Vec<u8>> {
+    STANDARD
+        .decode(rows)
+        .map_err(|e| Error::Communication(format!("invalid rowsetBase64: {e}")))
+}
+
+/// Reads the batches of an Arrow IPC stream and converts them to the Arrow types of
+/// [`arrow_data_type`].
+fn arrow_batches(body: &[u8], columns: &Columns) -> Result<Vec<RecordBatch>> {
+    let schema = arrow_schema(columns);
+    read_ipc(body)?
+        .iter()
+        .map(|batch| {
+            check_width(batch, columns)?;
+            let arrays = (0..columns.len())
+                .map(|i| arrow_array(batch.column(i), columns.column_type(i)))
+                .collect::<Result<Vec<_>>>()?;
+            RecordBatch::try_new(Arc::clone(&schema), arrays).map_err(arrow_error)
+        })
+        .collect()
+}
+
+/// Builds the batch of the rows of a JSON result, with the Arrow types of [`arrow_data_type`],
+/// parsing each value as its column type; no batch for no rows. `locate` attaches the position
+/// of its row to the error of a value.
+fn json_batches(
+    rows: &RowValues,
+    columns: &Columns,
+    locate: impl Fn(Error, usize) -> Error,
+) -> Result<Vec<RecordBatch>> {
+    if rows.len() == 0 {
+        return Ok(vec![]);
+    }
+    let arrays = (0..columns.len())
+        .map(|column| json_array(rows, columns, column, &locate))
+        .collect::<Result<Vec<_>>>()?;
+    let batch = RecordBatch::try_new(arrow_schema(columns), arrays).map_err(arrow_error)?;
+    Ok(vec![batch])
+}
+
+/// Builds the array of a column of the rows of a JSON result.
+fn json_array(
+    rows: &RowValues,
+    columns: &Columns,
+    column: usize,
+    locate: &dyn Fn(Error, usize) -> Error,
+) -> Result<ArrayRef> {
+    let column_type = columns.column_type(column);
+    let snowflake_type = column_type.snowflake_type();
+    let scale = column_scale(column_type);
+    let not_temporal = |value: &str| Error::decode(format!("'{value}' is not a {snowflake_type}"));
+    let array: ArrayRef = match snowflake_type {
+        "fixed" => {
+            let type_name = format!("NUMBER with scale {scale}");
+            let values = parse_column(rows, columns, column, locate, |value| {
+                parse_scaled(value, scale, &type_name)
+            })?;
+            scaled_array(values, column_type)?
+        }
+        "time" | "timestamp_ntz" | "timestamp_ltz" => {
+            let values = parse_column(rows, columns, column, locate, |value| {
+                let seconds = ScaledSeconds::parse(value).ok_or_else(|| not_temporal(value))?;
+                Ok(scaled_seconds(seconds, scale))
+            })?;
+            scaled_array(values, column_type)?
+        }
+        "timestamp_tz" => {
+            let values = parse_column(rows, columns, column, locate, |value| {
+                let (instant, _) = parse_zoned(value).ok_or_else(|| not_temporal(value))?;
+                Ok(scaled_seconds(instant, scale))
+            })?;
+            scaled_array(values, column_type)?
+        }
+        "date" => Arc::new(Date32Array::from(parse_column(
+            rows,
+            columns,
+            column,
+            locate,
+            |value| {
+                parse_days(value)
+                    .and_then(|days| i32::try_from(days).ok())
+                    .ok_or_else(|| not_temporal(value))
+            },
+        )?)),
+        "real" => Arc::new(Float64Array::from(parse_column(
+            rows,
+            columns,
+            column,
+            locate,
+            |value| {
+                value
+                    .parse()
+                    .map_err(|_| Error::decode(format!("'{value}' is not f64")))
+            },
+        )?)),
+        "boolean" => Arc::new(BooleanArray::from(parse_column(
+            rows, columns, column, locate, parse_bool,
+        )?)),
+        "binary" => Arc::new(BinaryArray::from_iter(parse_column(
+            rows, columns, column, locate, parse_hex,
+        )?)),
+        _ => Arc::new(StringArray::from_iter(
+            (0..rows.len()).map(|row| rows.get(row, column)),
+        )),
+    };
+    Ok(array)
+}
+
+/// Parses the values of a column of the rows of a JSON result with `parse`. The error of a
+/// value that does not parse names its column and, through `locate`, its row.
+fn parse_column<T>(
+    rows: &RowValues,
+    columns: &Columns,
+    column: usize,
+    locate: &dyn Fn(Error, usize) -> Error,
+    parse: impl Fn(&str) -> Result<T>,
+) -> Result<Vec<Option<T>>> {
+    let column_type = columns.column_type(column);
+    let type_name = arrow_type_name(&arrow_data_type(column_type));
+    (0..rows.len())
+        .map(|row| {
+            let value = rows.get(row, column);
+            value.map(&parse).transpose().map_err(|e| {
+                let e = e.with_column(
+                    columns.name(column),
+                    column,
+                    column_type.snowflake_type(),
+                    type_name,
+                    value,
+                );
+                locate(e, row)
+            })
+        })
+        .collect()
+}
+
+/// The name of the Arrow type of the values of a column, in errors.
+fn arrow_type_name(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Int64 => "Int64",
+        DataType::Decimal128(_, _) => "Decimal128",
+        DataType::Float64 => "Float64",
+        DataType::Boolean => "Boolean",
+        DataType::Date32 => "Date32",
+        DataType::Time64(_) => "Time64",
+        DataType::Timestamp(_, _) => "Timestamp",
+        DataType::Binary => "Binary",
+        _ => "Utf8",
+    }
+}
+
+/// Seconds as an integer of units of `10^-scale` seconds.
+fn scaled_seconds(seconds: ScaledSeconds, scale: u32) -> i128 {
+    let scale = scale.min(9);
+    i128::from(seconds.secs) * 10i128.pow(scale) + i128::from(seconds.nanos) / 10i128.pow(9 - scale)
+}
+
+/// Converts batches as Snowflake sends them to the text of the values, as a JSON result has
+/// them: NUMBER, TIME and TIMESTAMP values as decimals with the column scale, TIMESTAMP_TZ
+/// values followed by their biased offset, DATE values as days, BOOLEAN values as `1` or `0`
+/// and BINARY values as hex.
+fn row_values(batches: &[RecordBatch], columns: &Columns) -> Result<RowValues> {
+    let mut rows = vec![];
+    for batch in batches {
+        check_width(batch, columns)?;
+        let texts = (0..columns.len())
+            .map(|i| column_text(batch.column(i), columns.column_type(i)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut texts = texts.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+        for _ in 0..batch.num_rows() {
+            rows.push(
+                texts
+                    .iter_mut()
+                    .map(|column| column.next().flatten())
+                    .collect(),
+            );
+        }
+    }
+    Ok(RowValues::from_rows(rows))
+}
+
+fn check_width(batch: &RecordBatch, columns: &Columns) -> Result<()> {
+    if batch.num_columns() != columns.len() {
+        return Err(Error::Communication(format!(
+            "an Arrow batch has {} columns, the result {}",
+            batch.num_columns(),
+            columns.len()
+        )));
+    }
+    Ok(())
+}
+
+/// The text of each value of a column.
+fn column_text(array: &ArrayRef, column_type: &SnowflakeColumnType) -> Result<Vec<Option<String>>> {
+    let scale = column_scale(column_type);
+    let texts = match (column_type.snowflake_type(), array.data_type()) {
+        ("fixed" | "time" | "timestamp_ntz" | "timestamp_ltz", _) => scaled_values(array, scale)?
+            .into_iter()
+            .map(|value| value.map(|value| scaled_text(value, scale)))
+            .collect(),
+        ("timestamp_tz", _) => {
+            let offsets = integers(struct_field(array, "timezone")?)?;
+            scaled_values(array, scale)?
+                .into_iter()
+                .zip(offsets)
+                .map(|(value, offset)| match (value, offset) {
+                    (Some(value), Some(offset)) => {
+                        Some(format!("{} {offset}", scaled_text(value, scale)))
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+        ("date", _) => integers(array)?
+            .into_iter()
+            .map(|days| days.map(|days| days.to_string()))
+            .collect(),
+        (_, DataType::Boolean) => array
+            .as_boolean()
+            .iter()
+            .map(|value| value.map(|value| if value { "1" } else { "0" }.to_string()))
+            .collect(),
+        (_, DataType::Float64) => array
+            .as_primitive::<arrow_array::types::Float64Type>()
+            .iter()
+            .map(|value| value.map(|value| value.to_string()))
+            .collect(),
+        (_, DataType::Binary) => array
+            .as_binary::<i32>()
+            .iter()
+            .map(|value| value.map(hex))
+            .collect(),
+        (_, DataType::Utf8) => array
+            .as_string::<i32>()
+            .iter()
+            .map(|value| value.map(str::to_string))
+            .collect(),
+        (snowflake_type, data_type) => return Err(unexpected_type(snowflake_type, data_type)),
+    };
+    Ok(texts)
+}
+
+/// Converts a column as Snowflake sends it to the Arrow type of [`arrow_data_type`].
+fn arrow_array(array: &ArrayRef, column_type: &SnowflakeColumnType) -> Result<ArrayRef> {
+    let data_type = arrow_data_type(column_type);
+    match column_type.snowflake_type() {
+        "fixed" | "time" | "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => scaled_array(
+            scaled_values(array, column_scale(column_type))?,
+            column_type,
+        ),
+        "date" if array.data_type() != &DataType::Date32 => {
+            let days = integers(array)?
+                .into_iter()
+                .map(|days| days.map(i32::try_from).transpose())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|_| Error::decode("a DATE value is out of the range of Date32"))?;
+            Ok(Arc::new(Date32Array::from(days)))
+        }
+        snowflake_type if array.data_type() != &data_type => {
+            Err(unexpected_type(snowflake_type, array.data_type()))
+        }
+        _ => Ok(Arc::clone(array)),
+    }
+}
+
+/// Builds the array of a NUMBER, TIME or TIMESTAMP column from its values as integers of units
+/// of `10^-scale`, with the column scale.
+pub(crate) fn scaled_array(
+    values: Vec<Option<i128>>,
+    column_type: &SnowflakeColumnType,
+) -> Result<ArrayRef> {
+    let scale = column_scale(column_type);
+    let rescaled = |target: u32| {
+        values
+            .iter()
+            .map(|value| value.map(|value| rescale(value, scale, target)).transpose())
+            .collect::<Result<Vec<Option<i64>>>>()
+    };
+    let array: ArrayRef = match arrow_data_type(column_type) {
+        DataType::Int64 => Arc::new(Int64Array::from(rescaled(0)?)),
+        DataType::Decimal128(precision, scale) => Arc::new(
+            Decimal128Array::from(values)
+                .with_precision_and_scale(precision, scale)
+                .map_err(arrow_error)?,
+        ),
+        DataType::Time64(_) => Arc::new(Time64NanosecondArray::from(rescaled(9)?)),
+        DataType::Timestamp(unit, timezone) => {
+            let values = rescaled(unit_scale(&unit))?;
+            match unit {
+                TimeUnit::Second => timestamps::<TimestampSecondType>(values, timezone),
+                TimeUnit::Millisecond => timestamps::<TimestampMillisecondType>(values, timezone),
+                TimeUnit::Microsecond => timestamps::<TimestampMicrosecondType>(values, timezone),
+                TimeUnit::Nanosecond => timestamps::<TimestampNanosecondType>(values, timezone),
+            }
+        }
+        data_type => return Err(unexpected_type(column_type.snowflake_type(), &data_type)),
+    };
+    Ok(array)
+}
+
+fn timestamps<T>(values: Vec<Option<i64>>, timezone: Option<Arc<str>>) -> ArrayRef
+where
+    T: arrow_array::types::ArrowTimestampType,
+{
+    Arc::new(PrimitiveArray::<T>::from_iter(values).with_timezone_opt(timezone))
+}
+
+/// Converts a value in units of `10^-scale` to units of `10^-target`, truncating toward
+/// negative infinity as Snowflake truncates fractions of a timestamp.
+fn rescale(value: i128, scale: u32, target: u32) -> Result<i64> {
+    let rescaled = match target.checked_sub(scale) {
+        Some(digits) => 10i128
+            .checked_pow(digits)
+            .and_then(|factor| value.checked_mul(factor)),
+        None => 10i128
+            .checked_pow(scale - target)
+            .map(|factor| value.div_euclid(factor)),
+    };
+    rescaled
+        .and_then(|value| i64::try_from(value).ok())
+        .ok_or_else(|| {
+            Error::decode(format!(
+                "'{}' is out of the range of a 64-bit Arrow value",
+                scaled_text(value, scale)
+            ))
+        })
+}
+
+/// The values of a NUMBER, TIME or TIMESTAMP column as integers of units of `10^-scale`: from
+/// integers or decimals already in those units, or from structs of an `epoch` in seconds and a
+/// `fraction` in nanoseconds. A struct without a `fraction` has its `epoch` in those units.
+fn scaled_values(array: &ArrayRef, scale: u32) -> Result<Vec<Option<i128>>> {
+    let DataType::Struct(_) = array.data_type() else {
+        return integers(array);
+    };
+    let epochs = integers(struct_field(array, "epoch")?)?;
+    let Some(fractions) = array.as_struct().column_by_name("fraction") else {
+        return Ok(epochs);
+    };
+    let (units, divisor) = (10i128.pow(scale.min(9)), 10i128.pow(9 - scale.min(9)));
+    Ok(epochs
+        .into_iter()
+        .zip(integers(fractions)?)
+        .map(|(epoch, fraction)| Some(epoch? * units + fraction? / divisor))
+        .collect())
+}
+
+fn struct_field<'a>(array: &'a ArrayRef, name: &str) -> Result<&'a ArrayRef> {
+    let DataType::Struct(_) = array.data_type() else {
+        return Err(Error::Communication(format!(
+            "expected a struct with '{name}' in an Arrow result, got {}",
+            array.data_type()
+        )));
+    };
+    array.as_struct().column_by_name(name).ok_or_else(|| {
+        Error::Communication(format!("an Arrow struct in the result has no '{name}'"))
+    })
+}
+
+/// The values of an integer, decimal or date array as `i128`. The values of a struct are NULL
+/// where the struct is.
+fn integers(array: &ArrayRef) -> Result<Vec<Option<i128>>> {
+    fn widen<T>(array: &PrimitiveArray<T>) -> Vec<Option<i128>>
+    where
+        T: arrow_array::ArrowPrimitiveType,
+        T::Native: Into<i128>,
+    {
+        array.iter().map(|value| value.map(Into::into)).collect()
+    }
+    Ok(match array.data_type() {
+        DataType::Int8 => widen(array.as_primitive::<Int8Type>()),
+        DataType::Int16 => widen(array.as_primitive::<Int16Type>()),
+        DataType::Int32 => widen(array.as_primitive::<Int32Type>()),
+        DataType::Int64 => widen(array.as_primitive::<Int64Type>()),
+        DataType::Date32 => widen(array.as_primitive::<Date32Type>()),
+        DataType::Decimal128(_, _) => widen(array.as_primitive::<Decimal128Type>()),
+        data_type => {
+            return Err(Error::Communication(format!(
+                "expected integers in an Arrow result, got {data_type}"
+            )))
+        }
+    })
+}
+
+/// Writes an integer of units of `10^-scale` as a decimal with `scale` fractional digits.
+fn scaled_text(value: i128, scale: u32) -> String {
+    if scale == 0 {
+        return value.to_string();
+    }
+    let digits = format!(
+        "{:0>width$}",
+        value.unsigned_abs(),
+        width = scale as usize + 1
+    );
+    let (integer, fraction) = digits.split_at(digits.len() - scale as usize);
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{sign}{integer}.{fraction}")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+fn unexpected_type(snowflake_type: &str, data_type: &DataType) -> Error {
+    Error::UnsupportedFormat(format!("Arrow {data_type} for a {snowflake_type} column"))
+}
+
+fn arrow_error(error: ArrowError) -> Error {
+    Error::Communication(format!("invalid Arrow result: {error}"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use arrow_array::{
+        BinaryArray, BooleanArray, Float64Array, Int16Array, Int32Array, StringArray, StructArray,
+        TimestampMillisecondArray, TimestampNanosecondArray,
+    };
+    use arrow_ipc::writer::StreamWriter;
+    use arrow_schema::Fields;
+
+    use super::*;
+
+    fn columns(columns: &[(&str, &str, Option<i64>)]) -> Columns {
+        Columns::new(
+            columns
+                .iter()
+                .map(|(name, snowflake_type, scale)| {
+                    (
+                        name.to_string(),
+                        SnowflakeColumnType::new(snowflake_type, *scale),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// An Arrow IPC stream of one batch of the given columns.
+    pub(crate) fn ipc(arrays: Vec<(&str, ArrayRef)>) -> Vec<u8> {
+        let batch = RecordBatch::try_from_iter(arrays).unwrap();
+        let mut body = vec![];
+        let mut writer = StreamWriter::try_new(&mut body, &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        body
+    }
+
+    /// A struct of `epoch` seconds and `fraction` nanoseconds, and a `timezone` if given.
+    fn epoch_fraction(values: Vec<Option<(i64, i32)>>, timezones: Option<Vec<i32>>) -> ArrayRef {
+        let epochs = values.iter().map(|value| value.map(|(epoch, _)| epoch));
+        let fractions = values
+            .iter()
+            .map(|value| value.map(|(_, fraction)| fraction));
+        let mut fields: Vec<(Arc<Field>, ArrayRef)> = vec![
+            (
+                Arc::new(Field::new("epoch", DataType::Int64, true)),
+                Arc::new(Int64Array::from_iter(epochs)),
+            ),
+            (
+                Arc::new(Field::new("fraction", DataType::Int32, true)),
+                Arc::new(Int32Array::from_iter(fractions)),
+            ),
+        ];
+        if let Some(timezones) = timezones {
+            fields.push((
+                Arc::new(Field::new("timezone", DataType::Int32, true)),
+                Arc::new(Int32Array::from(timezones)),
+            ));
+        }
+        let nulls = values.iter().map(Option::is_some).collect::<Vec<_>>();
+        let (fields, arrays): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+        Arc::new(StructArray::new(
+            Fields::from(fields),
+            arrays,
+            Some(nulls.into()),
+        ))
+    }
+
+    fn texts(values: &RowValues) -> Vec<Vec<Option<&str>>> {
+        (0..values.len())
+            .map(|row| {
+                (0..values.row_len(row))
+                    .map(|column| values.get(row, column))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_row_values() -> Result<()> {
+        let columns = columns(&[
+            ("ID", "fixed", Some(0)),
+            ("PRICE", "fixed", Some(2)),
+            ("RATIO", "real", None),
+            ("NAME", "text", None),
+            ("OK", "boolean", None),
+            ("DAY", "date", None),
+            ("AT", "time", Some(3)),
+            ("BYTES", "binary", None),
+        ]);
+        let body = ipc(vec![
+            ("ID", Arc::new(Int16Array::from(vec![Some(-7), None]))),
+            (
+                "PRICE",
+                Arc::new(Int64Array::from(vec![Some(-5), Some(12345)])),
+            ),
+            (
+                "RATIO",
+                Arc::new(Float64Array::from(vec![Some(1.5), Some(f64::NAN)])),
+            ),
+            ("NAME", Arc::new(StringArray::from(vec![Some("a"), None]))),
+            (
+                "OK",
+                Arc::new(BooleanArray::from(vec![Some(true), Some(false)])),
+            ),
+            (
+                "DAY",
+                Arc::new(Date32Array::from(vec![Some(19000), Some(-1)])),
+            ),
+            (
+                "AT",
+                Arc::new(Int32Array::from(vec![Some(45296789), Some(0)])),
+            ),
+            (
+                "BYTES",
+                Arc::new(BinaryArray::from(vec![Some(&[0xAB, 0x01][..]), None])),
+            ),
+        ]);
+
+        let rows = parse_arrow_chunk(&body, None, &columns)?;
+
+        assert_eq!(
+            texts(&rows),
+            vec![
+                vec![
+                    Some("-7"),
+                    Some("-0.05"),
+                    Some("1.5"),
+                    Some("a"),
+                    Some("1"),
+                    Some("19000"),
+                    Some("45296.789"),
+                    Some("AB01")
+                ],
+                vec![
+                    None,
+                    Some("123.45"),
+                    Some("NaN"),
+                    None,
+                    Some("0"),
+                    Some("-1"),
+                    Some("0.000"),
+                    None
+                ],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp_row_values() -> Result<()> {
+        let columns = columns(&[
+            ("NTZ", "timestamp_ntz", Some(9)),
+            ("LTZ", "timestamp_ltz", Some(3)),
+            ("TZ", "timestamp_tz", Some(9)),
+            ("TZ3", "timestamp_tz", Some(3)),
+        ]);
+        // Before the epoch, the fraction is still positive and the seconds round down.
+        let ntz = epoch_fraction(
+            vec![Some((1700000000, 123456789)), Some((-2, 500000000))],
+            None,
+        );
+        let tz = epoch_fraction(
+            vec![Some((1700000000, 120000000)), None],
+            Some(vec![1980, 0]),
+        );
+        let tz3 = Arc::new(StructArray::from(vec![
+            (
+                Arc::new(Field::new("epoch", DataType::Int64, false)),
+                Arc::new(Int64Array::from(vec![1700000000123, -1])) as ArrayRef,
+            ),
+            (
+                Arc::new(Field::new("timezone", DataType::Int32, false)),
+                Arc::new(Int32Array::from(vec![1440, 1380])) as ArrayRef,
+            ),
+        ]));
+        let body = ipc(vec![
+            ("NTZ", ntz),
+            (
+                "LTZ",
+                Arc::new(Int64Array::from(vec![Some(1700000000123), None])),
+            ),
+            ("TZ", tz),
+            ("TZ3", tz3),
+        ]);
+
+        let rows = parse_arrow_chunk(&body, None, &columns)?;
+
+        assert_eq!(
+            texts(&rows),
+            vec![
+                vec![
+                    Some("1700000000.123456789"),
+                    Some("1700000000.123"),
+                    Some("1700000000.120000000 1980"),
+                    Some("1700000000.123 1440"),
+                ],
+                vec![Some("-1.500000000"), None, None, Some("-0.001 1380")],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrow_batches() -> Result<()> {
+        let columns = columns(&[
+            ("ID", "fixed", Some(0)),
+            ("PRICE", "fixed", Some(2)),
+            ("NTZ", "timestamp_ntz", Some(9)),
+            ("LTZ", "timestamp_ltz", Some(3)),
+            ("TZ", "timestamp_tz", Some(9)),
+            ("AT", "time", Some(3)),
+            ("DAY", "date", None),
+            ("NAME", "text", None),
+        ]);
+        let body = ipc(vec![
+            ("ID", Arc::new(Int32Array::from(vec![Some(-7), None]))),
+            (
+                "PRICE",
+                Arc::new(Int64Array::from(vec![Some(-5), Some(12345)])),
+            ),
+            (
+                "NTZ",
+                epoch_fraction(
+                    vec![Some((1700000000, 123456789)), Some((-2, 500000000))],
+                    None,
+                ),
+            ),
+            (
+                "LTZ",
+                Arc::new(Int64Array::from(vec![Some(1700000000123), None])),
+            ),
+            (
+                "TZ",
+                epoch_fraction(vec![Some((1700000000, 5)), None], Some(vec![1980, 0])),
+            ),
+            ("AT", Arc::new(Int64Array::from(vec![Some(1500), Some(0)]))),
+            ("DAY", Arc::new(Date32Array::from(vec![Some(19000), None]))),
+            ("NAME", Arc::new(StringArray::from(vec![Some("a"), None]))),
+        ]);
+
+        let batches = arrow_batches(&body, &columns)?;
+
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.schema(), arrow_schema(&columns));
+        let ids = batch.column(0).as_primitive::<Int64Type>();
+        assert_eq!(ids.iter().collect::<Vec<_>>(), vec![Some(-7), None]);
+        let prices = batch.column(1).as_primitive::<Decimal128Type>();
+        assert_eq!(prices.data_type(), &DataType::Decimal128(38, 2));
+        assert_eq!(prices.value_as_string(0), "-0.05");
+        assert_eq!(prices.value_as_string(1), "123.45");
+        let ntz = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>();
+        assert_eq!(
+            ntz.unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(1700000000123456789), Some(-1500000000)]
+        );
+        let ltz = batch
+            .column(3)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>();
+        assert_eq!(ltz.unwrap().timezone(), Some("UTC"));
+        assert_eq!(
+            ltz.unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(1700000000123), None]
+        );
+        let tz = batch
+            .column(4)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>();
+        assert_eq!(
+            tz.unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(1700000000000000005), None]
+        );
+        let times = batch
+            .column(5)
+            .as_any()
+            .downcast_ref::<Time64NanosecondArray>();
+        assert_eq!(
+            times.unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(1500000000), Some(0)]
+        );
+        assert_eq!(batch.column(6).null_count(), 1);
+        assert_eq!(batch.column(7).as_string::<i32>().value(0), "a");
+        Ok(())
+    }
+
+    /// The rows of a JSON result.
+    fn json_rows(rows: Vec<Vec<Option<&str>>>) -> RowValues {
+        RowValues::from_rows(
+            rows.into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|value| value.map(str::to_string))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_json_batches() -> Result<()> {
+        let columns = columns(&[
+            ("ID", "fixed", Some(0)),
+            ("PRICE", "fixed", Some(2)),
+            ("RATIO", "real", None),
+            ("NAME", "text", None),
+            ("OK", "boolean", None),
+            ("DAY", "date", None),
+            ("AT", "time", Some(9)),
+            ("NTZ", "timestamp_ntz", Some(9)),
+            ("LTZ", "timestamp_ltz", Some(3)),
+            ("TZ", "timestamp_tz", Some(9)),
+            ("BYTES", "binary", None),
+        ]);
+        let rows = json_rows(vec![
+            vec![
+                Some("-7"),
+                Some("12.5"),
+                Some("1.5e3"),
+                Some("a"),
+                Some("1"),
+                Some("19000"),
+                Some("45296.789000000"),
+                Some("-1.500000000"),
+                Some("1700000000.123"),
+                Some("1700000000.000000005 1980"),
+                Some("AB01"),
+            ],
+            vec![None; 11],
+            vec![
+                Some("9223372036854775807"),
+                Some("-0.05"),
+                Some("NaN"),
+                Some(""),
+                Some("0"),
+                Some("-1"),
+                Some("0.000000000"),
+                Some("0.000000000"),
+                Some("-0.001"),
+                Some("0.000000000 1440"),
+                Some(""),
+            ],
+        ]);
+
+        let batches = json_batches(&rows, &columns, Error::with_row)?;
+
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.schema(), arrow_schema(&columns));
+        for column in batch.columns() {
+            assert_eq!(column.null_count(), 1);
+            assert!(column.is_null(1));
+        }
+        let ids = batch.column(0).as_primitive::<Int64Type>();
+        assert_eq!(ids.value(0), -7);
+        assert_eq!(ids.value(2), i64::MAX);
+        let prices = batch.column(1).as_primitive::<Decimal128Type>();
+        assert_eq!(prices.data_type(), &DataType::Decimal128(38, 2));
+        assert_eq!((prices.value(0), prices.value(2)), (1250, -5));
+        assert_eq!(prices.value_as_string(0), "12.50");
+        let ratios = batch
+            .column(2)
+            .as_primitive::<arrow_array::types::Float64Type>();
+        assert_eq!(ratios.value(0), 1500.0);
+        assert!(ratios.value(2).is_nan());
+        let names = batch.column(3).as_string::<i32>();
+        assert_eq!((names.value(0), names.value(2)), ("a", ""));
+        let ok = batch.column(4).as_boolean();
+        assert_eq!((ok.value(0), ok.value(2)), (true, false));
+        let days = batch.column(5).as_primitive::<Date32Type>();
+        assert_eq!((days.value(0), days.value(2)), (19000, -1));
+        let times = batch
+            .column(6)
+            .as_any()
+            .downcast_ref::<Time64NanosecondArray>();
+        assert_eq!(times.unwrap().value(0), 45296789000000);
+        let ntz = batch
+            .column(7)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>();
+        assert_eq!(ntz.unwrap().value(0), -1500000000);
+        let ltz = batch
+            .column(8)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>();
+        assert_eq!(
+            (ltz.unwrap().value(0), ltz.unwrap().value(2)),
+            (1700000000123, -1)
+        );
+        let tz = batch
+            .column(9)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>();
+        assert_eq!(tz.unwrap().value(0), 1700000000000000005);
+        assert_eq!(tz.unwrap().timezone(), Some("UTC"));
+        let bytes = batch.column(10).as_binary::<i32>();
+        assert_eq!(
+            (bytes.value(0), bytes.value(2)),
+            (&[0xAB, 0x01][..], &[][..])
+        );
+
+        // Without rows, there is no batch.
+        assert_eq!(
+            json_batches(&json_rows(vec![]), &columns, Error::with_row)?,
+            vec![]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_batch_errors() {
+        let columns = columns(&[("NAME", "text", None), ("PRICE", "fixed", Some(2))]);
+
+        // A value with more fractional digits than the scale is not rounded.
+        let rows = json_rows(vec![
+            vec![Some("a"), Some("1.20")],
+            vec![Some("b"), Some("1.234")],
+        ]);
+        let Err(Error::Decode(err)) = json_batches(&rows, &columns, |e, row| e.with_row(10 + row))
+        else {
+            panic!("expected a decode error");
+        };
+        assert_eq!(err.column_name(), Some("PRICE"));
+        assert_eq!(err.rust_type(), Some("Decimal128"));
+        assert_eq!(err.value(), Some("1.234"));
+        assert_eq!(err.row_index(), Some(11));
+
+        let columns = Columns::new(vec![(
+            "ID".into(),
+            SnowflakeColumnType::new("fixed", Some(0)).with_nullable(false),
+        )]);
+        assert!(json_batches(&json_rows(vec![vec![None]]), &columns, Error::with_row).is_err());
+        let rows = json_rows(vec![vec![Some("9223372036854775808")]]);
+        assert!(matches!(
+            json_batches(&rows, &columns, Error::with_row),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_timestamp_units() {
+        let unit = |scale| match arrow_data_type(&SnowflakeColumnType::new("timestamp_ntz", scale))
+        {
+            DataType::Timestamp(unit, None) => unit,
+            data_type => panic!("{data_type}"),
+        };
+        assert_eq!(unit(Some(0)), TimeUnit::Second);
+        assert_eq!(unit(Some(2)), TimeUnit::Millisecond);
+        assert_eq!(unit(Some(6)), TimeUnit::Microsecond);
+        assert_eq!(unit(Some(9)), TimeUnit::Nanosecond);
+        assert_eq!(unit(None), TimeUnit::Nanosecond);
+
+        // A scale below the unit's is padded, and an integer too large for i64 is rejected.
+        let column_type = SnowflakeColumnType::new("timestamp_ltz", Some(2));
+        let array = scaled_array(vec![Some(-15), None], &column_type).unwrap();
+        let array = array.as_any().downcast_ref::<TimestampMillisecondArray>();
+        assert_eq!(
+            array.unwrap().iter().collect::<Vec<_>>(),
+            vec![Some(-150), None]
+        );
+        let column_type = SnowflakeColumnType::new("fixed", Some(0));
+        let result = scaled_array(vec![Some(i128::from(i64::MAX) + 1)], &column_type);
+        assert!(matches!(result, Err(Error::Decode(_))));
+    }
+
+    #[test]
+    fn test_unexpected_arrow_type() {
+        let columns = columns(&[("OK", "boolean", None)]);
+        let body = ipc(vec![("OK", Arc::new(Int32Array::from(vec![1])))]);
+        assert!(matches!(
+            parse_arrow_chunk(&body, None, &columns),
+            Err(Error::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            parse_arrow_chunk(b"not arrow", None, &columns),
+            Err(Error::Communication(_))
+        ));
+    }
+}
@@ -1,18 +1,30 @@
-use base64::{engine::general_purpose::STANDARD, Engine};
-use jsonwebtoken::{Algorithm, EncodingKey, Header};
-use pkcs8::{DecodePrivateKey, EncodePrivateKey, EncodePublicKey, LineEnding};
-use rsa::RsaPrivateKey;
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use jsonwebtoken::errors::ErrorKind;
+use pkcs8::{DecodePrivateKey, EncodePublicKey};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
 use serde_json::json;
 use sha2::{Digest, Sha256};
 
 use crate::Result;
 
+/// The header of an RS256 JWT, `{"typ":"JWT","alg":"RS256"}`, encoded.
+const JWT_HEADER: &str = "eyJ0eXAiOiJKV1QiLCJhbGciOiJSUzI1NiJ9";
+
+/// Creates a JWT signed with the private key, valid for `lifetime` seconds from `timestamp`.
+///
+/// The key is signed with directly rather than through a `jsonwebtoken::EncodingKey`, which
+/// would keep a copy of it that is not zeroed; the decrypted key is zeroed when it is dropped,
+/// as soon as the JWT is signed.
 pub(super) fn generate_jwt_from_key_pair(
     encrypted_pem: &str,
     password: impl AsRef<[u8]>,
     username: &str,
     account: &str,
     timestamp: i64,
+    lifetime: i64,
 ) -> Result<String> {
     let account = account
         .split('.')
@@ -32,18 +44,17 @@ pub(super) fn generate_jwt_from_key_pair(
         "iss": format!("{}.{}.SHA256:{}", account, username, fingerprint),
         "sub": format!("{}.{}", account, username),
         "iat": timestamp,
-        "exp": timestamp + 600
+        "exp": timestamp + lifetime
     });
-    let key = EncodingKey::from_rsa_pem(private.to_pkcs8_pem(LineEnding::LF)?.as_bytes())?;
-    let jwt = jsonwebtoken::encode(
-        &Header {
-            alg: Algorithm::RS256,
-            ..Default::default()
-        },
-        &payload,
-        &key,
-    )?;
-    Ok(jwt)
+    let message = format!(
+        "{JWT_HEADER}.{}",
+        URL_SAFE_NO_PAD.encode(payload.to_string())
+    );
+    let signature = private
+        .sign(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(&message))
+        .map_err(|e| jsonwebtoken::errors::Error::from(ErrorKind::InvalidRsaKey(e.to_string())))?;
+    drop(private);
+    Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
 }
 
 #[cfg(test)]
@@ -59,6 +70,7 @@ mod tests {
             "USER_NAME",
             "myaccount.ap-northeast-1.aws",
             1700746374,
+            600,
         )?;
         assert_eq!(
             jwt,
@@ -1,24 +1,105 @@
 mod key_pair;
 
+use std::sync::{Mutex, PoisonError};
+
 use chrono::Utc;
-use reqwest::Client;
-use serde_json::{json, Value};
+use http::header::{ACCEPT, AUTHORIZATION};
+use serde_json::{json, Map, Value};
+use zeroize::Zeroize;
 
-use crate::{Error, Result, SnowflakeAuthMethod, SnowflakeClientConfig};
+use crate::{
+    query::timeout_seconds,
+    server_info::{Parameter, ServerVersion},
+    transport::{send, HttpClient},
+    Error, Result, SecretBytes, SecretString, SnowflakeAuthMethod, SnowflakeClientConfig,
+};
 
 use self::key_pair::generate_jwt_from_key_pair;
 
-/// Login to Snowflake and return a session token.
+/// How long the JWT sent to log in is valid for, in seconds.
+const LOGIN_JWT_LIFETIME: i64 = 600;
+
+/// How long the JWTs of [`KeyPairJwt`] are valid for, in seconds; Snowflake accepts at most an
+/// hour.
+const JWT_LIFETIME: i64 = 3600;
+
+/// How long before it expires a JWT of [`KeyPairJwt`] is replaced with a new one, in seconds.
+const JWT_RENEWAL: i64 = 300;
+
+/// Signs the JWTs sent with each request to the APIs that authenticate requests rather than
+/// sessions, reusing each until shortly before it expires.
+pub(crate) struct KeyPairJwt {
+    encrypted_pem: String,
+    password: SecretBytes,
+    username: String,
+    account: String,
+    /// The JWT returned last, and when it expires.
+    cached: Mutex<Option<(String, i64)>>,
+}
+
+impl KeyPairJwt {
+    /// Fails for a key that cannot be read, rather than the first request.
+    pub(crate) fn new(
+        encrypted_pem: &str,
+        password: &SecretBytes,
+        username: &str,
+        account: &str,
+    ) -> Result<Self> {
+        let jwt = Self {
+            encrypted_pem: encrypted_pem.to_string(),
+            password: password.clone(),
+            username: username.to_string(),
+            account: account.to_string(),
+            cached: Mutex::new(None),
+        };
+        jwt.token()?;
+        Ok(jwt)
+    }
+
+    pub(crate) fn token(&self) -> Result<String> {
+        let mut cached = self.cached.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Utc::now().timestamp();
+        if let Some((token, expires)) = cached.as_ref() {
+            if now < expires - JWT_RENEWAL {
+                return Ok(token.clone());
+            }
+        }
+        let token = generate_jwt_from_key_pair(
+            &self.encrypted_pem,
+            self.password.expose_secret(),
+            &self.username,
+            &self.account,
+            now,
+            JWT_LIFETIME,
+        )?;
+        *cached = Some((token.clone(), now + JWT_LIFETIME));
+        Ok(token)
+    }
+}
+
+/// The tokens of a session: the session token sent with each request, and the master token
+/// that renews it.
+#[derive(Clone)]
+pub(crate) struct SessionTokens {
+    pub(crate) session: SecretString,
+    pub(crate) master: SecretString,
+}
+
+/// What the login response tells of a new session.
+pub(crate) struct Login {
+    pub(crate) tokens: SessionTokens,
+    pub(crate) server_version: Option<ServerVersion>,
+    pub(crate) parameters: Vec<Parameter>,
+}
+
+/// Login to Snowflake and return the tokens of the new session.
 pub(super) async fn login(
-    http: &Client,
+    http: &HttpClient,
     username: &str,
     auth: &SnowflakeAuthMethod,
     config: &SnowflakeClientConfig,
-) -> Result<String> {
-    let url = format!(
-        "https://{account}.snowflakecomputing.com/session/v1/login-request",
-        account = config.account
-    );
+) -> Result<Login> {
+    let url = format!("{}/session/v1/login-request", config.base_url());
 
     let mut queries = vec![];
     if let Some(warehouse) = &config.warehouse {
@@ -34,27 +115,73 @@ pub(super) async fn login(
         queries.push(("roleName", role));
     }
 
-    let login_data = login_request_data(username, auth, config)?;
-    let response = http
-        .post(url)
-        .query(&queries)
-        .json(&json!({
-            "data": login_data
-        }))
-        .send()
-        .await?;
-    let status = response.status();
-    let body = response.text().await?;
-    if !status.is_success() {
-        return Err(Error::Communication(body));
+    let mut login_data = login_request_data(username, auth, config)?;
+    let session_parameters = session_parameters(config);
+    if !session_parameters.is_empty() {
+        login_data["SESSION_PARAMETERS"] = Value::Object(session_parameters);
     }
+    let request = http.post(url).query(&queries).json(&json!({
+        "data": login_data
+    }));
+    let mut reply = send(http, request).await?;
 
-    let response: Response = serde_json::from_str(&body).map_err(|_| Error::Communication(body))?;
-    if !response.success {
-        return Err(Error::Communication(response.message.unwrap_or_default()));
-    }
+    // The response holds the session and master tokens.
+    let response: Response<LoginResponse> = reply.parse_secret()?;
+    let login = match response.data {
+        Some(LoginResponse {
+            token: Some(session),
+            master_token: Some(master),
+            server_version,
+            parameters,
+        }) if response.success => Ok(Login {
+            tokens: SessionTokens {
+                session: session.into(),
+                master: master.into(),
+            },
+            server_version: server_version.as_deref().and_then(ServerVersion::parse),
+            parameters,
+        }),
+        _ => Err(Error::Communication(response.message.unwrap_or_default())),
+    };
+    reply.body.zeroize();
+    login
+}
+
+/// Exchanges an expired session token for a new one with the master token of the session.
+/// The session itself, with its state, carries on.
+pub(crate) async fn renew_session(
+    http: &HttpClient,
+    base_url: &str,
+    tokens: &SessionTokens,
+) -> Result<SessionTokens> {
+    let request_id = uuid::Uuid::new_v4();
+    let url = format!("{base_url}/session/token-request?requestId={request_id}");
+    let request = http
+        .post(url)
+        .header(ACCEPT, "application/snowflake")
+        .header(
+            AUTHORIZATION,
+            format!("Snowflake Token=\"{}\"", tokens.master.expose_secret()),
+        )
+        .json(&json!({
+            "oldSessionToken": tokens.session.expose_secret(),
+            "requestType": "RENEW"
+        }));
+    let mut reply = send(http, request).await?;
 
-    Ok(response.data.token)
+    let response: Response<RenewResponse> = reply.parse_secret()?;
+    let renewed = match response.data {
+        Some(RenewResponse {
+            session_token: Some(session),
+            master_token,
+        }) if response.success => Ok(SessionTokens {
+            session: session.into(),
+            master: master_token.map_or_else(|| tokens.master.clone(), SecretString::from),
+        }),
+        _ => Err(response.into_error()),
+    };
+    reply.body.zeroize();
+    renewed
 }
 
 fn login_request_data(
@@ -65,7 +192,7 @@ fn login_request_data(
     match auth {
         SnowflakeAuthMethod::Password(password) => Ok(json!({
             "LOGIN_NAME": username,
-            "PASSWORD": password,
+            "PASSWORD": password.expose_secret(),
             "ACCOUNT_NAME": config.account
         })),
         SnowflakeAuthMethod::KeyPair {
@@ -74,10 +201,11 @@ fn login_request_data(
         } => {
             let jwt = generate_jwt_from_key_pair(
                 encrypted_pem,
-                password,
+                password.expose_secret(),
                 username,
                 &config.account,
                 Utc::now().timestamp(),
+                LOGIN_JWT_LIFETIME,
             )?;
             Ok(json!({
                 "LOGIN_NAME": username,
@@ -86,17 +214,113 @@ fn login_request_data(
                 "AUTHENTICATOR": "SNOWFLAKE_JWT"
             }))
         }
+        SnowflakeAuthMethod::OAuth(token) => Ok(json!({
+            "LOGIN_NAME": username,
+            "ACCOUNT_NAME": config.account,
+            "TOKEN": token.expose_secret(),
+            "AUTHENTICATOR": "OAUTH"
+        })),
+    }
+}
+
+/// Session parameters set at login from the client config.
+pub(crate) fn session_parameters(config: &SnowflakeClientConfig) -> Map<String, Value> {
+    let mut parameters = Map::new();
+    if let Some(format) = config.geo_output_format {
+        let format = format.parameter_value();
+        parameters.insert("GEOGRAPHY_OUTPUT_FORMAT".into(), json!(format));
+        parameters.insert("GEOMETRY_OUTPUT_FORMAT".into(), json!(format));
+    }
+    if let Some(timeout) = config.default_statement_timeout {
+        let timeout = timeout_seconds(timeout);
+        parameters.insert("STATEMENT_TIMEOUT_IN_SECONDS".into(), json!(timeout));
     }
+    if let Some(size) = config.result_chunk_size_mb {
+        parameters.insert("CLIENT_RESULT_CHUNK_SIZE".into(), json!(size));
+    }
+    if let Some(rows) = config.rows_per_result_set {
+        parameters.insert("ROWS_PER_RESULTSET".into(), json!(rows));
+    }
+    parameters
 }
 
 #[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct LoginResponse {
-    token: String,
+    token: Option<String>,
+    master_token: Option<String>,
+    server_version: Option<String>,
+    #[serde(default)]
+    parameters: Vec<Parameter>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenewResponse {
+    session_token: Option<String>,
+    /// Sent when the master token has been replaced as well.
+    master_token: Option<String>,
 }
 
-#[derive(serde:: Deserialize)]
-struct Response {
-    data: LoginResponse,
+#[derive(serde::Deserialize)]
+struct Response<T> {
+    /// Missing or without tokens when the request fails.
+    data: Option<T>,
+    code: Option<String>,
     message: Option<String>,
     success: bool,
 }
+
+impl<T> Response<T> {
+    /// The error for the code of a failed renewal if it has a numeric one, so that an expired
+    /// master token is told apart, otherwise [`Error::Communication`].
+    fn into_error(self) -> Error {
+        let message = self.message.unwrap_or_default();
+        match self.code.as_deref().map(str::parse) {
+            Some(Ok(code)) => Error::from_code(code, String::new(), message, None),
+            _ => Error::Communication(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    use super::*;
+
+    #[test]
+    fn test_key_pair_jwt() -> Result<()> {
+        let jwt = KeyPairJwt::new(
+            include_str!("test_snowflake_key.p8"),
+            &b"12345".to_vec().into(),
+            "user_name",
+            "myaccount.ap-northeast-1.aws",
+        )?;
+        let token = jwt.token()?;
+        // The token is reused until it is about to expire.
+        assert_eq!(jwt.token()?, token);
+
+        let payload = token.split('.').nth(1).unwrap();
+        let claims: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        assert_eq!(claims["sub"], "MYACCOUNT.USER_NAME");
+        assert!(claims["iss"]
+            .as_str()
+            .unwrap()
+            .starts_with("MYACCOUNT.USER_NAME.SHA256:"));
+        assert_eq!(
+            claims["exp"].as_i64().unwrap() - claims["iat"].as_i64().unwrap(),
+            JWT_LIFETIME
+        );
+
+        let invalid = KeyPairJwt::new(
+            include_str!("test_snowflake_key.p8"),
+            &b"x".to_vec().into(),
+            "u",
+            "a",
+        );
+        assert!(invalid.is_err());
+        Ok(())
+    }
+}
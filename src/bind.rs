@@ -0,0 +1,1281 @@
+//! Values bound to the `?` placeholders of a statement, and their encoding in the query request.
+
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use serde::{
+    ser::{self, Impossible, SerializeMap, SerializeSeq, SerializeStruct},
+    Serialize, Serializer,
+};
+
+use crate::{Error, Json, Result};
+
+/// The type a bound value is sent with, which tells the server how to read its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum BindType {
+    Fixed,
+    Real,
+    Text,
+    Boolean,
+    /// Hex-encoded bytes.
+    Binary,
+    /// JSON text, sent as `TEXT` and read with `PARSE_JSON` where it is bound.
+    #[serde(rename = "TEXT")]
+    Json,
+    /// Milliseconds since the epoch of the date's midnight, UTC.
+    Date,
+    /// Nanoseconds since midnight.
+    Time,
+    /// Nanoseconds since the epoch of the wall-clock time, as if it were UTC.
+    TimestampNtz,
+    /// Nanoseconds since the epoch.
+    TimestampLtz,
+    /// Nanoseconds since the epoch and the offset in minutes biased by 1440, separated by a
+    /// space, as TIMESTAMP_TZ values arrive in results but in nanoseconds.
+    TimestampTz,
+}
+
+impl BindType {
+    /// The name the type is sent with.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            BindType::Fixed => "FIXED",
+            BindType::Real => "REAL",
+            BindType::Text | BindType::Json => "TEXT",
+            BindType::Boolean => "BOOLEAN",
+            BindType::Binary => "BINARY",
+            BindType::Date => "DATE",
+            BindType::Time => "TIME",
+            BindType::TimestampNtz => "TIMESTAMP_NTZ",
+            BindType::TimestampLtz => "TIMESTAMP_LTZ",
+            BindType::TimestampTz => "TIMESTAMP_TZ",
+        }
+    }
+
+    /// The expression a placeholder for a value of this type is written as.
+    pub(crate) fn placeholder(self) -> &'static str {
+        match self {
+            BindType::Json => "PARSE_JSON(?)",
+            _ => "?",
+        }
+    }
+}
+
+/// A value for one placeholder: its type and its text, or `None` for NULL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BindValue {
+    pub(crate) bind_type: BindType,
+    pub(crate) value: Option<String>,
+}
+
+impl BindValue {
+    fn new(bind_type: BindType, value: impl ToString) -> Self {
+        Self {
+            bind_type,
+            value: Some(value.to_string()),
+        }
+    }
+
+    pub(crate) fn null() -> Self {
+        Self {
+            bind_type: BindType::Text,
+            value: None,
+        }
+    }
+
+    /// The value as JSON text: numbers and booleans as they are, bytes as an array of numbers,
+    /// as serde_json writes them, and anything else as a string.
+    fn into_json(self) -> Option<String> {
+        let value = self.value?;
+        Some(match self.bind_type {
+            BindType::Json | BindType::Fixed | BindType::Boolean => value,
+            BindType::Real if value.parse::<f64>().is_ok_and(f64::is_finite) => value,
+            BindType::Binary => serde_json::Value::from(unhex(&value)).to_string(),
+            _ => serde_json::Value::String(value).to_string(),
+        })
+    }
+
+    /// Values as one JSON array, converted as for a JSON column (see [`Binding::many`]).
+    pub(crate) fn json_array(values: Vec<BindValue>) -> Self {
+        let elements = values
+            .into_iter()
+            .map(|value| value.into_json().unwrap_or_else(|| "null".to_string()))
+            .collect::<Vec<_>>();
+        Self::new(BindType::Json, format!("[{}]", elements.join(",")))
+    }
+
+    /// The value as an SQL literal of the type it would be bound with, for statements that
+    /// take no placeholders, e.g. `SET`.
+    pub(crate) fn to_sql_literal(&self) -> String {
+        let Some(value) = &self.value else {
+            return "NULL".to_string();
+        };
+        let quoted = || format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"));
+        match self.bind_type {
+            BindType::Fixed => value.clone(),
+            BindType::Real => format!("{}::FLOAT", quoted()),
+            BindType::Text => quoted(),
+            BindType::Boolean => value.to_uppercase(),
+            BindType::Binary => format!("TO_BINARY({}, 'HEX')", quoted()),
+            BindType::Json => format!("PARSE_JSON({})", quoted()),
+            BindType::Date
+            | BindType::Time
+            | BindType::TimestampNtz
+            | BindType::TimestampLtz
+            | BindType::TimestampTz => {
+                temporal_literal(self.bind_type, value).unwrap_or_else(quoted)
+            }
+        }
+    }
+}
+
+/// A bound date, time or timestamp as a literal of its type, e.g. `'2024-01-31'::DATE`.
+fn temporal_literal(bind_type: BindType, value: &str) -> Option<String> {
+    let (nanos, offset) = match value.split_once(' ') {
+        Some((nanos, offset)) => (nanos, Some(offset)),
+        None => (value, None),
+    };
+    let nanos = nanos.parse::<i128>().ok()?;
+    let instant = |scale: i128| {
+        let nanos = nanos.checked_mul(scale)?;
+        let secs = i64::try_from(nanos.div_euclid(NANOS_PER_SEC)).ok()?;
+        DateTime::from_timestamp(secs, nanos.rem_euclid(NANOS_PER_SEC) as u32)
+    };
+    let (text, type_name) = match bind_type {
+        BindType::Date => (instant(1_000_000)?.format("%Y-%m-%d"), "DATE"),
+        BindType::Time => (instant(1)?.format("%H:%M:%S%.9f"), "TIME"),
+        BindType::TimestampNtz => (instant(1)?.format("%Y-%m-%d %H:%M:%S%.9f"), "TIMESTAMP_NTZ"),
+        BindType::TimestampLtz => (instant(1)?.format(ZONED_FORMAT), "TIMESTAMP_LTZ"),
+        BindType::TimestampTz => {
+            let offset_minutes = offset?.parse::<i32>().ok()? - 1440;
+            let offset = FixedOffset::east_opt(offset_minutes * 60)?;
+            let text = instant(1)?.with_timezone(&offset).format(ZONED_FORMAT);
+            return Some(format!("'{text}'::TIMESTAMP_TZ"));
+        }
+        _ => return None,
+    };
+    Some(format!("'{text}'::{type_name}"))
+}
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+const ZONED_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.9f %:z";
+
+/// A placeholder's binding in a query request, with one value for each row the statement is
+/// run for (an array binding).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct Binding {
+    #[serde(rename = "type")]
+    pub(crate) bind_type: BindType,
+    pub(crate) value: Vec<Option<String>>,
+}
+
+impl Binding {
+    /// Binds a value for each row, typed as the first of them that is not NULL, or as JSON if
+    /// any of them is, e.g. for a column of `serde_json::Value`s of which some are strings.
+    pub(crate) fn many(values: Vec<BindValue>) -> Self {
+        let mut non_null = values.iter().filter(|value| value.value.is_some());
+        let bind_type = match non_null.clone().any(|v| v.bind_type == BindType::Json) {
+            true => BindType::Json,
+            false => non_null
+                .next()
+                .map_or(BindType::Text, |value| value.bind_type),
+        };
+        let value = values.into_iter().map(|value| match bind_type {
+            BindType::Json => value.into_json(),
+            _ => value.value,
+        });
+        Self {
+            bind_type,
+            value: value.collect(),
+        }
+    }
+}
+
+/// Binds a value as JSON, into a VARIANT, OBJECT or ARRAY column, where it would otherwise be
+/// bound as its own type or not at all, e.g. a struct.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Json, Result, SnowflakeSession};
+/// #[derive(serde::Serialize)]
+/// struct Event {
+///     id: i64,
+///     payload: Json<Payload>,
+/// }
+///
+/// #[derive(serde::Serialize)]
+/// struct Payload {
+///     kind: String,
+///     tags: Vec<String>,
+/// }
+///
+/// # async fn run(session: &SnowflakeSession, events: Vec<Event>) -> Result<()> {
+/// session.insert_into("events").values(&events).execute().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// A `serde_json::Value` that is an object or an array, or any other map or sequence, is bound
+/// as JSON without it; other values are bound as their own type, e.g. a string as `TEXT`, and a
+/// JSON `null` as SQL NULL. `Json(serde_json::Value::Null)` is bound as JSON `null`.
+///
+/// That includes a `Vec<u8>`, which serde cannot tell from other sequences: bind bytes as
+/// `BINARY` with [`Bind`], or as any type that serializes as bytes, e.g. `bytes::Bytes` with the
+/// `bytes` feature.
+///
+/// Other serializers see only the value inside, as if it were not wrapped.
+impl<T: Serialize> Serialize for Json<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(JSON_NEWTYPE, &self.0)
+    }
+}
+
+/// The name [`Json`] serializes as, for [`ValueSerializer`] to tell it from other newtypes.
+const JSON_NEWTYPE: &str = "$snowflake_connector_rs::Json";
+
+/// Binds a date, time or timestamp of chrono, or of the time crate with the `time` feature, or
+/// bytes, as the Snowflake type it stands for, which serde has no way to tell.
+///
+/// | Type                          | With `time`         | Bound as        |
+/// |-------------------------------|---------------------|-----------------|
+/// | `NaiveDate`                   | `Date`              | `DATE`          |
+/// | `NaiveTime`                   | `Time`              | `TIME`          |
+/// | `NaiveDateTime`               | `PrimitiveDateTime` | `TIMESTAMP_NTZ` |
+/// | `DateTime<Utc>`               |                     | `TIMESTAMP_LTZ` |
+/// | `DateTime<FixedOffset>`       | `OffsetDateTime`    | `TIMESTAMP_TZ`  |
+/// | `Vec<u8>`, `&[u8]`, `[u8; N]` |                     | `BINARY`        |
+///
+/// Times and timestamps are sent in nanoseconds, so none of their precision is lost, and dates
+/// before 1970 bind like any other.
+///
+/// Without `Bind`, chrono and time values serialize only with their crates' `serde` feature,
+/// and then as text, e.g. `2024-01-31`, which is bound as `TEXT`. Snowflake casts the text to
+/// the type of the column it is inserted into or compared with, reading it with the session's
+/// `DATE_INPUT_FORMAT` or `TIMESTAMP_INPUT_FORMAT`, but elsewhere, e.g. in `SELECT ?`, it stays
+/// a `VARCHAR`.
+///
+/// A `DateTime<Utc>` is an instant, which Snowflake shows in the session's `TIMEZONE`. Into a
+/// TIMESTAMP_NTZ column it goes as the wall-clock time in that time zone, and into a
+/// TIMESTAMP_TZ column with that time zone's offset. Bind a `NaiveDateTime` to insert a
+/// wall-clock time as it is, or a `DateTime<FixedOffset>` to choose the offset.
+///
+/// ```rust
+/// # use chrono::{DateTime, NaiveDate, Utc};
+/// # use snowflake_connector_rs::{Bind, Result, SnowflakeSession};
+/// #[derive(serde::Serialize)]
+/// struct Event {
+///     id: i64,
+///     day: Bind<NaiveDate>,
+///     received_at: Option<Bind<DateTime<Utc>>>,
+/// }
+///
+/// # async fn run(session: &SnowflakeSession, events: Vec<Event>) -> Result<()> {
+/// session.insert_into("events").values(&events).execute().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Other serializers see the text a date, time or timestamp is bound as, e.g. the milliseconds
+/// since the epoch of a date, and bytes as bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Bind<T>(pub T);
+
+const DATE_NEWTYPE: &str = "$snowflake_connector_rs::Date";
+const TIME_NEWTYPE: &str = "$snowflake_connector_rs::Time";
+const TIMESTAMP_NTZ_NEWTYPE: &str = "$snowflake_connector_rs::TimestampNtz";
+const TIMESTAMP_LTZ_NEWTYPE: &str = "$snowflake_connector_rs::TimestampLtz";
+const TIMESTAMP_TZ_NEWTYPE: &str = "$snowflake_connector_rs::TimestampTz";
+
+/// The names the values [`Bind`] wraps serialize as, for [`ValueSerializer`] to bind them as
+/// their types.
+const TEMPORAL_NEWTYPES: [(&str, BindType); 5] = [
+    (DATE_NEWTYPE, BindType::Date),
+    (TIME_NEWTYPE, BindType::Time),
+    (TIMESTAMP_NTZ_NEWTYPE, BindType::TimestampNtz),
+    (TIMESTAMP_LTZ_NEWTYPE, BindType::TimestampLtz),
+    (TIMESTAMP_TZ_NEWTYPE, BindType::TimestampTz),
+];
+
+fn epoch_nanos(instant: DateTime<Utc>) -> i128 {
+    i128::from(instant.timestamp()) * NANOS_PER_SEC + i128::from(instant.timestamp_subsec_nanos())
+}
+
+impl Serialize for Bind<NaiveDate> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let millis = self.0.and_time(NaiveTime::MIN).and_utc().timestamp_millis();
+        serializer.serialize_newtype_struct(DATE_NEWTYPE, &millis.to_string())
+    }
+}
+
+impl Serialize for Bind<NaiveTime> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let nanos = i128::from(self.0.num_seconds_from_midnight()) * NANOS_PER_SEC
+            + i128::from(self.0.nanosecond());
+        serializer.serialize_newtype_struct(TIME_NEWTYPE, &nanos.to_string())
+    }
+}
+
+impl Serialize for Bind<NaiveDateTime> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let nanos = epoch_nanos(self.0.and_utc());
+        serializer.serialize_newtype_struct(TIMESTAMP_NTZ_NEWTYPE, &nanos.to_string())
+    }
+}
+
+impl Serialize for Bind<DateTime<Utc>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let nanos = epoch_nanos(self.0);
+        serializer.serialize_newtype_struct(TIMESTAMP_LTZ_NEWTYPE, &nanos.to_string())
+    }
+}
+
+impl Serialize for Bind<DateTime<FixedOffset>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let nanos = epoch_nanos(self.0.to_utc());
+        let offset_minutes = self.0.offset().local_minus_utc() / 60;
+        let value = format!("{nanos} {}", offset_minutes + 1440);
+        serializer.serialize_newtype_struct(TIMESTAMP_TZ_NEWTYPE, &value)
+    }
+}
+
+impl Serialize for Bind<Vec<u8>> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl Serialize for Bind<&[u8]> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl<const N: usize> Serialize for Bind<[u8; N]> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use serde::{Serialize, Serializer};
+    use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+    use super::{
+        Bind, DATE_NEWTYPE, NANOS_PER_SEC, TIMESTAMP_NTZ_NEWTYPE, TIMESTAMP_TZ_NEWTYPE,
+        TIME_NEWTYPE,
+    };
+
+    impl Serialize for Bind<Date> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let days = (self.0 - OffsetDateTime::UNIX_EPOCH.date()).whole_days();
+            serializer.serialize_newtype_struct(DATE_NEWTYPE, &(days * 86_400_000).to_string())
+        }
+    }
+
+    impl Serialize for Bind<Time> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let (hours, minutes, seconds, nanos) = self.0.as_hms_nano();
+            let secs = i128::from(hours) * 3600 + i128::from(minutes) * 60 + i128::from(seconds);
+            let nanos = secs * NANOS_PER_SEC + i128::from(nanos);
+            serializer.serialize_newtype_struct(TIME_NEWTYPE, &nanos.to_string())
+        }
+    }
+
+    impl Serialize for Bind<PrimitiveDateTime> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let nanos = self.0.assume_utc().unix_timestamp_nanos();
+            serializer.serialize_newtype_struct(TIMESTAMP_NTZ_NEWTYPE, &nanos.to_string())
+        }
+    }
+
+    impl Serialize for Bind<OffsetDateTime> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let nanos = self.0.unix_timestamp_nanos();
+            let offset_minutes = i32::from(self.0.offset().whole_minutes());
+            let value = format!("{nanos} {}", offset_minutes + 1440);
+            serializer.serialize_newtype_struct(TIMESTAMP_TZ_NEWTYPE, &value)
+        }
+    }
+}
+
+/// The bindings of a query request, keyed by the 1-based position of their placeholder.
+pub(crate) type Bindings = BTreeMap<String, Binding>;
+
+/// The bindings of a statement in the order of their placeholders, which is not the order of
+/// their keys: the positions sort as text, `10` before `2`.
+pub(crate) fn in_placeholder_order(bindings: &Bindings) -> impl Iterator<Item = &Binding> {
+    (1..=bindings.len()).filter_map(|position| bindings.get(&position.to_string()))
+}
+
+/// Converts a value into what it is bound to a placeholder as.
+pub(crate) fn to_bind_value<T: Serialize + ?Sized>(value: &T) -> Result<BindValue> {
+    value.serialize(ValueSerializer).map_err(Error::from)
+}
+
+/// Converts a struct or a map into the values of its fields, named as they serialize.
+pub(crate) fn to_bind_row<T: Serialize + ?Sized>(value: &T) -> Result<Vec<(String, BindValue)>> {
+    value.serialize(RowSerializer).map_err(Error::from)
+}
+
+type BindResult<T> = std::result::Result<T, BindError>;
+
+/// Why a value could not be bound.
+#[derive(Debug)]
+pub(crate) struct BindError(String);
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BindError {}
+
+impl ser::Error for BindError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl From<BindError> for Error {
+    fn from(error: BindError) -> Self {
+        Error::Bind(error.0)
+    }
+}
+
+fn unsupported(what: &str) -> BindError {
+    BindError(format!("{what} cannot be bound to a placeholder"))
+}
+
+fn json_error(error: serde_json::Error) -> BindError {
+    BindError(format!("JSON: {error}"))
+}
+
+fn json_value(value: serde_json::Value) -> BindValue {
+    BindValue::new(BindType::Json, value)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+/// The bytes of the hex text [`hex`] writes.
+fn unhex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A map bound as JSON, built by serde_json's own serializer.
+struct JsonMap(<JsonSerializer as Serializer>::SerializeMap);
+
+type JsonSerializer = serde_json::value::Serializer;
+
+/// A sequence or a tuple bound as JSON, built by serde_json's own serializer.
+struct JsonSeq(<JsonSerializer as Serializer>::SerializeSeq);
+
+impl SerializeSeq for JsonSeq {
+    type Ok = BindValue;
+    type Error = BindError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> BindResult<()> {
+        self.0.serialize_element(value).map_err(json_error)
+    }
+
+    fn end(self) -> BindResult<BindValue> {
+        SerializeSeq::end(self.0)
+            .map(json_value)
+            .map_err(json_error)
+    }
+}
+
+impl ser::SerializeTuple for JsonSeq {
+    type Ok = BindValue;
+    type Error = BindError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> BindResult<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> BindResult<BindValue> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeMap for JsonMap {
+    type Ok = BindValue;
+    type Error = BindError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> BindResult<()> {
+        self.0.serialize_key(key).map_err(json_error)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> BindResult<()> {
+        self.0.serialize_value(value).map_err(json_error)
+    }
+
+    fn end(self) -> BindResult<BindValue> {
+        SerializeMap::end(self.0)
+            .map(json_value)
+            .map_err(json_error)
+    }
+}
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = BindValue;
+    type Error = BindError;
+    type SerializeSeq = JsonSeq;
+    type SerializeTuple = JsonSeq;
+    type SerializeTupleStruct = Impossible<BindValue, BindError>;
+    type SerializeTupleVariant = Impossible<BindValue, BindError>;
+    type SerializeMap = JsonMap;
+    type SerializeStruct = Impossible<BindValue, BindError>;
+    type SerializeStructVariant = Impossible<BindValue, BindError>;
+
+    fn serialize_bool(self, v: bool) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Boolean, v))
+    }
+
+    fn serialize_i8(self, v: i8) -> BindResult<BindValue> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> BindResult<BindValue> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> BindResult<BindValue> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Fixed, v))
+    }
+
+    fn serialize_i128(self, v: i128) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Fixed, v))
+    }
+
+    fn serialize_u8(self, v: u8) -> BindResult<BindValue> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> BindResult<BindValue> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> BindResult<BindValue> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Fixed, v))
+    }
+
+    fn serialize_u128(self, v: u128) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Fixed, v))
+    }
+
+    fn serialize_f32(self, v: f32) -> BindResult<BindValue> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> BindResult<BindValue> {
+        // Snowflake reads the special values of a FLOAT by these names.
+        let value = match v {
+            v if v.is_nan() => "NaN".to_string(),
+            v if v.is_infinite() && v > 0.0 => "inf".to_string(),
+            v if v.is_infinite() => "-inf".to_string(),
+            v => v.to_string(),
+        };
+        Ok(BindValue::new(BindType::Real, value))
+    }
+
+    fn serialize_char(self, v: char) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Text, v))
+    }
+
+    fn serialize_str(self, v: &str) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Text, v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> BindResult<BindValue> {
+        Ok(BindValue::new(BindType::Binary, hex(v)))
+    }
+
+    fn serialize_none(self) -> BindResult<BindValue> {
+        Ok(BindValue::null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> BindResult<BindValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> BindResult<BindValue> {
+        Ok(BindValue::null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> BindResult<BindValue> {
+        Ok(BindValue::null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> BindResult<BindValue> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> BindResult<BindValue> {
+        if name == JSON_NEWTYPE {
+            return serde_json::to_string(value)
+                .map(|json| BindValue::new(BindType::Json, json))
+                .map_err(json_error);
+        }
+        if let Some((_, bind_type)) = TEMPORAL_NEWTYPES.iter().find(|(n, _)| *n == name) {
+            let value = value.serialize(self)?;
+            return Ok(BindValue {
+                bind_type: *bind_type,
+                ..value
+            });
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> BindResult<BindValue> {
+        Err(unsupported(&format!(
+            "the enum variant {variant} with a value"
+        )))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> BindResult<Self::SerializeSeq> {
+        let seq = serde_json::value::Serializer
+            .serialize_seq(len)
+            .map_err(json_error)?;
+        Ok(JsonSeq(seq))
+    }
+
+    fn serialize_tuple(self, len: usize) -> BindResult<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeTupleStruct> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeTupleVariant> {
+        Err(unsupported(variant))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> BindResult<Self::SerializeMap> {
+        let map = serde_json::value::Serializer
+            .serialize_map(len)
+            .map_err(json_error)?;
+        Ok(JsonMap(map))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeStruct> {
+        Err(BindError(format!(
+            "{name} cannot be bound to a placeholder, other than as JSON with Json"
+        )))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeStructVariant> {
+        Err(unsupported(variant))
+    }
+}
+
+/// Serializes a struct or a map into its named values.
+struct RowSerializer;
+
+#[derive(Default)]
+struct RowFields {
+    fields: Vec<(String, BindValue)>,
+    key: Option<String>,
+}
+
+fn not_a_row() -> BindError {
+    BindError("a row must serialize as a struct or a map".to_string())
+}
+
+impl Serializer for RowSerializer {
+    type Ok = Vec<(String, BindValue)>;
+    type Error = BindError;
+    type SerializeSeq = Impossible<Self::Ok, BindError>;
+    type SerializeTuple = Impossible<Self::Ok, BindError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, BindError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, BindError>;
+    type SerializeMap = RowFields;
+    type SerializeStruct = RowFields;
+    type SerializeStructVariant = Impossible<Self::Ok, BindError>;
+
+    fn serialize_map(self, len: Option<usize>) -> BindResult<RowFields> {
+        Ok(RowFields {
+            fields: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> BindResult<RowFields> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> BindResult<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> BindResult<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_i8(self, _v: i8) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_i16(self, _v: i16) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_i32(self, _v: i32) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_i64(self, _v: i64) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_u8(self, _v: u8) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_u16(self, _v: u16) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_u32(self, _v: u32) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_u64(self, _v: u64) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_f32(self, _v: f32) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_f64(self, _v: f64) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_char(self, _v: char) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_str(self, _v: &str) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_none(self) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_unit(self) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> BindResult<Self::Ok> {
+        Err(not_a_row())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> BindResult<Self::SerializeSeq> {
+        Err(not_a_row())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> BindResult<Self::SerializeTuple> {
+        Err(not_a_row())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeTupleStruct> {
+        Err(not_a_row())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeTupleVariant> {
+        Err(not_a_row())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> BindResult<Self::SerializeStructVariant> {
+        Err(not_a_row())
+    }
+}
+
+impl RowFields {
+    fn push<T: Serialize + ?Sized>(&mut self, name: String, value: &T) -> BindResult<()> {
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|e| BindError(format!("field {name}: {e}")))?;
+        self.fields.push((name, value));
+        Ok(())
+    }
+}
+
+impl SerializeStruct for RowFields {
+    type Ok = Vec<(String, BindValue)>;
+    type Error = BindError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> BindResult<()> {
+        self.push(key.to_string(), value)
+    }
+
+    fn end(self) -> BindResult<Self::Ok> {
+        Ok(self.fields)
+    }
+}
+
+impl SerializeMap for RowFields {
+    type Ok = Vec<(String, BindValue)>;
+    type Error = BindError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> BindResult<()> {
+        let key = key.serialize(ValueSerializer)?;
+        match (key.bind_type, key.value) {
+            (BindType::Text, Some(key)) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(BindError("the keys of a row must be strings".to_string())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> BindResult<()> {
+        let key = self.key.take().unwrap_or_default();
+        self.push(key, value)
+    }
+
+    fn end(self) -> BindResult<Self::Ok> {
+        Ok(self.fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Bytes that serialize as bytes rather than as a sequence.
+    struct Bytes(&'static [u8]);
+
+    impl Serialize for Bytes {
+        fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    #[test]
+    fn test_bind_values() -> Result<()> {
+        let cases = [
+            (to_bind_value(&-42i32)?, BindType::Fixed, Some("-42")),
+            (
+                to_bind_value(&u64::MAX)?,
+                BindType::Fixed,
+                Some("18446744073709551615"),
+            ),
+            (to_bind_value(&1.5f64)?, BindType::Real, Some("1.5")),
+            (
+                to_bind_value(&f64::NEG_INFINITY)?,
+                BindType::Real,
+                Some("-inf"),
+            ),
+            (to_bind_value(&true)?, BindType::Boolean, Some("true")),
+            (to_bind_value("it's")?, BindType::Text, Some("it's")),
+            (to_bind_value(&Some("x"))?, BindType::Text, Some("x")),
+            (to_bind_value(&None::<i64>)?, BindType::Text, None),
+            (
+                to_bind_value(&Bytes(&[0x0f, 0xa0]))?,
+                BindType::Binary,
+                Some("0FA0"),
+            ),
+        ];
+        for (value, bind_type, text) in cases {
+            assert_eq!((value.bind_type, value.value.as_deref()), (bind_type, text));
+        }
+        #[derive(Serialize)]
+        struct Point(i32, i32);
+        assert!(matches!(
+            to_bind_value(&Point(1, 2)),
+            Err(Error::Bind(message)) if message == "Point cannot be bound to a placeholder"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_json() -> Result<()> {
+        #[derive(Serialize)]
+        struct Payload {
+            kind: &'static str,
+            tags: Vec<&'static str>,
+        }
+        let json = |value: BindValue| {
+            assert_eq!(value.bind_type, BindType::Json);
+            serde_json::from_str::<serde_json::Value>(&value.value.unwrap()).unwrap()
+        };
+        let nested = serde_json::json!({
+            "name": "it's \"quoted\"",
+            "city": "東京 🗼",
+            "items": [1, 2.5, null, {"deep": [true]}],
+        });
+        assert_eq!(json(to_bind_value(&nested)?), nested);
+        assert_eq!(json(to_bind_value(&vec![1, 2])?), serde_json::json!([1, 2]));
+        let payload = Payload {
+            kind: "click",
+            tags: vec!["a"],
+        };
+        assert_eq!(
+            json(to_bind_value(&Json(payload))?),
+            serde_json::json!({"kind": "click", "tags": ["a"]})
+        );
+        assert_eq!(
+            json(to_bind_value(&Json(serde_json::Value::Null))?),
+            serde_json::Value::Null
+        );
+        assert_eq!(to_bind_value(&serde_json::Value::Null)?, BindValue::null());
+        assert_eq!(
+            to_bind_value(&serde_json::json!("x"))?,
+            BindValue::new(BindType::Text, "x")
+        );
+        assert!(matches!(
+            to_bind_value(&Payload { kind: "", tags: vec![] }),
+            Err(Error::Bind(message))
+                if message == "Payload cannot be bound to a placeholder, other than as JSON with Json"
+        ));
+        assert_eq!(
+            serde_json::to_string(&Json(Payload {
+                kind: "click",
+                tags: vec![]
+            }))
+            .unwrap(),
+            r#"{"kind":"click","tags":[]}"#
+        );
+
+        assert_eq!(
+            to_bind_value(&serde_json::json!({"a": "it's"}))?.to_sql_literal(),
+            r#"PARSE_JSON('{"a":"it''s"}')"#
+        );
+        let column = Binding::many(vec![
+            to_bind_value(&serde_json::json!("x"))?,
+            to_bind_value(&serde_json::json!({"a": 1}))?,
+            to_bind_value(&serde_json::json!(1.5))?,
+            BindValue::null(),
+        ]);
+        assert_eq!(
+            serde_json::to_value(column).unwrap(),
+            serde_json::json!({"type": "TEXT", "value": ["\"x\"", r#"{"a":1}"#, "1.5", null]})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_bytes() -> Result<()> {
+        let bytes = vec![0x0f_u8, 0xa0];
+        let binary = |text: &str| BindValue::new(BindType::Binary, text);
+        let json = |text: &str| BindValue::new(BindType::Json, text);
+        assert_eq!(to_bind_value(&Bind(bytes.clone()))?, binary("0FA0"));
+        assert_eq!(to_bind_value(&Bind(bytes.as_slice()))?, binary("0FA0"));
+        assert_eq!(to_bind_value(&Bind([0xff_u8; 3]))?, binary("FFFFFF"));
+        assert_eq!(to_bind_value(&Bind(Vec::new()))?, binary(""));
+        assert_eq!(to_bind_value(&Some(Bind(vec![1_u8])))?, binary("01"));
+        #[cfg(feature = "bytes")]
+        assert_eq!(
+            to_bind_value(&bytes::Bytes::from_static(&[0x0f, 0xa0]))?,
+            binary("0FA0")
+        );
+
+        // Sequences bind as JSON, whatever their elements, and so does an empty one.
+        assert_eq!(to_bind_value(&bytes)?, json("[15,160]"));
+        assert_eq!(to_bind_value(&Vec::<u8>::new())?, json("[]"));
+        assert_eq!(to_bind_value(&Vec::<String>::new())?, json("[]"));
+        assert_eq!(to_bind_value(&serde_json::json!([]))?, json("[]"));
+        assert_eq!(to_bind_value(&()).map(|value| value.value)?, None);
+
+        let column = Binding::many(vec![
+            to_bind_value(&Bind(Vec::new()))?,
+            BindValue::null(),
+            to_bind_value(&Bind(bytes.clone()))?,
+        ]);
+        assert_eq!(
+            serde_json::to_value(column).unwrap(),
+            serde_json::json!({"type": "BINARY", "value": ["", null, "0FA0"]})
+        );
+        let column = Binding::many(vec![
+            to_bind_value(&Vec::<i64>::new())?,
+            to_bind_value(&Vec::<i64>::new())?,
+        ]);
+        assert_eq!(
+            serde_json::to_value(column).unwrap(),
+            serde_json::json!({"type": "TEXT", "value": ["[]", "[]"]})
+        );
+        let column = Binding::many(vec![
+            to_bind_value(&Bind(bytes))?,
+            to_bind_value(&serde_json::json!({"a": 1}))?,
+        ]);
+        assert_eq!(
+            serde_json::to_value(column).unwrap()["value"],
+            serde_json::json!(["[15,160]", r#"{"a":1}"#])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_temporal() -> Result<()> {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let time = |h, m, s, nano| NaiveTime::from_hms_nano_opt(h, m, s, nano).unwrap();
+        let bound = |value: BindValue| (value.bind_type, value.value.unwrap());
+        let text = |value: &str| value.to_string();
+
+        assert_eq!(
+            bound(to_bind_value(&Bind(date(2024, 1, 31)))?),
+            (BindType::Date, text("1706659200000"))
+        );
+        assert_eq!(
+            bound(to_bind_value(&Bind(date(1969, 12, 31)))?),
+            (BindType::Date, text("-86400000"))
+        );
+        assert_eq!(
+            bound(to_bind_value(&Bind(time(12, 34, 56, 123_456_789)))?),
+            (BindType::Time, text("45296123456789"))
+        );
+        // Unwrapped, with chrono's serde feature, a date is bound as the text Snowflake casts.
+        assert_eq!(
+            bound(to_bind_value(&date(2024, 1, 31))?),
+            (BindType::Text, text("2024-01-31"))
+        );
+        let ntz = date(1969, 12, 31).and_time(time(23, 59, 59, 500_000_000));
+        assert_eq!(
+            bound(to_bind_value(&Bind(ntz))?),
+            (BindType::TimestampNtz, text("-500000000"))
+        );
+        let utc = date(2023, 11, 14).and_time(time(22, 13, 20, 1)).and_utc();
+        assert_eq!(
+            bound(to_bind_value(&Bind(utc))?),
+            (BindType::TimestampLtz, text("1700000000000000001"))
+        );
+        let tokyo = utc.with_timezone(&FixedOffset::east_opt(9 * 3600).unwrap());
+        assert_eq!(
+            bound(to_bind_value(&Bind(tokyo))?),
+            (BindType::TimestampTz, text("1700000000000000001 1980"))
+        );
+        let far = date(2500, 1, 1).and_time(NaiveTime::MIN);
+        assert_eq!(bound(to_bind_value(&Bind(far))?).1, "16725225600000000000");
+
+        let column = Binding::many(vec![BindValue::null(), to_bind_value(&Bind(utc))?]);
+        assert_eq!(
+            serde_json::to_value(column).unwrap()["type"],
+            "TIMESTAMP_LTZ"
+        );
+
+        let literal = |value: BindValue| value.to_sql_literal();
+        assert_eq!(
+            literal(to_bind_value(&Bind(date(1969, 12, 31)))?),
+            "'1969-12-31'::DATE"
+        );
+        assert_eq!(
+            literal(to_bind_value(&Bind(time(12, 34, 56, 5)))?),
+            "'12:34:56.000000005'::TIME"
+        );
+        assert_eq!(
+            literal(to_bind_value(&Bind(ntz))?),
+            "'1969-12-31 23:59:59.500000000'::TIMESTAMP_NTZ"
+        );
+        assert_eq!(
+            literal(to_bind_value(&Bind(utc))?),
+            "'2023-11-14 22:13:20.000000001 +00:00'::TIMESTAMP_LTZ"
+        );
+        assert_eq!(
+            literal(to_bind_value(&Bind(tokyo))?),
+            "'2023-11-15 07:13:20.000000001 +09:00'::TIMESTAMP_TZ"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_bind_time_crate() -> Result<()> {
+        use time::{Date, Month, PrimitiveDateTime, Time, UtcOffset};
+
+        let date = |y, m, d| Date::from_calendar_date(y, m, d).unwrap();
+        let time = |h, m, s, nano| Time::from_hms_nano(h, m, s, nano).unwrap();
+        let bound = |value: BindValue| (value.bind_type, value.value.unwrap());
+        let text = |value: &str| value.to_string();
+
+        assert_eq!(
+            bound(to_bind_value(&Bind(date(2024, Month::January, 31)))?),
+            (BindType::Date, text("1706659200000"))
+        );
+        assert_eq!(
+            bound(to_bind_value(&Bind(date(1969, Month::December, 31)))?),
+            (BindType::Date, text("-86400000"))
+        );
+        assert_eq!(
+            bound(to_bind_value(&Bind(time(12, 34, 56, 123_456_789)))?),
+            (BindType::Time, text("45296123456789"))
+        );
+        let ntz = PrimitiveDateTime::new(
+            date(1969, Month::December, 31),
+            time(23, 59, 59, 500_000_000),
+        );
+        assert_eq!(
+            bound(to_bind_value(&Bind(ntz))?),
+            (BindType::TimestampNtz, text("-500000000"))
+        );
+        let utc = PrimitiveDateTime::new(date(2023, Month::November, 14), time(22, 13, 20, 1))
+            .assume_utc();
+        let tokyo = utc.to_offset(UtcOffset::from_hms(9, 0, 0).unwrap());
+        assert_eq!(
+            bound(to_bind_value(&Bind(tokyo))?),
+            (BindType::TimestampTz, text("1700000000000000001 1980"))
+        );
+        assert_eq!(
+            to_bind_value(&Bind(tokyo))?.to_sql_literal(),
+            "'2023-11-15 07:13:20.000000001 +09:00'::TIMESTAMP_TZ"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sql_literals() -> Result<()> {
+        let literal = |value: BindValue| value.to_sql_literal();
+        assert_eq!(literal(to_bind_value(&-42i64)?), "-42");
+        assert_eq!(literal(to_bind_value(&1.5f64)?), "'1.5'::FLOAT");
+        assert_eq!(literal(to_bind_value(&f64::NAN)?), "'NaN'::FLOAT");
+        assert_eq!(literal(to_bind_value(&false)?), "FALSE");
+        assert_eq!(literal(to_bind_value(r"it's C:\")?), r"'it''s C:\\'");
+        assert_eq!(literal(to_bind_value(&None::<&str>)?), "NULL");
+        assert_eq!(
+            literal(to_bind_value(&Bytes(&[0x0f, 0xa0]))?),
+            "TO_BINARY('0FA0', 'HEX')"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_rows() -> Result<()> {
+        #[derive(Serialize)]
+        struct Event<'a> {
+            id: i64,
+            #[serde(rename = "Label")]
+            label: Option<&'a str>,
+        }
+        let row = to_bind_row(&Event { id: 7, label: None })?;
+        assert_eq!(
+            row,
+            [
+                ("id".to_string(), BindValue::new(BindType::Fixed, 7)),
+                ("Label".to_string(), BindValue::null()),
+            ]
+        );
+
+        let map = BTreeMap::from([("a", 1)]);
+        assert_eq!(to_bind_row(&map)?[0].0, "a");
+        assert!(matches!(
+            to_bind_row(&BTreeMap::from([(1, 1)])),
+            Err(Error::Bind(message)) if message == "the keys of a row must be strings"
+        ));
+        assert!(matches!(to_bind_row(&5), Err(Error::Bind(_))));
+
+        let column = Binding::many(vec![BindValue::null(), BindValue::new(BindType::Fixed, 2)]);
+        assert_eq!(
+            serde_json::to_value(column).unwrap(),
+            serde_json::json!({"type": "FIXED", "value": [null, "2"]})
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! A blocking API over the async one, for programs that do not otherwise use async Rust.
+//!
+//! [`SnowflakeClient`] and [`SnowflakeSession`] mirror their async counterparts and take the
+//! same configuration, authentication methods and row types. The client owns a current-thread
+//! Tokio runtime, shared with its sessions, that runs each call to completion.
+//!
+//! These types must not be used from within an async runtime: blocking a runtime thread on
+//! another runtime can deadlock, so every call panics there instead. Use the async API there.
+//!
+//! ```rust,no_run
+//! # use snowflake_connector_rs::{blocking::SnowflakeClient, Result, SnowflakeAuthMethod, SnowflakeClientConfig};
+//! # fn run() -> Result<()> {
+//! let client = SnowflakeClient::new(
+//!     "USERNAME",
+//!     SnowflakeAuthMethod::Password("PASSWORD".into()),
+//!     SnowflakeClientConfig {
+//!         account: "ACCOUNT".to_string(),
+//!         ..Default::default()
+//!     },
+//! )?;
+//! let session = client.create_session()?;
+//! let rows = session.query("SELECT 1 AS ONE")?;
+//! assert_eq!(rows[0].get::<i64>("ONE")?, 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use serde::de::DeserializeOwned;
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::{
+    FromRow, FromTuple, QueryRequest, QueryResult, QueryStats, Result, RowStream, ServerVersion,
+    SnowflakeAuthMethod, SnowflakeClientConfig, SnowflakeRow,
+};
+
+/// The blocking counterpart of [`crate::SnowflakeClient`].
+#[derive(Debug)]
+pub struct SnowflakeClient {
+    client: crate::SnowflakeClient,
+    runtime: Arc<Runtime>,
+}
+
+impl SnowflakeClient {
+    /// Creates a client, as [`crate::SnowflakeClient::new`] does, with the runtime its calls
+    /// run on.
+    ///
+    /// # Panics
+    ///
+    /// If called from within an async runtime.
+    pub fn new(
+        username: &str,
+        auth: SnowflakeAuthMethod,
+        config: SnowflakeClientConfig,
+    ) -> Result<Self> {
+        assert_not_async();
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self {
+            client: crate::SnowflakeClient::new(username, auth, config)?,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Logs in and creates a session, as [`crate::SnowflakeClient::create_session`] does.
+    pub fn create_session(&self) -> Result<SnowflakeSession> {
+        let session = block_on(&self.runtime, self.client.create_session())?;
+        Ok(SnowflakeSession {
+            session,
+            runtime: Arc::clone(&self.runtime),
+        })
+    }
+}
+
+/// The blocking counterpart of [`crate::SnowflakeSession`].
+#[derive(Debug)]
+pub struct SnowflakeSession {
+    session: crate::SnowflakeSession,
+    runtime: Arc<Runtime>,
+}
+
+impl SnowflakeSession {
+    /// Runs a query and returns all of its rows, as [`crate::SnowflakeSession::query`] does.
+    pub fn query<Q: Into<QueryRequest>>(&self, request: Q) -> Result<QueryResult> {
+        block_on(&self.runtime, self.session.query(request))
+    }
+
+    /// Runs a query and returns an iterator over its rows, which downloads the result chunks
+    /// as they are read, as [`crate::SnowflakeSession::query_stream`] does.
+    pub fn query_iter<Q: Into<QueryRequest>>(&self, request: Q) -> Result<RowIter> {
+        let stream = block_on(&self.runtime, self.session.query_stream(request))?;
+        Ok(RowIter {
+            stream,
+            runtime: Arc::clone(&self.runtime),
+        })
+    }
+
+    /// Runs a query and deserializes every row into `T`, as
+    /// [`crate::SnowflakeSession::query_as`] does.
+    pub fn query_as<T: DeserializeOwned>(
+        &self,
+        request: impl Into<QueryRequest>,
+    ) -> Result<Vec<T>> {
+        block_on(&self.runtime, self.session.query_as(request))
+    }
+
+    /// Runs a query and builds a `T` from every row with [`FromRow`], as
+    /// [`crate::SnowflakeSession::query_typed`] does.
+    pub fn query_typed<T: FromRow>(&self, request: impl Into<QueryRequest>) -> Result<Vec<T>> {
+        block_on(&self.runtime, self.session.query_typed(request))
+    }
+
+    /// Runs a query and decodes every row into a tuple, blocking until done, as
+    /// [`crate::SnowflakeSession::query_tuples`] does.
+    pub fn query_tuples<T: FromTuple>(&self, request: impl Into<QueryRequest>) -> Result<Vec<T>> {
+        block_on(&self.runtime, self.session.query_tuples(request))
+    }
+
+    /// See [`crate::SnowflakeSession::last_query_stats`].
+    pub fn last_query_stats(&self) -> Option<QueryStats> {
+        self.session.last_query_stats()
+    }
+
+    /// See [`crate::SnowflakeSession::server_version`].
+    pub fn server_version(&self) -> Option<ServerVersion> {
+        self.session.server_version()
+    }
+
+    /// See [`crate::SnowflakeSession::server_parameters`].
+    pub fn server_parameters(&self) -> HashMap<String, serde_json::Value> {
+        self.session.server_parameters()
+    }
+}
+
+/// The rows of a query, returned by [`SnowflakeSession::query_iter`]. Each item is a row or the
+/// error that ended the result, as [`RowStream::next_row`] returns them.
+pub struct RowIter {
+    stream: RowStream,
+    runtime: Arc<Runtime>,
+}
+
+impl RowIter {
+    /// The names of the columns, in order.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.stream.column_names()
+    }
+
+    /// See [`RowStream::stats`].
+    pub fn stats(&self) -> QueryStats {
+        self.stream.stats()
+    }
+}
+
+impl Iterator for RowIter {
+    type Item = Result<SnowflakeRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        block_on(&self.runtime, self.stream.next_row())
+    }
+}
+
+fn block_on<F: Future>(runtime: &Runtime, future: F) -> F::Output {
+    assert_not_async();
+    runtime.block_on(future)
+}
+
+/// Panics with an explanation within an async runtime, where blocking on a future would stall
+/// the runtime's thread or deadlock.
+fn assert_not_async() {
+    if Handle::try_current().is_ok() {
+        panic!(
+            "the blocking Snowflake API was called from within an async runtime, where it \
+             would block or deadlock the runtime; use the async API instead"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(account: &str) -> Result<SnowflakeClient> {
+        let config = SnowflakeClientConfig {
+            account: account.into(),
+            ..Default::default()
+        };
+        SnowflakeClient::new("user", SnowflakeAuthMethod::Password("p".into()), config)
+    }
+
+    #[test]
+    fn test_blocking_errors() -> Result<()> {
+        // An account that makes an invalid URL fails the login before anything is sent.
+        let client = client("not an account")?;
+        assert!(client.create_session().is_err());
+        assert!(client.create_session().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "called from within an async runtime")]
+    async fn test_blocking_in_async_runtime() {
+        let _ = client("acct");
+    }
+}
@@ -0,0 +1,655 @@
+//! Loading large numbers of rows into a table through a stage, with `PUT` and `COPY INTO`.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    pin::pin,
+};
+
+use flate2::{write::GzEncoder, Compression};
+use futures_core::Stream;
+use serde::Serialize;
+
+use crate::{
+    bind::{to_bind_row, BindValue},
+    catalog::{parse_object_name, quote_object_parts},
+    export::{CsvOptions, CsvWriter},
+    insert::{column_identifier, field_name},
+    spill::with_path,
+    transfer::all_transferred,
+    Error, PutOptions, Result, SnowflakeRow, SnowflakeSession,
+};
+
+/// The file format of the stage the files are loaded from: the CSV that [`CsvWriter`] writes,
+/// where an empty field is NULL and a quoted empty field the empty string.
+const FILE_FORMAT: &str = "TYPE = CSV COMPRESSION = GZIP RECORD_DELIMITER = '\\r\\n' \
+                           FIELD_OPTIONALLY_ENCLOSED_BY = '\"' ESCAPE_UNENCLOSED_FIELD = NONE \
+                           EMPTY_FIELD_AS_NULL = TRUE";
+
+/// How [`SnowflakeSession::bulk_load`] loads rows.
+#[derive(Debug, Clone)]
+pub struct BulkLoadOptions {
+    /// The columns to load, as for [`InsertBuilder::columns`](crate::InsertBuilder::columns).
+    /// Defaults to every field of the first row.
+    pub columns: Option<Vec<String>>,
+
+    /// The most bytes of CSV, before compression, written into one file. Snowflake loads
+    /// files in parallel, and loads best from files of 100 to 250 MB compressed. Defaults to
+    /// 256 MiB.
+    pub file_size: u64,
+
+    /// What `COPY INTO` does with a file that has rows it cannot load. Defaults to
+    /// [`OnError::AbortStatement`].
+    pub on_error: OnError,
+
+    /// The directory in which the files are written before they are uploaded, in a
+    /// subdirectory of their own. Defaults to the system's temporary directory.
+    pub directory: PathBuf,
+}
+
+impl Default for BulkLoadOptions {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            file_size: 256 << 20,
+            on_error: OnError::AbortStatement,
+            directory: std::env::temp_dir(),
+        }
+    }
+}
+
+/// The `ON_ERROR` option of `COPY INTO`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Loads nothing if any row cannot be loaded; the load fails with the [`Error::Sql`] of
+    /// the first such row, which names its file and line.
+    AbortStatement,
+    /// Loads the rows that can be loaded, and skips the others.
+    Continue,
+    /// Loads nothing from a file with a row that cannot be loaded.
+    SkipFile,
+}
+
+impl OnError {
+    fn as_sql(self) -> &'static str {
+        match self {
+            OnError::AbortStatement => "ABORT_STATEMENT",
+            OnError::Continue => "CONTINUE",
+            OnError::SkipFile => "SKIP_FILE",
+        }
+    }
+}
+
+/// What [`SnowflakeSession::bulk_load`] loaded, file by file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BulkLoadReport {
+    /// The files the rows were written to, in the order of the rows.
+    pub files: Vec<FileLoad>,
+}
+
+impl BulkLoadReport {
+    /// The rows loaded into the table.
+    pub fn rows_loaded(&self) -> u64 {
+        self.files.iter().map(|file| file.rows_loaded).sum()
+    }
+
+    /// Whether every row was loaded.
+    pub fn is_complete(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// The files with rows that were not loaded.
+    pub fn failures(&self) -> impl Iterator<Item = &FileLoad> {
+        self.files.iter().filter(|file| {
+            file.status != FileLoadStatus::Loaded
+                || file.rows_loaded < file.rows.end - file.rows.start
+        })
+    }
+}
+
+/// How one file of a [`BulkLoadReport`] was loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileLoad {
+    /// The name of the file on the stage.
+    pub file: String,
+    /// The positions of the rows written to the file, counted from 0 in the order they were
+    /// given.
+    pub rows: Range<u64>,
+    pub status: FileLoadStatus,
+    pub rows_parsed: u64,
+    pub rows_loaded: u64,
+    /// The rows that could not be loaded.
+    pub errors_seen: u64,
+    /// The first row that could not be loaded.
+    pub first_error: Option<FileLoadError>,
+}
+
+/// The outcome of `COPY INTO` for a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileLoadStatus {
+    Loaded,
+    PartiallyLoaded,
+    LoadFailed,
+    /// `COPY INTO` did not report on the file.
+    NotLoaded,
+    /// A status without a variant of its own, as Snowflake names it.
+    Other(String),
+}
+
+/// The first error of a file that was not wholly loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileLoadError {
+    pub message: String,
+    /// The line of the file the error is on, counted from 1.
+    pub line: Option<u64>,
+    /// The position of the character the error is at in its line, counted from 1.
+    pub character: Option<u64>,
+    /// The column the error is in, e.g. `"ORDERS"["AMOUNT":3]`.
+    pub column_name: Option<String>,
+    /// The position of the row, as in [`FileLoad::rows`], where it follows from the line: when
+    /// no value in the file spans lines.
+    pub row: Option<u64>,
+}
+
+impl SnowflakeSession {
+    /// Loads rows into a table through a temporary stage, which suits far more rows than
+    /// [`SnowflakeSession::insert_into`] does.
+    ///
+    /// The rows are serialized as [`InsertBuilder`](crate::InsertBuilder) serializes them, and
+    /// written to gzipped CSV files of about [`BulkLoadOptions::file_size`] bytes each. The
+    /// files are uploaded with [`SnowflakeSession::put`] and loaded with one `COPY INTO`
+    /// statement. Then the stage and the local files are removed, also when the load fails.
+    ///
+    /// The report tells, for each file, which rows were written to it, how many were loaded,
+    /// and the first error; with [`OnError::Continue`] or [`OnError::SkipFile`], check
+    /// [`BulkLoadReport::is_complete`].
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{BulkLoadOptions, OnError, Result, SnowflakeSession};
+    /// #[derive(serde::Serialize)]
+    /// struct Event {
+    ///     id: i64,
+    ///     kind: String,
+    /// }
+    ///
+    /// # async fn run(session: &SnowflakeSession, events: impl futures_core::Stream<Item = Event>) -> Result<()> {
+    /// let options = BulkLoadOptions {
+    ///     on_error: OnError::Continue,
+    ///     ..Default::default()
+    /// };
+    /// let report = session.bulk_load("events", events, &options).await?;
+    /// for file in report.failures() {
+    ///     println!("rows {:?}: {:?}", file.rows, file.first_error);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn bulk_load<T: Serialize>(
+        &self,
+        table: &str,
+        rows: impl Stream<Item = T>,
+        options: &BulkLoadOptions,
+    ) -> Result<BulkLoadReport> {
+        let table = parse_object_name(table)?;
+        let directory = create_load_directory(options)?;
+        let result = self.load_from(&table, rows, options, &directory).await;
+        remove_load_directory(&directory, result)
+    }
+
+    async fn load_from<T: Serialize>(
+        &self,
+        table: &[String],
+        rows: impl Stream<Item = T>,
+        options: &BulkLoadOptions,
+        directory: &Path,
+    ) -> Result<BulkLoadReport> {
+        let csv = CsvOptions {
+            header: false,
+            ..Default::default()
+        };
+        let mut files = LoadFiles::new(directory, options, &csv);
+        let mut rows = pin!(rows);
+        while let Some(row) = std::future::poll_fn(|cx| rows.as_mut().poll_next(cx)).await {
+            files.push(to_bind_row(&row)?)?;
+        }
+        let (columns, files) = files.finish()?;
+        let Some(columns) = columns else {
+            return Ok(BulkLoadReport { files: vec![] });
+        };
+        let format = CopyFormat {
+            file_format: FILE_FORMAT,
+            pattern: "*.csv.gz",
+            columns: Some(columns),
+        };
+        self.copy_through_stage(table, &files, &format, options)
+            .await
+    }
+
+    /// Uploads the files of a load to a temporary stage and loads them into the table with
+    /// `COPY INTO`, then removes the stage, also when the load fails.
+    pub(crate) async fn copy_through_stage(
+        &self,
+        table: &[String],
+        files: &[LocalFile],
+        format: &CopyFormat,
+        options: &BulkLoadOptions,
+    ) -> Result<BulkLoadReport> {
+        if files.is_empty() {
+            return Ok(BulkLoadReport { files: vec![] });
+        }
+        // The stage goes in the schema of the table, or the current one.
+        let mut stage = table[..table.len() - 1].to_vec();
+        stage.push(format!("SNOWFLAKE_LOAD_{}", uuid::Uuid::new_v4().simple()).to_uppercase());
+        let stage = self
+            .create_temp_stage(&quote_object_parts(&stage), Some(format.file_format))
+            .await?;
+        let result = self.copy_files(table, files, &stage, format, options).await;
+        let removed = self.remove_from_stage(&format!("@{stage}")).await;
+        let dropped = self.query(format!("DROP STAGE IF EXISTS {stage}")).await;
+        let report = result?;
+        removed?;
+        dropped?;
+        Ok(report)
+    }
+
+    async fn copy_files(
+        &self,
+        table: &[String],
+        files: &[LocalFile],
+        stage: &str,
+        format: &CopyFormat,
+        options: &BulkLoadOptions,
+    ) -> Result<BulkLoadReport> {
+        let directory = files[0].path.parent().unwrap_or(Path::new("."));
+        // The stage is new, so there is nothing to check for files of the same name.
+        let put = PutOptions {
+            overwrite: true,
+            ..Default::default()
+        };
+        let uploaded = self
+            .put_files(&directory.join(format.pattern), &format!("@{stage}"), &put)
+            .await?;
+        all_transferred(uploaded)?;
+        let mut sql = format!("COPY INTO {}", quote_object_parts(table));
+        match &format.columns {
+            Some(columns) => sql.push_str(&format!(
+                " ({}) FROM @{stage}",
+                columns
+                    .iter()
+                    .map(|column| column_identifier(column))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            None => sql.push_str(&format!(
+                " FROM @{stage} MATCH_BY_COLUMN_NAME = CASE_INSENSITIVE"
+            )),
+        }
+        sql.push_str(&format!(" ON_ERROR = {}", options.on_error.as_sql()));
+        let results = self.query(sql).await?;
+        report(files, &results)
+    }
+}
+
+/// Creates the directory a load writes its files in, under [`BulkLoadOptions::directory`].
+pub(crate) fn create_load_directory(options: &BulkLoadOptions) -> Result<PathBuf> {
+    let directory = options
+        .directory
+        .join(format!("snowflake-load-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).map_err(|e| with_path(e, &directory))?;
+    Ok(directory)
+}
+
+/// Removes the directory of a load and returns the outcome of the load.
+pub(crate) fn remove_load_directory(
+    directory: &Path,
+    result: Result<BulkLoadReport>,
+) -> Result<BulkLoadReport> {
+    let removed = std::fs::remove_dir_all(directory).map_err(|e| with_path(e, directory));
+    let report = result?;
+    removed?;
+    Ok(report)
+}
+
+/// How the files of a load are staged and read by `COPY INTO`.
+pub(crate) struct CopyFormat {
+    /// The options of the stage's `FILE_FORMAT`.
+    pub(crate) file_format: &'static str,
+    /// The files to upload from the directory of the load, e.g. `*.csv.gz`.
+    pub(crate) pattern: &'static str,
+    /// The columns the fields of the files are loaded into, in order; `None` loads each field
+    /// into the column of its name, whatever the case.
+    pub(crate) columns: Option<Vec<String>>,
+}
+
+/// A file of rows written for a load.
+#[derive(Debug)]
+pub(crate) struct LocalFile {
+    pub(crate) path: PathBuf,
+    pub(crate) rows: Range<u64>,
+    /// Whether the lines of the file do not tell its rows, e.g. because a value spans lines.
+    pub(crate) multiline: bool,
+}
+
+/// Writes rows into gzipped CSV files, starting a new file when one reaches the file size.
+struct LoadFiles<'a> {
+    directory: &'a Path,
+    file_size: u64,
+    columns: Option<Vec<String>>,
+    csv: &'a CsvOptions,
+    files: Vec<LocalFile>,
+    writer: Option<CsvWriter<'a, Counted<GzEncoder<BufWriter<File>>>>>,
+    rows: u64,
+}
+
+impl<'a> LoadFiles<'a> {
+    fn new(directory: &'a Path, options: &BulkLoadOptions, csv: &'a CsvOptions) -> Self {
+        Self {
+            directory,
+            file_size: options.file_size.max(1),
+            columns: options.columns.clone(),
+            csv,
+            files: vec![],
+            writer: None,
+            rows: 0,
+        }
+    }
+
+    fn push(&mut self, mut row: Vec<(String, BindValue)>) -> Result<()> {
+        let columns = self
+            .columns
+            .get_or_insert_with(|| row.iter().map(|(name, _)| name.clone()).collect());
+        if columns.is_empty() {
+            return Err(Error::Bind("a load needs at least one column".to_string()));
+        }
+        let mut values = Vec::with_capacity(columns.len());
+        for column in columns.iter() {
+            let field_name = field_name(column);
+            let Some((_, value)) = row.iter_mut().find(|(name, _)| *name == field_name) else {
+                return Err(Error::Bind(format!(
+                    "row {} has no field {column}",
+                    self.rows
+                )));
+            };
+            values.push(value.value.take());
+        }
+
+        if self.writer.is_none() {
+            let path = self
+                .directory
+                .join(format!("data_{}.csv.gz", self.files.len()));
+            let file = File::create(&path).map_err(|e| with_path(e, &path))?;
+            let encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+            self.writer = Some(CsvWriter::new(Counted::new(encoder), self.csv)?);
+            self.files.push(LocalFile {
+                path,
+                rows: self.rows..self.rows,
+                multiline: false,
+            });
+        }
+        let (Some(writer), Some(file)) = (&mut self.writer, self.files.last_mut()) else {
+            unreachable!("a file is open");
+        };
+        writer.write_record(values.iter().map(Option::as_deref))?;
+        file.multiline |= values
+            .iter()
+            .flatten()
+            .any(|value| value.contains(['\n', '\r']));
+        self.rows += 1;
+        file.rows.end = self.rows;
+        if writer.get_ref().bytes >= self.file_size {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?.inner.finish()?.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Closes the last file and returns the columns and the files.
+    fn finish(mut self) -> Result<(Option<Vec<String>>, Vec<LocalFile>)> {
+        self.close()?;
+        Ok((self.columns.take(), std::mem::take(&mut self.files)))
+    }
+}
+
+/// A writer that counts the bytes written to it.
+struct Counted<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W> Counted<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Matches the rows of a `COPY INTO` result to the files the rows were written to.
+fn report(files: &[LocalFile], results: &[SnowflakeRow]) -> Result<BulkLoadReport> {
+    let mut loads = Vec::with_capacity(files.len());
+    for file in files {
+        let name = file
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let mut load = FileLoad {
+            file: name.clone(),
+            rows: file.rows.clone(),
+            status: FileLoadStatus::NotLoaded,
+            rows_parsed: 0,
+            rows_loaded: 0,
+            errors_seen: 0,
+            first_error: None,
+        };
+        // The result names the file with the stage or its path, e.g. `stage/data_0.csv.gz`.
+        let result = results.iter().find(|row| {
+            row.try_get::<Option<String>>("file")
+                .ok()
+                .flatten()
+                .flatten()
+                .is_some_and(|path| path == name || path.ends_with(&format!("/{name}")))
+        });
+        if let Some(row) = result {
+            let count = |column| -> Result<u64> {
+                Ok(row.try_get::<Option<u64>>(column)?.flatten().unwrap_or(0))
+            };
+            load.status = match row.get::<String>("status")?.as_str() {
+                "LOADED" => FileLoadStatus::Loaded,
+                "PARTIALLY_LOADED" => FileLoadStatus::PartiallyLoaded,
+                "LOAD_FAILED" => FileLoadStatus::LoadFailed,
+                status => FileLoadStatus::Other(status.to_string()),
+            };
+            load.rows_parsed = count("rows_parsed")?;
+            load.rows_loaded = count("rows_loaded")?;
+            load.errors_seen = count("errors_seen")?;
+            let message = row.try_get::<Option<String>>("first_error")?.flatten();
+            load.first_error = match message {
+                Some(message) => {
+                    let line = row.try_get::<Option<u64>>("first_error_line")?.flatten();
+                    Some(FileLoadError {
+                        message,
+                        line,
+                        character: row
+                            .try_get::<Option<u64>>("first_error_character")?
+                            .flatten(),
+                        column_name: row
+                            .try_get::<Option<String>>("first_error_column_name")?
+                            .flatten(),
+                        row: line
+                            .filter(|line| !file.multiline && *line > 0)
+                            .map(|line| file.rows.start + line - 1),
+                    })
+                }
+                None => None,
+            };
+        }
+        loads.push(load);
+    }
+    Ok(BulkLoadReport { files: loads })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::SnowflakeColumnType;
+
+    #[derive(Serialize)]
+    struct Event {
+        id: i64,
+        note: Option<&'static str>,
+    }
+
+    fn read(path: &Path) -> String {
+        let mut csv = String::new();
+        GzDecoder::new(File::open(path).unwrap())
+            .read_to_string(&mut csv)
+            .unwrap();
+        csv
+    }
+
+    #[test]
+    fn test_load_files() -> Result<()> {
+        let directory =
+            std::env::temp_dir().join(format!("snowflake-load-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory)?;
+        let options = BulkLoadOptions {
+            file_size: 10,
+            ..Default::default()
+        };
+        let csv = CsvOptions {
+            header: false,
+            ..Default::default()
+        };
+        let mut files = LoadFiles::new(&directory, &options, &csv);
+        let notes = [
+            Some("a \"quoted\" note"),
+            None,
+            Some(""),
+            Some("two\nlines"),
+        ];
+        for (id, note) in notes.into_iter().enumerate() {
+            files.push(to_bind_row(&Event {
+                id: id as i64,
+                note,
+            })?)?;
+        }
+        let (columns, files) = files.finish()?;
+        assert_eq!(columns, Some(vec!["id".to_string(), "note".to_string()]));
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| file.rows.clone())
+                .collect::<Vec<_>>(),
+            [0..1, 1..3, 3..4]
+        );
+        assert_eq!(read(&files[0].path), "0,\"a \"\"quoted\"\" note\"\r\n");
+        assert_eq!(read(&files[1].path), "1,\r\n2,\"\"\r\n");
+        assert!(files[2].multiline && !files[1].multiline);
+
+        let options = BulkLoadOptions {
+            columns: Some(vec!["missing".to_string()]),
+            ..Default::default()
+        };
+        let mut files = LoadFiles::new(&directory, &options, &csv);
+        let error = files.push(to_bind_row(&Event { id: 0, note: None })?);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "bind error: row 0 has no field missing"
+        );
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_report() -> Result<()> {
+        let text = || SnowflakeColumnType::new("text", None);
+        let fixed = || SnowflakeColumnType::new("fixed", Some(0));
+        let columns = [
+            ("file", text()),
+            ("status", text()),
+            ("rows_parsed", fixed()),
+            ("rows_loaded", fixed()),
+            ("errors_seen", fixed()),
+            ("first_error", text()),
+            ("first_error_line", fixed()),
+            ("first_error_character", fixed()),
+            ("first_error_column_name", text()),
+        ];
+        let row = |values: [Option<&str>; 9]| {
+            SnowflakeRow::from_values(
+                columns.clone(),
+                values.iter().map(|v| v.map(str::to_string)).collect(),
+            )
+        };
+        let results = [
+            row([
+                Some("snowflake_load_1/data_0.csv.gz"),
+                Some("LOADED"),
+                Some("10"),
+                Some("10"),
+                Some("0"),
+                None,
+                None,
+                None,
+                None,
+            ]),
+            row([
+                Some("snowflake_load_1/data_1.csv.gz"),
+                Some("PARTIALLY_LOADED"),
+                Some("10"),
+                Some("9"),
+                Some("1"),
+                Some("Numeric value 'x' is not recognized"),
+                Some("4"),
+                Some("1"),
+                Some("\"EVENTS\"[\"ID\":1]"),
+            ]),
+        ];
+        let file = |index: usize, rows: Range<u64>| LocalFile {
+            path: PathBuf::from(format!("/tmp/load/data_{index}.csv.gz")),
+            rows,
+            multiline: false,
+        };
+        let files = [file(0, 0..10), file(1, 10..20), file(2, 20..25)];
+        let report = report(&files, &results)?;
+        assert_eq!(report.rows_loaded(), 19);
+        assert!(!report.is_complete());
+        let failures = report.failures().collect::<Vec<_>>();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].status, FileLoadStatus::PartiallyLoaded);
+        let error = failures[0].first_error.as_ref().unwrap();
+        assert_eq!((error.line, error.row), (Some(4), Some(13)));
+        assert_eq!(error.column_name.as_deref(), Some("\"EVENTS\"[\"ID\":1]"));
+        assert_eq!(failures[1].file, "data_2.csv.gz");
+        assert_eq!(failures[1].status, FileLoadStatus::NotLoaded);
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+//! Databases, schemas, tables and columns, read with `SHOW` and `DESCRIBE`.
+
+use crate::{Error, FromRow, Result, SnowflakeRow, SnowflakeSession};
+
+/// A database, as listed by [`SnowflakeSession::list_databases`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DatabaseInfo {
+    pub name: String,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// A schema, as listed by [`SnowflakeSession::list_schemas`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SchemaInfo {
+    pub database_name: String,
+    pub name: String,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// A table, as listed by [`SnowflakeSession::list_tables`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TableInfo {
+    pub database_name: String,
+    pub schema_name: String,
+    pub name: String,
+    pub kind: TableKind,
+    /// The rows in the table; `None` where Snowflake does not count them, e.g. for external
+    /// tables.
+    pub rows: Option<u64>,
+    /// The bytes the table takes up; `None` where Snowflake does not count them.
+    pub bytes: Option<u64>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl TableInfo {
+    /// The quoted, fully qualified name of the table, e.g. `"DB"."PUBLIC"."Orders"`, for
+    /// [`SnowflakeSession::describe_table`] or a statement.
+    pub fn qualified_name(&self) -> String {
+        [&self.database_name, &self.schema_name, &self.name]
+            .map(|name| quote_identifier(name))
+            .join(".")
+    }
+}
+
+/// The kind of a [`TableInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TableKind {
+    /// A permanent table.
+    Table,
+    /// A transient table, without Fail-safe.
+    Transient,
+    /// A temporary table, which lives as long as the session that created it.
+    Temporary,
+    /// A table whose data lives in files on a stage.
+    External,
+    /// A dynamic table, refreshed from a query.
+    Dynamic,
+    /// A kind without a variant of its own, as Snowflake names it.
+    Other(String),
+}
+
+/// A column of a table, as described by [`SnowflakeSession::describe_table`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ColumnInfo {
+    pub name: String,
+    /// The type as Snowflake describes it, e.g. `NUMBER(38,0)` or `VARCHAR(16777216)`.
+    pub data_type: String,
+    /// The name of the type, without its parameters, e.g. `NUMBER` or `VARCHAR`.
+    pub type_name: String,
+    /// The precision of a `NUMBER`, or the fractional-second digits of a `TIME` or
+    /// `TIMESTAMP`.
+    pub precision: Option<u32>,
+    /// The scale of a `NUMBER`.
+    pub scale: Option<u32>,
+    /// The most characters of a `VARCHAR` or `CHAR`, or the most bytes of a `BINARY`.
+    pub length: Option<u32>,
+    pub nullable: bool,
+    /// The default value, as an SQL expression.
+    pub default: Option<String>,
+    pub primary_key: bool,
+    pub comment: Option<String>,
+}
+
+impl SnowflakeSession {
+    /// Lists the databases the role can see.
+    pub async fn list_databases(&self) -> Result<Vec<DatabaseInfo>> {
+        self.query_typed("SHOW DATABASES").await
+    }
+
+    /// Lists the schemas of a database, named exactly as Snowflake stores it, e.g. `MY_DB` for
+    /// a database created as `my_db`.
+    pub async fn list_schemas(&self, database: &str) -> Result<Vec<SchemaInfo>> {
+        let sql = format!("SHOW SCHEMAS IN DATABASE {}", quote_identifier(database));
+        self.query_typed(sql).await
+    }
+
+    /// Lists the tables of a schema, with the database and schema named exactly as Snowflake
+    /// stores them.
+    pub async fn list_tables(&self, database: &str, schema: &str) -> Result<Vec<TableInfo>> {
+        let sql = format!(
+            "SHOW TABLES IN SCHEMA {}.{}",
+            quote_identifier(database),
+            quote_identifier(schema)
+        );
+        self.query_typed(sql).await
+    }
+
+    /// Describes the columns of a table. `name` is written as in SQL, optionally qualified
+    /// with its schema and database: unquoted parts are case-insensitive, as in
+    /// `my_db.public.orders`, and quoted parts are exact, as in `"Sales"."Order Lines"`.
+    /// [`TableInfo::qualified_name`] gives the name of a listed table.
+    pub async fn describe_table(&self, name: &str) -> Result<Vec<ColumnInfo>> {
+        let name = quote_object_name(name)?;
+        self.query_typed(format!("DESCRIBE TABLE {name}")).await
+    }
+}
+
+/// Quotes an identifier so that Snowflake takes it exactly as written, whatever its case and
+/// characters, by doubling its double quotes and enclosing it in double quotes.
+///
+/// ```rust
+/// # use snowflake_connector_rs::quote_identifier;
+/// assert_eq!(quote_identifier("Order Lines"), r#""Order Lines""#);
+/// assert_eq!(quote_identifier(r#"say "hi""#), r#""say ""hi""""#);
+/// ```
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quotes every part of a possibly qualified object name written as in SQL, as parsed by
+/// [`parse_object_name`].
+pub(crate) fn quote_object_name(name: &str) -> Result<String> {
+    Ok(quote_object_parts(&parse_object_name(name)?))
+}
+
+/// Quotes the parts of an object name and joins them, e.g. into `"DB"."PUBLIC"."ORDERS"`.
+pub(crate) fn quote_object_parts(parts: &[String]) -> String {
+    parts
+        .iter()
+        .map(|part| quote_identifier(part))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Splits a possibly qualified object name, as written in SQL, into the names Snowflake stores:
+/// unquoted parts are uppercased, quoted parts are taken exactly, without their quotes.
+pub(crate) fn parse_object_name(name: &str) -> Result<Vec<String>> {
+    let invalid = || Error::InvalidIdentifier(name.to_string());
+    let mut parts = Vec::new();
+    let mut chars = name.trim().chars().peekable();
+    loop {
+        let mut part = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next().ok_or_else(invalid)? {
+                    '"' if chars.next_if_eq(&'"').is_none() => break,
+                    c => part.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|&c| c != '.') {
+                if c == '"' || c.is_whitespace() {
+                    return Err(invalid());
+                }
+                part.push(c.to_ascii_uppercase());
+            }
+        }
+        if part.is_empty() {
+            return Err(invalid());
+        }
+        parts.push(part);
+        match chars.next() {
+            None if parts.len() <= 3 => return Ok(parts),
+            Some('.') => {}
+            _ => return Err(invalid()),
+        }
+    }
+}
+
+/// A text column of a `SHOW` result that is empty rather than NULL when unset.
+pub(crate) fn optional_text(row: &SnowflakeRow, column_name: &str) -> Result<Option<String>> {
+    Ok(row
+        .try_get::<Option<String>>(column_name)?
+        .flatten()
+        .filter(|text| !text.is_empty()))
+}
+
+/// A `Y`/`N` column of a `DESCRIBE` result.
+fn flag(row: &SnowflakeRow, column_name: &str) -> Result<bool> {
+    Ok(optional_text(row, column_name)?.is_some_and(|flag| flag.eq_ignore_ascii_case("Y")))
+}
+
+impl FromRow for DatabaseInfo {
+    fn from_row(row: &SnowflakeRow) -> Result<Self> {
+        Ok(Self {
+            name: row.get("name")?,
+            owner: optional_text(row, "owner")?,
+            comment: optional_text(row, "comment")?,
+        })
+    }
+}
+
+impl FromRow for SchemaInfo {
+    fn from_row(row: &SnowflakeRow) -> Result<Self> {
+        Ok(Self {
+            database_name: row.get("database_name")?,
+            name: row.get("name")?,
+            owner: optional_text(row, "owner")?,
+            comment: optional_text(row, "comment")?,
+        })
+    }
+}
+
+impl FromRow for TableInfo {
+    fn from_row(row: &SnowflakeRow) -> Result<Self> {
+        let kind = match optional_text(row, "kind")?.unwrap_or_default().as_str() {
+            _ if flag(row, "is_external")? => TableKind::External,
+            _ if flag(row, "is_dynamic")? => TableKind::Dynamic,
+            "TABLE" => TableKind::Table,
+            "TRANSIENT" => TableKind::Transient,
+            "TEMPORARY" | "LOCAL TEMPORARY" => TableKind::Temporary,
+            kind => TableKind::Other(kind.to_string()),
+        };
+        Ok(Self {
+            database_name: row.get("database_name")?,
+            schema_name: row.get("schema_name")?,
+            name: row.get("name")?,
+            kind,
+            rows: row.try_get::<Option<u64>>("rows")?.flatten(),
+            bytes: row.try_get::<Option<u64>>("bytes")?.flatten(),
+            owner: optional_text(row, "owner")?,
+            comment: optional_text(row, "comment")?,
+        })
+    }
+}
+
+impl FromRow for ColumnInfo {
+    fn from_row(row: &SnowflakeRow) -> Result<Self> {
+        let data_type: String = row.get("type")?;
+        let (type_name, parameters) = match data_type.split_once('(') {
+            Some((name, parameters)) => (name.trim(), parameters),
+            None => (data_type.as_str(), ""),
+        };
+        // Types such as `VARCHAR(16777216) COLLATE 'en-ci'` carry more after their parameters.
+        let parameters = parameters
+            .split(')')
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|parameter| parameter.trim().parse::<u32>().ok())
+            .collect::<Vec<_>>();
+        let type_name = type_name.to_string();
+        let (precision, scale, length) = match (type_name.as_str(), parameters.as_slice()) {
+            ("NUMBER" | "DECIMAL" | "NUMERIC", &[precision, scale]) => {
+                (Some(precision), Some(scale), None)
+            }
+            (
+                "VARCHAR" | "CHAR" | "CHARACTER" | "STRING" | "TEXT" | "BINARY" | "VARBINARY",
+                &[length],
+            ) => (None, None, Some(length)),
+            (_, &[precision]) => (Some(precision), None, None),
+            _ => (None, None, None),
+        };
+        Ok(Self {
+            name: row.get("name")?,
+            type_name,
+            precision,
+            scale,
+            length,
+            nullable: flag(row, "null?")?,
+            default: optional_text(row, "default")?,
+            primary_key: flag(row, "primary key")?,
+            comment: optional_text(row, "comment")?,
+            data_type,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::tests::typed_row;
+
+    #[test]
+    fn test_parse_object_name() {
+        let parse = |name| parse_object_name(name).ok();
+        assert_eq!(parse("orders"), Some(vec!["ORDERS".to_string()]));
+        assert_eq!(
+            parse(r#"my_db."Sales"."Order ""Lines"".v2""#),
+            Some(vec![
+                "MY_DB".to_string(),
+                "Sales".to_string(),
+                r#"Order "Lines".v2"#.to_string()
+            ])
+        );
+        for invalid in [
+            "",
+            "a..b",
+            r#""open"#,
+            "a.b.c.d",
+            "two words",
+            r#"a"b"#,
+            "a.",
+        ] {
+            assert_eq!(parse(invalid), None, "{invalid}");
+        }
+        assert!(matches!(
+            parse_object_name("a..b"),
+            Err(Error::InvalidIdentifier(name)) if name == "a..b"
+        ));
+    }
+
+    #[test]
+    fn test_show_tables_row() -> Result<()> {
+        let table = TableInfo::from_row(&typed_row(&[
+            ("created_on", "timestamp_ltz", Some("1700000000.000000000")),
+            ("name", "text", Some("Order Lines")),
+            ("database_name", "text", Some("SALES")),
+            ("schema_name", "text", Some("PUBLIC")),
+            ("kind", "text", Some("TRANSIENT")),
+            ("comment", "text", Some("")),
+            ("rows", "fixed", Some("42")),
+            ("bytes", "fixed", None),
+            ("owner", "text", Some("SYSADMIN")),
+            ("is_external", "text", Some("N")),
+        ]))?;
+        assert_eq!(table.kind, TableKind::Transient);
+        assert_eq!((table.rows, table.bytes), (Some(42), None));
+        assert_eq!(table.comment, None);
+        assert_eq!(table.qualified_name(), r#""SALES"."PUBLIC"."Order Lines""#);
+
+        let external = TableInfo::from_row(&typed_row(&[
+            ("name", "text", Some("EVENTS")),
+            ("database_name", "text", Some("SALES")),
+            ("schema_name", "text", Some("RAW")),
+            ("kind", "text", Some("TABLE")),
+            ("is_external", "text", Some("Y")),
+        ]))?;
+        assert_eq!(external.kind, TableKind::External);
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe_table_rows() -> Result<()> {
+        let column = |name, data_type, null, default| {
+            ColumnInfo::from_row(&typed_row(&[
+                ("name", "text", Some(name)),
+                ("type", "text", Some(data_type)),
+                ("kind", "text", Some("COLUMN")),
+                ("null?", "text", Some(null)),
+                ("default", "text", default),
+                ("primary key", "text", Some("N")),
+                ("comment", "text", None),
+            ]))
+        };
+        let id = column("ID", "NUMBER(38,0)", "N", None)?;
+        assert_eq!(
+            (id.type_name.as_str(), id.precision, id.scale, id.length),
+            ("NUMBER", Some(38), Some(0), None)
+        );
+        assert!(!id.nullable);
+
+        let name = column(
+            "Name",
+            "VARCHAR(100) COLLATE 'en-ci'",
+            "Y",
+            Some("'unknown'"),
+        )?;
+        assert_eq!(
+            (name.type_name.as_str(), name.length, name.precision),
+            ("VARCHAR", Some(100), None)
+        );
+        assert!(name.nullable);
+        assert_eq!(name.default.as_deref(), Some("'unknown'"));
+
+        let at = column("AT", "TIMESTAMP_NTZ(9)", "Y", None)?;
+        assert_eq!((at.precision, at.scale), (Some(9), None));
+        let data = column("DATA", "VARIANT", "Y", None)?;
+        assert_eq!((data.type_name.as_str(), data.precision), ("VARIANT", None));
+        Ok(())
+    }
+}
@@ -1,53 +1,972 @@
-use std::io::Read;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io::Read,
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+    time::{Duration, Instant},
+};
 
 use flate2::bufread::GzDecoder;
-use reqwest::header::HeaderMap;
+use reqwest::{
+    header::{HeaderMap, ACCEPT_ENCODING, CONTENT_ENCODING},
+    Method, StatusCode,
+};
+use tokio::{
+    sync::Semaphore,
+    task::{JoinError, JoinHandle},
+    time::sleep,
+};
 
-use crate::{Error, Result};
+use crate::{
+    metrics::{ConnectorMetrics, NoMetrics, RetryKind},
+    stats::StatsRecorder,
+    trace::{debug_event, span, Instrument},
+    transport::{http_response_error, redact_url, request_error},
+    values::RowValues,
+    Error, Result,
+};
 
 const HEADER_SSE_C_ALGORITHM: &str = "x-amz-server-side-encryption-customer-algorithm";
 const HEADER_SSE_C_KEY: &str = "x-amz-server-side-encryption-customer-key";
 const AES256: &str = "AES256";
 
-pub(crate) async fn download_chunk(
-    client: reqwest::Client,
-    chunk_url: String,
-    mut headers: HeaderMap,
-    qrmk: String,
-) -> Result<Vec<Vec<Option<String>>>> {
-    if headers.is_empty() {
-        headers.append(HEADER_SSE_C_ALGORITHM, AES256.parse()?);
-        headers.append(HEADER_SSE_C_KEY, qrmk.parse()?);
+type ChunkDownload = Pin<Box<dyn Future<Output = AttemptResult<RowValues>> + Send>>;
+
+type ChunkRefresh = Pin<Box<dyn Future<Output = Result<ChunkSet>> + Send>>;
+
+/// The content encodings of chunk bodies that [`parse_chunk`] can decode, sent as
+/// `Accept-Encoding` with chunk requests.
+const ACCEPTED_ENCODINGS: &str = "gzip";
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// How many times a result's chunk URLs are fetched again after they have expired.
+const MAX_URL_REFRESHES: usize = 3;
+
+/// How the chunks of a result are downloaded; see the matching fields of
+/// [`SnowflakeClientConfig`](crate::SnowflakeClientConfig).
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkDownloadConfig {
+    /// The client of the downloads from the storage holding the chunks.
+    pub(crate) client: reqwest::Client,
+    pub(crate) max_concurrent: usize,
+    pub(crate) prefetch: usize,
+    pub(crate) max_attempts: usize,
+    pub(crate) backoff: Duration,
+    pub(crate) request_timeout: Duration,
+    pub(crate) metrics: Arc<dyn ConnectorMetrics>,
+}
+
+/// The presigned URLs of a result's chunks and the headers to download them with.
+pub(crate) struct ChunkSet {
+    pub(crate) urls: Vec<String>,
+    /// The row count of each chunk from the result metadata, checked as the chunks arrive.
+    /// Chunks without one are not checked.
+    pub(crate) row_counts: Vec<usize>,
+    pub(crate) headers: HeaderMap,
+}
+
+impl ChunkSet {
+    /// Uses the `chunkHeaders` of the response as they are if there are any. Otherwise, chunks
+    /// stored on S3 are encrypted with the result master key `qrmk`, sent as SSE-C headers;
+    /// without a key, as for some Azure and GCS results, the presigned URLs need no headers.
+    pub(crate) fn new(
+        urls: Vec<String>,
+        row_counts: Vec<usize>,
+        mut headers: HeaderMap,
+        qrmk: Option<&str>,
+    ) -> Result<Self> {
+        match qrmk {
+            Some(qrmk) if headers.is_empty() && !qrmk.is_empty() => {
+                headers.append(HEADER_SSE_C_ALGORITHM, AES256.parse()?);
+                headers.append(HEADER_SSE_C_KEY, qrmk.parse()?);
+            }
+            _ => {}
+        }
+        Ok(Self {
+            urls,
+            row_counts,
+            headers,
+        })
     }
+}
+
+/// Decodes and parses the body of a chunk with its `Content-Encoding`, as [`parse_chunk`] does.
+pub(crate) type ParseChunk = Arc<dyn Fn(&[u8], Option<&str>) -> Result<RowValues> + Send + Sync>;
+
+/// Downloads the chunks of a result in order, on demand.
+///
+/// Downloads start on the first call to [`ChunkFetcher::next_chunk`] and run concurrently, while
+/// chunks are still returned in order: a chunk that finishes before the ones ahead of it waits
+/// among the started downloads, so the chunks held for reordering are bounded by the same
+/// window. Unordered, chunks are returned as soon as they finish instead. Each call starts the downloads of the chunk it returns and
+/// of up to `prefetch` chunks after it, so that at most `prefetch` chunks are downloading or
+/// downloaded but not yet taken while the caller works; with a `prefetch` of 0, chunks are only
+/// downloaded when asked for. At most `max_concurrent` of the started downloads run at the same
+/// time. Downloads still running when the fetcher is dropped are aborted.
+///
+/// Chunk URLs expire some time after the query. When a download is refused because its URL has
+/// expired, the fetcher asks for fresh URLs with its refresh function and downloads that chunk
+/// and the ones started after it again.
+pub(crate) struct ChunkFetcher {
+    chunks: ChunkSet,
+    next_index: usize,
+    pending: VecDeque<(usize, JoinHandle<AttemptResult<RowValues>>)>,
+    download: Box<dyn Fn(String, HeaderMap) -> ChunkDownload + Send + Sync>,
+    refresh: Option<Box<dyn Fn() -> ChunkRefresh + Send + Sync>>,
+    refreshes: usize,
+    prefetch: usize,
+    ordered: bool,
+    downloads: Arc<Semaphore>,
+    /// The index of the chunk returned last.
+    returned: Option<usize>,
+    metrics: Arc<dyn ConnectorMetrics>,
+}
 
-    let response = client.get(chunk_url).headers(headers).send().await?;
-    if !response.status().is_success() {
-        let body = response.text().await?;
-        return Err(Error::ChunkDownload(body));
+impl ChunkFetcher {
+    /// Creates a fetcher whose chunk bodies are parsed with `parse`: [`parse_chunk`] for the
+    /// bare lists of rows of a JSON result.
+    pub(crate) fn parsing(
+        chunks: ChunkSet,
+        config: ChunkDownloadConfig,
+        stats: StatsRecorder,
+        parse: ParseChunk,
+    ) -> Self {
+        let (prefetch, max_concurrent) = (config.prefetch, config.max_concurrent);
+        let metrics = Arc::clone(&config.metrics);
+        let mut fetcher = Self::with_download(
+            chunks,
+            prefetch,
+            max_concurrent,
+            move |chunk_url, headers| {
+                let (stats, config, parse) = (stats.clone(), config.clone(), parse.clone());
+                Box::pin(async move {
+                    let start = Instant::now();
+                    let (body, encoding) = retry(&config, || {
+                        fetch_chunk(&config.client, &chunk_url, &headers, config.request_timeout)
+                    })
+                    .await?;
+                    let bytes = body.len() as u64;
+                    stats.record_download(bytes);
+                    config.metrics.on_chunk_downloaded(bytes, start.elapsed());
+                    parse_blocking(parse, body, encoding)
+                        .await
+                        .map_err(FailedAttempt::fatal)
+                })
+            },
+        );
+        fetcher.metrics = metrics;
+        fetcher
     }
 
-    let body = response.bytes().await?;
-    if body.len() < 2 {
-        return Err(Error::ChunkDownload("invalid chunk format".into()));
+    fn with_download(
+        chunks: ChunkSet,
+        prefetch: usize,
+        max_concurrent: usize,
+        download: impl Fn(String, HeaderMap) -> ChunkDownload + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            chunks,
+            next_index: 0,
+            pending: VecDeque::new(),
+            download: Box::new(download),
+            refresh: None,
+            refreshes: 0,
+            prefetch,
+            ordered: true,
+            downloads: Arc::new(Semaphore::new(
+                max_concurrent.clamp(1, Semaphore::MAX_PERMITS),
+            )),
+            returned: None,
+            metrics: Arc::new(NoMetrics),
+        }
+    }
+
+    /// Sets how many chunks are downloaded ahead of the one being read. Only affects downloads
+    /// started afterwards.
+    pub(crate) fn set_prefetch(&mut self, prefetch: usize) {
+        self.prefetch = prefetch;
+    }
+
+    /// Sets whether chunks are returned in the order of the result, or as soon as they have been
+    /// downloaded.
+    pub(crate) fn set_ordered(&mut self, ordered: bool) {
+        self.ordered = ordered;
+    }
+
+    /// Sets the function fetching fresh chunk URLs once the current ones have expired.
+    pub(crate) fn with_refresh(
+        mut self,
+        refresh: impl Fn() -> ChunkRefresh + Send + Sync + 'static,
+    ) -> Self {
+        self.refresh = Some(Box::new(refresh));
+        self
+    }
+
+    /// Starts at the chunk at `index` rather than the first, before any download has started.
+    /// Returns the rows of the chunks skipped, if the result metadata has them all.
+    pub(crate) fn skip_to(&mut self, index: usize) -> Option<usize> {
+        debug_assert!(self.next_index == 0 && self.pending.is_empty());
+        self.next_index = index.min(self.chunks.urls.len());
+        self.chunks
+            .row_counts
+            .get(..self.next_index)
+            .map(|row_counts| row_counts.iter().sum())
+    }
+
+    /// The index of the chunk returned last by [`ChunkFetcher::next_chunk`].
+    pub(crate) fn returned_index(&self) -> Option<usize> {
+        self.returned
     }
 
-    let bytes = if body[0] == 0x1f && body[1] == 0x8b {
-        let mut d = GzDecoder::new(&body[..]);
-        let mut buf = vec![];
-        d.read_to_end(&mut buf)?;
-        buf
-    } else {
-        body.to_vec()
+    /// The number of chunks of the result.
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.chunks.urls.len()
+    }
+
+    /// Returns the next chunk, or `None` once every chunk has been returned.
+    pub(crate) async fn next_chunk(
+        &mut self,
+    ) -> Option<std::result::Result<RowValues, FailedChunk>> {
+        loop {
+            while self.pending.len() <= self.prefetch && self.next_index < self.chunks.urls.len() {
+                let handle = self.spawn(self.next_index);
+                self.pending.push_back((self.next_index, handle));
+                self.next_index += 1;
+            }
+            let (index, result) = if self.ordered {
+                let (index, handle) = self.pending.pop_front()?;
+                (index, handle.await)
+            } else {
+                self.next_finished().await?
+            };
+            let failure = match result {
+                Ok(Ok(rows)) => {
+                    self.returned = Some(index);
+                    return Some(self.check_row_count(index, rows));
+                }
+                Ok(Err(failure)) => failure,
+                Err(e) => FailedAttempt::fatal(e.into()),
+            };
+            let error = match &self.refresh {
+                Some(refresh) if failure.url_expired && self.refreshes < MAX_URL_REFRESHES => {
+                    self.refreshes += 1;
+                    debug_event!(index, "chunk URLs expired, fetching fresh ones");
+                    self.metrics.on_retry(RetryKind::ChunkUrlRefresh);
+                    match refresh().await {
+                        Ok(chunks) => self.restart(index, chunks),
+                        Err(e) => Err(e),
+                    }
+                }
+                _ => Err(*failure.error),
+            };
+            if let Err(error) = error {
+                let url = self.chunks.urls[index].clone();
+                let error = Box::new(error);
+                return Some(Err(FailedChunk { index, url, error }));
+            }
+        }
+    }
+
+    /// Fails a chunk with fewer or more rows than the result metadata announced, e.g. from a
+    /// connection closed early after a successful status.
+    fn check_row_count(
+        &self,
+        index: usize,
+        rows: RowValues,
+    ) -> std::result::Result<RowValues, FailedChunk> {
+        match self.chunks.row_counts.get(index) {
+            Some(&expected) if expected != rows.len() => Err(FailedChunk {
+                index,
+                url: self.chunks.urls[index].clone(),
+                error: Box::new(Error::IncompleteResult {
+                    chunk_index: Some(index),
+                    expected,
+                    received: rows.len(),
+                }),
+            }),
+            _ => Ok(rows),
+        }
+    }
+
+    fn spawn(&self, index: usize) -> JoinHandle<AttemptResult<RowValues>> {
+        let download =
+            (self.download)(self.chunks.urls[index].clone(), self.chunks.headers.clone());
+        let downloads = Arc::clone(&self.downloads);
+        let download = async move {
+            let _permit = downloads.acquire_owned().await;
+            download.await
+        };
+        tokio::spawn(download.instrument(span!("snowflake.chunk", index)))
+    }
+
+    /// Waits for the first of the started downloads to finish, or returns `None` if there are
+    /// none.
+    async fn next_finished(
+        &mut self,
+    ) -> Option<(
+        usize,
+        std::result::Result<AttemptResult<RowValues>, JoinError>,
+    )> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        std::future::poll_fn(|cx| {
+            for position in 0..self.pending.len() {
+                if let Poll::Ready(result) = Pin::new(&mut self.pending[position].1).poll(cx) {
+                    let finished = self.pending.remove(position);
+                    return Poll::Ready(finished.map(|(index, _)| (index, result)));
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Aborts the running downloads and starts no more, so that no further chunks are returned.
+    pub(crate) fn stop(&mut self) {
+        for (_, handle) in self.pending.drain(..) {
+            handle.abort();
+        }
+        self.next_index = self.chunks.urls.len();
+    }
+
+    /// Replaces the chunk URLs and downloads again the chunk at `index` and those still pending.
+    fn restart(&mut self, index: usize, chunks: ChunkSet) -> Result<()> {
+        if chunks.urls.len() != self.chunks.urls.len() {
+            return Err(Error::ChunkDownload(format!(
+                "refreshed result has {} chunks, expected {}",
+                chunks.urls.len(),
+                self.chunks.urls.len()
+            )));
+        }
+        let mut indices = vec![index];
+        for (index, handle) in self.pending.drain(..) {
+            handle.abort();
+            indices.push(index);
+        }
+        self.chunks = chunks;
+        for index in indices {
+            let handle = self.spawn(index);
+            self.pending.push_back((index, handle));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ChunkFetcher {
+    fn drop(&mut self) {
+        for (_, handle) in &self.pending {
+            handle.abort();
+        }
+    }
+}
+
+/// A chunk whose download failed after all retries.
+pub(crate) struct FailedChunk {
+    index: usize,
+    url: String,
+    error: Box<Error>,
+}
+
+impl FailedChunk {
+    /// Converts into [`Error::ChunkFailed`], given the number of rows the caller has received.
+    /// A chunk that was downloaded but is incomplete stays an [`Error::IncompleteResult`].
+    pub(crate) fn into_error(self, rows_delivered: usize) -> Error {
+        match *self.error {
+            Error::IncompleteResult { .. } => *self.error,
+            _ => Error::ChunkFailed {
+                chunk_index: self.index,
+                url: redact_url(&self.url),
+                rows_delivered,
+                source: self.error,
+            },
+        }
+    }
+}
+
+type AttemptResult<T> = std::result::Result<T, FailedAttempt>;
+
+/// A failed download attempt.
+struct FailedAttempt {
+    error: Box<Error>,
+    /// Whether the failure may go away on retry.
+    transient: bool,
+    /// Whether the chunk URL has expired, so that only fresh URLs can help.
+    url_expired: bool,
+}
+
+impl FailedAttempt {
+    fn fatal(error: Error) -> Self {
+        Self {
+            error: Box::new(error),
+            transient: false,
+            url_expired: false,
+        }
+    }
+}
+
+/// Runs `download` up to `config.max_attempts` times, doubling the wait between attempts. Only
+/// transient failures are retried.
+async fn retry<T, F, Fut>(config: &ChunkDownloadConfig, download: F) -> AttemptResult<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = AttemptResult<T>>,
+{
+    let mut backoff = config.backoff;
+    let mut attempt = 1;
+    loop {
+        match download().await {
+            Err(e) if e.transient && attempt < config.max_attempts => {
+                debug_event!(attempt, error = %e.error, "retrying chunk download");
+                config.metrics.on_retry(RetryKind::ChunkDownload);
+                sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Downloads chunk `index` of a result, retrying as the fetcher does, and returns its body
+/// decompressed but not parsed.
+pub(crate) async fn download_raw(
+    config: &ChunkDownloadConfig,
+    chunks: &ChunkSet,
+    index: usize,
+) -> Result<Vec<u8>> {
+    let url = &chunks.urls[index];
+    let download = retry(config, || {
+        fetch_chunk(&config.client, url, &chunks.headers, config.request_timeout)
+    });
+    let (body, encoding) = download.await.map_err(|e| {
+        FailedChunk {
+            index,
+            url: url.clone(),
+            error: e.error,
+        }
+        .into_error(0)
+    })?;
+    let mut buf = Vec::with_capacity(body.len());
+    decode_body(&body, encoding.as_deref(), &mut buf)?;
+    Ok(buf)
+}
+
+/// Fetches the body of a chunk and its `Content-Encoding`, if any, within `timeout`. Connection
+/// errors, timeouts and 5xx responses are transient; a 403 means the presigned chunk URL has
+/// expired, or that the storage service rejected the headers.
+async fn fetch_chunk(
+    client: &reqwest::Client,
+    chunk_url: &str,
+    headers: &HeaderMap,
+    timeout: Duration,
+) -> AttemptResult<(Vec<u8>, Option<String>)> {
+    let start = Instant::now();
+    let connection_error = |e: reqwest::Error| FailedAttempt {
+        transient: !e.is_builder(),
+        url_expired: false,
+        error: Box::new(request_error(e, start.elapsed())),
     };
+    let response = client
+        .get(chunk_url)
+        .headers(headers.clone())
+        .header(ACCEPT_ENCODING, ACCEPTED_ENCODINGS)
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(connection_error)?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.map_err(connection_error)?;
+        return Err(FailedAttempt {
+            // The body of a storage service error (XML from S3, Azure and GCS) names the cause,
+            // e.g. `AuthenticationFailed` or `InvalidArgument`.
+            error: Box::new(http_response_error(
+                Method::GET,
+                chunk_url,
+                status,
+                &body,
+                None,
+                false,
+            )),
+            transient: status.is_server_error(),
+            // Presigned URLs are refused with 403 once they expire, and the bearer tokens of
+            // SQL API partitions with 401.
+            url_expired: matches!(status, StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED),
+        });
+    }
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+    let body = response.bytes().await.map_err(connection_error)?;
+    Ok((body.to_vec(), encoding))
+}
+
+/// Decompresses and parses a chunk on the blocking thread pool, so that large chunks do not
+/// stall other tasks on the runtime. Parsing cannot be interrupted: if the download is aborted
+/// meanwhile, it runs to completion and the rows are dropped.
+pub(crate) async fn parse_chunk_blocking(
+    body: Vec<u8>,
+    content_encoding: Option<String>,
+) -> Result<RowValues> {
+    parse_blocking(Arc::new(parse_chunk), body, content_encoding).await
+}
+
+async fn parse_blocking(
+    parse: ParseChunk,
+    body: Vec<u8>,
+    content_encoding: Option<String>,
+) -> Result<RowValues> {
+    tokio::task::spawn_blocking(move || parse(&body, content_encoding.as_deref())).await?
+}
+
+/// Decodes and parses a chunk body with the given `Content-Encoding`. Without one, the encoding
+/// is told from the first bytes of the body, as storage services serve chunks stored compressed
+/// without the header.
+pub(crate) fn parse_chunk(body: &[u8], content_encoding: Option<&str>) -> Result<RowValues> {
+    if body.len() < 2 {
+        return Err(Error::ChunkDownload("invalid chunk format".into()));
+    }
 
-    let mut buf = vec![b'['];
-    buf.extend(bytes);
+    // The body is a list of rows without the enclosing brackets, decompressed straight into
+    // the buffer that adds them.
+    let mut buf = Vec::with_capacity(body.len() + 2);
+    buf.push(b'[');
+    decode_body(body, content_encoding, &mut buf)?;
     buf.push(b']');
-    let rows: Vec<Vec<Option<String>>> = match serde_json::from_slice(&buf) {
-        Ok(rows) => rows,
-        Err(e) => {
-            return Err(Error::Json(e, String::from_utf8_lossy(&buf).into_owned()));
+
+    let text = String::from_utf8(buf)?;
+    serde_json::from_str(&text).map_err(|e| Error::Json(e, text))
+}
+
+/// Decodes a chunk body with the given `Content-Encoding` into `buf`. Without one, the encoding
+/// is told from the first bytes of the body, as storage services serve chunks stored compressed
+/// without the header.
+pub(crate) fn decode_body(
+    body: &[u8],
+    content_encoding: Option<&str>,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let encoding = match content_encoding.map(str::trim) {
+        Some(encoding) if !encoding.is_empty() && !encoding.eq_ignore_ascii_case("identity") => {
+            encoding.to_ascii_lowercase()
         }
+        _ if body.starts_with(&GZIP_MAGIC) => "gzip".into(),
+        _ if body.starts_with(&ZSTD_MAGIC) => "zstd".into(),
+        _ => "identity".into(),
     };
-    Ok(rows)
+
+    match encoding.as_str() {
+        "identity" => buf.extend_from_slice(body),
+        "gzip" | "x-gzip" => {
+            GzDecoder::new(body).read_to_end(buf)?;
+        }
+        encoding => {
+            return Err(Error::ChunkDownload(format!(
+                "unsupported chunk encoding: {encoding}"
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{metrics::tests::CountingMetrics, TimeoutPhase};
+
+    fn chunk_set(urls: Vec<String>) -> ChunkSet {
+        ChunkSet {
+            urls,
+            row_counts: vec![],
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// A fetcher serving `count` chunks of one row holding the chunk number, counting how many
+    /// downloads have started.
+    pub(crate) fn fake_fetcher(count: usize, prefetch: usize) -> (ChunkFetcher, Arc<AtomicUsize>) {
+        let started = Arc::new(AtomicUsize::new(0));
+        let urls = (0..count).map(|i| i.to_string()).collect();
+        let chunks = ChunkSet {
+            row_counts: vec![1; count],
+            ..chunk_set(urls)
+        };
+        let counter = Arc::clone(&started);
+        let fetcher = ChunkFetcher::with_download(chunks, prefetch, usize::MAX, move |url, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                Ok(RowValues::from_rows(vec![vec![Some(url)]]))
+            })
+        });
+        (fetcher, started)
+    }
+
+    #[tokio::test]
+    async fn test_parse_chunk() -> Result<()> {
+        use std::io::Write;
+
+        let body = br#"["1", null], ["2", "b"]"#;
+        let expected = RowValues::from_rows(vec![
+            vec![Some("1".to_string()), None],
+            vec![Some("2".to_string()), Some("b".to_string())],
+        ]);
+        assert_eq!(parse_chunk_blocking(body.to_vec(), None).await?, expected);
+        assert_eq!(parse_chunk(body, Some("identity"))?, expected);
+
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        gzip.write_all(body)?;
+        let gzip = gzip.finish()?;
+        assert_eq!(parse_chunk_blocking(gzip.clone(), None).await?, expected);
+        assert_eq!(parse_chunk(&gzip, Some("GZIP"))?, expected);
+
+        assert!(matches!(
+            parse_chunk_blocking(b"[1".to_vec(), None).await,
+            Err(Error::Json(..))
+        ));
+        assert!(parse_chunk_blocking(vec![], None).await.is_err());
+
+        // Encodings that cannot be decoded are named instead of failing to parse as JSON.
+        let err = parse_chunk(b"\x8b\x0b\x80", Some("br")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "chunk download error: unsupported chunk encoding: br"
+        );
+        let zstd = [&ZSTD_MAGIC[..], b"\x00\x58\x51"].concat();
+        let err = parse_chunk(&zstd, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .ends_with("unsupported chunk encoding: zstd"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetcher_bounds_resident_chunks() -> Result<()> {
+        let (mut fetcher, started) = fake_fetcher(50, 3);
+        assert_eq!(started.load(Ordering::SeqCst), 0);
+
+        let mut taken = 0;
+        let mut peak = 0;
+        while let Some(chunk) = fetcher.next_chunk().await {
+            let chunk = chunk.map_err(|e| e.into_error(taken))?;
+            assert_eq!(chunk.get(0, 0), Some(taken.to_string().as_str()));
+            // Resident chunks: the one just taken plus those the fetcher has started.
+            peak = peak.max(started.load(Ordering::SeqCst) - taken);
+            taken += 1;
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(taken, 50);
+        assert_eq!(started.load(Ordering::SeqCst), 50);
+        assert_eq!(peak, 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_depth() -> Result<()> {
+        for prefetch in [0, 1, 3] {
+            let (mut fetcher, started) = fake_fetcher(10, prefetch);
+            let settle = || async {
+                for _ in 0..20 {
+                    tokio::task::yield_now().await;
+                }
+            };
+            settle().await;
+            assert_eq!(started.load(Ordering::SeqCst), 0);
+
+            for taken in 1..=3 {
+                fetcher
+                    .next_chunk()
+                    .await
+                    .unwrap()
+                    .map_err(|e| e.into_error(0))?;
+                settle().await;
+                // The chunks taken so far, and `prefetch` chunks after them.
+                assert_eq!(started.load(Ordering::SeqCst), taken + prefetch);
+            }
+        }
+
+        let (mut fetcher, started) = fake_fetcher(10, 3);
+        fetcher.set_prefetch(0);
+        fetcher
+            .next_chunk()
+            .await
+            .unwrap()
+            .map_err(|e| e.into_error(0))?;
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_timeout() {
+        // A server that accepts connections but never responds.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/results/data_0?sig=secret",
+            listener.local_addr().unwrap()
+        );
+        let timeout = Duration::from_millis(50);
+        let failure = fetch_chunk(&reqwest::Client::new(), &url, &HeaderMap::new(), timeout)
+            .await
+            .unwrap_err();
+        assert!(failure.transient);
+        let Error::Timeout {
+            phase: TimeoutPhase::Request,
+            elapsed,
+            query_id: None,
+        } = *failure.error
+        else {
+            panic!("expected a request timeout, got {:?}", failure.error);
+        };
+        assert!(elapsed >= timeout);
+    }
+
+    #[tokio::test]
+    async fn test_retry() -> Result<()> {
+        let metrics = Arc::new(CountingMetrics::default());
+        let config = ChunkDownloadConfig {
+            client: reqwest::Client::new(),
+            max_concurrent: 1,
+            prefetch: 0,
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+            request_timeout: Duration::from_secs(1),
+            metrics: metrics.clone(),
+        };
+        fn fail<T>(transient: bool) -> AttemptResult<T> {
+            Err(FailedAttempt {
+                error: Box::new(Error::ChunkDownload("unavailable".into())),
+                transient,
+                url_expired: false,
+            })
+        }
+
+        let attempts = AtomicUsize::new(0);
+        let value = retry(&config, || async {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => fail(true),
+                _ => Ok(42),
+            }
+        })
+        .await
+        .map_err(|e| *e.error)?;
+        assert_eq!((value, attempts.load(Ordering::SeqCst)), (42, 3));
+
+        let attempts = AtomicUsize::new(0);
+        let result = retry(&config, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            fail::<()>(true)
+        })
+        .await;
+        assert!(matches!(
+            result.map_err(|e| *e.error),
+            Err(Error::ChunkDownload(_))
+        ));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let attempts = AtomicUsize::new(0);
+        let result = retry(&config, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            fail::<()>(false)
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // Only the attempts after a transient failure count as retries.
+        assert_eq!(metrics.retries(RetryKind::ChunkDownload), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_chunk_error() {
+        let urls = vec![
+            "https://chunk/0".to_string(),
+            "https://chunk/1?X-Amz-Signature=secret".to_string(),
+        ];
+        let mut fetcher = ChunkFetcher::with_download(chunk_set(urls), 1, usize::MAX, |url, _| {
+            Box::pin(async move {
+                if url.contains("/1") {
+                    Err(FailedAttempt::fatal(Error::ChunkDownload("gone".into())))
+                } else {
+                    Ok(RowValues::from_rows(vec![vec![None]]))
+                }
+            })
+        });
+        assert!(fetcher.next_chunk().await.unwrap().is_ok());
+        let Err(failed) = fetcher.next_chunk().await.unwrap() else {
+            panic!("expected the second chunk to fail");
+        };
+        match failed.into_error(10) {
+            Error::ChunkFailed {
+                chunk_index,
+                url,
+                rows_delivered,
+                source,
+            } => {
+                assert_eq!((chunk_index, url.as_str()), (1, "https://chunk/1"));
+                assert_eq!(rows_delivered, 10);
+                assert!(matches!(*source, Error::ChunkDownload(_)));
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetcher_downloads_concurrently() -> Result<()> {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let urls = (0..20).map(|i| i.to_string()).collect();
+        let (counter, max) = (Arc::clone(&in_flight), Arc::clone(&peak));
+        let mut fetcher = ChunkFetcher::with_download(chunk_set(urls), 10, 4, move |url, _| {
+            let (counter, max) = (Arc::clone(&counter), Arc::clone(&max));
+            Box::pin(async move {
+                let current = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                max.fetch_max(current, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                counter.fetch_sub(1, Ordering::SeqCst);
+                Ok(RowValues::from_rows(vec![vec![Some(url)]]))
+            })
+        });
+
+        let mut taken = vec![];
+        while let Some(chunk) = fetcher.next_chunk().await {
+            let chunk = chunk.map_err(|e| e.into_error(taken.len()))?;
+            taken.extend(chunk.get(0, 0).map(str::to_string));
+        }
+        assert_eq!(taken, (0..20).map(|i| i.to_string()).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunk_row_counts() -> Result<()> {
+        let (mut fetcher, _) = fake_fetcher(3, 1);
+        fetcher.chunks.row_counts = vec![1, 2];
+        assert!(fetcher.next_chunk().await.unwrap().is_ok());
+        let Err(failed) = fetcher.next_chunk().await.unwrap() else {
+            panic!("expected chunk 1 to be incomplete");
+        };
+        let err = failed.into_error(2);
+        assert!(matches!(
+            err,
+            Error::IncompleteResult {
+                chunk_index: Some(1),
+                expected: 2,
+                received: 1
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "incomplete result (chunk 1): expected 2 rows, received 1"
+        );
+        // Chunks without a row count in the metadata are not checked.
+        assert!(fetcher.next_chunk().await.unwrap().is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunk_order() -> Result<()> {
+        // Earlier chunks take longer to download, so the last chunk finishes first.
+        let delayed_fetcher = || {
+            let urls = (0..5).map(|i| i.to_string()).collect();
+            ChunkFetcher::with_download(chunk_set(urls), 4, usize::MAX, |url, _| {
+                Box::pin(async move {
+                    let delay = 5 - url.parse::<u64>().unwrap();
+                    sleep(Duration::from_millis(20 * delay)).await;
+                    Ok(RowValues::from_rows(vec![vec![Some(url)]]))
+                })
+            })
+        };
+        let take_all = |mut fetcher: ChunkFetcher| async move {
+            let mut taken = vec![];
+            while let Some(chunk) = fetcher.next_chunk().await {
+                let chunk = chunk.map_err(|e| e.into_error(taken.len()))?;
+                taken.extend(chunk.get(0, 0).map(str::to_string));
+            }
+            Result::Ok(taken)
+        };
+
+        let ordered = take_all(delayed_fetcher()).await?;
+        assert_eq!(ordered, vec!["0", "1", "2", "3", "4"]);
+
+        let mut fetcher = delayed_fetcher();
+        fetcher.set_ordered(false);
+        let mut unordered = take_all(fetcher).await?;
+        assert_eq!(unordered[0], "4");
+        unordered.sort();
+        assert_eq!(unordered, ordered);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_expired_urls() -> Result<()> {
+        // URLs of the first generation expire from chunk 2 on; refreshed URLs always work.
+        let download = |url: String, _| -> ChunkDownload {
+            Box::pin(async move {
+                let (generation, index) = url.split_once('/').unwrap();
+                if generation == "old" && index >= "2" {
+                    return Err(FailedAttempt {
+                        error: Box::new(Error::ChunkDownload("expired".into())),
+                        transient: false,
+                        url_expired: true,
+                    });
+                }
+                Ok(RowValues::from_rows(vec![vec![Some(index.to_string())]]))
+            })
+        };
+        let urls = |generation: &str| (0..5).map(|i| format!("{generation}/{i}")).collect();
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&refreshes);
+        let mut fetcher =
+            ChunkFetcher::with_download(chunk_set(urls("old")), 1, usize::MAX, download)
+                .with_refresh(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async move { Ok(chunk_set(urls("new"))) })
+                });
+        let metrics = Arc::new(CountingMetrics::default());
+        fetcher.metrics = metrics.clone();
+
+        let mut taken = vec![];
+        while let Some(chunk) = fetcher.next_chunk().await {
+            let chunk = chunk.map_err(|e| e.into_error(taken.len()))?;
+            taken.extend(chunk.get(0, 0).map(str::to_string));
+        }
+        assert_eq!(taken, vec!["0", "1", "2", "3", "4"]);
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+        assert_eq!(metrics.retries(RetryKind::ChunkUrlRefresh), 1);
+
+        // URLs that keep expiring give up after a bounded number of refreshes.
+        let mut fetcher =
+            ChunkFetcher::with_download(chunk_set(urls("old")), 1, usize::MAX, download)
+                .with_refresh(move || Box::pin(async move { Ok(chunk_set(urls("old"))) }));
+        assert!(fetcher.next_chunk().await.unwrap().is_ok());
+        assert!(fetcher.next_chunk().await.unwrap().is_ok());
+        let Err(failed) = fetcher.next_chunk().await.unwrap() else {
+            panic!("expected chunk 2 to fail");
+        };
+        assert_eq!(failed.index, 2);
+        assert_eq!(fetcher.refreshes, MAX_URL_REFRESHES);
+
+        // A result that has expired on the server fails with the refresh error.
+        let mut fetcher =
+            ChunkFetcher::with_download(chunk_set(urls("old")), 0, usize::MAX, download)
+                .with_refresh(|| {
+                    Box::pin(async { Err(Error::ResultExpired("01b2c3d4".to_string())) })
+                });
+        fetcher.next_chunk().await;
+        fetcher.next_chunk().await;
+        let Err(failed) = fetcher.next_chunk().await.unwrap() else {
+            panic!("expected chunk 2 to fail");
+        };
+        assert!(matches!(*failed.error, Error::ResultExpired(_)));
+        Ok(())
+    }
 }
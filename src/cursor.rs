@@ -0,0 +1,274 @@
+//! Reading a query result a page at a time, from a position that can be handed out and resumed
+//! later.
+
+use std::{fmt, str::FromStr};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::{Error, QueryRequest, Result, RowStream, SnowflakeRow, SnowflakeSession};
+
+/// The rows of a query result in pages of a fixed number of rows, downloading the chunks of the
+/// result as the pages need them. Returned by [`SnowflakeSession::query_cursor`].
+///
+/// A page may span several chunks. After any page, [`QueryCursor::position`] tells where the
+/// next page starts; [`SnowflakeSession::resume_cursor`] continues from there with a new
+/// cursor, e.g. in the next request to a stateless service, for as long as Snowflake keeps the
+/// result.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{CursorPosition, Result, SnowflakeSession};
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let mut cursor = session.query_cursor("SELECT * FROM events ORDER BY id", 100).await?;
+/// let first_page = cursor.next_page().await?.unwrap_or_default();
+/// let token = cursor.position().to_string();
+///
+/// // Later, with the token the client sent back:
+/// let position: CursorPosition = token.parse()?;
+/// let mut cursor = session.resume_cursor(&position, 100).await?;
+/// let second_page = cursor.next_page().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryCursor {
+    rows: RowStream,
+    page_size: usize,
+    query_id: String,
+    total_rows: Option<usize>,
+}
+
+/// Where a [`QueryCursor`] stands in its result: the query and the position of the next row in
+/// it. Written as an opaque token with `to_string` and read back with `parse`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorPosition {
+    query_id: String,
+    /// The part of the result: 0 for the rows sent with the query response, `n` for chunk
+    /// `n - 1`.
+    part: usize,
+    offset: usize,
+}
+
+impl CursorPosition {
+    /// The ID of the query whose result the position is in.
+    pub fn query_id(&self) -> &str {
+        &self.query_id
+    }
+}
+
+impl fmt::Display for CursorPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = format!("{}:{}:{}", self.part, self.offset, self.query_id);
+        f.write_str(&URL_SAFE_NO_PAD.encode(position))
+    }
+}
+
+impl FromStr for CursorPosition {
+    type Err = Error;
+
+    fn from_str(token: &str) -> Result<Self> {
+        let invalid = || Error::InvalidCursor(format!("'{token}' is not a cursor position"));
+        let position = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+        let position = String::from_utf8(position).map_err(|_| invalid())?;
+        let mut parts = position.splitn(3, ':');
+        let mut number = || parts.next()?.parse().ok();
+        let (Some(part), Some(offset)) = (number(), number()) else {
+            return Err(invalid());
+        };
+        match parts.next() {
+            Some(query_id) if !query_id.is_empty() => Ok(Self {
+                query_id: query_id.to_string(),
+                part,
+                offset,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl QueryCursor {
+    fn new(rows: RowStream, page_size: usize) -> Self {
+        let stats = rows.stats();
+        Self {
+            rows,
+            page_size: page_size.max(1),
+            query_id: stats.query_id,
+            total_rows: stats.total_rows,
+        }
+    }
+
+    /// Returns the next page of rows, which is shorter than the page size only at the end of
+    /// the result, or `None` once every row has been returned.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<SnowflakeRow>>> {
+        let mut page = Vec::with_capacity(self.page_size);
+        while page.len() < self.page_size {
+            match self.rows.next_rows(self.page_size - page.len()).await {
+                Some(rows) => page.extend(rows?),
+                None => break,
+            }
+        }
+        Ok((!page.is_empty()).then_some(page))
+    }
+
+    /// The position of the next page, to resume from with [`SnowflakeSession::resume_cursor`].
+    pub fn position(&self) -> CursorPosition {
+        let (part, offset) = self.rows.position();
+        CursorPosition {
+            query_id: self.query_id.clone(),
+            part,
+            offset,
+        }
+    }
+
+    /// The rows in the whole result, if the query response tells.
+    pub fn total_rows(&self) -> Option<usize> {
+        self.total_rows
+    }
+
+    /// The ID of the query.
+    pub fn query_id(&self) -> &str {
+        &self.query_id
+    }
+
+    /// Returns the column names of the result.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.rows.column_names()
+    }
+}
+
+impl SnowflakeSession {
+    /// Runs a query and returns a [`QueryCursor`] reading its result `page_size` rows at a
+    /// time; a `page_size` of 0 is taken as 1.
+    pub async fn query_cursor<Q: Into<QueryRequest>>(
+        &self,
+        request: Q,
+        page_size: usize,
+    ) -> Result<QueryCursor> {
+        let result = self.query_lazy(request).await?;
+        Ok(QueryCursor::new(result.stream(), page_size))
+    }
+
+    /// Continues reading the result of a query at a position returned by
+    /// [`QueryCursor::position`], with pages of `page_size` rows. The result is fetched again
+    /// by its query ID, and the chunks before the position are not downloaded.
+    ///
+    /// Fails with [`Error::ResultExpired`] once Snowflake no longer keeps the result, and with
+    /// [`Error::InvalidCursor`] for a position that is not in it.
+    pub async fn resume_cursor(
+        &self,
+        position: &CursorPosition,
+        page_size: usize,
+    ) -> Result<QueryCursor> {
+        let mut rows = self.query_result(&position.query_id).await?.stream();
+        rows.seek(position.part, position.offset)?;
+        Ok(QueryCursor::new(rows, page_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::Ordering, Arc};
+
+    use super::*;
+    use crate::{
+        chunk::tests::fake_fetcher, row::Columns, stats::StatsRecorder, types::SnowflakeColumnType,
+        values::RowValues, QueryStats,
+    };
+
+    /// A result of the rows `a` and `b` sent with the response and `chunks` chunks of one row
+    /// each, holding the chunk number.
+    fn result(chunks: usize) -> (RowStream, Arc<std::sync::atomic::AtomicUsize>) {
+        let columns = Columns::new(vec![(
+            "VALUE".to_string(),
+            SnowflakeColumnType::new("text", None),
+        )]);
+        let row_set = RowValues::from_rows(vec![vec![Some("a".into())], vec![Some("b".into())]]);
+        let (fetcher, started) = fake_fetcher(chunks, 2);
+        let stats = StatsRecorder::default();
+        stats.record_response(QueryStats {
+            query_id: "01b0-query".into(),
+            total_rows: Some(2 + chunks),
+            ..Default::default()
+        });
+        let rows = RowStream::new(
+            Arc::new(columns),
+            row_set,
+            fetcher,
+            Some(2 + chunks),
+            0,
+            stats,
+        );
+        (rows, started)
+    }
+
+    async fn page(cursor: &mut QueryCursor) -> Result<Option<Vec<String>>> {
+        let Some(rows) = cursor.next_page().await? else {
+            return Ok(None);
+        };
+        let values = rows.iter().map(|row| row.get("VALUE"));
+        values.collect::<Result<_>>().map(Some)
+    }
+
+    #[tokio::test]
+    async fn test_pages() -> Result<()> {
+        let mut cursor = QueryCursor::new(result(4).0, 3);
+        assert_eq!(
+            (cursor.total_rows(), cursor.query_id()),
+            (Some(6), "01b0-query")
+        );
+        assert_eq!(page(&mut cursor).await?.unwrap(), ["a", "b", "0"]);
+        assert_eq!(page(&mut cursor).await?.unwrap(), ["1", "2", "3"]);
+        assert_eq!(page(&mut cursor).await?, None);
+        assert_eq!((cursor.position().part, cursor.position().offset), (5, 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume() -> Result<()> {
+        let mut cursor = QueryCursor::new(result(4).0, 1);
+        let mut positions = vec![cursor.position()];
+        let mut values = vec![];
+        while let Some(page) = page(&mut cursor).await? {
+            values.extend(page);
+            positions.push(cursor.position());
+        }
+        assert_eq!(values, ["a", "b", "0", "1", "2", "3"]);
+
+        // Resuming from each position reads the rest of the result, without downloading the
+        // chunks before it.
+        for (read, position) in positions.iter().enumerate() {
+            let position: CursorPosition = position.to_string().parse()?;
+            assert_eq!(position.query_id(), "01b0-query");
+            let (mut rows, started) = result(4);
+            rows.seek(position.part, position.offset)?;
+            let mut cursor = QueryCursor::new(rows, 10);
+            let rest = page(&mut cursor).await?.unwrap_or_default();
+            assert_eq!(rest, values[read..]);
+            assert_eq!(started.load(Ordering::SeqCst), (4 + 2 - read).min(4));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_positions() -> Result<()> {
+        assert!(matches!(
+            "not a token".parse::<CursorPosition>(),
+            Err(Error::InvalidCursor(_))
+        ));
+        let token = URL_SAFE_NO_PAD.encode("1:x:01b0-query");
+        assert!(token.parse::<CursorPosition>().is_err());
+
+        let (mut rows, _) = result(2);
+        assert!(matches!(rows.seek(0, 3), Err(Error::InvalidCursor(_))));
+        assert!(rows.seek(3, 1).is_err());
+        assert!(rows.seek(4, 0).is_err());
+
+        // An offset past the end of its chunk fails once the chunk arrives.
+        let (mut rows, _) = result(2);
+        rows.seek(1, 2)?;
+        let mut cursor = QueryCursor::new(rows, 10);
+        assert!(matches!(
+            cursor.next_page().await,
+            Err(Error::InvalidCursor(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,496 @@
+//! A [`serde::Deserializer`] over [`SnowflakeRow`].
+//!
+//! A row deserializes as a map from column names to cell values. Struct fields are matched to
+//! columns case-insensitively, so `id` picks up the `ID` column; use `#[serde(rename = "...")]`
+//! when a field name differs from its column. Fields without a matching column are reported as
+//! missing, which serde turns into `None` for `Option` fields.
+
+use std::fmt::Display;
+
+use serde::de::{
+    self, value::StrDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+
+use crate::{
+    numeric::parse_integer,
+    row::{parse_bool, unquote_json_string, Columns},
+    types::SnowflakeColumnType,
+    Error, Result, SnowflakeRow,
+};
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::decode(msg.to_string())
+    }
+}
+
+pub(crate) struct RowDeserializer<'a> {
+    row: &'a SnowflakeRow,
+}
+
+impl<'a> RowDeserializer<'a> {
+    pub(crate) fn new(row: &'a SnowflakeRow) -> Self {
+        Self { row }
+    }
+
+    fn cell(&self, index: usize) -> CellDeserializer<'a> {
+        CellDeserializer {
+            value: self.row.value(index),
+            column_type: self.row.columns.column_type(index),
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RowDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let columns = &self.row.columns;
+        let fields = (0..columns.len())
+            .map(|index| (columns.name(index), index))
+            .collect();
+        visitor.visit_map(RowMapAccess::new(self, fields))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let fields = resolve_fields(&self.row.columns, fields);
+        visitor.visit_map(RowMapAccess::new(self, fields))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(RowSeqAccess { de: self, index: 0 })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// Maps each struct field to the column it reads from, skipping fields without a column.
+fn resolve_fields(
+    columns: &Columns,
+    fields: &'static [&'static str],
+) -> Vec<(&'static str, usize)> {
+    fields
+        .iter()
+        .filter_map(|field| columns.index_of(field).map(|index| (*field, index)))
+        .collect()
+}
+
+struct RowMapAccess<'a, K> {
+    de: RowDeserializer<'a>,
+    fields: std::vec::IntoIter<(K, usize)>,
+    current: Option<(K, usize)>,
+}
+
+impl<'a, K> RowMapAccess<'a, K> {
+    fn new(de: RowDeserializer<'a>, fields: Vec<(K, usize)>) -> Self {
+        Self {
+            de,
+            fields: fields.into_iter(),
+            current: None,
+        }
+    }
+}
+
+impl<'de, 'a, K: AsRef<str> + Copy> MapAccess<'de> for RowMapAccess<'a, K> {
+    type Error = Error;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        self.current = self.fields.next();
+        match self.current {
+            Some((key, _)) => {
+                let key: StrDeserializer<'_, Error> = key.as_ref().into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value> {
+        let (key, index) = self
+            .current
+            .take()
+            .ok_or_else(|| Error::decode("value requested before key"))?;
+        seed.deserialize(self.de.cell(index)).map_err(|e| {
+            e.decode_context(format_args!(
+                "failed to decode field '{}' from column '{}'",
+                key.as_ref(),
+                self.de.row.columns.name(index),
+            ))
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+struct RowSeqAccess<'a> {
+    de: RowDeserializer<'a>,
+    index: usize,
+}
+
+impl<'de, 'a> SeqAccess<'de> for RowSeqAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>> {
+        if self.index >= self.de.row.len() {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(self.de.cell(index))
+            .map(Some)
+            .map_err(|e| {
+                e.decode_context(format_args!(
+                    "failed to decode column {} '{}'",
+                    index,
+                    self.de.row.columns.name(index),
+                ))
+            })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.de.row.len() - self.index)
+    }
+}
+
+/// Deserializes a single cell, using the column type to interpret the wire string.
+struct CellDeserializer<'a> {
+    value: Option<&'a str>,
+    column_type: &'a SnowflakeColumnType,
+}
+
+impl<'a> CellDeserializer<'a> {
+    fn value(&self) -> Result<&'a str> {
+        self.value.ok_or_else(|| Error::decode("value is null"))
+    }
+
+    fn parse<T: std::str::FromStr>(&self, type_name: &str) -> Result<T> {
+        let value = self.value()?;
+        value
+            .parse()
+            .map_err(|_| Error::decode(format!("'{value}' is not {type_name}")))
+    }
+
+    fn json(&self) -> Result<serde_json::Value> {
+        let value = self.value()?;
+        serde_json::from_str(value).map_err(|e| Error::Json(e, value.to_string()))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(parse_integer::<$ty>(self.value()?, stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for CellDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let Some(value) = self.value else {
+            return visitor.visit_unit();
+        };
+        match self.column_type.snowflake_type.as_str() {
+            "fixed" if self.column_type.scale.unwrap_or(0) == 0 => {
+                if let Ok(v) = value.parse::<i64>() {
+                    visitor.visit_i64(v)
+                } else if let Ok(v) = value.parse::<u64>() {
+                    visitor.visit_u64(v)
+                } else {
+                    visitor.visit_str(value)
+                }
+            }
+            "fixed" | "real" => match value.parse::<f64>() {
+                Ok(v) => visitor.visit_f64(v),
+                Err(_) => visitor.visit_str(value),
+            },
+            "boolean" => self.deserialize_bool(visitor),
+            _ if self.column_type.is_semi_structured() => self
+                .json()?
+                .deserialize_any(visitor)
+                .map_err(|e| Error::Json(e, value.to_string())),
+            _ => visitor.visit_str(value),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(parse_bool(self.value()?)?)
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+    }
+
+    deserialize_parsed! {
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.value()?;
+        if self.column_type.is_semi_structured() {
+            return visitor.visit_string(unquote_json_string(value));
+        }
+        visitor.visit_str(value)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Some(_) => visitor.visit_some(self),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.column_type.is_semi_structured() {
+            let value = self.value()?;
+            return self
+                .json()?
+                .deserialize_enum(name, variants, visitor)
+                .map_err(|e| Error::Json(e, value.to_string()));
+        }
+        let value: StrDeserializer<'_, Error> = self.value()?.into_deserializer();
+        value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_json(visitor, |json, visitor| json.deserialize_seq(visitor))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_json(visitor, |json, visitor| json.deserialize_map(visitor))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_json(visitor, |json, visitor| {
+            json.deserialize_struct(name, fields, visitor)
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bytes byte_buf unit_struct tuple tuple_struct identifier ignored_any
+    }
+}
+
+impl<'a> CellDeserializer<'a> {
+    /// Nested values are stored as JSON text regardless of the column type, so compound targets
+    /// always go through `serde_json`.
+    fn deserialize_json<'de, V, F>(self, visitor: V, f: F) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+        F: FnOnce(serde_json::Value, V) -> serde_json::Result<V::Value>,
+    {
+        let value = self.value()?;
+        f(self.json()?, visitor).map_err(|e| Error::Json(e, value.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn row(columns: &[(&str, &str, Option<i64>, Option<&str>)]) -> SnowflakeRow {
+        SnowflakeRow::new(
+            columns
+                .iter()
+                .map(|(_, _, _, value)| value.map(str::to_string))
+                .collect(),
+            Arc::new(Columns::new(
+                columns
+                    .iter()
+                    .map(|(name, ty, scale, _)| {
+                        (name.to_string(), SnowflakeColumnType::new(ty, *scale))
+                    })
+                    .collect(),
+            )),
+        )
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Nested {
+        a: i64,
+        b: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Record {
+        id: i64,
+        name: String,
+        score: f64,
+        active: bool,
+        note: Option<String>,
+        missing: Option<String>,
+        #[serde(rename = "PAYLOAD")]
+        data: Nested,
+        raw: serde_json::Value,
+    }
+
+    #[test]
+    fn test_deserialize_struct() -> Result<()> {
+        let row = row(&[
+            ("ID", "fixed", Some(0), Some("42")),
+            ("NAME", "text", None, Some("hello")),
+            ("SCORE", "fixed", Some(2), Some("1.25")),
+            ("ACTIVE", "boolean", None, Some("1")),
+            ("NOTE", "text", None, None),
+            (
+                "PAYLOAD",
+                "object",
+                None,
+                Some(r#"{"a": 1, "b": ["x", "y"]}"#),
+            ),
+            ("RAW", "variant", None, Some("[1, 2]")),
+        ]);
+
+        let record: Record = row.deserialize()?;
+        assert_eq!(
+            record,
+            Record {
+                id: 42,
+                name: "hello".into(),
+                score: 1.25,
+                active: true,
+                note: None,
+                missing: None,
+                data: Nested {
+                    a: 1,
+                    b: vec!["x".into(), "y".into()],
+                },
+                raw: serde_json::json!([1, 2]),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_map_uses_column_types() -> Result<()> {
+        let row = row(&[
+            ("ID", "fixed", Some(0), Some("42")),
+            ("NAME", "text", None, Some("hello")),
+            ("NOTE", "text", None, None),
+            ("PAYLOAD", "variant", None, Some(r#"{"a": 1}"#)),
+        ]);
+
+        let map: HashMap<String, serde_json::Value> = row.deserialize()?;
+        assert_eq!(map["ID"], serde_json::json!(42));
+        assert_eq!(map["NAME"], serde_json::json!("hello"));
+        assert_eq!(map["NOTE"], serde_json::Value::Null);
+        assert_eq!(map["PAYLOAD"], serde_json::json!({"a": 1}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_tuple() -> Result<()> {
+        let row = row(&[
+            ("ID", "fixed", Some(0), Some("1")),
+            ("NAME", "text", None, Some("a")),
+        ]);
+
+        let (id, name): (i64, String) = row.deserialize()?;
+        assert_eq!((id, name), (1, "a".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_error_names_field_and_column() {
+        #[derive(Debug, Deserialize)]
+        #[allow(unused)]
+        struct Record {
+            id: i64,
+        }
+
+        let row = row(&[("ID", "text", None, Some("abc"))]);
+        let err = row.deserialize::<Record>().unwrap_err().to_string();
+        assert!(err.contains("field 'id'"), "{err}");
+        assert!(err.contains("column 'ID'"), "{err}");
+        assert!(err.contains("'abc' is not i64"), "{err}");
+    }
+}
@@ -0,0 +1,222 @@
+//! The rows a DML statement changed, from the row of counts it returns.
+
+use crate::{
+    Error, QueryRequest, QueryResult, Result, SnowflakeRow, SnowflakeSession, StatementType,
+};
+
+/// What a DML statement changed, read from the single row of counts Snowflake returns for it.
+/// See [`QueryResult::dml_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DmlStats {
+    Insert {
+        inserted: u64,
+    },
+    Update {
+        updated: u64,
+        /// The rows updated that the `FROM` clause joined more than once.
+        multi_joined: u64,
+    },
+    Delete {
+        deleted: u64,
+    },
+    /// A `MERGE`; the counts of the actions the statement has no clause for are 0.
+    Merge {
+        inserted: u64,
+        updated: u64,
+        deleted: u64,
+    },
+    /// An `INSERT ALL` or `INSERT FIRST`, with the rows inserted by each `INTO` clause in
+    /// order. The name is that of the column of its count, which names the target table, with
+    /// `number of rows inserted into ` taken off where it starts with that.
+    MultiTableInsert(Vec<(String, u64)>),
+}
+
+impl DmlStats {
+    /// All the rows the statement inserted, updated or deleted.
+    pub fn total(&self) -> u64 {
+        match self {
+            DmlStats::Insert { inserted } => *inserted,
+            DmlStats::Update { updated, .. } => *updated,
+            DmlStats::Delete { deleted } => *deleted,
+            DmlStats::Merge {
+                inserted,
+                updated,
+                deleted,
+            } => inserted + updated + deleted,
+            DmlStats::MultiTableInsert(tables) => tables.iter().map(|(_, count)| count).sum(),
+        }
+    }
+
+    /// Reads the counts of a statement of type `statement_type` from its row.
+    fn from_row(statement_type: StatementType, row: &SnowflakeRow) -> Result<Option<Self>> {
+        let count = |column_name| -> Result<u64> {
+            Ok(row.try_get::<u64>(column_name)?.unwrap_or_default())
+        };
+        let stats = match statement_type {
+            StatementType::Insert => DmlStats::Insert {
+                inserted: count(INSERTED)?,
+            },
+            StatementType::Update => DmlStats::Update {
+                updated: count(UPDATED)?,
+                multi_joined: count(MULTI_JOINED_UPDATED)?,
+            },
+            StatementType::Delete => DmlStats::Delete {
+                deleted: count(DELETED)?,
+            },
+            StatementType::Merge => DmlStats::Merge {
+                inserted: count(INSERTED)?,
+                updated: count(UPDATED)?,
+                deleted: count(DELETED)?,
+            },
+            StatementType::MultiTableInsert => {
+                let tables = row
+                    .column_names()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, name)| {
+                        let table = strip_prefix_ignore_case(name, INSERTED_INTO).unwrap_or(name);
+                        Ok((table.to_string(), row.get_index::<u64>(i)?))
+                    })
+                    .collect::<Result<_>>()?;
+                DmlStats::MultiTableInsert(tables)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(stats))
+    }
+}
+
+/// The columns of the counts of a DML result.
+const INSERTED: &str = "number of rows inserted";
+const UPDATED: &str = "number of rows updated";
+const DELETED: &str = "number of rows deleted";
+const MULTI_JOINED_UPDATED: &str = "number of multi-joined rows updated";
+const INSERTED_INTO: &str = "number of rows inserted into ";
+
+fn strip_prefix_ignore_case<'a>(name: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = name.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &name[prefix.len()..])
+}
+
+impl QueryResult {
+    /// The rows an `INSERT`, `UPDATE`, `DELETE`, `MERGE` or multi-table `INSERT` changed,
+    /// from its columns by name, with the statement type telling which. `None` for other
+    /// statements, including `COPY INTO`, and for results without a statement type.
+    pub fn dml_stats(&self) -> Result<Option<DmlStats>> {
+        match (self.statement_type(), self.first()) {
+            (Some(statement_type), Some(row)) => DmlStats::from_row(statement_type, row),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl SnowflakeSession {
+    /// Runs a DML statement and returns the rows it changed; see [`QueryResult::dml_stats`].
+    /// Fails with [`Error::Unsupported`] for a statement that reports no counts.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{DmlStats, Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let stats = session
+    ///     .execute_dml("MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN UPDATE SET t.v = s.v")
+    ///     .await?;
+    /// if let DmlStats::Merge { updated, .. } = stats {
+    ///     println!("{updated} rows updated");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_dml(&self, request: impl Into<QueryRequest>) -> Result<DmlStats> {
+        let result = self.query(request).await?;
+        if let Some(stats) = result.dml_stats()? {
+            return Ok(stats);
+        }
+        let statement_type = match result.statement_type() {
+            Some(statement_type) => format!("{statement_type:?}"),
+            None => "unknown".into(),
+        };
+        Err(Error::Unsupported(format!(
+            "DML counts of a statement of type {statement_type}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{__private::rows, QueryStats, ResultMetadata};
+
+    fn result(statement_type_id: i64, columns: &[&str], counts: &[u64]) -> QueryResult {
+        let columns = columns
+            .iter()
+            .map(|name| (*name, "fixed"))
+            .collect::<Vec<_>>();
+        let values = vec![counts.iter().map(|count| Some(count.to_string())).collect()];
+        let rows = rows(&columns, values);
+        let metadata = ResultMetadata::new(
+            std::sync::Arc::clone(&rows[0].columns),
+            QueryStats {
+                statement_type_id: Some(statement_type_id),
+                ..Default::default()
+            },
+        );
+        QueryResult::new(rows, metadata)
+    }
+
+    #[test]
+    fn test_dml_stats() -> Result<()> {
+        let insert = result(0x3100, &[INSERTED], &[3]);
+        assert_eq!(insert.dml_stats()?, Some(DmlStats::Insert { inserted: 3 }));
+
+        let update = result(0x3200, &[UPDATED, MULTI_JOINED_UPDATED], &[5, 1]);
+        assert_eq!(
+            update.dml_stats()?,
+            Some(DmlStats::Update {
+                updated: 5,
+                multi_joined: 1
+            })
+        );
+
+        let delete = result(0x3300, &[DELETED], &[2]);
+        assert_eq!(delete.dml_stats()?, Some(DmlStats::Delete { deleted: 2 }));
+
+        // Columns in another order, and no column for the missing DELETE clause.
+        let merge = result(0x3400, &[UPDATED, INSERTED], &[4, 6]);
+        let stats = merge.dml_stats()?.unwrap();
+        assert_eq!(
+            stats,
+            DmlStats::Merge {
+                inserted: 6,
+                updated: 4,
+                deleted: 0
+            }
+        );
+        assert_eq!(stats.total(), 10);
+
+        let multi = result(
+            0x3500,
+            &[
+                "number of rows inserted into T1",
+                "Number Of Rows Inserted Into \"t2\"",
+                "T3",
+            ],
+            &[1, 2, 3],
+        );
+        let stats = multi.dml_stats()?.unwrap();
+        assert_eq!(
+            stats,
+            DmlStats::MultiTableInsert(vec![
+                ("T1".into(), 1),
+                ("\"t2\"".into(), 2),
+                ("T3".into(), 3)
+            ])
+        );
+        assert_eq!(stats.total(), 6);
+
+        assert_eq!(result(0x1000, &["N"], &[1]).dml_stats()?, None);
+        assert_eq!(result(0x3600, &["rows_loaded"], &[1]).dml_stats()?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Decoding of string-valued columns into Rust enums.
+
+/// Defines an enum that decodes from a string column with [`SnowflakeDecode`](crate::SnowflakeDecode).
+///
+/// Each variant is listed with the string it is stored as. An unknown value fails with an error
+/// listing the expected values, unless the enum ends with `..Name`, which adds a `Name(String)`
+/// variant catching every other value. Values match exactly; start the macro with
+/// `case_insensitive;` to match ignoring ASCII case. The generated `as_str` method returns the
+/// stored string of a variant.
+///
+/// ```rust
+/// use snowflake_connector_rs::snowflake_enum;
+///
+/// snowflake_enum! {
+///     #[derive(Debug, Clone, PartialEq, Eq)]
+///     pub enum Status {
+///         Pending = "PENDING",
+///         Active = "ACTIVE",
+///         Closed = "CLOSED",
+///     }
+/// }
+///
+/// snowflake_enum! {
+///     case_insensitive;
+///     #[derive(Debug, Clone, PartialEq, Eq)]
+///     pub enum Channel {
+///         Web = "web",
+///         Store = "store",
+///         ..Other
+///     }
+/// }
+///
+/// assert_eq!(Status::Active.as_str(), "ACTIVE");
+/// assert_eq!(Channel::Other("phone".into()).as_str(), "phone");
+/// ```
+///
+/// Enums deriving `serde::Deserialize` also work with
+/// [`SnowflakeRow::deserialize`](crate::SnowflakeRow::deserialize) and
+/// [`SnowflakeSession::query_as`](crate::SnowflakeSession::query_as), where unknown values are
+/// reported with the expected variants as well.
+#[macro_export]
+macro_rules! snowflake_enum {
+    (case_insensitive; $($rest:tt)*) => {
+        $crate::snowflake_enum!(@impl true; $($rest)*);
+    };
+    (@impl $case_insensitive:literal;
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident = $value:literal),+
+            $(, ..$other:ident)? $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant,)+
+            $($other(::std::string::String),)?
+        }
+
+        impl $name {
+            /// Returns the string this value is stored as.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)+
+                    $(Self::$other(value) => value,)?
+                }
+            }
+        }
+
+        impl $crate::SnowflakeDecode for $name {
+            fn try_decode(value: &::std::option::Option<::std::string::String>) -> $crate::Result<Self> {
+                Self::try_decode_typed(value, &$crate::__private::text_type())
+            }
+
+            fn try_decode_typed(
+                value: &::std::option::Option<::std::string::String>,
+                column_type: &$crate::SnowflakeColumnType,
+            ) -> $crate::Result<Self> {
+                Self::try_decode_str(value.as_deref(), column_type)
+            }
+
+            #[allow(unreachable_code)]
+            fn try_decode_str(
+                value: ::std::option::Option<&str>,
+                column_type: &$crate::SnowflakeColumnType,
+            ) -> $crate::Result<Self> {
+                let value = <::std::string::String as $crate::SnowflakeDecode>::try_decode_str(
+                    value,
+                    column_type,
+                )?;
+                $(
+                    if $crate::__private::enum_value_eq(&value, $value, $case_insensitive) {
+                        return ::std::result::Result::Ok(Self::$variant);
+                    }
+                )+
+                $(return ::std::result::Result::Ok(Self::$other(value));)?
+                ::std::result::Result::Err($crate::__private::unknown_enum_value(
+                    &value,
+                    stringify!($name),
+                    &[$($value),+],
+                ))
+            }
+        }
+    };
+    ($($rest:tt)*) => {
+        $crate::snowflake_enum!(@impl false; $($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{row::tests::typed_row, Result};
+
+    snowflake_enum! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        enum Status {
+            Pending = "PENDING",
+            Active = "ACTIVE",
+            Closed = "CLOSED",
+        }
+    }
+
+    snowflake_enum! {
+        case_insensitive;
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        enum Channel {
+            Web = "web",
+            Store = "store",
+            ..Other
+        }
+    }
+
+    #[test]
+    fn test_snowflake_enum() -> Result<()> {
+        let row = typed_row(&[
+            ("STATUS", "text", Some("ACTIVE")),
+            ("LOWER", "text", Some("active")),
+            ("VAR", "variant", Some("\"CLOSED\"")),
+            ("CHANNEL", "text", Some("WEB")),
+            ("UNKNOWN", "text", Some("phone")),
+            ("NUL", "text", None),
+        ]);
+
+        assert_eq!(row.get::<Status>("STATUS")?, Status::Active);
+        assert_eq!(row.get::<Status>("VAR")?, Status::Closed);
+        assert_eq!(row.get::<Option<Status>>("NUL")?, None);
+        assert_eq!(
+            row.get::<Status>("LOWER").unwrap_err().to_string(),
+            "decode error: column 'LOWER' (index 1, type text) as Status: \
+             'active' is not a valid Status, expected one of: PENDING, ACTIVE, CLOSED"
+        );
+
+        assert_eq!(row.get::<Channel>("CHANNEL")?, Channel::Web);
+        assert_eq!(
+            row.get::<Channel>("UNKNOWN")?,
+            Channel::Other("phone".into())
+        );
+        assert_eq!(Status::Pending.as_str(), "PENDING");
+        assert_eq!(Channel::Other("phone".into()).as_str(), "phone");
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_enum() -> Result<()> {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(rename_all = "UPPERCASE")]
+        enum Status {
+            Pending,
+            Active,
+            #[serde(untagged)]
+            Other(String),
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(rename_all = "UPPERCASE")]
+        enum Strict {
+            Pending,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Record<T> {
+            status: T,
+        }
+
+        let active = typed_row(&[("STATUS", "text", Some("ACTIVE"))]);
+        let closed = typed_row(&[("STATUS", "text", Some("CLOSED"))]);
+        assert_eq!(
+            active.deserialize::<Record<Status>>()?,
+            Record {
+                status: Status::Active
+            }
+        );
+        assert_eq!(
+            closed.deserialize::<Record<Status>>()?,
+            Record {
+                status: Status::Other("CLOSED".into())
+            }
+        );
+        let err = closed
+            .deserialize::<Record<Strict>>()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("unknown variant `CLOSED`, expected `PENDING`"),
+            "{err}"
+        );
+        Ok(())
+    }
+}
@@ -1,58 +1,1088 @@
-use std::string::FromUtf8Error;
+use std::{fmt::Display, string::FromUtf8Error, time::Duration};
 
 use reqwest::header::InvalidHeaderValue;
 use tokio::task::JoinError;
 
 /// An error that can occur when interacting with Snowflake.
 ///
+/// Variants that wrap the error of another library, or another [`Error`], return it from
+/// [`source`](std::error::Error::source) rather than repeating it in their message, so that
+/// error reporters such as `anyhow` show each cause once.
+///
 /// Note: Errors may include sensitive information from Snowflake.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error("HTTP client error: {0}")]
+    #[error("HTTP client error")]
     Reqwest(#[from] reqwest::Error),
 
     #[error("communication error: {0}")]
     Communication(String),
 
-    #[error("invalid header value: {0}")]
+    /// A request to Snowflake or to the storage service of the result chunks got a response
+    /// with an error status, or one that could not be parsed (`parse_error`). `url` is without
+    /// its query string, which may hold credentials. `code` and `message` are the top-level
+    /// fields of a JSON body, the code if it is a Snowflake error code, and `body` is the start
+    /// of the response, pretty-printed if it is JSON; all of it with
+    /// [`SnowflakeClientConfig::full_response_bodies`](crate::SnowflakeClientConfig::full_response_bodies).
+    #[error(
+        "{method} {url} returned {status}{}{}: {body}",
+        if .parse_error.is_some() { " with a body that could not be parsed" } else { "" },
+        server_message(.code, .message)
+    )]
+    HttpResponse {
+        method: http::Method,
+        url: String,
+        status: http::StatusCode,
+        code: Option<u32>,
+        message: Option<String>,
+        body: String,
+        #[source]
+        parse_error: Option<serde_json::Error>,
+    },
+
+    /// Snowflake answered a request with an empty body, which usually means that the account
+    /// identifier does not name an account, e.g. because it lacks its region. `url` is without
+    /// its query string.
+    #[error(
+        "Snowflake returned an empty response from {url} (status {status}); \
+         check your account identifier"
+    )]
+    EmptyResponse {
+        url: String,
+        status: http::StatusCode,
+    },
+
+    /// A request to Snowflake was answered with `content_type`, e.g. an HTML page, rather than
+    /// JSON, which usually means that a proxy or captive portal answered instead of Snowflake.
+    /// `body` is the start of the response, as in [`Error::HttpResponse`].
+    #[error(
+        "received {} instead of JSON from {url} (status {status}); \
+         you may be behind a captive portal or proxy: {body}",
+        content_type_name(.content_type)
+    )]
+    NotJson {
+        url: String,
+        status: http::StatusCode,
+        content_type: String,
+        body: String,
+    },
+
+    /// A request to Snowflake was refused with 429 Too Many Requests, and still was after
+    /// `retries` retries. `url` is without its query string.
+    #[error("{method} {url} was rate limited (429 Too Many Requests) after {retries} retries")]
+    RateLimited {
+        method: http::Method,
+        url: String,
+        retries: usize,
+    },
+
+    #[error("invalid header value")]
     InvalidHeader(#[from] InvalidHeaderValue),
 
-    #[error("http error: {0}")]
+    #[error("invalid HTTP request")]
     Http(#[from] http::Error),
 
-    #[error("session expired")]
-    SessionExpired,
+    /// The session token has expired (error 390112). The session can be renewed.
+    #[error("session expired ({code}): {message}")]
+    SessionExpired { code: u32, message: String },
+
+    /// The authentication token has expired (error 390114), so a new session has to be created
+    /// by logging in again.
+    #[error("authentication token expired ({code}): {message}")]
+    AuthTokenExpired { code: u32, message: String },
+
+    /// The session no longer exists on the server (error 390195), e.g. because it was closed;
+    /// it should be discarded.
+    #[error("session no longer exists ({code}): {message}")]
+    SessionGone { code: u32, message: String },
+
+    /// Snowflake failed internally (error 000603, or a message that names an incident), which
+    /// is usually transient: the statement may succeed when retried after a delay. Support asks
+    /// for `incident_id`, taken from the message when it has one.
+    #[error(
+        "Snowflake internal error {code:06}{}: {message}",
+        .incident_id.as_ref().map(|id| format!(" (incident {id})")).unwrap_or_default()
+    )]
+    ServerIncident {
+        incident_id: Option<String>,
+        code: u32,
+        message: String,
+        query_id: Option<String>,
+    },
+
+    /// The warehouse of a statement did not become available while the statement was sent
+    /// again with
+    /// [`retry_on_warehouse_resume`](crate::SnowflakeClientConfig::retry_on_warehouse_resume),
+    /// `waited` after it was first sent. `warehouse` is named by the error, or else by the
+    /// configuration, and `source` is the error of the last attempt.
+    #[error(
+        "warehouse {} never became available, waited {waited:?}",
+        .warehouse.as_deref().unwrap_or("(unnamed)")
+    )]
+    WarehouseUnavailable {
+        warehouse: Option<String>,
+        waited: Duration,
+        source: Box<Error>,
+    },
+
+    /// A request or the wait for a query to finish took too long. `query_id` is the ID of a
+    /// query that was abandoned while still running, which can be used to find or cancel it.
+    #[error(
+        "{phase} timed out after {elapsed:?}{}",
+        .query_id.as_ref().map(|id| format!(" (query {id})")).unwrap_or_default()
+    )]
+    Timeout {
+        phase: TimeoutPhase,
+        elapsed: Duration,
+        query_id: Option<String>,
+    },
 
     #[error("chunk download error: {0}")]
     ChunkDownload(String),
 
-    #[error("io error: {0}")]
+    /// A result chunk could not be downloaded, even after retrying. Rows up to
+    /// `rows_delivered` have been returned to the caller; the rest of the result is lost.
+    #[error("chunk {chunk_index} failed after {rows_delivered} rows were delivered")]
+    ChunkFailed {
+        chunk_index: usize,
+        /// The URL of the chunk, without the query string that holds its signature.
+        url: String,
+        rows_delivered: usize,
+        source: Box<Error>,
+    },
+
+    #[error("io error")]
     IO(#[from] std::io::Error),
 
-    #[error("json parse error: {0} {1}")]
-    Json(serde_json::Error, String),
+    /// The result of the query with this ID is no longer available on the server.
+    #[error("query result expired: {0}")]
+    ResultExpired(String),
+
+    /// The JSON in the string could not be parsed.
+    #[error("json parse error: {1}")]
+    Json(#[source] serde_json::Error, String),
 
-    #[error("utf-8 error: {0}")]
+    #[error("invalid utf-8")]
     Utf8Error(#[from] FromUtf8Error),
 
-    #[error("future join error: {0}")]
+    #[error("background task failed")]
     FutureJoin(#[from] JoinError),
 
     #[error("decode error: {0}")]
-    Decode(String),
+    Decode(Box<DecodeError>),
 
-    #[error("decrypt error: {0}")]
+    #[error("failed to decrypt the private key")]
     Decryption(#[from] pkcs8::Error),
 
-    #[error("der error: {0}")]
+    #[error("invalid private key")]
     Der(#[from] pkcs8::spki::Error),
 
-    #[error("jwt error: {0}")]
+    #[error("failed to create the login JWT")]
     JWT(#[from] jsonwebtoken::errors::Error),
 
+    /// A statement failed on the server, with Snowflake's error code, e.g. `2003` for an object
+    /// that does not exist, and its SQLSTATE. `message` is the message from the server, and
+    /// `position` where in the statement it points to, e.g. for a syntax error; see
+    /// [`Error::render_snippet`].
+    #[error("SQL error {code:06} ({sqlstate}): {message}")]
+    Sql {
+        code: u32,
+        sqlstate: String,
+        message: String,
+        query_id: Option<String>,
+        position: Option<SqlPosition>,
+    },
+
     #[error("unsupported format: {0}")]
     UnsupportedFormat(String),
+
+    /// A cursor position does not name a row of its result, e.g. because it was not returned by
+    /// [`QueryCursor::position`](crate::QueryCursor::position).
+    #[error("invalid cursor position: {0}")]
+    InvalidCursor(String),
+
+    /// The configuration asks for something the connector cannot do, e.g. password
+    /// authentication with the SQL API.
+    #[error("not supported: {0}")]
+    Unsupported(String),
+
+    /// A value could not be bound to a placeholder, e.g. because it is a struct, or the statement
+    /// has no placeholder for it.
+    #[error("bind error: {0}")]
+    Bind(String),
+
+    /// An object name given to the connector is not a valid SQL identifier, e.g. because a quote
+    /// is not closed.
+    #[error("invalid identifier: {0}")]
+    InvalidIdentifier(String),
+
+    /// The result metadata announced `expected` rows but `received` rows were read, either for
+    /// the chunk at `chunk_index` or, without one, for the whole result, e.g. because a download
+    /// was cut short or some of the rows were sent in a form that is not parsed.
+    #[error(
+        "incomplete result{}: expected {expected} rows, received {received}",
+        .chunk_index.map(|i| format!(" (chunk {i})")).unwrap_or_default()
+    )]
+    IncompleteResult {
+        chunk_index: Option<usize>,
+        expected: usize,
+        received: usize,
+    },
+
+    /// Writing a result to disk would have exceeded
+    /// [`SpillConfig::max_disk_bytes`](crate::SpillConfig::max_disk_bytes).
+    #[error("spilled result exceeds the disk budget of {0} bytes")]
+    SpillLimitExceeded(u64),
+
+    /// The result is larger than
+    /// [`max_result_rows`](crate::SnowflakeClientConfig::max_result_rows) or
+    /// [`max_result_bytes`](crate::SnowflakeClientConfig::max_result_bytes) allow. Rows up to
+    /// `rows_so_far` have been returned to the caller; no further chunks are downloaded.
+    #[error("result exceeds the limit of {limit} after {rows_so_far} rows")]
+    ResultTooLarge {
+        rows_so_far: usize,
+        limit: ResultLimit,
+    },
+
+    /// The columns of the data to load do not match the table; `mismatches` names each column
+    /// that does not, and why, e.g. `AMOUNT: Decimal128(12, 4) does not fit NUMBER(10,2)`.
+    #[error("the data does not match table {table}: {}", .mismatches.join("; "))]
+    SchemaMismatch {
+        table: String,
+        mismatches: Vec<String>,
+    },
+
+    /// Writing an exported result failed after `rows_written` rows had been written, e.g.
+    /// because the upload it was streamed to broke off. Failures of the query and of its chunk
+    /// downloads keep their own variants.
+    #[error("writing the export failed after {rows_written} rows")]
+    ExportWrite {
+        rows_written: u64,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// The code and message of a response body, for the message of an [`Error::HttpResponse`].
+fn server_message(code: &Option<u32>, message: &Option<String>) -> String {
+    match (code, message) {
+        (Some(code), Some(message)) => format!(" (code {code:06}: {message})"),
+        (Some(code), None) => format!(" (code {code:06})"),
+        (None, Some(message)) => format!(" ({message})"),
+        (None, None) => String::new(),
+    }
+}
+
+/// The ID of the incident a message of Snowflake names, as in "Processing aborted due to error
+/// 300002:2523766418; incident 4829384.".
+fn incident_id(message: &str) -> Option<String> {
+    let start = message.to_ascii_lowercase().find("incident")? + "incident".len();
+    let id = message[start..]
+        .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '#' | '='))
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .next()?;
+    id.contains(|c: char| c.is_ascii_digit())
+        .then(|| id.to_string())
+}
+
+/// Names the content type of an [`Error::NotJson`]: `HTML` for an HTML page, the type itself
+/// otherwise.
+fn content_type_name(content_type: &str) -> &str {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    match essence.eq_ignore_ascii_case("text/html") {
+        true => "HTML",
+        false => content_type,
+    }
+}
+
+/// What an [`Error::Timeout`] happened in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeoutPhase {
+    /// Connecting to Snowflake or to the storage service of the result chunks.
+    Connect,
+    /// Sending a request and receiving its response, e.g. within
+    /// [`chunk_request_timeout`](crate::SnowflakeClientConfig::chunk_request_timeout).
+    Request,
+    /// Polling for the result of a running query, after
+    /// [`max_polling_attempts`](crate::SnowflakeClientConfig::max_polling_attempts) or once the
+    /// statement timeout has passed.
+    Polling,
+    /// Running a statement, which Snowflake cancelled after its statement timeout, e.g.
+    /// [`default_statement_timeout`](crate::SnowflakeClientConfig::default_statement_timeout).
+    Statement,
+}
+
+impl Display for TimeoutPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TimeoutPhase::Connect => "connection",
+            TimeoutPhase::Request => "request",
+            TimeoutPhase::Polling => "polling for the query result",
+            TimeoutPhase::Statement => "statement",
+        })
+    }
+}
+
+/// The limit an [`Error::ResultTooLarge`] ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultLimit {
+    /// The most rows of a result.
+    Rows(usize),
+    /// The most bytes of result chunks, uncompressed.
+    Bytes(u64),
+}
+
+impl Display for ResultLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultLimit::Rows(rows) => write!(f, "{rows} rows"),
+            ResultLimit::Bytes(bytes) => write!(f, "{bytes} bytes"),
+        }
+    }
+}
+
+/// A position in a statement, as Snowflake error messages name it, e.g. `line 3 at position 15`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqlPosition {
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The character in the line, starting at 0.
+    pub column: usize,
+}
+
+/// The lines shown before and after the line of a [`SqlPosition`] by
+/// [`Error::render_snippet`].
+const SNIPPET_CONTEXT: usize = 2;
+
+impl SqlPosition {
+    /// Finds the first `line <n> at position <m>` in an error message.
+    fn from_message(message: &str) -> Option<Self> {
+        message.match_indices("line ").find_map(|(start, _)| {
+            let rest = &message[start + "line ".len()..];
+            let (line, rest) = rest.split_once(" at position ")?;
+            let column = rest.split(|c: char| !c.is_ascii_digit()).next()?;
+            Some(Self {
+                line: line.parse().ok().filter(|&line| line > 0)?,
+                column: column.parse().ok()?,
+            })
+        })
+    }
+
+    fn render(self, sql: &str) -> Option<String> {
+        let lines = sql.lines().collect::<Vec<_>>();
+        let index = self.line - 1;
+        let line = lines.get(index)?;
+        let first = index.saturating_sub(SNIPPET_CONTEXT);
+        let last = (index + SNIPPET_CONTEXT).min(lines.len() - 1);
+        let width = (last + 1).to_string().len();
+        let mut snippet = String::new();
+        for (i, text) in lines.iter().enumerate().take(last + 1).skip(first) {
+            snippet.push_str(&format!("{:>width$} | {text}\n", i + 1));
+            if i == index {
+                // Tabs before the position are kept, so that the caret lines up with them.
+                let indent = line
+                    .chars()
+                    .take(self.column)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect::<String>();
+                snippet.push_str(&format!("{:>width$} | {indent}^\n", ""));
+            }
+        }
+        snippet.pop();
+        Some(snippet)
+    }
+}
+
+/// Snowflake error codes of [`Error::Sql`].
+const OBJECT_NOT_FOUND: u32 = 2003;
+const SYNTAX_ERROR: u32 = 1003;
+const INSUFFICIENT_PRIVILEGES: u32 = 3001;
+const DUPLICATE_ROW: u32 = 100090;
+const STATEMENT_TIMEOUT: u32 = 630;
+const NO_ACTIVE_WAREHOUSE: u32 = 606;
+
+/// The SQLSTATE of the errors of statements whose warehouse cannot run them at the moment.
+const WAREHOUSE_UNAVAILABLE: &str = "57P03";
+
+/// Snowflake error codes with variants of their own.
+const SESSION_EXPIRED: u32 = 390112;
+const AUTH_TOKEN_EXPIRED: u32 = 390114;
+const SESSION_GONE: u32 = 390195;
+const INTERNAL_ERROR: u32 = 603;
+
+impl Error {
+    /// The error of a failed request with a Snowflake error code: the session and
+    /// authentication errors and the internal errors that callers react to specifically get
+    /// their own variants, everything else is an [`Error::Sql`].
+    pub(crate) fn from_code(
+        code: u32,
+        sqlstate: String,
+        message: String,
+        query_id: Option<String>,
+    ) -> Self {
+        let incident_id = incident_id(&message);
+        match code {
+            SESSION_EXPIRED => Error::SessionExpired { code, message },
+            AUTH_TOKEN_EXPIRED => Error::AuthTokenExpired { code, message },
+            SESSION_GONE => Error::SessionGone { code, message },
+            _ if code == INTERNAL_ERROR || incident_id.is_some() => Error::ServerIncident {
+                incident_id,
+                code,
+                message,
+                query_id,
+            },
+            _ => Error::Sql {
+                code,
+                sqlstate,
+                position: SqlPosition::from_message(&message),
+                message,
+                query_id,
+            },
+        }
+    }
+
+    /// The error of a statement that Snowflake cancelled for running past its timeout as an
+    /// [`Error::Timeout`], `elapsed` after it was sent; other errors are returned as they are.
+    pub(crate) fn into_statement_timeout(self, elapsed: Duration) -> Self {
+        match self {
+            Error::Sql {
+                code: STATEMENT_TIMEOUT,
+                query_id,
+                ..
+            } => Error::Timeout {
+                phase: TimeoutPhase::Statement,
+                elapsed,
+                query_id,
+            },
+            error => error,
+        }
+    }
+
+    /// Whether a statement failed because its warehouse cannot run statements yet, e.g. while it
+    /// resumes, so that it can succeed when sent again a little later. A session without a
+    /// warehouse and a warehouse its resource monitor suspended do not count, nor does a
+    /// statement timeout (000630), as it does not say whether the statement ran.
+    pub(crate) fn is_warehouse_resuming(&self) -> bool {
+        match self {
+            Error::Sql {
+                code,
+                sqlstate,
+                message,
+                ..
+            } => {
+                sqlstate == WAREHOUSE_UNAVAILABLE
+                    && *code != NO_ACTIVE_WAREHOUSE
+                    && !message.to_ascii_lowercase().contains("resource monitor")
+            }
+            _ => false,
+        }
+    }
+
+    /// The warehouse the message of an [`Error::Sql`] names, as in "Warehouse 'LOAD_WH' is
+    /// suspended".
+    pub(crate) fn warehouse_name(&self) -> Option<String> {
+        let Error::Sql { message, .. } = self else {
+            return None;
+        };
+        let start = message.to_ascii_lowercase().find("warehouse '")? + "warehouse '".len();
+        let end = message[start..].find('\'')?;
+        Some(message[start..start + end].to_string())
+    }
+
+    /// The Snowflake error code of an [`Error::Sql`].
+    pub fn sql_code(&self) -> Option<u32> {
+        match self {
+            Error::Sql { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// The ID of the query that failed, for an [`Error::Sql`], an [`Error::ServerIncident`], an
+    /// [`Error::Timeout`] of a query the server had accepted, or the last attempt of an
+    /// [`Error::WarehouseUnavailable`].
+    pub fn query_id(&self) -> Option<&str> {
+        match self {
+            Error::Sql { query_id, .. }
+            | Error::ServerIncident { query_id, .. }
+            | Error::Timeout { query_id, .. } => query_id.as_deref(),
+            Error::WarehouseUnavailable { source, .. } => source.query_id(),
+            _ => None,
+        }
+    }
+
+    /// Where in the statement an [`Error::Sql`] points to, if its message names a line and
+    /// position.
+    pub fn sql_position(&self) -> Option<SqlPosition> {
+        match self {
+            Error::Sql { position, .. } => *position,
+            _ => None,
+        }
+    }
+
+    /// Shows where in `sql` an [`Error::Sql`] points to: the line it names, with up to two
+    /// lines before and after it, numbered, and a caret under the position. `None` for other
+    /// errors, errors without a position, and positions outside `sql`.
+    ///
+    /// The position is within the statement that failed, so `sql` is the text of that
+    /// statement; for a statement of a multi-statement script, the text of that statement
+    /// rather than of the script.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let sql = "SELECT id\nFORM orders";
+    /// if let Err(e) = session.query(sql).await {
+    ///     if let Some(snippet) = e.render_snippet(sql) {
+    ///         eprintln!("{e}\n{snippet}");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_snippet(&self, sql: &str) -> Option<String> {
+        self.sql_position()?.render(sql)
+    }
+
+    /// Whether a statement failed because an object does not exist or is not authorized
+    /// (error 002003).
+    pub fn is_object_not_found(&self) -> bool {
+        self.sql_code() == Some(OBJECT_NOT_FOUND)
+    }
+
+    /// Whether a statement failed to compile because of a syntax error (error 001003).
+    pub fn is_syntax_error(&self) -> bool {
+        self.sql_code() == Some(SYNTAX_ERROR)
+    }
+
+    /// Whether the role lacks the privileges for an operation (error 003001).
+    pub fn is_insufficient_privileges(&self) -> bool {
+        self.sql_code() == Some(INSUFFICIENT_PRIVILEGES)
+    }
+
+    /// Whether a DML statement matched a target row more than once (error 100090).
+    pub fn is_duplicate_row(&self) -> bool {
+        self.sql_code() == Some(DUPLICATE_ROW)
+    }
+
+    /// Whether the operation that failed may succeed if tried again, possibly after a new
+    /// session has been created. Retryable are:
+    ///
+    /// - connection failures, [`Error::Timeout`], [`Error::RateLimited`], and HTTP 429 and 5xx
+    ///   responses;
+    /// - [`Error::SessionExpired`], once the session has been renewed;
+    /// - [`Error::AuthTokenExpired`] and [`Error::SessionGone`], with a new session;
+    /// - [`Error::ServerIncident`] and [`Error::WarehouseUnavailable`], after a delay;
+    /// - [`Error::IncompleteResult`], as a download may have been cut short;
+    /// - [`Error::ChunkFailed`] if its cause is retryable;
+    /// - [`Error::Sql`] for a statement that timed out while queued or running (000630), and
+    ///   the SQLSTATE classes `08` (connection exception) and `40` (transaction rollback).
+    ///
+    /// Everything else is not: SQL compilation and permission errors, data that cannot be
+    /// decoded, invalid configuration, and results that are too large or have expired.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Reqwest(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.status().is_some_and(|status| {
+                        status.is_server_error() || status == http::StatusCode::TOO_MANY_REQUESTS
+                    })
+            }
+            Error::HttpResponse { status, .. } => {
+                status.is_server_error() || *status == http::StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::SessionExpired { .. }
+            | Error::AuthTokenExpired { .. }
+            | Error::SessionGone { .. }
+            | Error::ServerIncident { .. }
+            | Error::Timeout { .. }
+            | Error::RateLimited { .. }
+            | Error::WarehouseUnavailable { .. }
+            | Error::IncompleteResult { .. } => true,
+            Error::ChunkFailed { source, .. } => source.is_retryable(),
+            Error::Sql { code, sqlstate, .. } => {
+                *code == STATEMENT_TIMEOUT
+                    || sqlstate.starts_with("08")
+                    || sqlstate.starts_with("40")
+            }
+            Error::Communication(_)
+            | Error::EmptyResponse { .. }
+            | Error::NotJson { .. }
+            | Error::InvalidHeader(_)
+            | Error::Http(_)
+            | Error::ChunkDownload(_)
+            | Error::IO(_)
+            | Error::ResultExpired(_)
+            | Error::Json(..)
+            | Error::Utf8Error(_)
+            | Error::FutureJoin(_)
+            | Error::Decode(_)
+            | Error::Decryption(_)
+            | Error::Der(_)
+            | Error::JWT(_)
+            | Error::UnsupportedFormat(_)
+            | Error::Unsupported(_)
+            | Error::InvalidCursor(_)
+            | Error::InvalidIdentifier(_)
+            | Error::Bind(_)
+            | Error::SpillLimitExceeded(_)
+            | Error::ResultTooLarge { .. }
+            | Error::SchemaMismatch { .. }
+            | Error::ExportWrite { .. } => false,
+        }
+    }
+
+    /// Creates an [`Error::Decode`] without column context, e.g. from a
+    /// [`SnowflakeDecode`](crate::SnowflakeDecode) implementation.
+    pub fn decode(message: impl Into<String>) -> Self {
+        Error::Decode(Box::new(DecodeError {
+            message: message.into(),
+            row: None,
+            chunk: None,
+            column: None,
+        }))
+    }
+
+    /// Prefixes a decode error with `context`, e.g. the column or element it came from. Other
+    /// errors are returned unchanged, keeping their sources.
+    pub(crate) fn decode_context(self, context: impl Display) -> Self {
+        match self {
+            Error::Decode(mut e) => {
+                e.message = format!("{context}: {}", e.message);
+                Error::Decode(e)
+            }
+            e => e,
+        }
+    }
+
+    /// Attaches the column a decode error came from. Other errors are returned unchanged.
+    pub(crate) fn with_column(
+        self,
+        column_name: &str,
+        column_index: usize,
+        snowflake_type: &str,
+        rust_type: &'static str,
+        value: Option<&str>,
+    ) -> Self {
+        match self {
+            Error::Decode(mut e) => {
+                e.column = Some(ColumnContext {
+                    name: column_name.to_string(),
+                    index: column_index,
+                    snowflake_type: snowflake_type.to_string(),
+                    rust_type: short_type_name(rust_type),
+                    value: value.map(truncate),
+                });
+                Error::Decode(e)
+            }
+            e => e,
+        }
+    }
+
+    /// Attaches the position of the row a decode error came from in its result set. Other
+    /// errors are returned unchanged.
+    pub(crate) fn with_row(self, row_index: usize) -> Self {
+        match self {
+            Error::Decode(mut e) => {
+                e.row = Some(row_index);
+                Error::Decode(e)
+            }
+            e => e,
+        }
+    }
+
+    /// Attaches the chunk a decode error came from and the position of the row in it. Other
+    /// errors are returned unchanged.
+    pub(crate) fn with_chunk(self, chunk_index: usize, row_offset: usize) -> Self {
+        match self {
+            Error::Decode(mut e) => {
+                e.chunk = Some((chunk_index, row_offset));
+                Error::Decode(e)
+            }
+            e => e,
+        }
+    }
+}
+
+/// Details of an [`Error::Decode`].
+///
+/// Errors from [`SnowflakeRow::get`](crate::SnowflakeRow::get) and the other row accessors know
+/// which column failed; errors created by a decoder on its own only carry a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    message: String,
+    row: Option<usize>,
+    /// The index of the chunk and the position of the row in it.
+    chunk: Option<(usize, usize)>,
+    column: Option<ColumnContext>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ColumnContext {
+    name: String,
+    index: usize,
+    snowflake_type: String,
+    rust_type: String,
+    value: Option<String>,
+}
+
+impl DecodeError {
+    /// The reason decoding failed, without the column context.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The position of the row in its result set, for errors from whole-result helpers such as
+    /// [`RowsExt::column`](crate::RowsExt::column).
+    pub fn row_index(&self) -> Option<usize> {
+        self.row
+    }
+
+    /// The index of the result chunk the row came from, for errors from typed streams such as
+    /// [`SnowflakeSession::query_as_stream`](crate::SnowflakeSession::query_as_stream). `None`
+    /// for the rows sent with the query response.
+    pub fn chunk_index(&self) -> Option<usize> {
+        self.chunk.map(|(chunk, _)| chunk)
+    }
+
+    /// The position of the row in the chunk given by [`DecodeError::chunk_index`].
+    pub fn chunk_row_offset(&self) -> Option<usize> {
+        self.chunk.map(|(_, offset)| offset)
+    }
+
+    /// The name of the column, as the server sent it.
+    pub fn column_name(&self) -> Option<&str> {
+        self.column.as_ref().map(|c| c.name.as_str())
+    }
+
+    /// The position of the column in the SELECT list.
+    pub fn column_index(&self) -> Option<usize> {
+        self.column.as_ref().map(|c| c.index)
+    }
+
+    /// The Snowflake type of the column from the result metadata, e.g. `fixed` or `text`.
+    pub fn snowflake_type(&self) -> Option<&str> {
+        self.column.as_ref().map(|c| c.snowflake_type.as_str())
+    }
+
+    /// The Rust type that was requested, without module paths, e.g. `Option<NaiveDate>`.
+    pub fn rust_type(&self) -> Option<&str> {
+        self.column.as_ref().map(|c| c.rust_type.as_str())
+    }
+
+    /// The offending value, truncated to 64 characters. `None` if the value was NULL or the
+    /// column is unknown.
+    pub fn value(&self) -> Option<&str> {
+        self.column.as_ref().and_then(|c| c.value.as_deref())
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(row) = self.row {
+            write!(f, "row {row}, ")?;
+        }
+        if let Some((chunk, offset)) = self.chunk {
+            write!(f, "chunk {chunk} row {offset}, ")?;
+        }
+        match &self.column {
+            Some(column) => write!(
+                f,
+                "column '{}' (index {}, type {}) as {}: {}",
+                column.name, column.index, column.snowflake_type, column.rust_type, self.message
+            ),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Strips module paths from a `std::any::type_name`, e.g. `alloc::vec::Vec<i64>` to `Vec<i64>`.
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    for (i, c) in name.char_indices() {
+        if c.is_alphanumeric() || c == '_' {
+            continue;
+        }
+        if c == ':' {
+            segment_start = i + 1;
+            continue;
+        }
+        short.push_str(&name[segment_start..i]);
+        short.push(c);
+        segment_start = i + 1;
+    }
+    short.push_str(&name[segment_start..]);
+    short
+}
+
+fn truncate(value: &str) -> String {
+    const MAX_CHARS: usize = 64;
+    match value.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}...", &value[..end]),
+        None => value.to_string(),
+    }
 }
 
 /// A `Result` alias where the `Err` case is `snowflake::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name("i64"), "i64");
+        assert_eq!(short_type_name("alloc::vec::Vec<i64>"), "Vec<i64>");
+        assert_eq!(
+            short_type_name("core::option::Option<chrono::datetime::DateTime<chrono::offset::fixed::FixedOffset>>"),
+            "Option<DateTime<FixedOffset>>"
+        );
+        assert_eq!(short_type_name("(i64, &str)"), "(i64, &str)");
+    }
+
+    #[test]
+    fn test_timeout_message() {
+        let polling = Error::Timeout {
+            phase: TimeoutPhase::Polling,
+            elapsed: Duration::from_millis(1500),
+            query_id: Some("01b0a1b2-0000-4c5d-0000-0001234f5678".into()),
+        };
+        assert_eq!(
+            polling.to_string(),
+            "polling for the query result timed out after 1.5s \
+             (query 01b0a1b2-0000-4c5d-0000-0001234f5678)"
+        );
+        let connect = Error::Timeout {
+            phase: TimeoutPhase::Connect,
+            elapsed: Duration::from_secs(30),
+            query_id: None,
+        };
+        assert_eq!(connect.to_string(), "connection timed out after 30s");
+
+        let cancelled = Error::from_code(
+            630,
+            "57014".into(),
+            "Statement reached its statement or warehouse timeout of 900 second(s) and was canceled.".into(),
+            Some("01b0".into()),
+        );
+        let timeout = cancelled.into_statement_timeout(Duration::from_secs(900));
+        assert!(matches!(
+            &timeout,
+            Error::Timeout { phase: TimeoutPhase::Statement, query_id: Some(id), .. } if id == "01b0"
+        ));
+        assert_eq!(
+            timeout.to_string(),
+            "statement timed out after 900s (query 01b0)"
+        );
+    }
+
+    #[test]
+    fn test_source_chain() {
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let json_message = json.to_string();
+        let error = Error::ChunkFailed {
+            chunk_index: 3,
+            url: String::new(),
+            rows_delivered: 10,
+            source: Box::new(Error::Json(json, "[{]".into())),
+        };
+        let chain = std::iter::successors(Some(&error as &dyn std::error::Error), |e| e.source())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            vec![
+                "chunk 3 failed after 10 rows were delivered".to_string(),
+                "json parse error: [{]".to_string(),
+                json_message,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_context() {
+        let decode = Error::decode("not a number").decode_context("array element 2");
+        assert_eq!(
+            decode.to_string(),
+            "decode error: array element 2: not a number"
+        );
+
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let error = Error::Json(json, "{".into()).decode_context("array element 2");
+        assert!(matches!(error, Error::Json(..)));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn test_sql_position() {
+        let error = |message: &str| Error::from_code(1003, "42000".into(), message.into(), None);
+        let syntax =
+            error("SQL compilation error:\nsyntax error line 4 at position 11 unexpected '='.");
+        assert_eq!(
+            syntax.sql_position(),
+            Some(SqlPosition {
+                line: 4,
+                column: 11
+            })
+        );
+        let invalid =
+            error("SQL compilation error: error line 1 at position 7\ninvalid identifier 'X'");
+        assert_eq!(
+            invalid.sql_position().map(|p| (p.line, p.column)),
+            Some((1, 7))
+        );
+        assert_eq!(error("pipeline 2 is busy").sql_position(), None);
+        assert_eq!(
+            error("Object 'MISSING' does not exist.").sql_position(),
+            None
+        );
+
+        let sql = "SELECT id,\n  name\nFROM orders\nWHERE id = = 1\n  AND name IS NOT NULL\nORDER BY id\nLIMIT 10";
+        assert_eq!(
+            syntax.render_snippet(sql).unwrap(),
+            [
+                "2 |   name",
+                "3 | FROM orders",
+                "4 | WHERE id = = 1",
+                "  |            ^",
+                "5 |   AND name IS NOT NULL",
+                "6 | ORDER BY id",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            invalid.render_snippet("SELECT x\tFROM t").unwrap(),
+            "1 | SELECT x\tFROM t\n  |        ^"
+        );
+        assert_eq!(syntax.render_snippet("SELECT 1"), None);
+        assert_eq!(
+            Error::Communication("line 1 at position 1".into()).render_snippet(sql),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_retryable() {
+        let sql = |code, sqlstate: &str| Error::Sql {
+            code,
+            sqlstate: sqlstate.to_string(),
+            message: String::new(),
+            query_id: None,
+            position: None,
+        };
+        let chunk_failed = |source| Error::ChunkFailed {
+            chunk_index: 0,
+            url: String::new(),
+            rows_delivered: 0,
+            source: Box::new(source),
+        };
+        let http_response = |status| Error::HttpResponse {
+            method: http::Method::GET,
+            url: String::new(),
+            status: http::StatusCode::from_u16(status).unwrap(),
+            code: None,
+            message: None,
+            body: String::new(),
+            parse_error: None,
+        };
+        let incomplete = || Error::IncompleteResult {
+            chunk_index: None,
+            expected: 2,
+            received: 1,
+        };
+
+        // A port nothing listens on any more.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let connect = reqwest::get(&url).await.unwrap_err();
+        assert!(connect.is_connect());
+
+        let retryable = [
+            Error::Reqwest(connect),
+            Error::from_code(390112, String::new(), String::new(), None),
+            Error::from_code(390114, String::new(), String::new(), None),
+            Error::from_code(390195, String::new(), String::new(), None),
+            Error::from_code(603, String::new(), String::new(), None),
+            incomplete(),
+            chunk_failed(incomplete()),
+            sql(630, "57014"),
+            sql(390114, "08001"),
+            sql(1, "08006"),
+            sql(2, "40001"),
+            http_response(503),
+            http_response(429),
+            Error::Timeout {
+                phase: TimeoutPhase::Polling,
+                elapsed: Duration::from_secs(1),
+                query_id: None,
+            },
+        ];
+        for e in retryable {
+            assert!(e.is_retryable(), "{e}");
+        }
+
+        let permanent = [
+            sql(2003, "42S02"),
+            sql(1003, "42000"),
+            sql(3001, "42501"),
+            sql(100038, "22018"),
+            chunk_failed(Error::ChunkDownload("forbidden".into())),
+            Error::Communication("unexpected".into()),
+            Error::ResultExpired("01b0".into()),
+            Error::decode("bad value"),
+            Error::UnsupportedFormat("arrow".into()),
+            Error::SpillLimitExceeded(1),
+            http_response(404),
+            Error::ResultTooLarge {
+                rows_so_far: 0,
+                limit: ResultLimit::Rows(1),
+            },
+        ];
+        for e in permanent {
+            assert!(!e.is_retryable(), "{e}");
+        }
+    }
+
+    #[test]
+    fn test_warehouse_resuming() {
+        let sql = |code, sqlstate: &str, message: &str| {
+            Error::from_code(code, sqlstate.into(), message.into(), None)
+        };
+        let resuming = sql(608, "57P03", "Warehouse 'LOAD_WH' is resuming.");
+        assert!(resuming.is_warehouse_resuming());
+        assert_eq!(resuming.warehouse_name().as_deref(), Some("LOAD_WH"));
+
+        let not_resuming = [
+            sql(606, "57P03", "No active warehouse selected in the current session."),
+            sql(
+                608,
+                "57P03",
+                "Warehouse 'LOAD_WH' cannot be resumed because resource monitor 'RM' has exceeded its quota.",
+            ),
+            sql(630, "57014", "Statement reached its statement or warehouse timeout."),
+            sql(2003, "42S02", "Object 'T' does not exist."),
+        ];
+        for e in not_resuming {
+            assert!(!e.is_warehouse_resuming(), "{e}");
+        }
+        assert_eq!(
+            sql(608, "57P03", "warehouse is busy").warehouse_name(),
+            None
+        );
+
+        let unavailable = Error::WarehouseUnavailable {
+            warehouse: Some("LOAD_WH".into()),
+            waited: Duration::from_secs(90),
+            source: Box::new(resuming),
+        };
+        assert_eq!(
+            unavailable.to_string(),
+            "warehouse LOAD_WH never became available, waited 90s"
+        );
+        assert!(unavailable.is_retryable());
+    }
+}
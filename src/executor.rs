@@ -0,0 +1,206 @@
+//! A trait for running queries, so that code taking a session can be tested without Snowflake.
+
+use std::future::Future;
+
+use serde::de::DeserializeOwned;
+
+use crate::{FromRow, QueryRequest, QueryResult, Result, SnowflakeSession};
+
+/// Runs queries, like a [`SnowflakeSession`] does. Application code that takes an
+/// `&impl SnowflakeExecutor` rather than an `&SnowflakeSession` can be tested with rows made by
+/// [`SnowflakeRow::from_values`], e.g. with `MockExecutor` from the `test-util` feature.
+///
+/// Only [`SnowflakeExecutor::query`] has to be implemented; the typed variants build on it.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeExecutor};
+/// async fn count_users(executor: &impl SnowflakeExecutor) -> Result<i64> {
+///     let rows = executor.query("SELECT COUNT(*) AS N FROM users").await?;
+///     rows[0].get("N")
+/// }
+/// ```
+pub trait SnowflakeExecutor: Send + Sync {
+    /// Runs a query and returns all of its rows, as [`SnowflakeSession::query`] does. An
+    /// implementation answering with made-up rows can build the result with
+    /// `QueryResult::from(rows)`.
+    fn query<Q: Into<QueryRequest> + Send>(
+        &self,
+        request: Q,
+    ) -> impl Future<Output = Result<QueryResult>> + Send;
+
+    /// Runs a query and deserializes every row into `T`, as [`SnowflakeSession::query_as`]
+    /// does.
+    fn query_as<T: DeserializeOwned, Q: Into<QueryRequest> + Send>(
+        &self,
+        request: Q,
+    ) -> impl Future<Output = Result<Vec<T>>> + Send {
+        async move {
+            let rows = self.query(request).await?;
+            rows.iter().map(|row| row.deserialize()).collect()
+        }
+    }
+
+    /// Runs a query and builds a `T` from every row, as [`SnowflakeSession::query_typed`]
+    /// does.
+    fn query_typed<T: FromRow, Q: Into<QueryRequest> + Send>(
+        &self,
+        request: Q,
+    ) -> impl Future<Output = Result<Vec<T>>> + Send {
+        async move {
+            let rows = self.query(request).await?;
+            rows.iter().map(T::from_row).collect()
+        }
+    }
+}
+
+impl SnowflakeExecutor for SnowflakeSession {
+    fn query<Q: Into<QueryRequest> + Send>(
+        &self,
+        request: Q,
+    ) -> impl Future<Output = Result<QueryResult>> + Send {
+        SnowflakeSession::query(self, request)
+    }
+}
+
+#[cfg(feature = "test-util")]
+pub use mock::MockExecutor;
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use std::{
+        collections::HashMap,
+        sync::{Mutex, PoisonError},
+    };
+
+    use super::*;
+    use crate::{Error, SnowflakeRow};
+
+    /// A [`SnowflakeExecutor`] that answers each statement with the rows or the error it was
+    /// given for it, and records the statements it runs. A statement it has no answer for fails
+    /// with [`Error::Communication`].
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{MockExecutor, SnowflakeColumnType, SnowflakeExecutor, SnowflakeRow};
+    /// # async fn run() -> snowflake_connector_rs::Result<()> {
+    /// let number = SnowflakeColumnType::new("fixed", Some(0));
+    /// let row = SnowflakeRow::from_values([("N", number)], vec![Some("3".to_string())]);
+    /// let executor = MockExecutor::new().with_rows("SELECT COUNT(*) AS N FROM users", vec![row]);
+    ///
+    /// let rows = executor.query("SELECT COUNT(*) AS N FROM users").await?;
+    /// assert_eq!(rows[0].get::<i64>("N")?, 3);
+    /// assert_eq!(executor.statements(), ["SELECT COUNT(*) AS N FROM users"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Default)]
+    pub struct MockExecutor {
+        answers: HashMap<String, Answer>,
+        statements: Mutex<Vec<String>>,
+    }
+
+    enum Answer {
+        Rows(Vec<SnowflakeRow>),
+        Error(Box<dyn Fn() -> Error + Send + Sync>),
+    }
+
+    impl MockExecutor {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Answers `sql` with `rows`, every time it is run.
+        pub fn with_rows(mut self, sql: impl Into<String>, rows: Vec<SnowflakeRow>) -> Self {
+            self.answers.insert(sql.into(), Answer::Rows(rows));
+            self
+        }
+
+        /// Fails `sql` with the error `error` makes, every time it is run.
+        pub fn with_error(
+            mut self,
+            sql: impl Into<String>,
+            error: impl Fn() -> Error + Send + Sync + 'static,
+        ) -> Self {
+            self.answers
+                .insert(sql.into(), Answer::Error(Box::new(error)));
+            self
+        }
+
+        /// The statements run so far, in order.
+        pub fn statements(&self) -> Vec<String> {
+            self.statements
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+
+        fn answer(&self, sql: String) -> Result<QueryResult> {
+            let answer = match self.answers.get(&sql) {
+                Some(Answer::Rows(rows)) => Ok(QueryResult::from(rows.clone())),
+                Some(Answer::Error(error)) => Err(error()),
+                None => Err(Error::Communication(format!("no mock answer for: {sql}"))),
+            };
+            self.statements
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(sql);
+            answer
+        }
+    }
+
+    impl SnowflakeExecutor for MockExecutor {
+        fn query<Q: Into<QueryRequest> + Send>(
+            &self,
+            request: Q,
+        ) -> impl Future<Output = Result<QueryResult>> + Send {
+            let answer = self.answer(request.into().sql_text);
+            async move { answer }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SnowflakeColumnType, SnowflakeRow};
+
+    /// Answers every statement with one row holding the statement.
+    struct Echo;
+
+    impl SnowflakeExecutor for Echo {
+        async fn query<Q: Into<QueryRequest> + Send>(&self, request: Q) -> Result<QueryResult> {
+            let sql = request.into().sql_text;
+            let text = SnowflakeColumnType::new("text", None);
+            Ok(QueryResult::from(vec![SnowflakeRow::from_values(
+                [("SQL", text)],
+                vec![Some(sql)],
+            )]))
+        }
+    }
+
+    struct Statement {
+        sql: String,
+    }
+
+    impl FromRow for Statement {
+        fn from_row(row: &SnowflakeRow) -> Result<Self> {
+            Ok(Self {
+                sql: row.get("sql")?,
+            })
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Deserialized {
+        #[serde(rename = "SQL")]
+        sql: String,
+    }
+
+    #[tokio::test]
+    async fn test_default_methods() -> Result<()> {
+        let typed = Echo.query_typed::<Statement, _>("SELECT 1").await?;
+        assert_eq!(typed[0].sql, "SELECT 1");
+        let deserialized = Echo.query_as::<Deserialized, _>("SELECT 2").await?;
+        assert_eq!(deserialized[0].sql, "SELECT 2");
+        Ok(())
+    }
+}
@@ -0,0 +1,675 @@
+//! Conversion of query results into other representations.
+
+use std::{collections::HashSet, io::Write};
+
+use chrono::format::{Item, StrftimeItems};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use serde_json::{Map, Number, Value};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    row::parse_bool,
+    temporal::{format_iso8601, parse_date, parse_time, parse_timestamp, parse_timestamp_tz},
+    types::SnowflakeColumnType,
+    Error, QueryRequest, Result, RowStream, SnowflakeRow, SnowflakeSession,
+};
+
+impl SnowflakeRow {
+    /// Converts the row into a JSON object keyed by column name, typing values by column
+    /// metadata:
+    ///
+    /// - NUMBER, FLOAT: JSON numbers when they fit without losing precision (integers in
+    ///   `i64`/`u64`, decimals with up to 15 significant digits), strings otherwise
+    /// - BOOLEAN: `true`/`false`
+    /// - DATE, TIME, TIMESTAMP_*: ISO-8601 strings; TIMESTAMP_LTZ is rendered in UTC
+    /// - VARIANT, OBJECT, ARRAY: the parsed JSON value
+    /// - everything else: strings
+    ///
+    /// NULL becomes `null`. If several columns share a name, the first one is kept. Key order
+    /// follows `serde_json::Map`, which sorts keys unless serde_json's `preserve_order` feature
+    /// is enabled; [`write_ndjson`] always writes keys in column order.
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::with_capacity(self.len());
+        for (name, value) in JsonRow(self).entries() {
+            object.insert(name.to_string(), value);
+        }
+        Value::Object(object)
+    }
+}
+
+/// Serializes a row as a JSON object with keys in column order.
+struct JsonRow<'a>(&'a SnowflakeRow);
+
+impl<'a> JsonRow<'a> {
+    fn entries(&self) -> impl Iterator<Item = (&'a str, Value)> + 'a {
+        let row = self.0;
+        let mut seen = HashSet::with_capacity(row.len());
+        row.values().enumerate().filter_map(move |(index, value)| {
+            let name = row.columns.name(index);
+            seen.insert(name).then(|| {
+                let value = cell_to_json(value, row.columns.column_type(index));
+                (name, value)
+            })
+        })
+    }
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (name, value) in self.entries() {
+            map.serialize_entry(name, &value)?;
+        }
+        map.end()
+    }
+}
+
+/// Converts rows into JSON objects with [`SnowflakeRow::to_json`].
+pub fn rows_to_json(rows: &[SnowflakeRow]) -> Vec<Value> {
+    rows.iter().map(SnowflakeRow::to_json).collect()
+}
+
+/// Writes rows as newline-delimited JSON, one [`SnowflakeRow::to_json`] object per line with
+/// keys in column order.
+pub fn write_ndjson<'a, W: Write>(
+    rows: impl IntoIterator<Item = &'a SnowflakeRow>,
+    mut writer: W,
+) -> Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut writer, &JsonRow(row))
+            .map_err(|e| crate::Error::IO(e.into()))?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// How [`write_csv`] and [`write_csv_stream`] write rows.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// The character between fields. Defaults to `,`.
+    pub delimiter: char,
+
+    /// Whether the first line names the columns, in the order and with the names the server
+    /// sent them. Defaults to `true`.
+    pub header: bool,
+
+    /// How NULL is written. Defaults to an empty field; an empty string is then written as
+    /// `""` to tell them apart.
+    pub null: String,
+
+    /// A `chrono` format for DATE values, e.g. `%d/%m/%Y`. Defaults to ISO 8601.
+    pub date_format: Option<String>,
+
+    /// A `chrono` format for TIME values. Defaults to ISO 8601.
+    pub time_format: Option<String>,
+
+    /// A `chrono` format for TIMESTAMP_NTZ, TIMESTAMP_LTZ (in UTC) and TIMESTAMP_TZ values.
+    /// Defaults to ISO 8601, with the offset for TIMESTAMP_LTZ and TIMESTAMP_TZ.
+    pub timestamp_format: Option<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+            null: String::new(),
+            date_format: None,
+            time_format: None,
+            timestamp_format: None,
+        }
+    }
+}
+
+/// Writes rows as CSV, quoting fields as RFC 4180 does when they hold the delimiter, a quote
+/// or a line break. Values are formatted by column type: booleans as `true`/`false`, dates,
+/// times and timestamps as [`CsvOptions`] says, and everything else as the server sent it,
+/// e.g. semi-structured values as JSON and binary values as hex.
+///
+/// The header comes from the first row, so nothing is written for no rows; use
+/// [`write_csv_stream`] to write the header of an empty result, or a result too large to
+/// hold in memory.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{write_csv, CsvOptions, Result, SnowflakeSession};
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let rows = session.query("SELECT * FROM orders").await?;
+/// let file = std::fs::File::create("orders.csv")?;
+/// write_csv(&rows, std::io::BufWriter::new(file), &CsvOptions::default())?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_csv<'a, W: Write>(
+    rows: impl IntoIterator<Item = &'a SnowflakeRow>,
+    writer: W,
+    options: &CsvOptions,
+) -> Result<()> {
+    let mut csv = CsvWriter::new(writer, options)?;
+    for (i, row) in rows.into_iter().enumerate() {
+        if i == 0 && options.header {
+            csv.write_header((0..row.columns.len()).map(|index| row.columns.name(index)))?;
+        }
+        csv.write_row(row)?;
+    }
+    csv.finish()?;
+    Ok(())
+}
+
+/// Writes the rows of a stream as CSV, as [`write_csv`] does, a chunk at a time as the chunks
+/// are downloaded. The header is written even if the result has no rows.
+pub async fn write_csv_stream<W: Write>(
+    mut rows: RowStream,
+    writer: W,
+    options: &CsvOptions,
+) -> Result<()> {
+    let mut csv = CsvWriter::new(writer, options)?;
+    if options.header {
+        csv.write_header(rows.column_names())?;
+    }
+    while let Some(batch) = rows.next_batch().await {
+        for row in batch? {
+            csv.write_row(&row)?;
+        }
+    }
+    csv.finish()?;
+    Ok(())
+}
+
+/// The format [`SnowflakeSession::query_to_writer`] writes rows in.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ExportFormat {
+    /// CSV, as [`write_csv_stream`] writes it.
+    Csv(CsvOptions),
+    /// Newline-delimited JSON, as [`write_ndjson`] writes it.
+    Ndjson,
+}
+
+/// What [`SnowflakeSession::query_to_writer`] wrote.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExportSummary {
+    /// The rows written.
+    pub rows: u64,
+    /// The bytes written, including the CSV header.
+    pub bytes: u64,
+}
+
+impl SnowflakeSession {
+    /// Runs a query and writes its rows to `writer` in `format` a chunk at a time, as the
+    /// chunks are downloaded, so that at most a chunk of rows and its serialized form are held
+    /// in memory. No chunk is downloaded beyond the
+    /// [prefetch](crate::SnowflakeClientConfig::chunk_prefetch) while the writer is busy, so a
+    /// slow writer slows down the downloads.
+    ///
+    /// Failures of the query or its chunks fail with their own errors, failures of the writer
+    /// with [`Error::ExportWrite`]. Either way, the rows written before stay written.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{CsvOptions, ExportFormat, Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession, upload: impl tokio::io::AsyncWrite + Unpin) -> Result<()> {
+    /// let format = ExportFormat::Csv(CsvOptions::default());
+    /// let summary = session
+    ///     .query_to_writer("SELECT * FROM orders", upload, format)
+    ///     .await?;
+    /// println!("exported {} rows in {} bytes", summary.rows, summary.bytes);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_to_writer<Q: Into<QueryRequest>, W: AsyncWrite + Unpin>(
+        &self,
+        request: Q,
+        writer: W,
+        format: ExportFormat,
+    ) -> Result<ExportSummary> {
+        let rows = self.query_stream(request).await?;
+        export(rows, writer, &format).await
+    }
+}
+
+/// Writes the rows of a stream to `writer` in `format`, serializing each chunk into a buffer
+/// that is written before the next chunk is read.
+async fn export<W: AsyncWrite + Unpin>(
+    mut rows: RowStream,
+    mut writer: W,
+    format: &ExportFormat,
+) -> Result<ExportSummary> {
+    let mut summary = ExportSummary::default();
+    let mut buf = vec![];
+    if let ExportFormat::Csv(options) = format {
+        let mut csv = CsvWriter::new(&mut buf, options)?;
+        if options.header {
+            csv.write_header(rows.column_names())?;
+        }
+    }
+    let mut batch_rows = 0;
+    loop {
+        if !buf.is_empty() {
+            writer
+                .write_all(&buf)
+                .await
+                .map_err(|e| summary.write_error(e))?;
+            summary.bytes += buf.len() as u64;
+            buf.clear();
+        }
+        summary.rows += batch_rows;
+        let Some(batch) = rows.next_batch().await else {
+            break;
+        };
+        let batch = batch?;
+        match format {
+            ExportFormat::Csv(options) => {
+                let mut csv = CsvWriter {
+                    writer: &mut buf,
+                    options,
+                };
+                for row in &batch {
+                    csv.write_row(row)?;
+                }
+            }
+            ExportFormat::Ndjson => {
+                for row in &batch {
+                    serde_json::to_writer(&mut buf, &JsonRow(row))
+                        .map_err(|e| Error::IO(e.into()))?;
+                    buf.push(b'\n');
+                }
+            }
+        }
+        batch_rows = batch.len() as u64;
+    }
+    writer.flush().await.map_err(|e| summary.write_error(e))?;
+    Ok(summary)
+}
+
+impl ExportSummary {
+    fn write_error(&self, source: std::io::Error) -> Error {
+        Error::ExportWrite {
+            rows_written: self.rows,
+            source,
+        }
+    }
+}
+
+pub(crate) struct CsvWriter<'a, W> {
+    writer: W,
+    options: &'a CsvOptions,
+}
+
+impl<'a, W: Write> CsvWriter<'a, W> {
+    pub(crate) fn new(writer: W, options: &'a CsvOptions) -> Result<Self> {
+        let formats = [
+            &options.date_format,
+            &options.time_format,
+            &options.timestamp_format,
+        ];
+        for format in formats.into_iter().flatten() {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(Error::UnsupportedFormat(format.clone()));
+            }
+        }
+        Ok(Self { writer, options })
+    }
+
+    fn write_header<'n>(&mut self, names: impl IntoIterator<Item = &'n str>) -> Result<()> {
+        self.write_record(names.into_iter().map(Some))
+    }
+
+    /// Writes a line of values, which are written as they are.
+    pub(crate) fn write_record<'v>(
+        &mut self,
+        values: impl IntoIterator<Item = Option<&'v str>>,
+    ) -> Result<()> {
+        for (index, value) in values.into_iter().enumerate() {
+            self.write_field(index, value)?;
+        }
+        self.writer.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &SnowflakeRow) -> Result<()> {
+        for (index, value) in row.values().enumerate() {
+            let value = value.map(|value| {
+                let column_type = row.columns.column_type(index);
+                self.format(value, column_type)
+                    .unwrap_or_else(|| value.to_string())
+            });
+            self.write_field(index, value.as_deref())?;
+        }
+        self.writer.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    /// Formats a value of a type that is not written as the server sent it.
+    fn format(&self, value: &str, column_type: &SnowflakeColumnType) -> Option<String> {
+        let options = self.options;
+        match column_type.snowflake_type() {
+            "boolean" => parse_bool(value).ok().map(|v| v.to_string()),
+            "date" => match &options.date_format {
+                Some(format) => parse_date(value).map(|v| v.format(format).to_string()),
+                None => format_iso8601(value, column_type),
+            },
+            "time" => match &options.time_format {
+                Some(format) => parse_time(value).map(|v| v.format(format).to_string()),
+                None => format_iso8601(value, column_type),
+            },
+            "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => {
+                let Some(format) = &options.timestamp_format else {
+                    return format_iso8601(value, column_type);
+                };
+                let timestamp = match column_type.snowflake_type() {
+                    "timestamp_tz" => parse_timestamp_tz(value),
+                    _ => parse_timestamp(value).map(|v| v.and_utc().fixed_offset()),
+                };
+                timestamp.map(|v| v.format(format).to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn write_field(&mut self, index: usize, value: Option<&str>) -> Result<()> {
+        let options = self.options;
+        if index > 0 {
+            write!(self.writer, "{}", options.delimiter)?;
+        }
+        let Some(value) = value else {
+            self.writer.write_all(options.null.as_bytes())?;
+            return Ok(());
+        };
+        let quote = value == options.null || value.contains([options.delimiter, '"', '\n', '\r']);
+        match quote {
+            true => write!(self.writer, "\"{}\"", value.replace('"', "\"\""))?,
+            false => self.writer.write_all(value.as_bytes())?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Flushes the writer and returns it.
+    pub(crate) fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+pub(crate) fn cell_to_json(value: Option<&str>, column_type: &SnowflakeColumnType) -> Value {
+    let Some(value) = value else {
+        return Value::Null;
+    };
+    let string = || Value::String(value.to_string());
+    match column_type.snowflake_type() {
+        "fixed" => {
+            if let Ok(v) = value.parse::<i64>() {
+                return Value::from(v);
+            }
+            if let Ok(v) = value.parse::<u64>() {
+                return Value::from(v);
+            }
+            if significant_digits(value) <= 15 {
+                if let Some(v) = value.parse::<f64>().ok().and_then(Number::from_f64) {
+                    return Value::Number(v);
+                }
+            }
+            string()
+        }
+        "real" => value
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or_else(string),
+        "boolean" => parse_bool(value)
+            .map(Value::Bool)
+            .unwrap_or_else(|_| string()),
+        "date" | "time" | "timestamp_ntz" | "timestamp_ltz" | "timestamp_tz" => {
+            format_iso8601(value, column_type)
+                .map(Value::String)
+                .unwrap_or_else(string)
+        }
+        _ if column_type.is_semi_structured() => {
+            serde_json::from_str(value).unwrap_or_else(|_| string())
+        }
+        _ => string(),
+    }
+}
+
+/// Counts the significant decimal digits of a numeric string such as `-0012.3400`.
+fn significant_digits(value: &str) -> usize {
+    let digits = value
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .skip_while(|&b| b == b'0')
+        .collect::<Vec<_>>();
+    let trailing_zeros = if value.contains('.') {
+        digits.iter().rev().take_while(|&&b| b == b'0').count()
+    } else {
+        0
+    };
+    digits.len() - trailing_zeros
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        chunk::tests::fake_fetcher, row::tests::typed_row, row::Columns, stats::StatsRecorder,
+        values::RowValues,
+    };
+
+    #[test]
+    fn test_to_json() {
+        let row = typed_row(&[
+            ("INT", "fixed", Some("-42")),
+            ("BIG", "fixed", Some("123456789012345678901234567890")),
+            ("DEC", "fixed", Some("12.50")),
+            ("DEC_PRECISE", "fixed", Some("1234567890.1234567890")),
+            ("FLOAT", "real", Some("1.5")),
+            ("NAN", "real", Some("NaN")),
+            ("BOOL", "boolean", Some("1")),
+            ("TEXT", "text", Some("hello")),
+            ("NUL", "text", None),
+            ("DATE", "date", Some("18262")),
+            ("TIME", "time", Some("45296.500000000")),
+            ("NTZ", "timestamp_ntz", Some("1700000000.123000000")),
+            ("LTZ", "timestamp_ltz", Some("1700000000.000000000")),
+            ("TZ", "timestamp_tz", Some("1700000000.000000000 1980")),
+            ("OBJ", "object", Some("{\n  \"a\": [1, null]\n}")),
+            ("VAR", "variant", Some("\"s\"")),
+            ("BIN", "binary", Some("48656C6C6F")),
+            ("INT", "fixed", Some("2")),
+        ]);
+
+        assert_eq!(
+            row.to_json(),
+            json!({
+                "INT": -42,
+                "BIG": "123456789012345678901234567890",
+                "DEC": 12.5,
+                "DEC_PRECISE": "1234567890.1234567890",
+                "FLOAT": 1.5,
+                "NAN": "NaN",
+                "BOOL": true,
+                "TEXT": "hello",
+                "NUL": null,
+                "DATE": "2020-01-01",
+                "TIME": "12:34:56.500",
+                "NTZ": "2023-11-14T22:13:20.123",
+                "LTZ": "2023-11-14T22:13:20Z",
+                "TZ": "2023-11-15T07:13:20+09:00",
+                "OBJ": {"a": [1, null]},
+                "VAR": "s",
+                "BIN": "48656C6C6F",
+            })
+        );
+    }
+
+    #[test]
+    fn test_write_ndjson() -> Result<()> {
+        let rows = vec![
+            typed_row(&[("ID", "fixed", Some("1")), ("A", "text", Some("a"))]),
+            typed_row(&[("ID", "fixed", Some("2")), ("A", "text", None)]),
+        ];
+
+        let mut buf = vec![];
+        write_ndjson(&rows, &mut buf)?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "{\"ID\":1,\"A\":\"a\"}\n{\"ID\":2,\"A\":null}\n"
+        );
+        assert_eq!(
+            rows_to_json(&rows),
+            vec![json!({"ID": 1, "A": "a"}), json!({"ID": 2, "A": null})]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_csv() -> Result<()> {
+        let rows = vec![
+            typed_row(&[
+                ("ID", "fixed", Some("1")),
+                ("NAME", "text", Some("say \"hi\", twice")),
+                ("NOTE", "text", Some("line\nbreak")),
+                ("OK", "boolean", Some("1")),
+                ("DAY", "date", Some("18262")),
+            ]),
+            typed_row(&[
+                ("ID", "fixed", Some("2")),
+                ("NAME", "text", Some("")),
+                ("NOTE", "text", None),
+                ("OK", "boolean", Some("0")),
+                ("DAY", "date", None),
+            ]),
+        ];
+
+        let csv = |options: &CsvOptions| -> Result<String> {
+            let mut buf = vec![];
+            write_csv(&rows, &mut buf, options)?;
+            Ok(String::from_utf8(buf)?)
+        };
+        assert_eq!(
+            csv(&CsvOptions::default())?,
+            "ID,NAME,NOTE,OK,DAY\r\n\
+             1,\"say \"\"hi\"\", twice\",\"line\nbreak\",true,2020-01-01\r\n\
+             2,\"\",,false,\r\n"
+        );
+        assert_eq!(
+            csv(&CsvOptions {
+                delimiter: ';',
+                header: false,
+                null: "NULL".to_string(),
+                date_format: Some("%d/%m/%Y".to_string()),
+                ..Default::default()
+            })?,
+            "1;\"say \"\"hi\"\", twice\";\"line\nbreak\";true;01/01/2020\r\n\
+             2;;NULL;false;NULL\r\n"
+        );
+
+        let rows = [typed_row(&[
+            ("TIME", "time", Some("45296.500000000")),
+            ("LTZ", "timestamp_ltz", Some("1700000000.000000000")),
+            ("TZ", "timestamp_tz", Some("1700000000.000000000 1980")),
+            ("NUL", "text", Some("NULL")),
+        ])];
+        let mut buf = vec![];
+        let options = CsvOptions {
+            null: "NULL".to_string(),
+            time_format: Some("%H.%M".to_string()),
+            timestamp_format: Some("%Y-%m-%d %H:%M %z".to_string()),
+            ..Default::default()
+        };
+        write_csv(&rows, &mut buf, &options)?;
+        assert_eq!(
+            String::from_utf8(buf)?,
+            "TIME,LTZ,TZ,NUL\r\n12.34,2023-11-14 22:13 +0000,2023-11-15 07:13 +0900,\"NULL\"\r\n"
+        );
+
+        let options = CsvOptions {
+            date_format: Some("%Q".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            write_csv(&rows, vec![], &options),
+            Err(Error::UnsupportedFormat(format)) if format == "%Q"
+        ));
+        Ok(())
+    }
+
+    /// A result of the rows `a` and `b` sent with the response and `chunks` chunks of one row
+    /// each, holding the chunk number.
+    fn stream(chunks: usize) -> RowStream {
+        let columns = Columns::new(vec![(
+            "VALUE".to_string(),
+            SnowflakeColumnType::new("text", None),
+        )]);
+        let row_set = RowValues::from_rows(vec![vec![Some("a".into())], vec![Some("b".into())]]);
+        let (fetcher, _) = fake_fetcher(chunks, 1);
+        let stats = StatsRecorder::default();
+        RowStream::new(Arc::new(columns), row_set, fetcher, None, 0, stats)
+    }
+
+    /// Accepts `accepted` writes, then fails.
+    struct FailingWriter {
+        accepted: usize,
+    }
+
+    impl AsyncWrite for FailingWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            if self.accepted == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()));
+            }
+            self.accepted -= 1;
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export() -> Result<()> {
+        let mut buf = vec![];
+        let format = ExportFormat::Csv(CsvOptions::default());
+        let summary = export(stream(2), &mut buf, &format).await?;
+        assert_eq!(String::from_utf8(buf)?, "VALUE\r\na\r\nb\r\n0\r\n1\r\n");
+        assert_eq!((summary.rows, summary.bytes), (4, 19));
+
+        let mut buf = vec![];
+        let summary = export(stream(1), &mut buf, &ExportFormat::Ndjson).await?;
+        let expected = "{\"VALUE\":\"a\"}\n{\"VALUE\":\"b\"}\n{\"VALUE\":\"0\"}\n";
+        assert_eq!(String::from_utf8(buf)?, expected);
+        assert_eq!((summary.rows, summary.bytes), (3, expected.len() as u64));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_write_error() {
+        // The rows sent with the response are written, the first chunk is not.
+        let writer = FailingWriter { accepted: 1 };
+        let result = export(stream(2), writer, &ExportFormat::Ndjson).await;
+        assert!(matches!(
+            result,
+            Err(Error::ExportWrite { rows_written: 2, source }) if source.kind() == std::io::ErrorKind::BrokenPipe
+        ));
+    }
+}
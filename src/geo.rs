@@ -0,0 +1,272 @@
+//! Decoding of GEOGRAPHY and GEOMETRY columns.
+//!
+//! Snowflake renders geospatial values according to the session's `GEOGRAPHY_OUTPUT_FORMAT` and
+//! `GEOMETRY_OUTPUT_FORMAT` parameters (GeoJSON by default). Set
+//! [`SnowflakeClientConfig::geo_output_format`](crate::SnowflakeClientConfig::geo_output_format)
+//! to pin the format, or decode into [`Wkt`], which accepts GeoJSON, WKT and EWKT output alike.
+//! With the `geo` feature, columns also decode into `geo_types::Geometry<f64>`.
+
+use serde_json::Value;
+
+use crate::{
+    row::{unwrap, unwrap_str},
+    types::SnowflakeColumnType,
+    Error, Result, SnowflakeDecode,
+};
+
+/// Output format requested for GEOGRAPHY and GEOMETRY values at login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoOutputFormat {
+    GeoJson,
+    Wkt,
+}
+
+impl GeoOutputFormat {
+    pub(crate) fn parameter_value(&self) -> &'static str {
+        match self {
+            GeoOutputFormat::GeoJson => "GeoJSON",
+            GeoOutputFormat::Wkt => "WKT",
+        }
+    }
+}
+
+/// A geospatial value as Well-Known Text, e.g. `POINT(-122.35 37.55)`.
+///
+/// Values the server sends as GeoJSON are converted to WKT and an EWKT `SRID=...;` prefix is
+/// dropped, so the result does not depend on the session's output format. WKB output is not
+/// supported.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Wkt(pub String);
+
+impl SnowflakeDecode for Wkt {
+    fn try_decode(value: &Option<String>) -> Result<Self> {
+        Self::parse(unwrap(value)?)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        ensure_geospatial(column_type)?;
+        Self::parse(unwrap_str(value)?)
+    }
+}
+
+impl Wkt {
+    fn parse(value: &str) -> Result<Self> {
+        match parse(value)? {
+            GeoText::GeoJson(json) => geojson_to_wkt(&json).map(Wkt),
+            GeoText::Wkt(wkt) => Ok(Wkt(wkt.to_string())),
+        }
+    }
+}
+
+fn ensure_geospatial(column_type: &SnowflakeColumnType) -> Result<()> {
+    if !column_type.is_geospatial() {
+        return Err(Error::decode(format!(
+            "column of type {} is not GEOGRAPHY or GEOMETRY",
+            column_type.snowflake_type()
+        )));
+    }
+    Ok(())
+}
+
+enum GeoText<'a> {
+    GeoJson(Value),
+    Wkt(&'a str),
+}
+
+fn parse(value: &str) -> Result<GeoText<'_>> {
+    let value = value.trim();
+    if value.starts_with('{') {
+        let json = serde_json::from_str(value).map_err(|e| Error::Json(e, value.to_string()))?;
+        return Ok(GeoText::GeoJson(json));
+    }
+    let wkt = match value.split_once(';') {
+        Some((srid, wkt)) if srid.to_ascii_uppercase().starts_with("SRID=") => wkt,
+        _ => value,
+    };
+    if !wkt.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(Error::decode(format!(
+            "'{value}' is neither GeoJSON nor WKT; WKB output is not supported"
+        )));
+    }
+    Ok(GeoText::Wkt(wkt))
+}
+
+fn geojson_to_wkt(json: &Value) -> Result<String> {
+    let invalid = || Error::decode(format!("invalid GeoJSON geometry: {json}"));
+    let geometry_type = json
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(invalid)?;
+    if geometry_type == "GeometryCollection" {
+        let geometries = json
+            .get("geometries")
+            .and_then(Value::as_array)
+            .ok_or_else(invalid)?;
+        if geometries.is_empty() {
+            return Ok("GEOMETRYCOLLECTION EMPTY".into());
+        }
+        let geometries = geometries
+            .iter()
+            .map(geojson_to_wkt)
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(format!("GEOMETRYCOLLECTION({})", geometries.join(",")));
+    }
+
+    let coordinates = json.get("coordinates").ok_or_else(invalid)?;
+    let (name, depth) = match geometry_type {
+        "Point" => ("POINT", 0),
+        "LineString" => ("LINESTRING", 1),
+        "Polygon" => ("POLYGON", 2),
+        "MultiPoint" => ("MULTIPOINT", 1),
+        "MultiLineString" => ("MULTILINESTRING", 2),
+        "MultiPolygon" => ("MULTIPOLYGON", 3),
+        _ => return Err(invalid()),
+    };
+    let is_empty = coordinates.as_array().is_some_and(|c| c.is_empty());
+    if is_empty {
+        return Ok(format!("{name} EMPTY"));
+    }
+    let body = wkt_coordinates(coordinates, depth).ok_or_else(invalid)?;
+    Ok(format!("{name}({body})"))
+}
+
+/// Renders GeoJSON coordinates nested `depth` arrays deep below a position.
+fn wkt_coordinates(coordinates: &Value, depth: usize) -> Option<String> {
+    let items = coordinates.as_array()?;
+    if depth == 0 {
+        let position = items
+            .iter()
+            .map(|n| n.as_number().map(ToString::to_string))
+            .collect::<Option<Vec<_>>>()?;
+        return (position.len() >= 2).then(|| position.join(" "));
+    }
+    let parts = items
+        .iter()
+        .map(|item| {
+            let inner = wkt_coordinates(item, depth - 1)?;
+            Some(if depth == 1 {
+                inner
+            } else {
+                format!("({inner})")
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join(","))
+}
+
+#[cfg(feature = "geo")]
+impl SnowflakeDecode for geo_types::Geometry<f64> {
+    fn try_decode(value: &Option<String>) -> Result<Self> {
+        parse_geometry(unwrap(value)?)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        ensure_geospatial(column_type)?;
+        parse_geometry(unwrap_str(value)?)
+    }
+}
+
+#[cfg(feature = "geo")]
+fn parse_geometry(value: &str) -> Result<geo_types::Geometry<f64>> {
+    use std::str::FromStr;
+
+    match parse(value)? {
+        GeoText::GeoJson(json) => {
+            let geojson = geojson::GeoJson::from_str(&json.to_string())
+                .map_err(|e| Error::decode(format!("invalid GeoJSON geometry: {e}")))?;
+            geo_types::Geometry::try_from(geojson)
+                .map_err(|e| Error::decode(format!("invalid GeoJSON geometry: {e}")))
+        }
+        GeoText::Wkt(text) => {
+            use wkt::TryFromWkt;
+            geo_types::Geometry::try_from_wkt_str(text)
+                .map_err(|e| Error::decode(format!("invalid WKT '{text}': {e}")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wkt(value: &str) -> Result<String> {
+        Wkt::try_decode_typed(
+            &Some(value.to_string()),
+            &SnowflakeColumnType::new("geography", None),
+        )
+        .map(|Wkt(wkt)| wkt)
+    }
+
+    #[test]
+    fn test_decode_wkt() -> Result<()> {
+        assert_eq!(
+            wkt(r#"{"coordinates": [-122.35, 37.55], "type": "Point"}"#)?,
+            "POINT(-122.35 37.55)"
+        );
+        assert_eq!(
+            wkt(r#"{"coordinates": [[0, 0], [1, 1.5]], "type": "LineString"}"#)?,
+            "LINESTRING(0 0,1 1.5)"
+        );
+        assert_eq!(
+            wkt(r#"{"coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]]], "type": "Polygon"}"#)?,
+            "POLYGON((0 0,1 0,1 1,0 0))"
+        );
+        assert_eq!(
+            wkt(
+                r#"{"coordinates": [[[[0, 0], [1, 0], [0, 1], [0, 0]]]], "type": "MultiPolygon"}"#
+            )?,
+            "MULTIPOLYGON(((0 0,1 0,0 1,0 0)))"
+        );
+        assert_eq!(
+            wkt(
+                r#"{"geometries": [{"coordinates": [1, 2], "type": "Point"}], "type": "GeometryCollection"}"#
+            )?,
+            "GEOMETRYCOLLECTION(POINT(1 2))"
+        );
+        assert_eq!(
+            wkt(r#"{"coordinates": [], "type": "MultiPoint"}"#)?,
+            "MULTIPOINT EMPTY"
+        );
+        assert_eq!(wkt("POINT(-122.35 37.55)")?, "POINT(-122.35 37.55)");
+        assert_eq!(
+            wkt("SRID=4326;POINT(-122.35 37.55)")?,
+            "POINT(-122.35 37.55)"
+        );
+
+        assert!(wkt("0101000000").is_err());
+        assert!(wkt(r#"{"type": "Circle"}"#).is_err());
+        assert!(Wkt::try_decode_typed(
+            &Some("POINT(1 2)".into()),
+            &SnowflakeColumnType::new("text", None)
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "geo")]
+    #[test]
+    fn test_decode_geo_types() -> Result<()> {
+        let column_type = SnowflakeColumnType::new("geography", None);
+        let expected = geo_types::Geometry::Point(geo_types::Point::new(-122.35, 37.55));
+        for value in [
+            r#"{"coordinates": [-122.35, 37.55], "type": "Point"}"#,
+            "POINT(-122.35 37.55)",
+            "SRID=4326;POINT(-122.35 37.55)",
+        ] {
+            let geometry = geo_types::Geometry::<f64>::try_decode_typed(
+                &Some(value.to_string()),
+                &column_type,
+            )?;
+            assert_eq!(geometry, expected);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+//! A client of Snowpipe's REST API, which loads staged files into a table through a pipe.
+//!
+//! [`IngestClient`] authenticates each request with a JWT signed by the key pair of the user,
+//! as [`SnowflakeAuthMethod::KeyPair`] logins do, and takes the account from the same
+//! [`SnowflakeClientConfig`]. The pipe, e.g. `CREATE PIPE db.public.events_pipe AS COPY INTO
+//! events FROM @events_stage`, and the files on its stage are created beforehand.
+//!
+//! ```rust,no_run
+//! # use snowflake_connector_rs::{ingest::{IngestClient, IngestFile}, Result, SnowflakeAuthMethod, SnowflakeClientConfig};
+//! # async fn run(encrypted_pem: String) -> Result<()> {
+//! let client = IngestClient::new(
+//!     "USERNAME",
+//!     SnowflakeAuthMethod::KeyPair {
+//!         encrypted_pem,
+//!         password: b"PASSWORD".to_vec().into(),
+//!     },
+//!     SnowflakeClientConfig {
+//!         account: "ACCOUNT".to_string(),
+//!         ..Default::default()
+//!     },
+//! )?;
+//! let pipe = "db.public.events_pipe";
+//! client
+//!     .insert_files(pipe, &[IngestFile::from("2024/06/01/events_0.json.gz")])
+//!     .await?;
+//!
+//! let report = client.insert_report(pipe, None).await?;
+//! for file in report.files {
+//!     println!("{}: {:?}, {} rows", file.path, file.status, file.rows_inserted);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use http::header::{ACCEPT, AUTHORIZATION};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    auth::KeyPairJwt,
+    transport::{send, HttpClient},
+    Error, Result, SnowflakeAuthMethod, SnowflakeClientConfig,
+};
+
+/// Sends files to Snowpipe and reports on their loading. See the [module docs](self).
+pub struct IngestClient {
+    http: HttpClient,
+    /// `https://<account>.snowflakecomputing.com/v1/data/pipes`.
+    pipes_url: String,
+    jwt: KeyPairJwt,
+}
+
+/// Shows the client without its key.
+impl fmt::Debug for IngestClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IngestClient")
+            .field("pipes_url", &self.pipes_url)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A file on the stage of a pipe, to load with [`IngestClient::insert_files`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IngestFile {
+    /// The path of the file, relative to the stage location of the pipe.
+    pub path: String,
+
+    /// The size of the file in bytes, which lets Snowpipe plan the load better. Optional.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl From<&str> for IngestFile {
+    fn from(path: &str) -> Self {
+        path.to_string().into()
+    }
+}
+
+impl From<String> for IngestFile {
+    fn from(path: String) -> Self {
+        Self { path, size: None }
+    }
+}
+
+/// The answer to [`IngestClient::insert_files`]: the files have been queued, not loaded yet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct InsertFilesResponse {
+    pub request_id: String,
+    /// `SUCCESS` once the files are queued.
+    pub response_code: String,
+}
+
+/// The files a pipe has loaded or tried to load recently, from [`IngestClient::insert_report`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct InsertReport {
+    pub pipe: String,
+    /// False if there were more events than the report holds, which are then lost.
+    pub complete_result: bool,
+    /// Where the next report starts, to pass to the next call to get only newer events.
+    pub next_begin_mark: Option<String>,
+    #[serde(default)]
+    pub files: Vec<FileIngestStatus>,
+}
+
+/// The files a pipe loaded in a time range, from [`IngestClient::load_history_scan`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct LoadHistory {
+    pub pipe: String,
+    /// False if there were more files than the history holds; scan a smaller range for the
+    /// rest.
+    pub complete_result: bool,
+    #[serde(default, deserialize_with = "optional_time")]
+    pub range_start_time: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "optional_time")]
+    pub range_end_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub files: Vec<FileIngestStatus>,
+}
+
+/// How the loading of one file went.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct FileIngestStatus {
+    pub path: String,
+    pub stage_location: Option<String>,
+    pub file_size: Option<u64>,
+    #[serde(default, deserialize_with = "optional_time")]
+    pub time_received: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "optional_time")]
+    pub last_insert_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub rows_inserted: u64,
+    #[serde(default)]
+    pub rows_parsed: u64,
+    #[serde(default)]
+    pub errors_seen: u64,
+    pub error_limit: Option<u64>,
+    pub first_error: Option<String>,
+    pub first_error_line_num: Option<u64>,
+    pub first_error_character_pos: Option<u64>,
+    pub first_error_column_name: Option<String>,
+    pub system_error: Option<String>,
+    /// Whether Snowpipe is done with the file.
+    #[serde(default)]
+    pub complete: bool,
+    pub status: IngestStatus,
+}
+
+/// The `status` of a [`FileIngestStatus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IngestStatus {
+    Loaded,
+    LoadFailed,
+    PartiallyLoaded,
+    LoadInProgress,
+    /// A status this version of the connector does not know.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize)]
+struct InsertFilesRequest<'a> {
+    files: &'a [IngestFile],
+}
+
+impl IngestClient {
+    /// Creates a client of the pipes of `config.account`, authenticating as `username` with
+    /// its key pair; other authentication methods fail with [`Error::Unsupported`]. Of the
+    /// config, only the account and the settings of the HTTP requests are used.
+    pub fn new(
+        username: &str,
+        auth: SnowflakeAuthMethod,
+        config: SnowflakeClientConfig,
+    ) -> Result<Self> {
+        let SnowflakeAuthMethod::KeyPair {
+            encrypted_pem,
+            password,
+        } = auth
+        else {
+            return Err(Error::Unsupported(
+                "Snowpipe only accepts key pair authentication".into(),
+            ));
+        };
+        Ok(Self {
+            http: config.http_client()?,
+            pipes_url: format!("{}/v1/data/pipes", config.base_url()),
+            jwt: KeyPairJwt::new(&encrypted_pem, &password, username, &config.account)?,
+        })
+    }
+
+    /// Queues staged files to be loaded by `pipe`, named as in SQL, e.g.
+    /// `db.public.events_pipe`. Snowpipe loads them some time later; their outcome is in
+    /// [`IngestClient::insert_report`]. A file already loaded by the pipe is skipped.
+    pub async fn insert_files(
+        &self,
+        pipe: &str,
+        files: &[IngestFile],
+    ) -> Result<InsertFilesResponse> {
+        let request = self
+            .request(reqwest::Method::POST, pipe, "insertFiles", &[])?
+            .json(&InsertFilesRequest { files });
+        send(&self.http, request).await?.parse()
+    }
+
+    /// Reports the files `pipe` loaded or failed to load in the last 10 minutes, at most 10,000
+    /// events, from `begin_mark` on if given: the
+    /// [`next_begin_mark`](InsertReport::next_begin_mark) of an earlier report.
+    pub async fn insert_report(
+        &self,
+        pipe: &str,
+        begin_mark: Option<&str>,
+    ) -> Result<InsertReport> {
+        let query = begin_mark
+            .map(|mark| ("beginMark", mark.to_string()))
+            .into_iter()
+            .collect::<Vec<_>>();
+        let request = self.request(reqwest::Method::GET, pipe, "insertReport", &query)?;
+        send(&self.http, request).await?.parse()
+    }
+
+    /// Reports the files `pipe` loaded from `start` until before `end`, or until now without
+    /// one, kept for 14 days. Meant for recovering from an outage rather than for polling; see
+    /// [`IngestClient::insert_report`] for that.
+    pub async fn load_history_scan(
+        &self,
+        pipe: &str,
+        start: DateTime<Utc>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<LoadHistory> {
+        let time = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Millis, true);
+        let mut query = vec![("startTimeInclusive", time(start))];
+        if let Some(end) = end {
+            query.push(("endTimeExclusive", time(end)));
+        }
+        let request = self.request(reqwest::Method::GET, pipe, "loadHistoryScan", &query)?;
+        send(&self.http, request).await?.parse()
+    }
+
+    /// A request to an endpoint of a pipe, with a request ID and the JWT.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        pipe: &str,
+        endpoint: &str,
+        query: &[(&str, String)],
+    ) -> Result<reqwest::RequestBuilder> {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let url = format!("{}/{pipe}/{endpoint}", self.pipes_url);
+        Ok(self
+            .http
+            .client()
+            .request(method, url)
+            .query(&[("requestId", request_id)])
+            .query(query)
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {}", self.jwt.token()?))
+            .header("X-Snowflake-Authorization-Token-Type", "KEYPAIR_JWT"))
+    }
+}
+
+/// Reads an RFC 3339 time, e.g. `2024-06-01T04:47:41.453Z`.
+fn optional_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+    let Some(time) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    DateTime::parse_from_rfc3339(&time)
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> Result<IngestClient> {
+        IngestClient::new(
+            "user",
+            SnowflakeAuthMethod::KeyPair {
+                encrypted_pem: include_str!("auth/test_snowflake_key.p8").into(),
+                password: b"12345".to_vec().into(),
+            },
+            SnowflakeClientConfig {
+                account: "myaccount.ap-northeast-1.aws".into(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_requests() -> Result<()> {
+        let client = client()?;
+        let start = DateTime::parse_from_rfc3339("2024-06-01T04:47:41.453Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let query = [("startTimeInclusive", "x".to_string())];
+        let request = client
+            .request(
+                reqwest::Method::GET,
+                "db.public.p",
+                "loadHistoryScan",
+                &query,
+            )?
+            .build()?;
+        let url = request.url();
+        assert_eq!(url.path(), "/v1/data/pipes/db.public.p/loadHistoryScan");
+        assert_eq!(
+            url.host_str(),
+            Some("myaccount.ap-northeast-1.aws.snowflakecomputing.com")
+        );
+        assert!(url.query_pairs().any(|(name, _)| name == "requestId"));
+        assert!(request.headers()[AUTHORIZATION]
+            .to_str()
+            .unwrap()
+            .starts_with("Bearer ey"));
+        assert_eq!(
+            start.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "2024-06-01T04:47:41.453Z"
+        );
+
+        let files = [
+            IngestFile::from("a.csv"),
+            IngestFile {
+                path: "b.csv".into(),
+                size: Some(100),
+            },
+        ];
+        assert_eq!(
+            serde_json::to_string(&InsertFilesRequest { files: &files }).unwrap(),
+            r#"{"files":[{"path":"a.csv"},{"path":"b.csv","size":100}]}"#
+        );
+
+        let password = IngestClient::new(
+            "user",
+            SnowflakeAuthMethod::Password("secret".into()),
+            SnowflakeClientConfig::default(),
+        );
+        assert!(matches!(password, Err(Error::Unsupported(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports() {
+        let report: InsertReport = serde_json::from_str(
+            r#"{"pipe":"DB.PUBLIC.P","completeResult":true,"nextBeginMark":"1_16","files":[{"path":"a.csv","stageLocation":"s3://bucket/","fileSize":57,"timeReceived":"2024-06-01T04:47:41.453Z","lastInsertTime":"2024-06-01T04:48:28.575Z","rowsInserted":1,"rowsParsed":1,"errorsSeen":0,"errorLimit":1,"complete":true,"status":"LOADED"},{"path":"b.csv","fileSize":12,"timeReceived":"2024-06-01T04:47:41.453Z","rowsInserted":0,"rowsParsed":2,"errorsSeen":2,"errorLimit":1,"firstError":"Numeric value 'x' is not recognized","firstErrorLineNum":1,"firstErrorCharacterPos":3,"firstErrorColumnName":"\"EVENTS\"[\"ID\":1]","complete":true,"status":"LOAD_FAILED"},{"path":"c.csv","complete":false,"status":"SOMETHING_NEW"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(report.next_begin_mark.as_deref(), Some("1_16"));
+        let statuses = report
+            .files
+            .iter()
+            .map(|file| file.status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                IngestStatus::Loaded,
+                IngestStatus::LoadFailed,
+                IngestStatus::Unknown
+            ]
+        );
+        assert_eq!(
+            report.files[0]
+                .last_insert_time
+                .map(|time| time.timestamp_millis()),
+            Some(1717217308575)
+        );
+        assert_eq!(report.files[1].first_error_line_num, Some(1));
+
+        let history: LoadHistory = serde_json::from_str(
+            r#"{"pipe":"DB.PUBLIC.P","completeResult":true,"startTimeInclusive":"2024-06-01T00:00:00.000Z","rangeStartTime":"2024-06-01T04:48:28.575Z","rangeEndTime":"2024-06-01T04:48:28.575Z","files":[]}"#,
+        )
+        .unwrap();
+        assert!(history.complete_result && history.files.is_empty());
+        assert!(history.range_start_time.is_some());
+    }
+}
@@ -0,0 +1,392 @@
+//! Inserting serializable values into a table with bound parameters.
+
+use serde::Serialize;
+
+use crate::{
+    bind::{in_placeholder_order, to_bind_row, BindType, BindValue, Binding, Bindings},
+    catalog::{quote_identifier, quote_object_name},
+    Error, QueryRequest, Result, SnowflakeSession,
+};
+
+/// The most values bound in one statement. Snowflake expects larger array bindings to be
+/// uploaded to a stage rather than sent with the statement.
+const MAX_BINDINGS_PER_STATEMENT: usize = 65_280;
+
+/// Inserts rows into a table with `INSERT ... VALUES (?, ...)` statements whose values are
+/// bound, rather than written into the SQL. Created by [`SnowflakeSession::insert_into`].
+///
+/// Each value is serialized with serde into one row: its fields are the columns, and `None`
+/// is NULL. Fields can be numbers, booleans, strings, bytes, unit enum variants, JSON (see
+/// [`Json`](crate::Json)), dates and timestamps (see [`Bind`](crate::Bind)) and their
+/// `Option`s. Bytes go into BINARY columns wrapped in [`Bind`](crate::Bind), or as types that
+/// serialize as bytes, such as `bytes::Bytes` with the `bytes` feature; a bare `Vec<u8>` is a
+/// JSON array like any other sequence. The rows are sent as array bindings, as many per
+/// statement as the binding limit allows.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeSession};
+/// #[derive(serde::Serialize)]
+/// struct Event {
+///     id: i64,
+///     kind: String,
+///     note: Option<String>,
+/// }
+///
+/// # async fn run(session: &SnowflakeSession, events: Vec<Event>) -> Result<()> {
+/// let inserted = session.insert_into("events").values(&events).execute().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "an insert does nothing until it is executed"]
+pub struct InsertBuilder<'a> {
+    session: &'a SnowflakeSession,
+    table: String,
+    columns: Option<Vec<String>>,
+    rows: Vec<Vec<(String, BindValue)>>,
+    rows_per_statement: Option<usize>,
+    error: Option<Error>,
+}
+
+impl SnowflakeSession {
+    /// Starts an insert into `table`, written as in SQL, optionally qualified with its schema
+    /// and database: unquoted parts are case-insensitive and quoted parts are exact.
+    pub fn insert_into(&self, table: &str) -> InsertBuilder<'_> {
+        InsertBuilder {
+            session: self,
+            table: table.to_string(),
+            columns: None,
+            rows: Vec::new(),
+            rows_per_statement: None,
+            error: None,
+        }
+    }
+}
+
+impl InsertBuilder<'_> {
+    /// Inserts only these fields of the values, into the columns of the same names, in this
+    /// order. Without it, every field of the first value is inserted.
+    ///
+    /// A name that is a valid unquoted identifier, such as `user_id`, is case-insensitive, as
+    /// in SQL; any other name, such as `Order Date`, is quoted and matches exactly. A name
+    /// written in double quotes is used as written.
+    pub fn columns<S: AsRef<str>>(mut self, columns: &[S]) -> Self {
+        self.columns = Some(columns.iter().map(|c| c.as_ref().to_string()).collect());
+        self
+    }
+
+    /// Adds rows to insert. A value that cannot be serialized into a row fails
+    /// [`InsertBuilder::execute`] with [`Error::Bind`].
+    pub fn values<T: Serialize>(mut self, values: impl IntoIterator<Item = T>) -> Self {
+        for value in values {
+            if self.error.is_some() {
+                break;
+            }
+            match to_bind_row(&value) {
+                Ok(row) => self.rows.push(row),
+                Err(e) => self.error = Some(e),
+            }
+        }
+        self
+    }
+
+    /// Sends at most `rows` rows in one statement, rather than as many as the binding limit
+    /// allows.
+    pub fn rows_per_statement(mut self, rows: usize) -> Self {
+        self.rows_per_statement = Some(rows.max(1));
+        self
+    }
+
+    /// Inserts the rows and returns how many were inserted. The statements run one after
+    /// another; if one fails, the rows of the statements before it stay inserted.
+    pub async fn execute(self) -> Result<u64> {
+        let session = self.session;
+        let mut inserted = 0;
+        for request in self.statements()? {
+            let result = session.query_lazy(request).await?;
+            let rows_inserted = result.stats().rows_inserted;
+            let rows = result.fetch_all().await?;
+            inserted += match (rows_inserted, rows.first()) {
+                (Some(rows_inserted), _) => rows_inserted,
+                (None, Some(row)) => row.get_index::<u64>(0)?,
+                (None, None) => 0,
+            };
+        }
+        Ok(inserted)
+    }
+
+    /// The statements that insert the rows, with their bindings.
+    fn statements(mut self) -> Result<Vec<QueryRequest>> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let Some(first) = self.rows.first() else {
+            return Ok(Vec::new());
+        };
+        let columns = match self.columns.take() {
+            Some(columns) => columns,
+            None => first.iter().map(|(name, _)| name.clone()).collect(),
+        };
+        if columns.is_empty() {
+            return Err(Error::Bind(
+                "an insert needs at least one column".to_string(),
+            ));
+        }
+        let insert_into = format!(
+            "INSERT INTO {} ({})",
+            quote_object_name(&self.table)?,
+            columns
+                .iter()
+                .map(|column| column_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        let rows_per_statement = self
+            .rows_per_statement
+            .unwrap_or(MAX_BINDINGS_PER_STATEMENT / columns.len())
+            .max(1);
+        let mut statements = Vec::new();
+        for (chunk_index, chunk) in self.rows.chunks_mut(rows_per_statement).enumerate() {
+            let mut values = vec![Vec::with_capacity(chunk.len()); columns.len()];
+            for (i, row) in chunk.iter_mut().enumerate() {
+                for (column, values) in columns.iter().zip(&mut values) {
+                    let field_name = field_name(column);
+                    let field = row.iter_mut().find(|(name, _)| *name == field_name);
+                    let Some((_, value)) = field else {
+                        let row_index = chunk_index * rows_per_statement + i;
+                        return Err(Error::Bind(format!(
+                            "row {row_index} has no field {column}"
+                        )));
+                    };
+                    values.push(std::mem::replace(value, BindValue::null()));
+                }
+            }
+            let bindings = values
+                .into_iter()
+                .enumerate()
+                .map(|(i, values)| ((i + 1).to_string(), Binding::many(values)))
+                .collect::<Bindings>();
+            statements.push(QueryRequest {
+                sql_text: insert_sql(&insert_into, &bindings),
+                bindings: Some(bindings),
+                parameters: Default::default(),
+                strict_nullability: None,
+            });
+        }
+        Ok(statements)
+    }
+}
+
+/// The statement inserting the values of `bindings`. JSON values are parsed with `PARSE_JSON`,
+/// which `VALUES` does not take, so they are inserted with `SELECT` instead.
+fn insert_sql(insert_into: &str, bindings: &Bindings) -> String {
+    let placeholders = in_placeholder_order(bindings)
+        .map(|binding| binding.bind_type.placeholder())
+        .collect::<Vec<_>>();
+    match bindings.values().any(|b| b.bind_type == BindType::Json) {
+        true => format!("{insert_into} SELECT {}", placeholders.join(", ")),
+        false => format!("{insert_into} VALUES ({})", placeholders.join(", ")),
+    }
+}
+
+/// Writes a column name as an identifier: a valid unquoted identifier or a quoted one as it
+/// is, anything else quoted.
+pub(crate) fn column_identifier(name: &str) -> String {
+    let mut chars = name.chars();
+    let unquoted = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$'));
+    match unquoted || quoted_identifier(name).is_some() {
+        true => name.to_string(),
+        false => quote_identifier(name),
+    }
+}
+
+/// The field a column is read from: the name inside the quotes of a quoted identifier, the
+/// name itself otherwise.
+pub(crate) fn field_name(column: &str) -> String {
+    quoted_identifier(column).unwrap_or_else(|| column.to_string())
+}
+
+/// The name a valid quoted identifier stands for.
+fn quoted_identifier(name: &str) -> Option<String> {
+    let inner = name.strip_prefix('"')?.strip_suffix('"')?;
+    let unescaped = inner.replace("\"\"", "\"");
+    (!inner.is_empty() && quote_identifier(&unescaped) == name).then_some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metrics::NoMetrics, tests::session, Bind};
+
+    #[derive(Serialize)]
+    struct Event {
+        id: i64,
+        #[serde(rename = "Order Date")]
+        date: &'static str,
+        note: Option<&'static str>,
+    }
+
+    fn events(count: i64) -> Vec<Event> {
+        (0..count)
+            .map(|id| Event {
+                id,
+                date: "2024-01-31",
+                note: (id % 2 == 0).then_some("even"),
+            })
+            .collect()
+    }
+
+    fn bindings(request: &QueryRequest) -> serde_json::Value {
+        serde_json::to_value(&request.bindings).unwrap()
+    }
+
+    #[test]
+    fn test_insert_statements() -> Result<()> {
+        let session = session("acct", std::sync::Arc::new(NoMetrics));
+        let statements = session
+            .insert_into(r#"sales."Raw Events""#)
+            .values(events(3))
+            .rows_per_statement(2)
+            .statements()?;
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0].sql_text,
+            r#"INSERT INTO "SALES"."Raw Events" (id, "Order Date", note) VALUES (?, ?, ?)"#
+        );
+        assert_eq!(
+            bindings(&statements[0]),
+            serde_json::json!({
+                "1": {"type": "FIXED", "value": ["0", "1"]},
+                "2": {"type": "TEXT", "value": ["2024-01-31", "2024-01-31"]},
+                "3": {"type": "TEXT", "value": ["even", null]},
+            })
+        );
+        assert_eq!(
+            bindings(&statements[1])["3"],
+            serde_json::json!({"type": "TEXT", "value": ["even"]})
+        );
+
+        let statements = session
+            .insert_into("events")
+            .columns(&["note", "id"])
+            .values(events(1))
+            .statements()?;
+        assert_eq!(
+            statements[0].sql_text,
+            "INSERT INTO \"EVENTS\" (note, id) VALUES (?, ?)"
+        );
+        assert_eq!(
+            bindings(&statements[0])["2"]["value"],
+            serde_json::json!(["0"])
+        );
+
+        let statements = session
+            .insert_into("events")
+            .values([serde_json::json!({"id": 1, "payload": {"tags": ["a"]}})])
+            .statements()?;
+        assert_eq!(
+            statements[0].sql_text,
+            "INSERT INTO \"EVENTS\" (id, payload) SELECT ?, PARSE_JSON(?)"
+        );
+        assert_eq!(
+            bindings(&statements[0])["2"],
+            serde_json::json!({"type": "TEXT", "value": [r#"{"tags":["a"]}"#]})
+        );
+
+        // The placeholders follow the columns past the ninth, whose positions sort as text
+        // before the second's.
+        let columns = [
+            "id", "payload", "c3", "c4", "c5", "c6", "c7", "c8", "c9", "c10", "c11",
+        ];
+        let mut row = serde_json::Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            row.insert(column.to_string(), serde_json::json!(i));
+        }
+        row["payload"] = serde_json::json!({"tags": ["a"]});
+        let statements = session
+            .insert_into("wide")
+            .columns(&columns)
+            .values([row])
+            .statements()?;
+        assert_eq!(
+            statements[0].sql_text,
+            format!(
+                "INSERT INTO \"WIDE\" ({}) SELECT ?, PARSE_JSON(?), {}",
+                columns.join(", "),
+                ["?"; 9].join(", ")
+            )
+        );
+        assert_eq!(bindings(&statements[0])["2"]["type"], "TEXT");
+        assert_eq!(
+            bindings(&statements[0])["11"]["value"],
+            serde_json::json!(["10"])
+        );
+
+        #[derive(Serialize)]
+        struct Blob {
+            hash: Bind<Vec<u8>>,
+        }
+        let blobs = [vec![0x0f, 0xa0], vec![], vec![0xff]].map(|hash| Blob { hash: Bind(hash) });
+        let statements = session
+            .insert_into("blobs")
+            .values(blobs)
+            .rows_per_statement(2)
+            .statements()?;
+        assert_eq!(
+            statements[0].sql_text,
+            "INSERT INTO \"BLOBS\" (hash) VALUES (?)"
+        );
+        assert_eq!(
+            bindings(&statements[0]),
+            serde_json::json!({"1": {"type": "BINARY", "value": ["0FA0", ""]}})
+        );
+        assert_eq!(
+            bindings(&statements[1]),
+            serde_json::json!({"1": {"type": "BINARY", "value": ["FF"]}})
+        );
+
+        assert!(session
+            .insert_into("events")
+            .values(events(0))
+            .statements()?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_errors() {
+        let session = session("acct", std::sync::Arc::new(NoMetrics));
+        let error = |insert: InsertBuilder<'_>| insert.statements().unwrap_err().to_string();
+        assert_eq!(
+            error(
+                session
+                    .insert_into("t")
+                    .columns(&["missing"])
+                    .values(events(1))
+            ),
+            "bind error: row 0 has no field missing"
+        );
+        assert_eq!(
+            error(session.insert_into("t").values([vec![1]])),
+            "bind error: a row must serialize as a struct or a map"
+        );
+        assert_eq!(
+            error(session.insert_into("t.").values(events(1))),
+            "invalid identifier: t."
+        );
+    }
+
+    #[test]
+    fn test_column_identifier() {
+        assert_eq!(column_identifier("user_id"), "user_id");
+        assert_eq!(column_identifier("Order Date"), r#""Order Date""#);
+        assert_eq!(column_identifier(r#""mixedCase""#), r#""mixedCase""#);
+        assert_eq!(column_identifier(r#"a"b"#), r#""a""b""#);
+        assert_eq!(column_identifier("1st"), r#""1st""#);
+        assert_eq!(column_identifier(r#""""#), r#""""""""#);
+        assert_eq!(field_name(r#""say ""hi""""#), r#"say "hi""#);
+        assert_eq!(field_name("user_id"), "user_id");
+    }
+}
@@ -0,0 +1,236 @@
+//! Decoding of interval-like values into durations.
+//!
+//! Snowflake has no interval result type: the difference of two timestamps comes out either as
+//! a number of seconds (`DATEDIFF`, `TIMESTAMPDIFF`, epoch arithmetic) or as text built by the
+//! query, such as `1 day, 2:03:04.500`. Both forms are accepted here.
+
+use crate::{
+    row::{impl_decode_from_str, unwrap_str},
+    temporal::ScaledSeconds,
+    types::SnowflakeColumnType,
+    Error, Result, SnowflakeDecode,
+};
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+impl_decode_from_str! {
+    /// Decodes a number of seconds (`90.5`, `-1.5e3`) or a textual interval.
+    ///
+    /// Textual intervals are a sequence of `<number> <unit>` parts and `[-]H:MM:SS[.fraction]`
+    /// clocks, optionally separated by commas, e.g. `1 day, 2:03:04.500`, `3 hours 15 minutes` or
+    /// `-00:00:01.25`. Each part carries its own sign, so `-1 day, 23:59:59` is minus one second,
+    /// except that a number directly followed by a clock is the SQL `D HH:MM:SS` form, where the
+    /// sign applies to the whole value: `-1 02:00:00` is minus 26 hours. Years and months have no
+    /// fixed length and are rejected.
+    chrono::Duration => parse_duration;
+    /// Decodes like `chrono::Duration`; negative intervals are an error.
+    std::time::Duration => |value| {
+        parse_duration(value)?.to_std().map_err(|_| {
+            Error::decode(format!(
+                "negative interval '{value}' cannot be decoded into std::time::Duration"
+            ))
+        })
+    };
+}
+
+fn parse_duration(value: &str) -> Result<chrono::Duration> {
+    let nanos = parse_interval_nanos(value)?;
+    let secs = i64::try_from(nanos.div_euclid(NANOS_PER_SEC))
+        .ok()
+        .filter(|secs| secs.unsigned_abs() <= i64::MAX as u64 / 1000)
+        .ok_or_else(|| out_of_range(value))?;
+    let subsec_nanos = nanos.rem_euclid(NANOS_PER_SEC) as i64;
+    Ok(chrono::Duration::seconds(secs) + chrono::Duration::nanoseconds(subsec_nanos))
+}
+
+fn parse_interval_nanos(value: &str) -> Result<i128> {
+    let value = value.trim();
+    if let Some(seconds) = ScaledSeconds::parse(value) {
+        return Ok(to_nanos(seconds));
+    }
+    if let Ok(seconds) = value.parse::<f64>() {
+        if seconds.is_finite() {
+            return Ok((seconds * NANOS_PER_SEC as f64).round() as i128);
+        }
+    }
+
+    let invalid = || Error::decode(format!("'{value}' is not an interval"));
+    let add = |total: i128, nanos: Option<i128>| {
+        nanos
+            .and_then(|nanos| total.checked_add(nanos))
+            .ok_or_else(|| out_of_range(value))
+    };
+    let mut tokens = value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .peekable();
+    let mut total = 0i128;
+    let mut parts = 0;
+    while let Some(token) = tokens.next() {
+        parts += 1;
+        if token.contains(':') {
+            total = add(total, Some(parse_clock(token).ok_or_else(invalid)?))?;
+            continue;
+        }
+        if token.trim_start_matches(['+', '-']).contains('-') {
+            return Err(year_month(value));
+        }
+        let amount =
+            to_nanos(ScaledSeconds::parse(token.trim_start_matches('+')).ok_or_else(invalid)?);
+        match tokens.peek() {
+            Some(next) if next.contains(':') => {
+                let clock = parse_clock(next.trim_start_matches(['+', '-'])).ok_or_else(invalid)?;
+                tokens.next();
+                let days = amount.checked_mul(86_400);
+                let nanos = match token.starts_with('-') {
+                    true => days.and_then(|days| days.checked_sub(clock)),
+                    false => days.and_then(|days| days.checked_add(clock)),
+                };
+                total = add(total, nanos)?;
+            }
+            Some(unit) => {
+                let unit_nanos = unit_nanos(unit).ok_or_else(|| {
+                    if is_year_month_unit(unit) {
+                        year_month(value)
+                    } else {
+                        invalid()
+                    }
+                })?;
+                tokens.next();
+                // `amount` is the count scaled to nanoseconds, so scale back down after multiplying.
+                let nanos = amount.checked_mul(unit_nanos).map(|n| n / NANOS_PER_SEC);
+                total = add(total, nanos)?;
+            }
+            None => return Err(invalid()),
+        }
+    }
+    if parts == 0 {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+fn to_nanos(ScaledSeconds { secs, nanos }: ScaledSeconds) -> i128 {
+    i128::from(secs) * NANOS_PER_SEC + i128::from(nanos)
+}
+
+/// Parses `[-]H:MM:SS[.fraction]` or `[-]H:MM` into nanoseconds.
+fn parse_clock(token: &str) -> Option<i128> {
+    let (negative, clock) = match token.strip_prefix('-') {
+        Some(clock) => (true, clock),
+        None => (false, token.strip_prefix('+').unwrap_or(token)),
+    };
+    let mut fields = clock.split(':');
+    let hours: u64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next()?.parse().ok()?;
+    let seconds = match fields.next() {
+        Some(seconds) => to_nanos(ScaledSeconds::parse(seconds).filter(|s| s.secs >= 0)?),
+        None => 0,
+    };
+    if fields.next().is_some() || minutes >= 60 || seconds >= 60 * NANOS_PER_SEC {
+        return None;
+    }
+    let nanos = (i128::from(hours) * 3600 + i128::from(minutes) * 60) * NANOS_PER_SEC + seconds;
+    Some(if negative { -nanos } else { nanos })
+}
+
+fn unit_nanos(unit: &str) -> Option<i128> {
+    let nanos = match unit.to_ascii_lowercase().as_str() {
+        "w" | "week" | "weeks" => 7 * 86_400 * NANOS_PER_SEC,
+        "d" | "day" | "days" => 86_400 * NANOS_PER_SEC,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3_600 * NANOS_PER_SEC,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60 * NANOS_PER_SEC,
+        "s" | "sec" | "secs" | "second" | "seconds" => NANOS_PER_SEC,
+        "ms" | "millisecond" | "milliseconds" => 1_000_000,
+        "us" | "microsecond" | "microseconds" => 1_000,
+        "ns" | "nanosecond" | "nanoseconds" => 1,
+        _ => return None,
+    };
+    Some(nanos)
+}
+
+fn is_year_month_unit(unit: &str) -> bool {
+    matches!(
+        unit.to_ascii_lowercase().as_str(),
+        "y" | "yr" | "yrs" | "year" | "years" | "mon" | "mons" | "month" | "months"
+    )
+}
+
+fn out_of_range(value: &str) -> Error {
+    Error::decode(format!("interval '{value}' is out of range"))
+}
+
+fn year_month(value: &str) -> Error {
+    Error::decode(format!(
+        "interval '{value}' has years or months, which have no fixed duration"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration(value: &str) -> Result<chrono::Duration> {
+        chrono::Duration::try_decode(&Some(value.to_string()))
+    }
+
+    #[test]
+    fn test_decode_interval() -> Result<()> {
+        use chrono::Duration;
+
+        assert_eq!(duration("90")?, Duration::seconds(90));
+        assert_eq!(duration("-1.250")?, Duration::milliseconds(-1250));
+        assert_eq!(duration("1.5e3")?, Duration::seconds(1500));
+        assert_eq!(
+            duration("1 day, 2:03:04.500")?,
+            Duration::days(1) + Duration::seconds(7384) + Duration::milliseconds(500)
+        );
+        assert_eq!(duration("-1 day, 23:59:59")?, Duration::seconds(-1));
+        assert_eq!(duration("-1 02:00:00")?, Duration::hours(-26));
+        assert_eq!(
+            duration("-00:00:01.000000001")?,
+            Duration::nanoseconds(-1_000_000_001)
+        );
+        assert_eq!(
+            duration("3 hours 15 minutes")?,
+            Duration::hours(3) + Duration::minutes(15)
+        );
+        assert_eq!(duration("1.5 days")?, Duration::hours(36));
+        assert_eq!(duration("250 ms")?, Duration::milliseconds(250));
+
+        assert!(duration("1 month")
+            .unwrap_err()
+            .to_string()
+            .contains("no fixed duration"));
+        assert!(duration("+1-02").is_err());
+        assert!(duration("soon").is_err());
+        assert!(duration("00:61:00").is_err());
+        assert!(duration("").is_err());
+
+        let overflow = format!("{} weeks", i64::MAX);
+        assert_eq!(
+            duration(&overflow).unwrap_err().to_string(),
+            format!("decode error: interval '{overflow}' is out of range")
+        );
+        let out_of_range = |value: &str| {
+            duration(value)
+                .unwrap_err()
+                .to_string()
+                .contains("is out of range")
+        };
+        assert!(out_of_range(&format!("{} 00:00:00", i64::MAX)));
+        assert!(out_of_range("1e300"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_std_duration() -> Result<()> {
+        let decode = |v: &str| std::time::Duration::try_decode(&Some(v.to_string()));
+        assert_eq!(
+            decode("1:00:00.000001")?,
+            std::time::Duration::from_micros(3_600_000_001)
+        );
+        assert!(decode("-1").is_err());
+        Ok(())
+    }
+}
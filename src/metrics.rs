@@ -0,0 +1,132 @@
+//! Hooks for collecting metrics about sessions, queries and chunk downloads.
+
+use std::{fmt, time::Duration};
+
+use crate::Error;
+
+/// Receives events from the connector, e.g. to export them as metrics. Set it with
+/// [`SnowflakeClientConfig::metrics`](crate::SnowflakeClientConfig::metrics).
+///
+/// Every method does nothing by default, so an implementation only overrides the events it
+/// needs. The methods are called inline, from the tasks that run queries and download chunks,
+/// and should return quickly.
+///
+/// ```rust
+/// # use std::{sync::atomic::{AtomicU64, Ordering}, time::Duration};
+/// # use snowflake_connector_rs::ConnectorMetrics;
+/// #[derive(Default)]
+/// struct DownloadedBytes(AtomicU64);
+///
+/// impl ConnectorMetrics for DownloadedBytes {
+///     fn on_chunk_downloaded(&self, bytes: u64, _duration: Duration) {
+///         self.0.fetch_add(bytes, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait ConnectorMetrics: Send + Sync {
+    /// A session has been created, after a login that took `duration`.
+    fn on_session_created(&self, duration: Duration) {
+        let _ = duration;
+    }
+
+    /// A query is about to be sent.
+    fn on_query_start(&self) {}
+
+    /// A query has finished `duration` after it was sent, with `error` if it failed. A query
+    /// finishes once its result has arrived; the chunks of the result are downloaded
+    /// afterwards, as they are read.
+    fn on_query_finish(&self, duration: Duration, error: Option<&Error>) {
+        let _ = (duration, error);
+    }
+
+    /// A result chunk of `bytes` bytes has been downloaded in `duration`, including any
+    /// retries.
+    fn on_chunk_downloaded(&self, bytes: u64, duration: Duration) {
+        let _ = (bytes, duration);
+    }
+
+    /// Something failed and is being tried again.
+    fn on_retry(&self, kind: RetryKind) {
+        let _ = kind;
+    }
+}
+
+impl fmt::Debug for dyn ConnectorMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConnectorMetrics")
+    }
+}
+
+/// What is being retried, for [`ConnectorMetrics::on_retry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RetryKind {
+    /// A chunk download failed with a transient error and is downloaded again.
+    ChunkDownload,
+    /// The chunk URLs of a result had expired and are fetched again.
+    ChunkUrlRefresh,
+    /// A request to Snowflake was refused with 429 Too Many Requests and is sent again.
+    RateLimited,
+    /// A statement failed as its warehouse is resuming and is sent again; see
+    /// [`retry_on_warehouse_resume`](crate::SnowflakeClientConfig::retry_on_warehouse_resume).
+    WarehouseResume,
+}
+
+/// The metrics of a client without
+/// [`SnowflakeClientConfig::metrics`](crate::SnowflakeClientConfig::metrics).
+pub(crate) struct NoMetrics;
+
+impl ConnectorMetrics for NoMetrics {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Counts the events it receives.
+    #[derive(Default)]
+    pub(crate) struct CountingMetrics {
+        pub(crate) queries_started: AtomicUsize,
+        pub(crate) queries_failed: AtomicUsize,
+        pub(crate) queries_finished: AtomicUsize,
+        chunk_retries: AtomicUsize,
+        url_refreshes: AtomicUsize,
+        rate_limited: AtomicUsize,
+        warehouse_resumes: AtomicUsize,
+    }
+
+    impl CountingMetrics {
+        pub(crate) fn retries(&self, kind: RetryKind) -> usize {
+            match kind {
+                RetryKind::ChunkDownload => self.chunk_retries.load(Ordering::SeqCst),
+                RetryKind::ChunkUrlRefresh => self.url_refreshes.load(Ordering::SeqCst),
+                RetryKind::RateLimited => self.rate_limited.load(Ordering::SeqCst),
+                RetryKind::WarehouseResume => self.warehouse_resumes.load(Ordering::SeqCst),
+            }
+        }
+    }
+
+    impl ConnectorMetrics for CountingMetrics {
+        fn on_query_start(&self) {
+            self.queries_started.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_query_finish(&self, _duration: Duration, error: Option<&Error>) {
+            self.queries_finished.fetch_add(1, Ordering::SeqCst);
+            if error.is_some() {
+                self.queries_failed.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn on_retry(&self, kind: RetryKind) {
+            match kind {
+                RetryKind::ChunkDownload => &self.chunk_retries,
+                RetryKind::ChunkUrlRefresh => &self.url_refreshes,
+                RetryKind::RateLimited => &self.rate_limited,
+                RetryKind::WarehouseResume => &self.warehouse_resumes,
+            }
+            .fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
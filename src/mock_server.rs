@@ -0,0 +1,690 @@
+//! A local server answering the requests of a client as Snowflake would, with the responses
+//! scripted for each statement, to test code that runs queries without a Snowflake account.
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    io::Write,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use hyper::{
+    header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+
+use crate::{Error, Result, SnowflakeClientConfig, SnowflakeColumnType};
+
+/// The statement type Snowflake reports for a `SELECT`.
+const SELECT: i64 = 0x1000;
+
+/// The version of Snowflake the server reports unless it is given another.
+const SERVER_VERSION: &str = "8.40.1";
+
+/// A Snowflake server on a local port, answering each statement with the responses scripted
+/// for it with [`MockServerBuilder`], and recording the statements it receives.
+///
+/// It logs in any user, issues tokens of its own, and serves the chunks of the results it
+/// answers with. A statement without a scripted response fails with an [`Error::Sql`] saying
+/// so. The server stops when it is dropped.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{MockResponse, MockServer, SnowflakeAuthMethod, SnowflakeClient, SnowflakeColumnType};
+/// # async fn run() -> snowflake_connector_rs::Result<()> {
+/// let number = SnowflakeColumnType::new("fixed", Some(0));
+/// let server = MockServer::builder()
+///     .query(
+///         "SELECT ID FROM users",
+///         MockResponse::rows([("ID", number)], vec![vec![Some("1".into())]])
+///             .with_chunk(vec![vec![Some("2".into())], vec![Some("3".into())]]),
+///     )
+///     .expire_token_after(2)
+///     .start()
+///     .await?;
+///
+/// let client = SnowflakeClient::new(
+///     "USER",
+///     SnowflakeAuthMethod::Password("PASSWORD".into()),
+///     server.client_config(),
+/// )?;
+/// let session = client.create_session().await?;
+/// let rows = session.query("SELECT ID FROM users").await?;
+/// assert_eq!(rows.len(), 3);
+/// assert_eq!(server.statements(), ["SELECT ID FROM users"]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+/// Scripts the responses of a [`MockServer`], created with [`MockServer::builder`].
+#[derive(Debug, Default)]
+pub struct MockServerBuilder {
+    answers: HashMap<String, Vec<MockResponse>>,
+    expire_token_after: Option<usize>,
+    server_version: Option<String>,
+    parameters: Vec<(String, Value)>,
+}
+
+/// A response of a [`MockServer`] to a statement: its rows, an error, or an HTTP response of
+/// any kind.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    answer: Answer,
+    polls: usize,
+    headers: Vec<(String, String)>,
+    parameters: Vec<(String, Value)>,
+    expire_chunk_urls: bool,
+    expire_result: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Answer {
+    Rows {
+        columns: Vec<(String, SnowflakeColumnType)>,
+        rows: Vec<Vec<Option<String>>>,
+        chunks: Vec<Vec<Vec<Option<String>>>>,
+        statement_type_id: i64,
+    },
+    Error {
+        code: u32,
+        sqlstate: String,
+        message: String,
+    },
+    Http {
+        status: u16,
+        body: String,
+    },
+}
+
+impl MockResponse {
+    /// A result of the given columns and rows, all in the response itself, of a `SELECT`.
+    pub fn rows<N: Into<String>>(
+        columns: impl IntoIterator<Item = (N, SnowflakeColumnType)>,
+        rows: Vec<Vec<Option<String>>>,
+    ) -> Self {
+        let columns = columns
+            .into_iter()
+            .map(|(name, column_type)| (name.into(), column_type))
+            .collect();
+        Self::new(Answer::Rows {
+            columns,
+            rows,
+            chunks: vec![],
+            statement_type_id: SELECT,
+        })
+    }
+
+    /// A failed statement, with the error code, SQL state and message Snowflake would send,
+    /// e.g. `MockResponse::error(2003, "42S02", "Object 'T' does not exist")`.
+    pub fn error(code: u32, sqlstate: &str, message: &str) -> Self {
+        Self::new(Answer::Error {
+            code,
+            sqlstate: sqlstate.to_string(),
+            message: message.to_string(),
+        })
+    }
+
+    /// An HTTP response with the given status and body, e.g. 429 Too Many Requests or a body
+    /// that is not a valid response. It has no `Content-Type` unless one is added with
+    /// [`MockResponse::with_header`].
+    pub fn http(status: u16, body: impl Into<String>) -> Self {
+        Self::new(Answer::Http {
+            status,
+            body: body.into(),
+        })
+    }
+
+    fn new(answer: Answer) -> Self {
+        Self {
+            answer,
+            polls: 0,
+            headers: vec![],
+            parameters: vec![],
+            expire_chunk_urls: false,
+            expire_result: false,
+        }
+    }
+
+    /// Adds a chunk of rows after those in the response, or after the chunks added before.
+    /// Chunks are served gzip-compressed, as from cloud storage.
+    pub fn with_chunk(mut self, chunk: Vec<Vec<Option<String>>>) -> Self {
+        if let Answer::Rows { chunks, .. } = &mut self.answer {
+            chunks.push(chunk);
+        }
+        self
+    }
+
+    /// Refuses the chunk URLs sent with the result with 403 Forbidden, as cloud storage refuses
+    /// presigned URLs once they have expired. Fetching the result again, as a client does for
+    /// fresh URLs, sends new URLs that are served.
+    pub fn with_expired_chunk_urls(mut self) -> Self {
+        self.expire_chunk_urls = true;
+        self
+    }
+
+    /// Answers fetches of the result once the statement has finished with the error of a
+    /// result that is no longer kept (`000612`), so that no fresh chunk URLs can be had.
+    pub fn with_expired_result(mut self) -> Self {
+        self.expire_result = true;
+        self
+    }
+
+    /// Sets the statement type reported for the result, e.g. `0x3100` for an `INSERT`.
+    pub fn with_statement_type_id(mut self, id: i64) -> Self {
+        if let Answer::Rows {
+            statement_type_id, ..
+        } = &mut self.answer
+        {
+            *statement_type_id = id;
+        }
+        self
+    }
+
+    /// Answers that the statement is still running, `polls` times before it finishes with
+    /// this response. Only a client that polls, i.e. with
+    /// [`polling_interval`](crate::SnowflakeClientConfig::polling_interval) and
+    /// [`max_polling_attempts`](crate::SnowflakeClientConfig::max_polling_attempts) set, waits
+    /// for it.
+    pub fn running(mut self, polls: usize) -> Self {
+        self.polls = polls;
+        self
+    }
+
+    /// Reports a changed session parameter with the result, as after an `ALTER SESSION`.
+    pub fn with_parameter(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.parameters.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Adds a header to the response, e.g. `Retry-After` to a 429.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl MockServerBuilder {
+    /// Answers `sql` with `response`, every time it is run.
+    pub fn query(self, sql: impl Into<String>, response: MockResponse) -> Self {
+        self.query_sequence(sql, [response])
+    }
+
+    /// Answers the runs of `sql` with `responses` in turn, and the runs after the last one
+    /// with the last one again, e.g. a 429 before the rows.
+    pub fn query_sequence(
+        mut self,
+        sql: impl Into<String>,
+        responses: impl IntoIterator<Item = MockResponse>,
+    ) -> Self {
+        let responses = responses.into_iter().collect::<Vec<_>>();
+        assert!(!responses.is_empty(), "a sequence needs a response");
+        self.answers.insert(sql.into(), responses);
+        self
+    }
+
+    /// Expires each session token after it has run `queries` statements, so that the next
+    /// statement fails with the error of an expired session until the token is renewed.
+    pub fn expire_token_after(mut self, queries: usize) -> Self {
+        self.expire_token_after = Some(queries);
+        self
+    }
+
+    /// Reports `version` as the version of Snowflake at login, rather than a recent one.
+    pub fn server_version(mut self, version: &str) -> Self {
+        self.server_version = Some(version.to_string());
+        self
+    }
+
+    /// Reports a session parameter at login.
+    pub fn parameter(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.parameters.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Starts the server on a free local port. Needs a Tokio runtime.
+    pub async fn start(self) -> Result<MockServer> {
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        listener.set_nonblocking(true)?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State {
+            url: url.clone(),
+            answers: self
+                .answers
+                .into_iter()
+                .map(|(sql, responses)| (sql, (responses, 0)))
+                .collect(),
+            expire_token_after: self.expire_token_after,
+            server_version: self
+                .server_version
+                .unwrap_or_else(|| SERVER_VERSION.to_string()),
+            parameters: self.parameters,
+            ..Default::default()
+        }));
+        let service_state = Arc::clone(&state);
+        let make_service = make_service_fn(move |_| {
+            let state = Arc::clone(&service_state);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(Arc::clone(&state), request)
+                }))
+            }
+        });
+        let server = Server::from_tcp(listener)
+            .map_err(|e| Error::Communication(format!("mock server: {e}")))?
+            .serve(make_service);
+        let server = tokio::spawn(async move {
+            let _ = server.await;
+        });
+        Ok(MockServer { url, state, server })
+    }
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// The address of the server, e.g. `http://127.0.0.1:49152`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// A config of a client sending its requests to the server, to set other fields of.
+    pub fn client_config(&self) -> SnowflakeClientConfig {
+        SnowflakeClientConfig {
+            account: "mock".to_string(),
+            base_url: Some(self.url.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// The statements received so far, in order, including those answered with an error or
+    /// an HTTP response. Statements refused for an expired token are left out.
+    pub fn statements(&self) -> Vec<String> {
+        self.state().statements.clone()
+    }
+
+    /// The number of logins so far.
+    pub fn logins(&self) -> usize {
+        self.state().logins
+    }
+
+    /// The number of session token renewals so far.
+    pub fn renewals(&self) -> usize {
+        self.state().renewals
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[derive(Default)]
+struct State {
+    url: String,
+    /// The responses to each statement, and how many times it has been run.
+    answers: HashMap<String, (Vec<MockResponse>, usize)>,
+    expire_token_after: Option<usize>,
+    server_version: String,
+    /// The session parameters reported at login.
+    parameters: Vec<(String, Value)>,
+    /// How many tokens have been issued; the current one is named after it.
+    token_generation: usize,
+    /// The statements run with the current token.
+    token_queries: usize,
+    statements: Vec<String>,
+    logins: usize,
+    renewals: usize,
+    /// The final responses of the queries still running, with the polls left until them.
+    running: HashMap<String, MockResponse>,
+    /// The gzip-compressed chunks of each result.
+    chunks: HashMap<String, Vec<Vec<u8>>>,
+    /// The final responses of the finished queries, to send their results again.
+    finished: HashMap<String, MockResponse>,
+    /// How many times the result of each query has been sent, numbering its chunk URLs.
+    result_fetches: HashMap<String, usize>,
+    /// The paths of the chunk URLs refused as expired.
+    expired_urls: HashSet<String>,
+}
+
+impl State {
+    fn session_token(&self) -> String {
+        format!("mock-session-token-{}", self.token_generation)
+    }
+
+    fn master_token(&self) -> String {
+        format!("mock-master-token-{}", self.token_generation)
+    }
+
+    /// Whether a request carries the current session token, and it has not expired.
+    fn authorized(&self, request: &Request<Body>) -> bool {
+        let expired = self
+            .expire_token_after
+            .is_some_and(|queries| self.token_queries >= queries);
+        !expired && token(request).as_deref() == Some(self.session_token().as_str())
+    }
+
+    fn issue_tokens(&mut self) -> Value {
+        self.token_generation += 1;
+        self.token_queries = 0;
+        json!({ "token": self.session_token(), "masterToken": self.master_token() })
+    }
+
+    /// The response to a statement, or its first poll if it runs for a while.
+    fn run(&mut self, sql: String) -> Response<Body> {
+        self.token_queries += 1;
+        let query_id = format!("01b0-mock-{:04}", self.statements.len());
+        self.statements.push(sql.clone());
+        let response = match self.answers.get_mut(&sql) {
+            Some((responses, runs)) => {
+                let response = responses[(*runs).min(responses.len() - 1)].clone();
+                *runs += 1;
+                response
+            }
+            None => MockResponse::error(2003, "42S02", &format!("no mock answer for: {sql}")),
+        };
+        self.respond(query_id, response)
+    }
+
+    fn respond(&mut self, query_id: String, mut response: MockResponse) -> Response<Body> {
+        if response.polls > 0 {
+            response.polls -= 1;
+            let body = json!({
+                "data": {
+                    "queryId": query_id,
+                    "getResultUrl": format!("/queries/{query_id}/result"),
+                },
+                "code": "333334",
+                "message": "Asynchronous execution in progress. Use provided query id to perform query monitoring and management.",
+                "success": true,
+            });
+            self.running.insert(query_id, response);
+            return json_response(body);
+        }
+        if let Answer::Rows { .. } = response.answer {
+            self.finished.insert(query_id.clone(), response.clone());
+        }
+        let body = match response.answer {
+            Answer::Rows {
+                columns,
+                rows,
+                chunks,
+                statement_type_id,
+            } => {
+                let manifest = self.store_chunks(&query_id, &chunks, response.expire_chunk_urls);
+                let total = rows.len() + chunks.iter().map(Vec::len).sum::<usize>();
+                let row_types = columns
+                    .iter()
+                    .map(|(name, column_type)| {
+                        json!({
+                            "name": name,
+                            "type": column_type.snowflake_type(),
+                            "scale": column_type.scale(),
+                            "precision": null,
+                            "byteLength": null,
+                            "length": null,
+                            "nullable": true,
+                            "database": "",
+                            "schema": "",
+                            "table": "",
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "data": {
+                        "queryId": query_id,
+                        "sqlState": "00000",
+                        "statementTypeId": statement_type_id,
+                        "rowtype": row_types,
+                        "rowset": rows,
+                        "total": total,
+                        "returned": total,
+                        "chunks": manifest,
+                        "chunkHeaders": {},
+                        "queryResultFormat": "json",
+                        "parameters": parameter_list(&response.parameters),
+                    },
+                    "code": null,
+                    "message": null,
+                    "success": true,
+                })
+            }
+            Answer::Error {
+                code,
+                sqlstate,
+                message,
+            } => json!({
+                "data": { "queryId": query_id, "sqlState": sqlstate },
+                "code": format!("{code:06}"),
+                "message": message,
+                "success": false,
+            }),
+            Answer::Http { status, body } => {
+                let mut http = Response::new(Body::from(body));
+                *http.status_mut() =
+                    StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                add_headers(&mut http, &response.headers);
+                return http;
+            }
+        };
+        let mut http = json_response(body);
+        add_headers(&mut http, &response.headers);
+        http
+    }
+
+    /// Compresses the chunks of a result to serve them, and returns their manifest. Each time
+    /// a result is sent, its chunks get new URLs; the first ones are refused with `expired`.
+    fn store_chunks(
+        &mut self,
+        query_id: &str,
+        chunks: &[Vec<Vec<Option<String>>>],
+        expired: bool,
+    ) -> Vec<Value> {
+        let fetches = self.result_fetches.entry(query_id.to_string()).or_default();
+        let generation = *fetches;
+        *fetches += 1;
+        let mut manifest = vec![];
+        let mut bodies = vec![];
+        for (index, rows) in chunks.iter().enumerate() {
+            // Chunks hold the rows without the brackets around them.
+            let text = rows
+                .iter()
+                .map(|row| serde_json::to_string(row).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join(",");
+            let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+            let body = gzip
+                .write_all(text.as_bytes())
+                .and_then(|()| gzip.finish())
+                .unwrap_or_default();
+            let path = format!("/chunks/{query_id}/{generation}/{index}");
+            if expired && generation == 0 {
+                self.expired_urls.insert(path.clone());
+            }
+            manifest.push(json!({
+                "url": format!("{}{path}", self.url),
+                "rowCount": rows.len(),
+                "uncompressedSize": text.len(),
+                "compressedSize": body.len(),
+            }));
+            bodies.push(body);
+        }
+        self.chunks.insert(query_id.to_string(), bodies);
+        manifest
+    }
+
+    /// The response to a poll or refetch of the result of a query.
+    fn result(&mut self, query_id: &str) -> Response<Body> {
+        if let Some(response) = self.running.remove(query_id) {
+            return self.respond(query_id.to_string(), response);
+        }
+        match self.finished.get(query_id).cloned() {
+            Some(response) if response.expire_result => json_response(json!({
+                "data": null,
+                "code": "000612",
+                "message": format!("Result for query {query_id} has expired"),
+                "success": false,
+            })),
+            Some(response) => self.respond(query_id.to_string(), response),
+            None => json_response(json!({
+                "data": null,
+                "code": "000709",
+                "message": format!("Statement {query_id} not found"),
+                "success": false,
+            })),
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> std::result::Result<Response<Body>, Infallible> {
+    let (method, path) = (request.method().clone(), request.uri().path().to_string());
+    let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    let response = match (&method, segments.as_slice()) {
+        (&Method::POST, ["session", "v1", "login-request"]) => {
+            let mut state = lock(&state);
+            state.logins += 1;
+            let mut data = state.issue_tokens();
+            data["serverVersion"] = json!(state.server_version);
+            data["parameters"] = json!(parameter_list(&state.parameters));
+            json_response(json!({
+                "data": data,
+                "code": null,
+                "message": null,
+                "success": true,
+            }))
+        }
+        (&Method::POST, ["session", "token-request"]) => {
+            let mut state = lock(&state);
+            if token(&request).as_deref() != Some(state.master_token().as_str()) {
+                return Ok(session_expired());
+            }
+            state.renewals += 1;
+            let tokens = state.issue_tokens();
+            json_response(json!({
+                "data": {
+                    "sessionToken": tokens["token"],
+                    "masterToken": tokens["masterToken"],
+                },
+                "code": null,
+                "message": null,
+                "success": true,
+            }))
+        }
+        (&Method::POST, ["queries", "v1", "query-request"]) => {
+            if !lock(&state).authorized(&request) {
+                return Ok(session_expired());
+            }
+            let body = hyper::body::to_bytes(request.into_body())
+                .await
+                .unwrap_or_default();
+            let sql = serde_json::from_slice::<Value>(&body)
+                .ok()
+                .and_then(|body| body["sqlText"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            lock(&state).run(sql)
+        }
+        (&Method::GET, ["queries", query_id, "result"]) => {
+            let mut state = lock(&state);
+            match state.authorized(&request) {
+                true => state.result(query_id),
+                false => session_expired(),
+            }
+        }
+        (&Method::GET, ["chunks", query_id, _, index]) => {
+            let state = lock(&state);
+            if state.expired_urls.contains(&path) {
+                return Ok(forbidden());
+            }
+            let chunk = index.parse::<usize>().ok().and_then(|index| {
+                state
+                    .chunks
+                    .get(*query_id)
+                    .and_then(|chunks| chunks.get(index))
+            });
+            match chunk {
+                Some(chunk) => Response::new(Body::from(chunk.clone())),
+                None => not_found(),
+            }
+        }
+        _ => not_found(),
+    };
+    Ok(response)
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The token of the `Snowflake Token="..."` authorization of a request.
+fn token(request: &Request<Body>) -> Option<String> {
+    let authorization = request.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let token = authorization.strip_prefix("Snowflake Token=\"")?;
+    Some(token.trim_end_matches('"').to_string())
+}
+
+/// Session parameters as responses list them.
+fn parameter_list(parameters: &[(String, Value)]) -> Vec<Value> {
+    parameters
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
+
+fn json_response(body: Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(body.to_string()));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn add_headers(response: &mut Response<Body>, headers: &[(String, String)]) {
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            hyper::header::HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+}
+
+fn session_expired() -> Response<Body> {
+    json_response(json!({
+        "data": null,
+        "code": "390112",
+        "message": "Your session has expired. Please login again.",
+        "success": false,
+    }))
+}
+
+/// The refusal of an expired presigned URL, as S3 words it.
+fn forbidden() -> Response<Body> {
+    let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>AccessDenied</Code><Message>Request has expired</Message></Error>";
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = StatusCode::FORBIDDEN;
+    response
+}
+
+fn not_found() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
@@ -0,0 +1,158 @@
+//! Checks of decoded values against the nullability of their columns in the result metadata.
+
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{error::short_type_name, Error, QueryResultSet, Result, SnowflakeSession};
+
+/// Told about values decoded into a type that decodes NULL, such as an `Option`, from a column
+/// that cannot hold NULL, set with
+/// [`SnowflakeClientConfig::nullability_lint`](crate::SnowflakeClientConfig::nullability_lint),
+/// e.g. to find fields of a model that need not be optional.
+///
+/// Called once per column of a result, with the name of the column and the Rust type, without
+/// module paths. Closures taking both implement it:
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use snowflake_connector_rs::SnowflakeClientConfig;
+/// let config = SnowflakeClientConfig {
+///     nullability_lint: Some(Arc::new(|column: &str, rust_type: &str| {
+///         eprintln!("{column} is NOT NULL but decoded as {rust_type}");
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+pub trait NullabilityLint: Send + Sync {
+    fn optional_not_nullable(&self, column_name: &str, rust_type: &str);
+}
+
+impl<F: Fn(&str, &str) + Send + Sync> NullabilityLint for F {
+    fn optional_not_nullable(&self, column_name: &str, rust_type: &str) {
+        self(column_name, rust_type)
+    }
+}
+
+impl fmt::Debug for dyn NullabilityLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NullabilityLint")
+    }
+}
+
+impl SnowflakeSession {
+    /// Sets up the nullability checks of the session for a result, with `strict` overriding
+    /// whether it is strict.
+    pub(crate) fn check_nullability(&self, strict: Option<bool>, result: &mut QueryResultSet) {
+        let strict = strict.unwrap_or(self.strict_nullability);
+        let check = NullabilityCheck::new(strict, self.nullability_lint.clone());
+        if check.is_some() {
+            result.stream.columns_mut().nullability = check;
+        }
+    }
+}
+
+/// The nullability checks of the values of one result, kept with its columns.
+#[derive(Debug, Clone)]
+pub(crate) struct NullabilityCheck {
+    strict: bool,
+    lint: Option<Arc<dyn NullabilityLint>>,
+    /// The columns the lint has been told about.
+    linted: Arc<Mutex<HashSet<usize>>>,
+}
+
+impl NullabilityCheck {
+    /// The checks to make, or `None` if there are none.
+    pub(crate) fn new(strict: bool, lint: Option<Arc<dyn NullabilityLint>>) -> Option<Self> {
+        (strict || lint.is_some()).then(|| Self {
+            strict,
+            lint,
+            linted: Default::default(),
+        })
+    }
+
+    /// Checks that a type that decodes NULL or not (`decodes_null`) matches column `index`.
+    pub(crate) fn check(
+        &self,
+        index: usize,
+        column_name: &str,
+        nullable: bool,
+        decodes_null: bool,
+        rust_type: &'static str,
+    ) -> Result<()> {
+        if self.strict && nullable && !decodes_null {
+            return Err(Error::decode(
+                "the column is nullable, decode it into an Option or turn off strict_nullability",
+            ));
+        }
+        if let Some(lint) = &self.lint {
+            let mut linted = self.linted.lock().unwrap_or_else(PoisonError::into_inner);
+            if !nullable && decodes_null && linted.insert(index) {
+                lint.optional_not_nullable(column_name, &short_type_name(rust_type));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{row::Columns, SnowflakeColumnType, SnowflakeRow};
+
+    fn row(check: Option<NullabilityCheck>) -> SnowflakeRow {
+        let mut columns = Columns::new(vec![
+            (
+                "ID".into(),
+                SnowflakeColumnType::new("fixed", Some(0)).with_nullable(false),
+            ),
+            ("NAME".into(), SnowflakeColumnType::new("text", None)),
+        ]);
+        columns.nullability = check;
+        SnowflakeRow::new(vec![Some("1".into()), Some("a".into())], Arc::new(columns))
+    }
+
+    #[test]
+    fn test_strict_nullability() -> Result<()> {
+        let lenient = row(None);
+        assert_eq!(lenient.get::<String>("NAME")?, "a");
+
+        let strict = row(NullabilityCheck::new(true, None));
+        assert_eq!(strict.get::<i64>("ID")?, 1);
+        assert_eq!(strict.get::<Option<String>>("NAME")?.as_deref(), Some("a"));
+        let err = strict.get::<String>("NAME").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: column 'NAME' (index 1, type text) as String: the column is \
+             nullable, decode it into an Option or turn off strict_nullability"
+        );
+        assert!(strict.get_tuple::<(i64, String)>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nullability_lint() -> Result<()> {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let lint = {
+            let warnings = Arc::clone(&warnings);
+            move |column: &str, rust_type: &str| {
+                warnings
+                    .lock()
+                    .unwrap()
+                    .push(format!("{column}: {rust_type}"));
+            }
+        };
+        let row = row(NullabilityCheck::new(false, Some(Arc::new(lint))));
+
+        for _ in 0..3 {
+            row.get::<Option<i64>>("ID")?;
+        }
+        row.get::<i64>("ID")?;
+        row.get::<Option<String>>("NAME")?;
+
+        assert_eq!(*warnings.lock().unwrap(), ["ID: Option<i64>"]);
+        Ok(())
+    }
+}
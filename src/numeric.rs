@@ -0,0 +1,168 @@
+//! Exact parsing of numeric values.
+//!
+//! NUMBER values normally arrive as plain decimals, but FLOAT results and some aggregates come
+//! back in scientific notation (`1.234567e+12`). Integers are parsed from the digits and the
+//! exponent instead of through `f64`, so they are either exact or rejected.
+
+use std::{borrow::Cow, str::FromStr};
+
+use crate::{Error, Result};
+
+/// Parses an integer written in plain (`42`), decimal (`42.000`) or scientific (`4.2e+1`)
+/// notation. Values with a non-zero fractional part and values out of the range of `T` are
+/// rejected.
+pub(crate) fn parse_integer<T>(value: &str, type_name: &str) -> Result<T>
+where
+    T: FromStr + TryFrom<i128>,
+{
+    if let Ok(v) = value.parse() {
+        return Ok(v);
+    }
+    let integer = parse_scaled(value, 0, type_name)?;
+    T::try_from(integer).map_err(|_| Error::decode(format!("'{value}' is not {type_name}")))
+}
+
+/// Parses a decimal as an integer of units of `10^-scale`, e.g. `12.5` with scale 2 as 1250,
+/// in any notation [`parse_integer`] reads. Values with more fractional digits than `scale`
+/// are rejected.
+pub(crate) fn parse_scaled(value: &str, scale: u32, type_name: &str) -> Result<i128> {
+    let not_integer = || Error::decode(format!("'{value}' is not {type_name}"));
+    let Decimal {
+        negative,
+        digits,
+        exponent,
+    } = Decimal::parse(value).ok_or_else(not_integer)?;
+    let exponent = exponent
+        .checked_add(i64::from(scale))
+        .ok_or_else(not_integer)?;
+
+    let digits = digits.trim_start_matches('0');
+    let integer = if exponent < 0 {
+        let fraction_len = exponent.unsigned_abs() as usize;
+        let (integer, fraction) = digits.split_at(digits.len().saturating_sub(fraction_len));
+        if fraction.bytes().any(|b| b != b'0') {
+            return Err(Error::decode(format!(
+                "'{value}' is not {type_name}: has fractional part"
+            )));
+        }
+        integer.to_string()
+    } else if digits.is_empty() {
+        String::new()
+    } else {
+        // i128 has at most 39 digits, so anything longer is out of range anyway.
+        let zeros = usize::try_from(exponent)
+            .ok()
+            .filter(|&zeros| digits.len() + zeros <= 39)
+            .ok_or_else(not_integer)?;
+        format!("{digits}{}", "0".repeat(zeros))
+    };
+
+    let magnitude = if integer.is_empty() {
+        0
+    } else {
+        integer.parse::<i128>().map_err(|_| not_integer())?
+    };
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// A decimal number as its significant digits and a power of ten: `-1.5e3` is
+/// `{ negative: true, digits: "15", exponent: 2 }`.
+struct Decimal<'a> {
+    negative: bool,
+    digits: Cow<'a, str>,
+    exponent: i64,
+}
+
+impl<'a> Decimal<'a> {
+    fn parse(value: &'a str) -> Option<Self> {
+        let (negative, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (unsigned, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        if !integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        let digits = if fraction.is_empty() {
+            integer.into()
+        } else {
+            format!("{integer}{fraction}").into()
+        };
+        Some(Self {
+            negative,
+            digits,
+            exponent: exponent.checked_sub(i64::try_from(fraction.len()).ok()?)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_integer() -> Result<()> {
+        assert_eq!(parse_integer::<i64>("42", "i64")?, 42);
+        assert_eq!(
+            parse_integer::<i64>("1.234567e+12", "i64")?,
+            1_234_567_000_000
+        );
+        assert_eq!(
+            parse_integer::<i64>("1.2345678901234567E+18", "i64")?,
+            1_234_567_890_123_456_700
+        );
+        assert_eq!(parse_integer::<i64>("-3.0e0", "i64")?, -3);
+        assert_eq!(parse_integer::<i64>("5E+1", "i64")?, 50);
+        assert_eq!(parse_integer::<i64>("1500e-2", "i64")?, 15);
+        assert_eq!(parse_integer::<i64>("12.000", "i64")?, 12);
+        assert_eq!(parse_integer::<i64>("0e5", "i64")?, 0);
+        assert_eq!(parse_integer::<i64>("-0.0", "i64")?, 0);
+        assert_eq!(
+            parse_integer::<u64>("1.8446744073709551615e19", "u64")?,
+            u64::MAX
+        );
+
+        let err = parse_integer::<i64>("1.5e0", "i64").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: '1.5e0' is not i64: has fractional part"
+        );
+        assert!(parse_integer::<i64>("12.5", "i64").is_err());
+        assert!(parse_integer::<i64>("1e-3", "i64").is_err());
+        assert!(parse_integer::<i32>("1e10", "i32").is_err());
+        assert!(parse_integer::<u64>("-1e0", "u64").is_err());
+        assert!(parse_integer::<i64>("1e400", "i64").is_err());
+        assert!(parse_integer::<i64>("e5", "i64").is_err());
+        assert!(parse_integer::<i64>("1.2.3", "i64").is_err());
+        assert!(parse_integer::<i64>("abc", "i64").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_scaled() -> Result<()> {
+        assert_eq!(parse_scaled("12.5", 2, "NUMBER(38,2)")?, 1250);
+        assert_eq!(parse_scaled("-0.05", 2, "NUMBER(38,2)")?, -5);
+        assert_eq!(parse_scaled("12", 2, "NUMBER(38,2)")?, 1200);
+        assert_eq!(parse_scaled("1.5e-1", 2, "NUMBER(38,2)")?, 15);
+        assert_eq!(parse_scaled("7", 0, "NUMBER(38,0)")?, 7);
+
+        let err = parse_scaled("1.234", 2, "NUMBER(38,2)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: '1.234' is not NUMBER(38,2): has fractional part"
+        );
+        assert!(parse_scaled("1e39", 0, "NUMBER(38,0)").is_err());
+        Ok(())
+    }
+}
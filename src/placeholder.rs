@@ -0,0 +1,393 @@
+//! Values bound to the placeholders of a query request: `?` for one value, and `:name` for a
+//! list of values, e.g. in `IN (:ids)`.
+
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::{
+    bind::{to_bind_value, BindType, BindValue, Binding, Bindings},
+    Error, QueryRequest, Result,
+};
+
+/// The most values of a list bound as a placeholder each; longer lists are bound as one JSON
+/// array. See [`QueryRequest::bind_list`].
+pub const MAX_EXPANDED_LIST_LEN: usize = 64;
+
+impl QueryRequest {
+    /// Binds a value to the first `?` placeholder of the statement without one, serialized
+    /// with serde as [`SnowflakeSession::insert_into`](crate::SnowflakeSession::insert_into)
+    /// serializes fields: numbers, booleans, strings, JSON (see [`Json`](crate::Json)), bytes,
+    /// dates and timestamps (see [`Bind`](crate::Bind)), and `None` as NULL. A date or a
+    /// timestamp not wrapped in `Bind` is bound as text, which Snowflake casts only where the
+    /// type is known, e.g. in a comparison with a column.
+    ///
+    /// A JSON value's placeholder is rewritten to `PARSE_JSON(?)`, which works anywhere but in
+    /// a `VALUES` clause; insert JSON with `INSERT ... SELECT ?, ?` instead.
+    ///
+    /// Fails with [`Error::Bind`] if the value cannot be bound, or if every `?` of the statement
+    /// already has a value.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{QueryRequest, Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let request = QueryRequest::from("SELECT * FROM events WHERE kind = ? AND id > ?")
+    ///     .bind("click")?
+    ///     .bind(&1000)?;
+    /// let rows = session.query(request).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind<T: Serialize + ?Sized>(mut self, value: &T) -> Result<Self> {
+        let value = to_bind_value(value)?;
+        let bindings = self.bindings.get_or_insert_with(Default::default);
+        let position = (1..=bindings.len() + 1)
+            .find(|position| !bindings.contains_key(&position.to_string()))
+            .unwrap_or(bindings.len() + 1);
+        let offset = placeholders(&self.sql_text)
+            .into_iter()
+            .filter_map(|placeholder| match placeholder {
+                Placeholder::Positional(offset) => Some(offset),
+                Placeholder::Named(_) => None,
+            })
+            .nth(position - 1)
+            .ok_or_else(|| {
+                Error::Bind(format!(
+                    "the statement has no placeholder for value {position}"
+                ))
+            })?;
+        if value.bind_type == BindType::Json {
+            self.sql_text
+                .replace_range(offset..offset + 1, BindType::Json.placeholder());
+        }
+        bindings.insert(position.to_string(), Binding::many(vec![value]));
+        Ok(self)
+    }
+
+    /// Binds a list of values to the `:name` placeholders of the statement, e.g. to
+    /// `WHERE id IN (:ids)`, as Snowflake does not bind a list to a single `?`.
+    ///
+    /// A list of up to [`MAX_EXPANDED_LIST_LEN`] (64) values is bound as a `?` placeholder
+    /// each. So that lists of different lengths share fewer statements in Snowflake's caches,
+    /// the list is padded to the next power of two with its last value, which does not change
+    /// what `IN` matches. A longer list, or an empty one, is bound as one JSON array, read with
+    /// `SELECT value FROM TABLE(FLATTEN(INPUT => PARSE_JSON(?)))` cast to the type of its
+    /// values, unless they are bytes, dates or timestamps, which have no JSON form and are
+    /// always bound a placeholder each.
+    ///
+    /// The values are serialized as with [`QueryRequest::bind`], and `?` placeholders may be
+    /// bound before or after the list: they take their values in order, skipping the list's.
+    /// The name is matched exactly, and a `:name` right after an identifier, e.g. the path
+    /// `payload:ids`, is not a placeholder. Fails with [`Error::Bind`] if the statement has no
+    /// `:name` placeholder.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{QueryRequest, Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession, ids: Vec<i64>) -> Result<()> {
+    /// let request = QueryRequest::from("SELECT * FROM events WHERE id IN (:ids) AND kind = ?")
+    ///     .bind_list("ids", &ids)?
+    ///     .bind("click")?;
+    /// let rows = session.query(request).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_list<T: Serialize>(
+        mut self,
+        name: &str,
+        values: impl IntoIterator<Item = T>,
+    ) -> Result<Self> {
+        let values = values
+            .into_iter()
+            .map(|value| to_bind_value(&value))
+            .collect::<Result<Vec<_>>>()?;
+        let (sql, list) = expand_list(values);
+        let mut bound = false;
+        while let Some((range, preceding)) = find_named(&self.sql_text, name) {
+            // The values of the placeholders after the list move up by its length.
+            let bindings = self.bindings.take().unwrap_or_default();
+            let mut bindings = bindings
+                .into_iter()
+                .map(|(position, binding)| match position.parse::<usize>() {
+                    Ok(p) if p > preceding => ((p + list.len()).to_string(), binding),
+                    _ => (position, binding),
+                })
+                .collect::<Bindings>();
+            for (i, value) in list.iter().enumerate() {
+                let position = preceding + i + 1;
+                bindings.insert(position.to_string(), Binding::many(vec![value.clone()]));
+            }
+            self.bindings = Some(bindings);
+            self.sql_text.replace_range(range, &sql);
+            bound = true;
+        }
+        match bound {
+            true => Ok(self),
+            false => Err(Error::Bind(format!(
+                "the statement has no placeholder :{name}"
+            ))),
+        }
+    }
+}
+
+/// The SQL a list placeholder is replaced with, and the values of its `?` placeholders.
+fn expand_list(mut values: Vec<BindValue>) -> (String, Vec<BindValue>) {
+    let mut non_null = values.iter().filter(|value| value.value.is_some());
+    let element_type = match non_null.clone().any(|v| v.bind_type == BindType::Json) {
+        true => Some(BindType::Json),
+        false => non_null.next().map(|value| value.bind_type),
+    };
+    let cast = match element_type {
+        None | Some(BindType::Json) => Some("value"),
+        Some(BindType::Fixed) => Some("value::NUMBER(38, 0)"),
+        Some(BindType::Real) => Some("value::FLOAT"),
+        Some(BindType::Text) => Some("value::STRING"),
+        Some(BindType::Boolean) => Some("value::BOOLEAN"),
+        Some(_) => None,
+    };
+    match cast {
+        Some(cast) if values.is_empty() || values.len() > MAX_EXPANDED_LIST_LEN => {
+            let sql = format!("SELECT {cast} FROM TABLE(FLATTEN(INPUT => PARSE_JSON(?)))");
+            (sql, vec![BindValue::json_array(values)])
+        }
+        _ => {
+            if let Some(last) = values.last().cloned() {
+                values.resize(values.len().next_power_of_two(), last);
+            }
+            let placeholders = values
+                .iter()
+                .map(|value| match value.value {
+                    Some(_) => value.bind_type.placeholder(),
+                    None => "?",
+                })
+                .collect::<Vec<_>>();
+            (placeholders.join(", "), values)
+        }
+    }
+}
+
+/// A placeholder of a statement.
+#[derive(Debug, PartialEq, Eq)]
+enum Placeholder {
+    /// A `?`, at this byte offset.
+    Positional(usize),
+    /// A `:name`, at this range of bytes.
+    Named(Range<usize>),
+}
+
+/// The first `:name` placeholder of `sql`, and how many `?` placeholders come before it.
+fn find_named(sql: &str, name: &str) -> Option<(Range<usize>, usize)> {
+    let mut preceding = 0;
+    for placeholder in placeholders(sql) {
+        match placeholder {
+            Placeholder::Positional(_) => preceding += 1,
+            Placeholder::Named(range) if sql[range.start + 1..range.end] == *name => {
+                return Some((range, preceding));
+            }
+            Placeholder::Named(_) => {}
+        }
+    }
+    None
+}
+
+/// The placeholders of `sql` outside its strings, quoted identifiers and comments, in order.
+fn placeholders(sql: &str) -> Vec<Placeholder> {
+    let bytes = sql.as_bytes();
+    let skip_to = |from: usize, end: &str| {
+        sql[from..]
+            .find(end)
+            .map_or(sql.len(), |i| from + i + end.len())
+    };
+    let is_identifier_byte = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'$');
+    let mut placeholders = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &sql[i..];
+        i = match bytes[i] {
+            b'\'' => skip_string(bytes, i),
+            b'"' => skip_to(i + 1, "\""),
+            b'$' if rest.starts_with("$$") => skip_to(i + 2, "$$"),
+            b'-' if rest.starts_with("--") => skip_to(i, "\n"),
+            b'/' if rest.starts_with("//") => skip_to(i, "\n"),
+            b'/' if rest.starts_with("/*") => skip_to(i + 2, "*/"),
+            b'?' => {
+                placeholders.push(Placeholder::Positional(i));
+                i + 1
+            }
+            b':' if rest.starts_with("::") => i + 2,
+            b':' => {
+                let after_identifier = i
+                    .checked_sub(1)
+                    .is_some_and(|j| is_identifier_byte(bytes[j]) || bytes[j] >= 0x80);
+                let after_value = i
+                    .checked_sub(1)
+                    .is_some_and(|j| matches!(bytes[j], b')' | b']' | b'"'));
+                let starts_name = bytes
+                    .get(i + 1)
+                    .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'_');
+                if after_identifier || after_value || !starts_name {
+                    i + 1
+                } else {
+                    let end = (i + 1..bytes.len())
+                        .find(|&j| !is_identifier_byte(bytes[j]))
+                        .unwrap_or(bytes.len());
+                    placeholders.push(Placeholder::Named(i..end));
+                    end
+                }
+            }
+            _ => i + 1,
+        };
+    }
+    placeholders
+}
+
+/// The offset after the string literal starting at `start`, which ends at a `'` that is not
+/// doubled or escaped with `\`.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            b'\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bind;
+
+    fn bindings(request: &QueryRequest) -> serde_json::Value {
+        serde_json::to_value(&request.bindings).unwrap()
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let sql = "SELECT '?', \"a?\", $$?$$, x::INT, payload:ids, f(x):y -- ?\n\
+                   /* :c */ FROM t WHERE a = ? AND b IN (:ids) AND c = 'it''s \\' ?' AND d = ?";
+        let found = placeholders(sql);
+        let names = found
+            .iter()
+            .map(|placeholder| match placeholder {
+                Placeholder::Positional(offset) => sql[*offset..offset + 1].to_string(),
+                Placeholder::Named(range) => sql[range.clone()].to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["?", ":ids", "?"]);
+        assert_eq!(
+            find_named(sql, "ids").map(|(_, preceding)| preceding),
+            Some(1)
+        );
+        assert_eq!(find_named(sql, "IDS"), None);
+        assert_eq!(placeholders("SELECT 'unterminated ?"), []);
+    }
+
+    #[test]
+    fn test_bind() -> Result<()> {
+        let request = QueryRequest::from("SELECT * FROM t WHERE a = ? AND b = ? AND c = ?")
+            .bind("x")?
+            .bind(&serde_json::json!({"k": 1}))?
+            .bind(&None::<i64>)?;
+        assert_eq!(
+            request.sql_text,
+            "SELECT * FROM t WHERE a = ? AND b = PARSE_JSON(?) AND c = ?"
+        );
+        assert_eq!(
+            bindings(&request),
+            serde_json::json!({
+                "1": {"type": "TEXT", "value": ["x"]},
+                "2": {"type": "TEXT", "value": [r#"{"k":1}"#]},
+                "3": {"type": "TEXT", "value": [null]},
+            })
+        );
+        assert!(matches!(
+            request.bind(&1),
+            Err(Error::Bind(message)) if message == "the statement has no placeholder for value 4"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_list() -> Result<()> {
+        let sql = "SELECT * FROM t WHERE a = ? AND id IN (:ids) AND b = ?";
+
+        // Positional values before and after the list take the placeholders around it.
+        for request in [
+            QueryRequest::from(sql)
+                .bind("a")?
+                .bind_list("ids", [1, 2, 3])?
+                .bind("b")?,
+            QueryRequest::from(sql)
+                .bind_list("ids", [1, 2, 3])?
+                .bind("a")?
+                .bind("b")?,
+            QueryRequest::from(sql)
+                .bind("a")?
+                .bind("b")?
+                .bind_list("ids", [1, 2, 3])?,
+        ] {
+            assert_eq!(
+                request.sql_text,
+                "SELECT * FROM t WHERE a = ? AND id IN (?, ?, ?, ?) AND b = ?"
+            );
+            let values = bindings(&request)
+                .as_object()
+                .unwrap()
+                .iter()
+                .map(|(position, binding)| (position.clone(), binding["value"][0].clone()))
+                .collect::<Vec<_>>();
+            let expected = [("1", "a"), ("2", "1"), ("3", "2"), ("4", "3"), ("5", "3")]
+                .into_iter()
+                .chain([("6", "b")])
+                .map(|(position, value)| (position.to_string(), serde_json::json!(value)))
+                .collect::<Vec<_>>();
+            assert_eq!(values, expected);
+        }
+
+        let long = QueryRequest::from("SELECT * FROM t WHERE id NOT IN (:ids) OR id IN (:ids)")
+            .bind_list("ids", 0..=MAX_EXPANDED_LIST_LEN as i64)?;
+        let flatten = "SELECT value::NUMBER(38, 0) FROM TABLE(FLATTEN(INPUT => PARSE_JSON(?)))";
+        assert_eq!(
+            long.sql_text,
+            format!("SELECT * FROM t WHERE id NOT IN ({flatten}) OR id IN ({flatten})")
+        );
+        let array = bindings(&long)["1"]["value"][0]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            serde_json::from_str::<Vec<i64>>(&array).unwrap(),
+            (0..=MAX_EXPANDED_LIST_LEN as i64).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            bindings(&long)["2"]["value"][0].as_str(),
+            Some(array.as_str())
+        );
+
+        let empty = QueryRequest::from("SELECT 1 WHERE 'a' IN (:names)")
+            .bind_list("names", Vec::<String>::new())?;
+        assert_eq!(
+            empty.sql_text,
+            "SELECT 1 WHERE 'a' IN (SELECT value FROM TABLE(FLATTEN(INPUT => PARSE_JSON(?))))"
+        );
+        assert_eq!(bindings(&empty)["1"]["value"], serde_json::json!(["[]"]));
+
+        let names = QueryRequest::from("SELECT 1 WHERE 'a' IN (:names)")
+            .bind_list("names", vec!["it's"; 65])?;
+        assert!(names.sql_text.contains("value::STRING"));
+
+        let bytes = vec![Bind(vec![0x0f_u8]); 65];
+        let blobs =
+            QueryRequest::from("SELECT 1 WHERE b IN (:blobs)").bind_list("blobs", &bytes)?;
+        assert_eq!(blobs.bindings.as_ref().map(|b| b.len()), Some(128));
+
+        assert!(matches!(
+            QueryRequest::from("SELECT 1").bind_list("ids", [1]),
+            Err(Error::Bind(message)) if message == "the statement has no placeholder :ids"
+        ));
+        Ok(())
+    }
+}
@@ -1,175 +1,435 @@
-use std::{collections::HashMap, sync::Arc};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use http::{
     header::{ACCEPT, AUTHORIZATION},
     HeaderMap,
 };
-use reqwest::Client;
+use serde_json::value::RawValue;
 use tokio::time::sleep;
 
-use crate::{chunk::download_chunk, Error, Result, SnowflakeRow};
+#[cfg(feature = "arrow")]
+use crate::arrow_result::{parse_arrow_chunk, parse_rowset_base64};
+use crate::{
+    bind::Bindings,
+    chunk::{parse_chunk, ChunkDownloadConfig, ChunkFetcher, ChunkSet, ParseChunk},
+    result_set::QueryResultSet,
+    row::Columns,
+    server_info::Parameter,
+    session::SessionToken,
+    stats::{QueryStats, StatsRecorder},
+    stream::RowStream,
+    trace::{span, Instrument, Span},
+    transfer::Transfer,
+    transport::{send, HttpClient, Reply},
+    types::SnowflakeColumnType,
+    values::RowValues,
+    Error, Result, TimeoutPhase,
+};
 
-pub(super) const SESSION_EXPIRED: &str = "390112";
+const RESULT_EXPIRED: &str = "000612";
 
-pub(super) async fn query<Q: Into<QueryRequest>>(
-    http: &Client,
-    account: &str,
-    request: Q,
-    session_token: &str,
-    polling_interval: Option<Duration>,
-    max_polling_attempts: Option<usize>,
-) -> Result<Vec<SnowflakeRow>> {
-    let request_id = uuid::Uuid::new_v4();
-    let url = format!(
-        r"https://{account}.snowflakecomputing.com/queries/v1/query-request?requestId={request_id}"
-    );
+/// The wait between polls of a running query without
+/// [`polling_interval`](crate::SnowflakeClientConfig::polling_interval).
+pub(crate) const DEFAULT_POLLING_INTERVAL: Duration = Duration::from_millis(500);
 
-    let request: QueryRequest = request.into();
-    let response = http
-        .post(url)
-        .header(ACCEPT, "application/snowflake")
-        .header(
-            AUTHORIZATION,
-            format!(r#"Snowflake Token="{}""#, session_token),
-        )
-        .json(&request)
-        .send()
-        .await?;
+/// How the result of a running query is waited for; see the matching fields of
+/// [`SnowflakeClientConfig`](crate::SnowflakeClientConfig).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Polling {
+    pub(crate) interval: Option<Duration>,
+    pub(crate) max_attempts: Option<usize>,
+    /// How long after it was sent the client stops waiting for the query.
+    pub(crate) deadline: Option<Duration>,
+}
 
-    let status = response.status();
-    let body = response.text().await?;
-    if !status.is_success() {
-        return Err(Error::Communication(body));
+impl Polling {
+    /// The wait between polls of the driver API, which only polls a query when it is told
+    /// when to give up.
+    fn driver_interval(&self) -> Option<Duration> {
+        match (self.interval, self.max_attempts, self.deadline) {
+            (Some(interval), Some(_), _) => Some(interval),
+            (interval, _, Some(_)) => Some(interval.unwrap_or(DEFAULT_POLLING_INTERVAL)),
+            _ => None,
+        }
     }
 
-    let mut response: SnowflakeResponse =
-        serde_json::from_str(&body).map_err(|e| Error::Json(e, body))?;
-
-    if let (Some(polling_interval), Some(max_polling_attempts)) = (polling_interval, max_polling_attempts) {
-        response = poll_for_results(
-            http,
-            account,
-            response,
-            session_token,
-            polling_interval,
-            max_polling_attempts,
-        )
-            .await?;
+    /// Whether to stop waiting for a query after `attempts` polls and `elapsed` since it was
+    /// sent.
+    pub(crate) fn expired(&self, attempts: usize, elapsed: Duration) -> bool {
+        self.max_attempts == Some(attempts)
+            || self.deadline.is_some_and(|deadline| elapsed >= deadline)
     }
+}
 
-    if let Some(SESSION_EXPIRED) = response.code.as_deref() {
-        return Err(Error::SessionExpired);
-    }
+/// The ID a statement is sent with, kept when the statement is sent again so that Snowflake
+/// runs it at most once, with the number of times it was sent before.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RequestId {
+    pub(crate) id: uuid::Uuid,
+    pub(crate) retry_count: usize,
+}
 
-    if !response.success {
-        return Err(Error::Communication(response.message.unwrap_or_default()));
+impl RequestId {
+    pub(crate) fn new() -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            retry_count: 0,
+        }
     }
 
-    if let Some(format) = &response.data.query_result_format {
-        if format != "json" {
-            return Err(Error::UnsupportedFormat(format.clone()));
+    /// The ID to send the statement with again.
+    pub(crate) fn retry(self) -> Self {
+        Self {
+            retry_count: self.retry_count + 1,
+            ..self
         }
     }
+}
 
-    let http = http.clone();
-    let qrmk = response.data.qrmk.unwrap_or_default();
-    let chunks = response.data.chunks.unwrap_or_default();
-    let row_types = response.data.row_types.unwrap_or_default();
-    let mut row_set = response.data.row_set.unwrap_or_default();
-
-    let chunk_headers = response.data.chunk_headers.unwrap_or_default();
-    let chunk_headers: HeaderMap = HeaderMap::try_from(&chunk_headers)?;
-
-    let mut handles = Vec::with_capacity(chunks.len());
-    for chunk in chunks {
-        let http = http.clone();
-        let chunk_headers = chunk_headers.clone();
-        let qrmk = qrmk.clone();
-        handles.push(tokio::spawn(async move {
-            download_chunk(http, chunk.url, chunk_headers, qrmk).await
-        }));
+/// Runs a query and returns its result without downloading the chunks yet.
+pub(super) async fn query_lazy(
+    http: &HttpClient,
+    base_url: &str,
+    request: &QueryRequest,
+    request_id: RequestId,
+    token: &Arc<SessionToken>,
+    polling: Polling,
+    chunk_download: ChunkDownloadConfig,
+) -> Result<QueryResultSet> {
+    let start = Instant::now();
+    let stats = StatsRecorder::new(start);
+    let reply = execute(
+        http,
+        base_url,
+        request,
+        request_id,
+        token.session_token().expose_secret(),
+        polling,
+        start,
+    )
+    .await?;
+    let response: SnowflakeResponse = reply.parse()?;
+    let mut data = response.into_data()?;
+    let parameters = data.parameters.take().unwrap_or_default();
+    // The response to a PUT or GET statement holds credentials, which errors leave out.
+    let transfer = match data.stage_info {
+        Some(_) => Some(reply.parse_secret::<TransferResponse>()?.data),
+        None => None,
+    };
+    let query_id = data.query_id.to_string();
+    let span = Span::current();
+    span.record("query_id", query_id.as_str());
+    if let Some(statement_type) = data.statement_type_id {
+        span.record("statement_type", statement_type);
     }
+    let fetcher = chunk_fetcher(http, base_url, query_id, token, chunk_download);
+    let mut result =
+        data.into_result_set(stats.clone(), |chunks, parse| fetcher(chunks, parse, stats))?;
+    result.transfer = transfer;
+    result.parameters = parameters;
+    span.record("row_count", result.total_rows());
+    span.record("chunk_count", result.chunk_count());
+    span.record("total_bytes", result.approx_compressed_size());
+    Ok(result)
+}
 
-    for fut in handles {
-        let result = fut.await?;
-        let rows = result?;
-        row_set.extend(rows);
+/// Runs a query and returns the `data` of its final response as it is, for statements whose
+/// results are not modelled.
+pub(super) async fn query_raw(
+    http: &HttpClient,
+    base_url: &str,
+    request: &QueryRequest,
+    request_id: RequestId,
+    token: &Arc<SessionToken>,
+    polling: Polling,
+) -> Result<serde_json::Value> {
+    let start = Instant::now();
+    let reply = execute(
+        http,
+        base_url,
+        request,
+        request_id,
+        token.session_token().expose_secret(),
+        polling,
+        start,
+    )
+    .await?;
+    let response: RawResponse = reply.parse()?;
+    if !response.success {
+        return Err(reply.parse::<SnowflakeResponse>()?.into_error());
     }
-
-    let column_names = row_types
-        .into_iter()
-        .enumerate()
-        .map(|(i, name)| (name.name.to_ascii_uppercase(), i))
-        .collect::<HashMap<_, _>>();
-    let column_names = Arc::new(column_names);
-    Ok(row_set
-        .into_iter()
-        .map(|row| SnowflakeRow {
-            row,
-            column_names: Arc::clone(&column_names),
-        })
-        .collect())
+    let data = response.data.filter(|data| !data.is_null());
+    data.ok_or_else(|| Error::Communication("the query response has no data".into()))
 }
 
-async fn poll_for_results(
-    http: &Client,
-    account: &str,
-    mut response: SnowflakeResponse,
+/// Sends a query, sent at `start`, and polls for its result while it is still running. Returns
+/// the final reply, without checking whether the query succeeded.
+async fn execute(
+    http: &HttpClient,
+    base_url: &str,
+    request: &QueryRequest,
+    request_id: RequestId,
     session_token: &str,
-    polling_interval: Duration,
-    max_attempts: usize,
-) -> Result<SnowflakeResponse> {
-    let mut attempts = 0;
+    polling: Polling,
+    start: Instant,
+) -> Result<Reply> {
+    let mut url = format!(
+        "{base_url}/queries/v1/query-request?requestId={}",
+        request_id.id
+    );
+    if request_id.retry_count > 0 {
+        url.push_str(&format!("&retryCount={}", request_id.retry_count));
+    }
+    let mut reply = send(
+        http,
+        http.post(url)
+            .header(ACCEPT, "application/snowflake")
+            .header(
+                AUTHORIZATION,
+                format!(r#"Snowflake Token="{}""#, session_token),
+            )
+            .json(request),
+    )
+    .await?;
 
-    while attempts < max_attempts {
-        if let Some(result_url) = response.data.get_result_url.clone() {
+    let Some(polling_interval) = polling.driver_interval() else {
+        return Ok(reply);
+    };
+    let mut attempts = 0;
+    loop {
+        let response: PollResponse = reply.parse()?;
+        let Some(data) = response.data else {
+            return Ok(reply);
+        };
+        let Some(result_url) = data.get_result_url else {
+            return Ok(reply);
+        };
+        if polling.expired(attempts, start.elapsed()) {
+            return Err(Error::Timeout {
+                phase: TimeoutPhase::Polling,
+                elapsed: start.elapsed(),
+                query_id: Some(data.query_id),
+            });
+        }
+        let url = format!("{base_url}{result_url}");
+        attempts += 1;
+        reply = async {
             sleep(polling_interval).await;
-            let url = format!("https://{account}.snowflakecomputing.com{}", result_url);
-
-            let resp = http
-                .get(url)
-                .header(ACCEPT, "application/snowflake")
-                .header(
-                    AUTHORIZATION,
-                    format!(r#"Snowflake Token="{}""#, session_token),
-                )
-                .send()
-                .await?;
-
-            let status = resp.status();
-            let body = resp.text().await?;
-            if !status.is_success() {
-                return Err(Error::Communication(body));
-            }
-
-            response =
-                serde_json::from_str(&body).map_err(|e| Error::Json(e, body))?;
-        } else {
-            break;
+            get(http, url, session_token).await
         }
+        .instrument(span!("snowflake.poll", attempt = attempts))
+        .await?;
+    }
+}
 
-        attempts += 1;
+/// Fetches the result of a finished query again, to read it from the start or from where a
+/// reader stopped, without downloading the chunks yet.
+pub(super) async fn query_result(
+    http: &HttpClient,
+    base_url: &str,
+    query_id: &str,
+    token: &Arc<SessionToken>,
+    chunk_download: ChunkDownloadConfig,
+) -> Result<QueryResultSet> {
+    let stats = StatsRecorder::new(Instant::now());
+    let session_token = token.session_token();
+    let reply = get(
+        http,
+        result_url(base_url, query_id),
+        session_token.expose_secret(),
+    )
+    .await?;
+    let response: SnowflakeResponse = reply.parse()?;
+    if let Some(RESULT_EXPIRED) = response.code.as_deref() {
+        return Err(Error::ResultExpired(query_id.to_string()));
     }
+    let fetcher = chunk_fetcher(http, base_url, query_id.to_string(), token, chunk_download);
+    let data = response.into_data()?;
+    data.into_result_set(stats.clone(), |chunks, parse| fetcher(chunks, parse, stats))
+}
 
-    if attempts == max_attempts {
-        return Err(Error::Communication("max polling attempts reached".into()));
+/// Creates the fetcher of the chunks of a query, which fetches the result again for fresh
+/// chunk URLs once they have expired, with the session token current at the time.
+fn chunk_fetcher(
+    http: &HttpClient,
+    base_url: &str,
+    query_id: String,
+    token: &Arc<SessionToken>,
+    chunk_download: ChunkDownloadConfig,
+) -> impl FnOnce(ChunkSet, ParseChunk, StatsRecorder) -> ChunkFetcher {
+    let base_url = base_url.to_string();
+    let token = Arc::clone(token);
+    let http = http.clone();
+    move |chunks, parse, stats| {
+        ChunkFetcher::parsing(chunks, chunk_download, stats, parse).with_refresh(move || {
+            let (http, base_url) = (http.clone(), base_url.clone());
+            let (query_id, session_token) = (query_id.clone(), token.session_token());
+            Box::pin(async move {
+                let session_token = session_token.expose_secret();
+                fetch_chunk_set(&http, &base_url, &query_id, session_token).await
+            })
+        })
     }
+}
+
+/// The URL of the result of a finished query.
+fn result_url(base_url: &str, query_id: &str) -> String {
+    let request_id = uuid::Uuid::new_v4();
+    format!("{base_url}/queries/{query_id}/result?requestId={request_id}")
+}
 
-    Ok(response)
+fn parse_response(body: &str) -> Result<SnowflakeResponse<'_>> {
+    serde_json::from_str(body).map_err(|e| Error::Json(e, body.to_string()))
+}
+
+/// Sends a GET request to Snowflake.
+async fn get(http: &HttpClient, url: String, session_token: &str) -> Result<Reply> {
+    let request = http
+        .get(url)
+        .header(ACCEPT, "application/snowflake")
+        .header(
+            AUTHORIZATION,
+            format!(r#"Snowflake Token="{}""#, session_token),
+        );
+    send(http, request).await
+}
+
+/// Parses a query response and returns the number of rows sent with it.
+pub(crate) fn parse_response_rows(body: &str) -> Result<usize> {
+    let (_, row_set) = parse_response(body)?.into_data()?.take_rows()?;
+    Ok(row_set.len())
+}
+
+/// Fetches the result of a finished query again for fresh chunk URLs.
+async fn fetch_chunk_set(
+    http: &HttpClient,
+    base_url: &str,
+    query_id: &str,
+    session_token: &str,
+) -> Result<ChunkSet> {
+    let reply = get(http, result_url(base_url, query_id), session_token).await?;
+    let response: SnowflakeResponse = reply.parse()?;
+    if let Some(RESULT_EXPIRED) = response.code.as_deref() {
+        return Err(Error::ResultExpired(query_id.to_string()));
+    }
+    response.into_data()?.chunk_set()
 }
 
 #[derive(Debug, serde::Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryRequest {
     pub sql_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) bindings: Option<Bindings>,
+    #[serde(skip_serializing_if = "StatementParameters::is_empty")]
+    pub(crate) parameters: StatementParameters,
+    /// Overrides [`SnowflakeClientConfig::strict_nullability`](crate::SnowflakeClientConfig::strict_nullability).
+    #[serde(skip)]
+    pub(crate) strict_nullability: Option<bool>,
+}
+
+/// The parameters a statement is run with, overriding those of the session.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub(crate) struct StatementParameters {
+    #[serde(
+        rename = "STATEMENT_TIMEOUT_IN_SECONDS",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_timeout"
+    )]
+    pub(crate) statement_timeout: Option<Duration>,
+    #[serde(
+        rename = "CLIENT_RESULT_CHUNK_SIZE",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) result_chunk_size_mb: Option<u32>,
+    #[serde(rename = "ROWS_PER_RESULTSET", skip_serializing_if = "Option::is_none")]
+    pub(crate) rows_per_result_set: Option<u64>,
+    #[serde(rename = "QUERY_TAG", skip_serializing_if = "Option::is_none")]
+    pub(crate) query_tag: Option<String>,
+    /// The format of the rows of the result, `JSON` unless asked for otherwise.
+    #[serde(
+        rename = "QUERY_RESULT_FORMAT",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) result_format: Option<&'static str>,
+}
+
+impl StatementParameters {
+    fn is_empty(&self) -> bool {
+        self.statement_timeout.is_none()
+            && self.result_chunk_size_mb.is_none()
+            && self.rows_per_result_set.is_none()
+            && self.query_tag.is_none()
+            && self.result_format.is_none()
+    }
+}
+
+impl QueryRequest {
+    /// Limits how long the statement may run, overriding
+    /// [`default_statement_timeout`](crate::SnowflakeClientConfig::default_statement_timeout)
+    /// whether it is shorter or longer. Rounded up to whole seconds.
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.parameters.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the size of the result chunks of the statement, overriding
+    /// [`result_chunk_size_mb`](crate::SnowflakeClientConfig::result_chunk_size_mb).
+    pub fn result_chunk_size_mb(mut self, megabytes: u32) -> Self {
+        self.parameters.result_chunk_size_mb = Some(megabytes);
+        self
+    }
+
+    /// Limits the rows the result of the statement is cut to, overriding
+    /// [`rows_per_result_set`](crate::SnowflakeClientConfig::rows_per_result_set); 0 for no
+    /// limit.
+    pub fn rows_per_result_set(mut self, rows: u64) -> Self {
+        self.parameters.rows_per_result_set = Some(rows);
+        self
+    }
+
+    /// Turns [`strict_nullability`](crate::SnowflakeClientConfig::strict_nullability) on or off
+    /// for the result of the statement, whatever the configuration says.
+    pub fn strict_nullability(mut self, strict: bool) -> Self {
+        self.strict_nullability = Some(strict);
+        self
+    }
+
+    /// Tags the statement in `QUERY_HISTORY`, overriding the session's `QUERY_TAG`. The pairs
+    /// of a [`QueryTagger`](crate::QueryTagger) are merged into it.
+    pub fn query_tag(mut self, tag: impl Into<String>) -> Self {
+        self.parameters.query_tag = Some(tag.into());
+        self
+    }
+
+    /// Asks for the rows of the result in Arrow format rather than JSON.
+    #[cfg(feature = "arrow")]
+    pub(crate) fn arrow_format(mut self) -> Self {
+        self.parameters.result_format = Some("ARROW");
+        self
+    }
+}
+
+/// A statement timeout in the whole seconds Snowflake takes, rounded up, as 0 would mean no
+/// timeout at all.
+pub(crate) fn timeout_seconds(timeout: Duration) -> u64 {
+    (timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0)).max(1)
+}
+
+fn serialize_timeout<S: serde::Serializer>(
+    timeout: &Option<Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match timeout {
+        Some(timeout) => serializer.serialize_u64(timeout_seconds(*timeout)),
+        None => serializer.serialize_none(),
+    }
 }
 
 impl From<&str> for QueryRequest {
     fn from(sql_text: &str) -> Self {
-        Self {
-            sql_text: sql_text.to_string(),
-        }
+        sql_text.to_string().into()
     }
 }
 impl From<&QueryRequest> for QueryRequest {
@@ -180,93 +440,693 @@ impl From<&QueryRequest> for QueryRequest {
 
 impl From<String> for QueryRequest {
     fn from(sql_text: String) -> Self {
-        Self { sql_text }
+        Self {
+            sql_text,
+            bindings: None,
+            parameters: StatementParameters::default(),
+            strict_nullability: None,
+        }
     }
 }
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RawQueryResponse {
-    #[allow(unused)]
-    parameters: Option<Vec<RawQueryResponseParameter>>,
-    #[allow(unused)]
-    query_id: String,
-    #[allow(unused)]
-    get_result_url: Option<String>,
-    #[allow(unused)]
-    returned: Option<i64>,
-    #[allow(unused)]
-    total: Option<i64>,
+struct RawQueryResponse<'a> {
+    /// The session parameters, sent with the responses to statements that change them.
+    parameters: Option<Vec<Parameter>>,
+    #[serde(borrow)]
+    query_id: Cow<'a, str>,
+    #[serde(borrow)]
+    sql_state: Option<Cow<'a, str>>,
+    statement_type_id: Option<i64>,
+    returned: Option<usize>,
+    total: Option<usize>,
+    stats: Option<RawDmlStats>,
 
     #[serde(rename = "rowset")]
-    row_set: Option<Vec<Vec<Option<String>>>>,
+    row_set: Option<RowValues>,
+
+    /// The inline rows in Arrow format, sent instead of `rowset` for Arrow results.
+    #[serde(rename = "rowsetBase64", borrow)]
+    row_set_base64: Option<Cow<'a, str>>,
 
-    #[serde(rename = "rowtype")]
-    row_types: Option<Vec<RawQueryResponseRowType>>,
+    #[serde(rename = "rowtype", borrow)]
+    row_types: Option<Vec<RawQueryResponseRowType<'a>>>,
 
     chunk_headers: Option<HashMap<String, String>>,
 
-    qrmk: Option<String>,
+    #[serde(borrow)]
+    qrmk: Option<Cow<'a, str>>,
+
+    #[serde(borrow)]
+    chunks: Option<Vec<RawQueryResponseChunk<'a>>>,
+    #[serde(borrow)]
+    query_result_format: Option<Cow<'a, str>>,
 
-    chunks: Option<Vec<RawQueryResponseChunk>>,
-    query_result_format: Option<String>,
+    /// Where the files of a PUT or GET statement go, read as part of a [`Transfer`].
+    #[serde(borrow)]
+    stage_info: Option<&'a RawValue>,
 }
+impl RawQueryResponse<'_> {
+    /// Builds the result of a query, with `fetcher` creating the fetcher of its chunks, and
+    /// records its statistics in `stats`.
+    fn into_result_set(
+        mut self,
+        stats: StatsRecorder,
+        fetcher: impl FnOnce(ChunkSet, ParseChunk) -> ChunkFetcher,
+    ) -> Result<QueryResultSet> {
+        let (columns, row_set) = self.take_rows()?;
+        let chunk_info = self.chunks.iter().flatten();
+        let chunk_rows = chunk_info
+            .clone()
+            .map(|chunk| chunk.row_count)
+            .sum::<usize>();
+        let compressed_size = chunk_info.clone().map(|chunk| chunk.compressed_size).sum();
+        let uncompressed_size = chunk_info
+            .clone()
+            .map(|chunk| chunk.uncompressed_size)
+            .sum();
+        let dml = self.stats.as_ref();
+        stats.record_response(QueryStats {
+            query_id: self.query_id.to_string(),
+            statement_type_id: self.statement_type_id,
+            total_rows: self.total,
+            returned_rows: self.returned,
+            rows_inserted: dml.and_then(|dml| dml.num_rows_inserted),
+            rows_updated: dml.and_then(|dml| dml.num_rows_updated),
+            rows_deleted: dml.and_then(|dml| dml.num_rows_deleted),
+            dml_duplicates: dml.and_then(|dml| dml.num_dml_duplicates),
+            chunk_count: chunk_info.count(),
+            compressed_bytes: compressed_size,
+            uncompressed_bytes: uncompressed_size,
+            ..Default::default()
+        });
+        let fetcher = fetcher(self.chunk_set()?, self.chunk_parser(&columns));
+        Ok(QueryResultSet {
+            total_rows: self.total.unwrap_or(row_set.len() + chunk_rows),
+            compressed_size,
+            stream: RowStream::new(
+                columns,
+                row_set,
+                fetcher,
+                self.total,
+                uncompressed_size,
+                stats,
+            ),
+            transfer: None,
+            parameters: vec![],
+        })
+    }
+
+    /// Takes the columns and inline rows of a result, failing for results in formats that are
+    /// not read rather than returning them without their rows. With the `arrow` feature, the
+    /// rows of Arrow results are read from `rowsetBase64`.
+    fn take_rows(&mut self) -> Result<(Arc<Columns>, RowValues)> {
+        let columns = Arc::new(columns(self.row_types.take().unwrap_or_default()));
+        let format = self.query_result_format.as_deref();
+        let base64_rows = self
+            .row_set_base64
+            .as_deref()
+            .filter(|rows| !rows.is_empty());
+        #[cfg(feature = "arrow")]
+        if format == Some("arrow") || base64_rows.is_some() {
+            let rows = base64_rows
+                .map(|rows| parse_rowset_base64(rows, &columns))
+                .transpose()?;
+            return Ok((columns, rows.unwrap_or_default()));
+        }
+        if let Some(format) = format.filter(|format| *format != "json") {
+            return Err(Error::UnsupportedFormat(format.to_string()));
+        }
+        if base64_rows.is_some() {
+            return Err(Error::UnsupportedFormat("arrow (rowsetBase64)".into()));
+        }
+        let row_set = self.row_set.take().unwrap_or_default();
+        Ok((columns, row_set))
+    }
+
+    /// How the chunks of the result are parsed: as Arrow batches of `columns` for an Arrow
+    /// result, otherwise as JSON rows.
+    #[cfg_attr(not(feature = "arrow"), allow(unused_variables))]
+    fn chunk_parser(&self, columns: &Columns) -> ParseChunk {
+        #[cfg(feature = "arrow")]
+        if self.query_result_format.as_deref() == Some("arrow") {
+            let columns = columns.clone();
+            return Arc::new(move |body, encoding| parse_arrow_chunk(body, encoding, &columns));
+        }
+        Arc::new(parse_chunk)
+    }
+
+    fn chunk_set(&mut self) -> Result<ChunkSet> {
+        let (urls, row_counts) = self
+            .chunks
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|chunk| (chunk.url.into_owned(), chunk.row_count))
+            .unzip();
+        let headers = HeaderMap::try_from(&self.chunk_headers.take().unwrap_or_default())?;
+        ChunkSet::new(urls, row_counts, headers, self.qrmk.as_deref())
+    }
+}
+
+/// The columns of a result, from its `rowtype`.
+fn columns(row_types: Vec<RawQueryResponseRowType<'_>>) -> Columns {
+    let columns = row_types
+        .into_iter()
+        .map(|row_type| {
+            let column_type = SnowflakeColumnType::new(&row_type.data_type, row_type.scale)
+                .with_nullable(row_type.nullable);
+            (row_type.name.into_owned(), column_type)
+        })
+        .collect();
+    Columns::new(columns)
+}
+
+/// The columns of a result from the data [`query_raw`] returns.
+#[cfg(feature = "arrow")]
+pub(crate) fn raw_columns(data: &serde_json::Value) -> Result<Columns> {
+    let row_types =
+        <Option<Vec<RawQueryResponseRowType>> as serde::Deserialize>::deserialize(&data["rowtype"])
+            .map_err(|e| Error::Json(e, data["rowtype"].to_string()))?;
+    Ok(columns(row_types.unwrap_or_default()))
+}
+
+/// The rows a DML statement changed.
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RawQueryResponseRowType {
-    #[allow(unused)]
-    database: String,
-    #[allow(unused)]
-    name: String,
+struct RawDmlStats {
+    num_rows_inserted: Option<u64>,
+    num_rows_updated: Option<u64>,
+    num_rows_deleted: Option<u64>,
+    num_dml_duplicates: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawQueryResponseRowType<'a> {
     #[allow(unused)]
+    #[serde(borrow)]
+    database: Cow<'a, str>,
+    #[serde(borrow)]
+    name: Cow<'a, str>,
     nullable: bool,
-    #[allow(unused)]
     scale: Option<i64>,
     #[allow(unused)]
     byte_length: Option<i64>,
     #[allow(unused)]
     length: Option<i64>,
     #[allow(unused)]
-    schema: String,
+    #[serde(borrow)]
+    schema: Cow<'a, str>,
     #[allow(unused)]
-    table: String,
+    #[serde(borrow)]
+    table: Cow<'a, str>,
     #[allow(unused)]
     precision: Option<i64>,
 
-    #[allow(unused)]
-    #[serde(rename = "type")]
-    data_type: String,
+    #[serde(rename = "type", borrow)]
+    data_type: Cow<'a, str>,
 }
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RawQueryResponseParameter {
-    #[allow(unused)]
-    name: String,
+struct RawQueryResponseChunk<'a> {
+    #[serde(borrow)]
+    url: Cow<'a, str>,
 
-    #[allow(unused)]
-    value: serde_json::Value,
+    row_count: usize,
+
+    uncompressed_size: u64,
+
+    compressed_size: u64,
 }
 
-#[derive(Debug, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct RawQueryResponseChunk {
-    url: String,
+/// The instructions of a PUT or GET statement, in the data of its response.
+#[derive(serde::Deserialize)]
+struct TransferResponse {
+    data: Transfer,
+}
 
-    #[allow(unused)]
-    row_count: i64,
+/// What polling needs of a response: whether the query is still running.
+#[derive(serde::Deserialize)]
+struct PollResponse {
+    data: Option<PollData>,
+}
 
-    #[allow(unused)]
-    uncompressed_size: i64,
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollData {
+    #[serde(default)]
+    query_id: String,
+    get_result_url: Option<String>,
+}
 
-    #[allow(unused)]
-    compressed_size: i64,
+/// A response whose data is kept as it is, for [`query_raw`].
+#[derive(serde::Deserialize)]
+struct RawResponse {
+    data: Option<serde_json::Value>,
+    success: bool,
 }
 
 #[derive(serde::Deserialize, Debug)]
-struct SnowflakeResponse {
-    data: RawQueryResponse,
-    message: Option<String>,
+struct SnowflakeResponse<'a> {
+    /// Missing or null in some error responses, e.g. for an expired session.
+    #[serde(borrow)]
+    data: Option<RawQueryResponse<'a>>,
+    #[serde(borrow)]
+    message: Option<Cow<'a, str>>,
     success: bool,
-    code: Option<String>,
+    #[serde(borrow)]
+    code: Option<Cow<'a, str>>,
+}
+
+impl<'a> SnowflakeResponse<'a> {
+    /// The data of a successful response, or the error of an unsuccessful one.
+    fn into_data(self) -> Result<RawQueryResponse<'a>> {
+        if !self.success {
+            return Err(self.into_error());
+        }
+        self.data
+            .ok_or_else(|| Error::Communication("the query response has no data".into()))
+    }
+
+    /// The error of an unsuccessful response: the error for its code if it has a numeric one,
+    /// as [`Error::from_code`] classifies them, otherwise [`Error::Communication`] with its
+    /// message.
+    fn into_error(self) -> Error {
+        let message = self.message.map(Cow::into_owned).unwrap_or_default();
+        let Some(Ok(code)) = self.code.as_deref().map(str::parse) else {
+            return Error::Communication(message);
+        };
+        let (sqlstate, query_id) = match self.data {
+            Some(data) => (
+                data.sql_state,
+                Some(data.query_id).filter(|id| !id.is_empty()),
+            ),
+            None => (None, None),
+        };
+        Error::from_code(
+            code,
+            sqlstate.map(Cow::into_owned).unwrap_or_default(),
+            message,
+            query_id.map(Cow::into_owned),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::tests::fake_fetcher;
+
+    const HEADER_SSE_C_ALGORITHM: &str = "x-amz-server-side-encryption-customer-algorithm";
+    const HEADER_SSE_C_KEY: &str = "x-amz-server-side-encryption-customer-key";
+
+    const ROW_TYPE: &str = r#"{"name":"VALUE","database":"DB","schema":"PUBLIC","table":"","nullable":true,"type":"text","byteLength":16777216,"length":16777216,"scale":null,"precision":null}"#;
+
+    /// Parses a response with the given fields in its data, leaking the body it borrows from.
+    fn response(data: &str) -> RawQueryResponse<'static> {
+        let body = format!(
+            r#"{{"data":{{"parameters":[],"rowtype":[{ROW_TYPE}],"queryId":"01b0a1b2-0000-4c5d-0000-0001234f5678",{data}}},"code":null,"message":null,"success":true}}"#
+        );
+        parse_response(String::leak(body)).unwrap().data.unwrap()
+    }
+
+    /// Builds a result whose chunks are served with one row each.
+    fn result_set(data: RawQueryResponse<'_>) -> Result<QueryResultSet> {
+        data.into_result_set(StatsRecorder::default(), |chunks, _| {
+            fake_fetcher(chunks.urls.len(), 2).0
+        })
+    }
+
+    /// Reads a whole result, serving its chunks with one row each.
+    async fn read_all(data: RawQueryResponse<'_>) -> Result<Vec<String>> {
+        let mut rows = result_set(data)?.stream();
+        let mut values = vec![];
+        while let Some(row) = rows.next_row().await {
+            values.push(row?.get::<String>("VALUE")?);
+        }
+        Ok(values)
+    }
+
+    #[tokio::test]
+    async fn test_inline_rows() -> Result<()> {
+        let data =
+            response(r#""rowset":[["a"],["b"]],"total":2,"returned":2,"queryResultFormat":"json""#);
+        assert_eq!(read_all(data).await?, vec!["a", "b"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rows_only_in_chunks() -> Result<()> {
+        let data = response(
+            r#""rowset":[],"total":2,"returned":0,"queryResultFormat":"json","qrmk":"key","chunkHeaders":{},"chunks":[{"url":"https://chunk/0","rowCount":1,"uncompressedSize":8,"compressedSize":28},{"url":"https://chunk/1","rowCount":1,"uncompressedSize":8,"compressedSize":28}]"#,
+        );
+        assert_eq!(read_all(data).await?, vec!["0", "1"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_result_set_metadata() -> Result<()> {
+        let data = response(
+            r#""rowset":[["a"]],"total":3,"returned":1,"queryResultFormat":"json","qrmk":"key","chunks":[{"url":"https://chunk/0","rowCount":1,"uncompressedSize":8,"compressedSize":28},{"url":"https://chunk/1","rowCount":1,"uncompressedSize":8,"compressedSize":30}]"#,
+        );
+        let result = result_set(data)?;
+        assert_eq!(result.total_rows(), 3);
+        assert_eq!(result.chunk_count(), 2);
+        assert_eq!(result.approx_compressed_size(), 58);
+        assert_eq!(result.column_names(), vec!["VALUE"]);
+        let rows = result.fetch_all().await?;
+        let values = rows
+            .iter()
+            .map(|row| row.get::<String>("VALUE"))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(values, vec!["a", "0", "1"]);
+
+        // Without a total, the rows are counted from the chunk metadata.
+        let data = response(
+            r#""rowset":[["a"]],"chunks":[{"url":"https://chunk/0","rowCount":2000,"uncompressedSize":8,"compressedSize":28}]"#,
+        );
+        assert_eq!(result_set(data)?.total_rows(), 2001);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_stats() -> Result<()> {
+        let data = response(
+            r#""rowset":[["a"]],"total":2,"returned":1,"statementTypeId":4096,"queryResultFormat":"json","qrmk":"key","chunks":[{"url":"https://chunk/0","rowCount":1,"uncompressedSize":8,"compressedSize":28}]"#,
+        );
+        let result = result_set(data)?;
+        let stats = result.stats();
+        assert_eq!(stats.query_id, "01b0a1b2-0000-4c5d-0000-0001234f5678");
+        assert_eq!(stats.statement_type_id, Some(4096));
+        assert_eq!((stats.total_rows, stats.returned_rows), (Some(2), Some(1)));
+        assert_eq!((stats.chunk_count, stats.compressed_bytes), (1, 28));
+        assert_eq!(stats.rows_inserted, None);
+        assert_eq!(stats.time_to_first_chunk, None);
+
+        let mut rows = result.stream();
+        while let Some(row) = rows.next_row().await {
+            row?;
+        }
+        assert!(rows.stats().time_to_first_chunk.is_some());
+
+        let data = response(
+            r#""rowset":[["1"]],"total":1,"returned":1,"statementTypeId":12544,"stats":{"numRowsInserted":1,"numRowsUpdated":2,"numDmlDuplicates":0}"#,
+        );
+        let stats = result_set(data)?.stats();
+        assert_eq!(stats.rows_inserted, Some(1));
+        assert_eq!(stats.rows_updated, Some(2));
+        assert_eq!(stats.rows_deleted, None);
+        assert_eq!(stats.dml_duplicates, Some(0));
+        assert_eq!(stats.chunk_count, 0);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "arrow"))]
+    #[tokio::test]
+    async fn test_arrow_rows_are_rejected() {
+        let rowset_base64 = r#""rowsetBase64":"/////3gAAAAQAAAAAAAKAAwABgAFAAgACgAAAAABBAAMAAAACAAIAAAABAAIAAAABAAAAAEAAAAUAAAA""#;
+        let arrow = response(&format!(
+            r#""rowset":[],{rowset_base64},"total":2,"returned":2,"queryResultFormat":"arrow""#
+        ));
+        assert!(matches!(
+            read_all(arrow).await,
+            Err(Error::UnsupportedFormat(format)) if format == "arrow"
+        ));
+
+        // Without a format, the Arrow rows are still not mistaken for an empty result.
+        let unlabeled = response(&format!(r#""rowset":[],{rowset_base64},"total":2"#));
+        assert!(matches!(
+            read_all(unlabeled).await,
+            Err(Error::UnsupportedFormat(_))
+        ));
+
+        let empty =
+            response(r#""rowset":[],"rowsetBase64":"","total":0,"queryResultFormat":"json""#);
+        assert_eq!(read_all(empty).await.unwrap(), Vec::<String>::new());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[tokio::test]
+    async fn test_arrow_rows() -> Result<()> {
+        use arrow_array::StringArray;
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        use crate::arrow_result::tests::ipc;
+
+        let body = ipc(vec![("VALUE", Arc::new(StringArray::from(vec!["a", "b"])))]);
+        let arrow = response(&format!(
+            r#""rowset":[],"rowsetBase64":"{}","total":2,"returned":2,"queryResultFormat":"arrow""#,
+            STANDARD.encode(&body)
+        ));
+        let parse = arrow.chunk_parser(&Columns::new(vec![(
+            "VALUE".into(),
+            SnowflakeColumnType::new("text", None),
+        )]));
+        assert_eq!(parse(&body, None)?.get(1, 0), Some("b"));
+        assert_eq!(read_all(arrow).await?, vec!["a", "b"]);
+
+        // Without a format, the Arrow rows are still read rather than taken for an empty result.
+        let unlabeled = response(&format!(
+            r#""rowset":[],"rowsetBase64":"{}","total":2"#,
+            STANDARD.encode(&body)
+        ));
+        assert_eq!(read_all(unlabeled).await?, vec!["a", "b"]);
+
+        let empty =
+            response(r#""rowset":[],"rowsetBase64":"","total":0,"queryResultFormat":"arrow""#);
+        assert_eq!(read_all(empty).await?, Vec::<String>::new());
+        Ok(())
+    }
+
+    /// The headers chunks are downloaded with, from the chunk metadata of a response.
+    fn chunk_headers(chunk_metadata: &str) -> Vec<(String, String)> {
+        let mut data = response(&format!(r#""rowset":[],{chunk_metadata}"#));
+        let mut headers = data
+            .chunk_set()
+            .unwrap()
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_str().unwrap().to_string()))
+            .collect::<Vec<_>>();
+        headers.sort();
+        headers
+    }
+
+    fn headers(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_chunk_headers() {
+        // S3, encrypted with the result master key.
+        assert_eq!(
+            chunk_headers(
+                r#""qrmk":"8M3xkEd6ZqYm0m1s7Gq4tS5lJwQhQkz2pV0Zb7F3cXk=","chunks":[{"url":"https://sfc-va-ds1-customer-stage.s3.amazonaws.com/results/01b0/main/data_0_0_0?x-amz-server-side-encryption-customer-algorithm=AES256&response-content-encoding=gzip&X-Amz-Signature=abc","rowCount":2000,"uncompressedSize":87012,"compressedSize":20436}]"#
+            ),
+            headers(&[
+                (HEADER_SSE_C_ALGORITHM, "AES256"),
+                (
+                    HEADER_SSE_C_KEY,
+                    "8M3xkEd6ZqYm0m1s7Gq4tS5lJwQhQkz2pV0Zb7F3cXk="
+                ),
+            ])
+        );
+
+        // S3, with the headers sent along; they take precedence over the key.
+        assert_eq!(
+            chunk_headers(
+                r#""qrmk":"unused","chunkHeaders":{"x-amz-server-side-encryption-customer-key":"c2VjcmV0","x-amz-server-side-encryption-customer-key-md5":"bWQ1"},"chunks":[{"url":"https://sfc-va-ds1-customer-stage.s3.amazonaws.com/results/01b0/main/data_0_0_0?X-Amz-Signature=abc","rowCount":2000,"uncompressedSize":87012,"compressedSize":20436}]"#
+            ),
+            headers(&[
+                (HEADER_SSE_C_KEY, "c2VjcmV0"),
+                ("x-amz-server-side-encryption-customer-key-md5", "bWQ1"),
+            ])
+        );
+
+        // Azure: encryption headers of Azure Blob Storage, no S3 headers.
+        assert_eq!(
+            chunk_headers(
+                r#""qrmk":"bGVnYWN5","chunkHeaders":{"x-ms-encryption-algorithm":"AES256","x-ms-encryption-key":"a2V5","x-ms-encryption-key-sha256":"c2hh"},"chunks":[{"url":"https://sfcwesteurope.blob.core.windows.net/results/01b0/main/data_0_0_0?sv=2020-08-04&spr=https&se=2024-03-01T10%3A00%3A00Z&sr=b&sp=r&sig=abc","rowCount":2000,"uncompressedSize":87012,"compressedSize":20436}]"#
+            ),
+            headers(&[
+                ("x-ms-encryption-algorithm", "AES256"),
+                ("x-ms-encryption-key", "a2V5"),
+                ("x-ms-encryption-key-sha256", "c2hh"),
+            ])
+        );
+
+        // GCS: encryption headers of Cloud Storage.
+        assert_eq!(
+            chunk_headers(
+                r#""chunkHeaders":{"x-goog-encryption-algorithm":"AES256","x-goog-encryption-key":"a2V5","x-goog-encryption-key-sha256":"c2hh"},"chunks":[{"url":"https://storage.googleapis.com/gcpuscentral1-results/01b0/main/data_0_0_0?X-Goog-Algorithm=GOOG4-RSA-SHA256&X-Goog-Signature=abc","rowCount":2000,"uncompressedSize":87012,"compressedSize":20436}]"#
+            ),
+            headers(&[
+                ("x-goog-encryption-algorithm", "AES256"),
+                ("x-goog-encryption-key", "a2V5"),
+                ("x-goog-encryption-key-sha256", "c2hh"),
+            ])
+        );
+
+        // Neither headers nor a key: the presigned URL is enough.
+        assert_eq!(
+            chunk_headers(
+                r#""qrmk":null,"chunkHeaders":null,"chunks":[{"url":"https://storage.googleapis.com/gcpuscentral1-results/01b0/main/data_0_0_0?X-Goog-Signature=abc","rowCount":2000,"uncompressedSize":87012,"compressedSize":20436}]"#
+            ),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_result() {
+        let data =
+            response(r#""rowset":[["a"]],"total":3,"returned":1,"queryResultFormat":"json""#);
+        let err = read_all(data).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::IncompleteResult {
+                chunk_index: None,
+                expected: 3,
+                received: 1
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "incomplete result: expected 3 rows, received 1"
+        );
+    }
+
+    #[test]
+    fn test_sql_error() {
+        let body = r#"{"data":{"internalError":false,"errorCode":"002003","age":0,"sqlState":"42S02","queryId":"01b0a1b2-0000-4c5d-0000-0001234f5678","line":-1,"pos":-1,"type":"COMPILATION"},"code":"002003","message":"SQL compilation error:\nObject 'MISSING' does not exist or not authorized.","success":false,"headers":null}"#;
+        let err = parse_response(body).unwrap().into_error();
+        let Error::Sql {
+            code,
+            sqlstate,
+            message,
+            query_id,
+            position: None,
+        } = &err
+        else {
+            panic!("expected an SQL error, got {err:?}");
+        };
+        assert_eq!(*code, 2003);
+        assert_eq!(sqlstate, "42S02");
+        assert_eq!(
+            message,
+            "SQL compilation error:\nObject 'MISSING' does not exist or not authorized."
+        );
+        assert_eq!(
+            query_id.as_deref(),
+            Some("01b0a1b2-0000-4c5d-0000-0001234f5678")
+        );
+        assert!(err.is_object_not_found());
+        assert!(!err.is_syntax_error());
+        assert!(err
+            .to_string()
+            .starts_with("SQL error 002003 (42S02): SQL compilation error:"));
+
+        // Without an error code, the message is all there is.
+        let body = r#"{"data":{"queryId":""},"code":null,"message":"unexpected","success":false}"#;
+        let err = parse_response(body).unwrap().into_error();
+        assert!(matches!(&err, Error::Communication(m) if m == "unexpected"));
+        assert_eq!(err.sql_code(), None);
+    }
+
+    #[test]
+    fn test_session_errors() {
+        let error = |code: &str| {
+            let body = format!(
+                r#"{{"data":null,"code":"{code}","message":"session error {code}","success":false}}"#
+            );
+            parse_response(&body).unwrap().into_data().unwrap_err()
+        };
+        assert!(matches!(
+            error("390112"),
+            Error::SessionExpired { code: 390112, message } if message == "session error 390112"
+        ));
+        assert!(matches!(
+            error("390114"),
+            Error::AuthTokenExpired { code: 390114, .. }
+        ));
+        assert!(matches!(
+            error("390195"),
+            Error::SessionGone { code: 390195, .. }
+        ));
+        assert!(matches!(error("390100"), Error::Sql { code: 390100, .. }));
+    }
+
+    #[test]
+    fn test_server_incidents() {
+        let error = |code: &str, message: &str| {
+            let body = format!(
+                r#"{{"data":{{"queryId":"01b0"}},"code":"{code}","message":"{message}","success":false}}"#
+            );
+            parse_response(&body).unwrap().into_data().unwrap_err()
+        };
+        let incident = error(
+            "000603",
+            "SQL execution internal error: Processing aborted due to error 300002:2523766418; incident 4829384.",
+        );
+        assert!(matches!(
+            &incident,
+            Error::ServerIncident { incident_id: Some(id), code: 603, query_id: Some(query_id), .. }
+                if id == "4829384" && query_id == "01b0"
+        ));
+        assert!(incident.is_retryable());
+        assert!(incident
+            .to_string()
+            .starts_with("Snowflake internal error 000603 (incident 4829384): SQL execution"));
+
+        assert!(matches!(
+            error("000603", "SQL execution internal error."),
+            Error::ServerIncident {
+                incident_id: None,
+                code: 603,
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("300010", "Internal error; Incident: 5a1b-23."),
+            Error::ServerIncident { incident_id: Some(id), code: 300010, .. } if id == "5a1b-23"
+        ));
+        assert!(matches!(
+            error("002003", "Table 'INCIDENTS' does not exist."),
+            Error::Sql { code: 2003, .. }
+        ));
+    }
+
+    #[test]
+    fn test_statement_timeout() {
+        let request = QueryRequest::from("SELECT 1");
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(body, serde_json::json!({"sqlText": "SELECT 1"}));
+        let request = request.statement_timeout(Duration::from_millis(2500));
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            body["parameters"],
+            serde_json::json!({"STATEMENT_TIMEOUT_IN_SECONDS": 3})
+        );
+        assert_eq!(timeout_seconds(Duration::ZERO), 1);
+
+        // A statement timeout makes the driver API poll, until it has passed.
+        let polling = Polling {
+            interval: None,
+            max_attempts: None,
+            deadline: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(polling.driver_interval(), Some(DEFAULT_POLLING_INTERVAL));
+        assert!(!polling.expired(100, Duration::from_secs(59)));
+        assert!(polling.expired(0, Duration::from_secs(60)));
+        let polling = Polling {
+            deadline: None,
+            ..polling
+        };
+        assert_eq!(polling.driver_interval(), None);
+    }
 }
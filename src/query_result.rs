@@ -0,0 +1,304 @@
+//! The rows of a query together with what is known about the query and its result.
+
+use std::{ops::Deref, sync::Arc};
+
+use crate::{row::Columns, QueryStats, SnowflakeColumnType, SnowflakeRow};
+
+/// The kind of statement a query ran, from the statement type Snowflake reports for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StatementType {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Merge,
+    /// `INSERT ALL` or `INSERT FIRST`.
+    MultiTableInsert,
+    /// `COPY INTO` a table.
+    Copy,
+    /// `COPY INTO` a stage.
+    Unload,
+    /// Another DML statement.
+    Dml,
+    /// A session statement, e.g. `USE` or `ALTER SESSION`.
+    Session,
+    /// `BEGIN`, `COMMIT` or `ROLLBACK`.
+    Transaction,
+    /// A DDL statement, e.g. `CREATE TABLE`.
+    Ddl,
+    /// A statement type this crate does not know, with Snowflake's number for it.
+    Other(i64),
+}
+
+impl StatementType {
+    /// Maps Snowflake's number for a statement type, e.g. [`QueryStats::statement_type_id`].
+    pub fn from_id(id: i64) -> Self {
+        match id {
+            0x1000 => Self::Select,
+            0x3100 => Self::Insert,
+            0x3200 => Self::Update,
+            0x3300 => Self::Delete,
+            0x3400 => Self::Merge,
+            0x3500 => Self::MultiTableInsert,
+            0x3600 => Self::Copy,
+            0x3700 => Self::Unload,
+            0x3000..=0x3fff => Self::Dml,
+            0x4000..=0x4fff => Self::Session,
+            0x5000..=0x5fff => Self::Transaction,
+            0x6000..=0x6fff => Self::Ddl,
+            _ => Self::Other(id),
+        }
+    }
+
+    /// Whether the statement changes the rows of tables.
+    pub fn is_dml(&self) -> bool {
+        matches!(
+            self,
+            Self::Insert
+                | Self::Update
+                | Self::Delete
+                | Self::Merge
+                | Self::MultiTableInsert
+                | Self::Copy
+                | Self::Dml
+        )
+    }
+}
+
+/// A column of a query result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultColumn<'a> {
+    name: &'a str,
+    column_type: &'a SnowflakeColumnType,
+}
+
+impl<'a> ResultColumn<'a> {
+    /// The name of the column, as the result names it.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The type of the column.
+    pub fn column_type(&self) -> &'a SnowflakeColumnType {
+        self.column_type
+    }
+}
+
+/// What is known about a query and its result apart from the rows: the columns, the query ID,
+/// the statement type and the [`QueryStats`].
+///
+/// Returned by [`QueryResult::metadata`], [`QueryResultSet::metadata`](crate::QueryResultSet::metadata)
+/// and [`RowStream::metadata`](crate::RowStream::metadata), so that helpers can take it
+/// whichever way the rows are read.
+#[derive(Debug, Clone)]
+pub struct ResultMetadata {
+    columns: Arc<Columns>,
+    stats: QueryStats,
+}
+
+impl ResultMetadata {
+    pub(crate) fn new(columns: Arc<Columns>, stats: QueryStats) -> Self {
+        Self { columns, stats }
+    }
+
+    /// The ID of the query; empty for a result that did not come from Snowflake, e.g. of a
+    /// `MockExecutor`.
+    pub fn query_id(&self) -> &str {
+        &self.stats.query_id
+    }
+
+    /// The kind of statement the query ran, if the response reported it.
+    pub fn statement_type(&self) -> Option<StatementType> {
+        self.stats.statement_type_id.map(StatementType::from_id)
+    }
+
+    /// The columns of the result, in order.
+    pub fn columns(&self) -> Vec<ResultColumn<'_>> {
+        (0..self.columns.len())
+            .map(|i| ResultColumn {
+                name: self.columns.name(i),
+                column_type: self.columns.column_type(i),
+            })
+            .collect()
+    }
+
+    /// The column names of the result, in order.
+    pub fn column_names(&self) -> Vec<&str> {
+        (0..self.columns.len())
+            .map(|i| self.columns.name(i))
+            .collect()
+    }
+
+    /// The number of rows in the result, if the response reported it.
+    pub fn total_rows(&self) -> Option<usize> {
+        self.stats.total_rows
+    }
+
+    /// The statistics of the query, as of when the metadata was taken.
+    pub fn stats(&self) -> &QueryStats {
+        &self.stats
+    }
+}
+
+/// All the rows of a query with the [`ResultMetadata`] of its result. Returned by
+/// [`SnowflakeSession::query`](crate::SnowflakeSession::query).
+///
+/// It dereferences to `[SnowflakeRow]` and iterates over its rows, so it can be read like the
+/// `Vec<SnowflakeRow>` `query` used to return; [`QueryResult::into_rows`] returns that `Vec`.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeSession};
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let result = session.query("SELECT ID, NAME FROM users").await?;
+/// println!("query {} returned {} rows", result.query_id(), result.len());
+/// for row in &result {
+///     let id: i64 = row.get("ID")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    rows: Vec<SnowflakeRow>,
+    metadata: ResultMetadata,
+}
+
+impl QueryResult {
+    pub(crate) fn new(rows: Vec<SnowflakeRow>, metadata: ResultMetadata) -> Self {
+        Self { rows, metadata }
+    }
+
+    /// The ID of the query; see [`ResultMetadata::query_id`].
+    pub fn query_id(&self) -> &str {
+        self.metadata.query_id()
+    }
+
+    /// The kind of statement the query ran; see [`ResultMetadata::statement_type`].
+    pub fn statement_type(&self) -> Option<StatementType> {
+        self.metadata.statement_type()
+    }
+
+    /// The columns of the result, in order, known even when there are no rows.
+    ///
+    /// This takes the place of [`RowsExt::columns`](crate::RowsExt::columns) on the result;
+    /// call that on [`QueryResult::rows`] instead.
+    pub fn columns(&self) -> Vec<ResultColumn<'_>> {
+        self.metadata.columns()
+    }
+
+    /// The statistics of the query, including the chunk downloads.
+    pub fn stats(&self) -> &QueryStats {
+        self.metadata.stats()
+    }
+
+    /// The columns, query ID, statement type and statistics together.
+    pub fn metadata(&self) -> &ResultMetadata {
+        &self.metadata
+    }
+
+    /// The rows of the result.
+    pub fn rows(&self) -> &[SnowflakeRow] {
+        &self.rows
+    }
+
+    /// Returns the rows, dropping the metadata.
+    pub fn into_rows(self) -> Vec<SnowflakeRow> {
+        self.rows
+    }
+}
+
+/// A result of the given rows, with the columns of the first row, e.g. for a
+/// [`SnowflakeExecutor`](crate::SnowflakeExecutor) answering with rows made by
+/// [`SnowflakeRow::from_values`]. It has no query ID or statement type.
+impl From<Vec<SnowflakeRow>> for QueryResult {
+    fn from(rows: Vec<SnowflakeRow>) -> Self {
+        let columns = match rows.first() {
+            Some(row) => Arc::clone(&row.columns),
+            None => Arc::new(Columns::new(vec![])),
+        };
+        let stats = QueryStats {
+            total_rows: Some(rows.len()),
+            returned_rows: Some(rows.len()),
+            ..Default::default()
+        };
+        Self::new(rows, ResultMetadata::new(columns, stats))
+    }
+}
+
+impl From<QueryResult> for Vec<SnowflakeRow> {
+    fn from(result: QueryResult) -> Self {
+        result.rows
+    }
+}
+
+impl Deref for QueryResult {
+    type Target = [SnowflakeRow];
+
+    fn deref(&self) -> &Self::Target {
+        &self.rows
+    }
+}
+
+impl IntoIterator for QueryResult {
+    type Item = SnowflakeRow;
+    type IntoIter = std::vec::IntoIter<SnowflakeRow>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a QueryResult {
+    type Item = &'a SnowflakeRow;
+    type IntoIter = std::slice::Iter<'a, SnowflakeRow>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_types() {
+        assert_eq!(StatementType::from_id(0x1000), StatementType::Select);
+        assert_eq!(StatementType::from_id(0x3400), StatementType::Merge);
+        assert_eq!(StatementType::from_id(0x3800), StatementType::Dml);
+        assert_eq!(StatementType::from_id(0x4100), StatementType::Session);
+        assert_eq!(StatementType::from_id(0x6000), StatementType::Ddl);
+        assert_eq!(StatementType::from_id(0x7100), StatementType::Other(0x7100));
+        assert!(StatementType::from_id(0x3500).is_dml());
+        assert!(!StatementType::Select.is_dml());
+    }
+
+    #[test]
+    fn test_result_from_rows() {
+        let number = SnowflakeColumnType::new("fixed", Some(0));
+        let rows = (1..=3)
+            .map(|i| SnowflakeRow::from_values([("N", number.clone())], vec![Some(i.to_string())]))
+            .collect::<Vec<_>>();
+        let result = QueryResult::from(rows);
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[2].get::<i64>("N").unwrap(), 3);
+        let columns = result.columns();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(
+            (columns[0].name(), columns[0].column_type()),
+            ("N", &number)
+        );
+        assert_eq!(result.query_id(), "");
+        assert_eq!(result.statement_type(), None);
+        assert_eq!(result.metadata().total_rows(), Some(3));
+        let values = result
+            .into_iter()
+            .map(|row| row.get::<i64>("N").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, [1, 2, 3]);
+
+        assert!(QueryResult::from(vec![]).columns().is_empty());
+    }
+}
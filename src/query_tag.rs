@@ -0,0 +1,175 @@
+//! Pairs added to the `QUERY_TAG` of each statement, e.g. to find the statements of a trace in
+//! `QUERY_HISTORY`.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::{QueryRequest, SnowflakeSession};
+
+/// The longest `QUERY_TAG` Snowflake accepts, in characters.
+const MAX_QUERY_TAG_LEN: usize = 2000;
+
+/// Adds key/value pairs to the `QUERY_TAG` of each statement a session sends, set with
+/// [`SnowflakeClientConfig::query_tagger`](crate::SnowflakeClientConfig::query_tagger).
+///
+/// The tag becomes a JSON object of the pairs, merged into the statement's own tag: the one set
+/// with [`QueryRequest::query_tag`], or else the session's `QUERY_TAG` as Snowflake reported it
+/// in [`SnowflakeSession::server_parameters`]. Keys of that tag are kept over the pairs, and a
+/// tag that is not a JSON object is kept under the key `tag`. Pairs that would make the tag
+/// longer than the 2000 characters Snowflake accepts are left out, the first that does not fit
+/// and those after it.
+///
+/// [`tags`](QueryTagger::tags) is called on the task that sends the statement, so it can read
+/// the current trace, e.g. with OpenTelemetry. Closures returning the pairs implement it:
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use snowflake_connector_rs::SnowflakeClientConfig;
+/// # fn current_trace() -> (String, String) { Default::default() }
+/// let config = SnowflakeClientConfig {
+///     query_tagger: Some(Arc::new(|| {
+///         let (trace_id, span_id) = current_trace();
+///         vec![("trace_id".to_string(), trace_id), ("span_id".to_string(), span_id)]
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+pub trait QueryTagger: Send + Sync {
+    /// The pairs to add to the tag of a statement about to be sent.
+    fn tags(&self) -> Vec<(String, String)>;
+}
+
+impl<F: Fn() -> Vec<(String, String)> + Send + Sync> QueryTagger for F {
+    fn tags(&self) -> Vec<(String, String)> {
+        self()
+    }
+}
+
+impl fmt::Debug for dyn QueryTagger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("QueryTagger")
+    }
+}
+
+impl SnowflakeSession {
+    /// Adds the pairs of the session's [`QueryTagger`], if it has one, to the tag of a
+    /// statement.
+    pub(crate) fn tag(&self, mut request: QueryRequest) -> QueryRequest {
+        let Some(tagger) = &self.query_tagger else {
+            return request;
+        };
+        let tag = request.parameters.query_tag.take().or_else(|| {
+            let session_tag = self.server_parameters.get("QUERY_TAG")?;
+            session_tag
+                .as_str()
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+        });
+        request.parameters.query_tag = merge_query_tag(tag, tagger.tags());
+        request
+    }
+}
+
+/// Merges `pairs` into `tag` as [`QueryTagger`] describes. A tag no pair fits into is kept as
+/// it is.
+fn merge_query_tag(tag: Option<String>, pairs: Vec<(String, String)>) -> Option<String> {
+    let mut object = match tag
+        .as_deref()
+        .map(serde_json::from_str::<Map<String, Value>>)
+    {
+        Some(Ok(object)) => object,
+        Some(Err(_)) => Map::from_iter([("tag".to_string(), Value::from(tag.clone()))]),
+        None => Map::new(),
+    };
+    let mut merged = None;
+    for (key, value) in pairs {
+        if object.contains_key(&key) {
+            continue;
+        }
+        object.insert(key.clone(), Value::String(value));
+        let text = Value::Object(object.clone()).to_string();
+        if text.chars().count() > MAX_QUERY_TAG_LEN {
+            break;
+        }
+        merged = Some(text);
+    }
+    merged.or(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{metrics::NoMetrics, server_info::Parameter, tests::session};
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_query_tag() {
+        let trace = pairs(&[("trace_id", "4bf9"), ("span_id", "00f0")]);
+        assert_eq!(
+            merge_query_tag(None, trace.clone()).unwrap(),
+            r#"{"span_id":"00f0","trace_id":"4bf9"}"#
+        );
+        assert_eq!(
+            merge_query_tag(
+                Some(r#"{"team":"etl","span_id":"mine"}"#.into()),
+                trace.clone()
+            )
+            .unwrap(),
+            r#"{"span_id":"mine","team":"etl","trace_id":"4bf9"}"#
+        );
+        assert_eq!(
+            merge_query_tag(Some("nightly load".into()), trace.clone()).unwrap(),
+            r#"{"span_id":"00f0","tag":"nightly load","trace_id":"4bf9"}"#
+        );
+        assert_eq!(
+            merge_query_tag(Some("nightly load".into()), vec![]).unwrap(),
+            "nightly load"
+        );
+        assert_eq!(merge_query_tag(None, vec![]), None);
+
+        let long = "x".repeat(1900);
+        let tag = merge_query_tag(
+            None,
+            pairs(&[("a", &long), ("b", &long), ("c", "fits but comes after")]),
+        )
+        .unwrap();
+        assert_eq!(tag, format!(r#"{{"a":"{long}"}}"#));
+        let long_tag = "y".repeat(2100);
+        assert_eq!(
+            merge_query_tag(Some(long_tag.clone()), trace).unwrap(),
+            long_tag
+        );
+    }
+
+    #[test]
+    fn test_session_tags_statements() {
+        let mut session = session("acct", Arc::new(NoMetrics));
+        let request = || QueryRequest::from("SELECT 1");
+        assert!(session.tag(request()).parameters.query_tag.is_none());
+
+        session.query_tagger = Some(Arc::new(|| pairs(&[("trace_id", "4bf9")])));
+        let tagged = session.tag(request().query_tag(r#"{"team":"etl"}"#));
+        assert_eq!(
+            serde_json::to_value(&tagged).unwrap()["parameters"]["QUERY_TAG"],
+            r#"{"team":"etl","trace_id":"4bf9"}"#
+        );
+
+        session.server_parameters.update([Parameter {
+            name: "QUERY_TAG".into(),
+            value: "nightly".into(),
+        }]);
+        assert_eq!(
+            session.tag(request()).parameters.query_tag.unwrap(),
+            r#"{"tag":"nightly","trace_id":"4bf9"}"#
+        );
+    }
+}
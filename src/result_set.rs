@@ -0,0 +1,126 @@
+//! Query results whose chunks have not been downloaded yet.
+
+use crate::{
+    server_info::Parameter,
+    spill::{spill, SpillConfig, SpilledResult},
+    transfer::Transfer,
+    QueryResult, QueryStats, Result, ResultMetadata, RowStream,
+};
+
+/// The result of a query, before its chunks are downloaded.
+///
+/// The size of the result is known from the query response, so it can be checked before
+/// committing to the download. Returned by
+/// [`SnowflakeSession::query_lazy`](crate::SnowflakeSession::query_lazy).
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeSession};
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let result = session.query_lazy("SELECT * FROM events").await?;
+/// if result.total_rows() > 1_000_000 {
+///     println!("too many rows to show: {}", result.total_rows());
+///     return Ok(());
+/// }
+/// let rows = result.fetch_all().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryResultSet {
+    pub(crate) total_rows: usize,
+    pub(crate) compressed_size: u64,
+    pub(crate) stream: RowStream,
+    /// What the client transfers for a PUT or GET statement.
+    pub(crate) transfer: Option<Transfer>,
+    /// The session parameters the response reported, for the session to take up.
+    pub(crate) parameters: Vec<Parameter>,
+}
+
+impl QueryResultSet {
+    /// The number of rows in the result, including those sent with the query response.
+    pub fn total_rows(&self) -> usize {
+        self.total_rows
+    }
+
+    /// The number of chunks to download, not counting the rows sent with the query response.
+    pub fn chunk_count(&self) -> usize {
+        self.stream.chunk_count()
+    }
+
+    /// The total compressed size of the chunks in bytes, as reported by the server; roughly the
+    /// amount of data [`QueryResultSet::fetch_all`] downloads.
+    pub fn approx_compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Returns the column names of the result.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.stream.column_names()
+    }
+
+    /// Returns the columns, query ID, statement type and statistics of the result, as
+    /// [`QueryResult::metadata`] does once the rows are fetched.
+    pub fn metadata(&self) -> ResultMetadata {
+        self.stream.metadata()
+    }
+
+    /// Returns the statistics of the query. No chunks have been downloaded yet, so the download
+    /// measurements are empty; see [`RowStream::stats`] or
+    /// [`SnowflakeSession::last_query_stats`](crate::SnowflakeSession::last_query_stats) for those.
+    pub fn stats(&self) -> QueryStats {
+        self.stream.stats()
+    }
+
+    /// Sets how many chunks are downloaded ahead of the one being read for this query, in place
+    /// of [`SnowflakeClientConfig::chunk_prefetch`](crate::SnowflakeClientConfig::chunk_prefetch).
+    /// With 0, a chunk is only downloaded once it is needed, e.g. for a consumer that is slower
+    /// than the chunk URLs take to expire.
+    pub fn chunk_prefetch(mut self, prefetch: usize) -> Self {
+        self.stream.set_chunk_prefetch(prefetch);
+        self
+    }
+
+    /// Returns the chunks in the order their downloads finish rather than in the order of the
+    /// result, so that no downloaded chunk waits for a slower one ahead of it. Rows within a
+    /// chunk stay in order, but the order of the query, e.g. from `ORDER BY`, is lost.
+    pub fn unordered_chunks(mut self) -> Self {
+        self.stream.set_chunks_ordered(false);
+        self
+    }
+
+    /// Sets the most rows of this result, in place of
+    /// [`SnowflakeClientConfig::max_result_rows`](crate::SnowflakeClientConfig::max_result_rows);
+    /// `None` lifts the limit.
+    pub fn max_result_rows(mut self, max_rows: Option<usize>) -> Self {
+        self.stream.result_limits_mut().max_rows = max_rows;
+        self
+    }
+
+    /// Sets the most uncompressed chunk bytes of this result, in place of
+    /// [`SnowflakeClientConfig::max_result_bytes`](crate::SnowflakeClientConfig::max_result_bytes);
+    /// `None` lifts the limit.
+    pub fn max_result_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.stream.result_limits_mut().max_bytes = max_bytes;
+        self
+    }
+
+    /// Downloads every chunk and returns all the rows, with the metadata of the result.
+    pub async fn fetch_all(self) -> Result<QueryResult> {
+        let mut stream = self.stream;
+        let mut rows = vec![];
+        while let Some(batch) = stream.next_batch().await {
+            rows.extend(batch?);
+        }
+        Ok(QueryResult::new(rows, stream.metadata()))
+    }
+
+    /// Downloads every chunk to disk, for results that do not fit in memory but are read more
+    /// than once. See [`SpilledResult`].
+    pub async fn spill(self, config: &SpillConfig) -> Result<SpilledResult> {
+        spill(self.stream, config).await
+    }
+
+    /// Returns the rows as a [`RowStream`], which downloads the chunks as they are read.
+    pub fn stream(self) -> RowStream {
+        self.stream
+    }
+}
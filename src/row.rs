@@ -1,152 +1,1724 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    hash::BuildHasher,
+    marker::PhantomData,
+    str::FromStr,
+    sync::Arc,
+};
 
-use chrono::{Days, NaiveDate, NaiveDateTime};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
 
-use crate::{Error, Result};
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, MapAccess, Visitor};
+use serde_json::value::RawValue;
 
-#[derive(Debug)]
+use crate::{
+    de::RowDeserializer,
+    error::short_type_name,
+    nullability::NullabilityCheck,
+    numeric::parse_integer,
+    temporal::{format_iso8601, parse_date, parse_time, parse_timestamp, parse_timestamp_tz},
+    types::SnowflakeColumnType,
+    values::RowValues,
+    Error, Result,
+};
+
+/// A row of a query result.
+///
+/// The rows of one result chunk share the storage of their values, so a row kept after the
+/// others are dropped holds on to the values of its whole chunk. Use
+/// [`SnowflakeRow::into_inner`] to keep just the values of one row.
+#[derive(Debug, Clone)]
 pub struct SnowflakeRow {
-    pub(crate) row: Vec<Option<String>>,
-    pub(crate) column_names: Arc<HashMap<String, usize>>,
+    pub(crate) values: Arc<RowValues>,
+    pub(crate) index: usize,
+    pub(crate) columns: Arc<Columns>,
 }
 
 impl SnowflakeRow {
+    /// Creates a row holding its own values.
+    #[cfg(test)]
+    pub(crate) fn new(values: Vec<Option<String>>, columns: Arc<Columns>) -> Self {
+        Self {
+            values: Arc::new(RowValues::from_rows(vec![values])),
+            index: 0,
+            columns,
+        }
+    }
+
+    /// Creates a row from columns and their values, e.g. as test data for a
+    /// [`SnowflakeExecutor`](crate::SnowflakeExecutor). The values are in the form Snowflake
+    /// sends them in JSON results, e.g. `12.50` for a `NUMBER(10, 2)`, `19000` (days since the
+    /// epoch) for a `DATE` and `1641042000.000000000` (seconds since the epoch) for a
+    /// `TIMESTAMP_NTZ`; `None` is NULL.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{SnowflakeColumnType, SnowflakeRow};
+    /// let row = SnowflakeRow::from_values(
+    ///     [
+    ///         ("ID", SnowflakeColumnType::new("fixed", Some(0))),
+    ///         ("NAME", SnowflakeColumnType::new("text", None)),
+    ///     ],
+    ///     vec![Some("1".to_string()), None],
+    /// );
+    /// assert_eq!(row.get::<i64>("id")?, 1);
+    /// assert_eq!(row.get::<Option<String>>("name")?, None);
+    /// # Ok::<(), snowflake_connector_rs::Error>(())
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If there are not as many values as columns.
+    pub fn from_values<N: Into<String>>(
+        columns: impl IntoIterator<Item = (N, SnowflakeColumnType)>,
+        values: Vec<Option<String>>,
+    ) -> Self {
+        let columns = columns
+            .into_iter()
+            .map(|(name, column_type)| (name.into(), column_type))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns.len(),
+            values.len(),
+            "a row needs one value for each column"
+        );
+        Self {
+            values: Arc::new(RowValues::from_rows(vec![values])),
+            index: 0,
+            columns: Arc::new(Columns::new(columns)),
+        }
+    }
+
+    /// Creates a row reading row `index` of `values`.
+    pub(crate) fn at(values: Arc<RowValues>, index: usize, columns: Arc<Columns>) -> Self {
+        Self {
+            values,
+            index,
+            columns,
+        }
+    }
+
+    /// The value at `index`, in the wire representation.
+    pub(crate) fn value(&self, index: usize) -> Option<&str> {
+        self.values.get(self.index, index)
+    }
+
+    /// The number of values in the row.
+    pub(crate) fn len(&self) -> usize {
+        self.values.row_len(self.index)
+    }
+
+    /// The values of the row in column order, in the wire representation.
+    pub(crate) fn values(&self) -> impl Iterator<Item = Option<&str>> + '_ {
+        (0..self.len()).map(|index| self.value(index))
+    }
+
+    /// Decodes the value of a column.
+    ///
+    /// `column_name` is resolved like a SQL identifier: a column with exactly that name is
+    /// preferred (e.g. one created as `"myColumn"`), then the uppercased name, then any column
+    /// matching ignoring case. Use [`SnowflakeRow::get_exact`] to skip the fallbacks. If several
+    /// columns share the name, the first one is read; see [`SnowflakeRow::get_all`].
     pub fn get<T: SnowflakeDecode>(&self, column_name: &str) -> Result<T> {
+        let index = self.index_of(column_name)?;
+        self.decode_at(index)
+    }
+
+    /// Decodes the value of a column that may be absent from the result.
+    ///
+    /// | column  | value                  | result           |
+    /// |---------|------------------------|------------------|
+    /// | missing | -                      | `Ok(None)`       |
+    /// | present | decodable as `T`       | `Ok(Some(v))`    |
+    /// | present | not decodable as `T`   | `Err`            |
+    /// | present | NULL, `T` is `Option`  | `Ok(Some(None))` |
+    /// | present | NULL, `T` is not       | `Err`            |
+    ///
+    /// Columns are resolved like [`SnowflakeRow::get`]. Errors name the column, so a NULL in a
+    /// non-`Option` target is reported the same way as any other undecodable value.
+    pub fn try_get<T: SnowflakeDecode>(&self, column_name: &str) -> Result<Option<T>> {
+        match self.columns.index_of(column_name) {
+            Some(index) => self.decode_at(index).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the column whose name is exactly `column_name`, without the case-insensitive
+    /// fallback of [`SnowflakeRow::get`].
+    pub fn get_exact<T: SnowflakeDecode>(&self, column_name: &str) -> Result<T> {
         let index = self
-            .column_names
-            .get(&column_name.to_ascii_uppercase())
-            .ok_or_else(|| Error::Decode(format!("column not found: {}", column_name)))?;
-        self.row[*index].try_get()
+            .columns
+            .index_of_exact(column_name)
+            .ok_or_else(|| Error::decode(format!("column not found: {}", column_name)))?;
+        self.decode_at(index)
+    }
+
+    /// Decodes every column that `column_name` resolves to, in column order.
+    ///
+    /// Joins often return several columns with the same name (`SELECT a.id, b.id ...`);
+    /// [`SnowflakeRow::get`] reads the first of them, and this method reads all of them.
+    pub fn get_all<T: SnowflakeDecode>(&self, column_name: &str) -> Result<Vec<T>> {
+        let indices = self.columns.indices_of(column_name);
+        if indices.is_empty() {
+            return Err(Error::decode(format!("column not found: {}", column_name)));
+        }
+        indices.iter().map(|&index| self.decode_at(index)).collect()
+    }
+
+    /// Decodes the column like [`SnowflakeRow::get`], but fails if `column_name` resolves to
+    /// more than one column instead of picking the first.
+    pub fn get_unique<T: SnowflakeDecode>(&self, column_name: &str) -> Result<T> {
+        match self.columns.indices_of(column_name) {
+            [] => Err(Error::decode(format!("column not found: {}", column_name))),
+            [index] => self.decode_at(*index),
+            indices => Err(Error::decode(format!(
+                "column name is ambiguous: {} matches columns at positions {:?}",
+                column_name, indices
+            ))),
+        }
+    }
+
+    /// Resolves `column_name` like [`SnowflakeRow::get`] and returns the position of the column,
+    /// for use with [`SnowflakeRow::get_index`]. Rows of the same result share their columns,
+    /// so the position can be resolved once and reused for every row.
+    pub fn column_index(&self, column_name: &str) -> Result<usize> {
+        self.index_of(column_name)
+    }
+
+    /// Decodes the value of the column at `index`, a position in the SELECT list.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeRow};
+    /// # fn run(rows: &[SnowflakeRow]) -> Result<()> {
+    /// if let Some(first) = rows.first() {
+    ///     let id = first.column_index("ID")?;
+    ///     for row in rows {
+    ///         let id: i64 = row.get_index(id)?;
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_index<T: SnowflakeDecode>(&self, index: usize) -> Result<T> {
+        if index >= self.len() {
+            return Err(Error::decode(format!(
+                "column index {index} is out of range for {} columns",
+                self.len()
+            )));
+        }
+        self.decode_at(index)
+    }
+
+    /// Decodes the columns of the row by position into a tuple, which must have one element per
+    /// column. Tuples of up to twelve elements are supported.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeRow};
+    /// # fn run(row: &SnowflakeRow) -> Result<()> {
+    /// let (id, name, created): (i64, String, chrono::NaiveDateTime) = row.get_tuple()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_tuple<T: FromTuple>(&self) -> Result<T> {
+        if self.len() != T::LEN {
+            return Err(Error::decode(format!(
+                "a row of {} columns does not decode into a tuple of {}",
+                self.len(),
+                T::LEN
+            )));
+        }
+        T::from_tuple(self)
+    }
+
+    /// Returns the positions of all columns that `column_name` resolves to, in column order.
+    pub fn columns_named(&self, column_name: &str) -> Vec<usize> {
+        self.columns.indices_of(column_name).to_vec()
+    }
+
+    /// Returns whether the value of the column is NULL. Fails only if the column does not exist.
+    pub fn is_null(&self, column_name: &str) -> Result<bool> {
+        let index = self.index_of(column_name)?;
+        Ok(self.value(index).is_none())
+    }
+
+    /// Decodes the column like [`SnowflakeRow::get`], returning `default` if the value is NULL.
+    pub fn get_or<T: SnowflakeDecode>(&self, column_name: &str, default: T) -> Result<T> {
+        let index = self.index_of(column_name)?;
+        match self.value(index) {
+            None => Ok(default),
+            _ => self.decode_at(index),
+        }
     }
+
+    /// Decodes the column like [`SnowflakeRow::get`], returning `T::default()` if the value is
+    /// NULL.
+    pub fn get_or_default<T: SnowflakeDecode + Default>(&self, column_name: &str) -> Result<T> {
+        self.get_or(column_name, T::default())
+    }
+
+    /// Decodes the value of a column without copying it out of the row, e.g. as `&str` or
+    /// `Cow<str>`. Columns are resolved like [`SnowflakeRow::get`].
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeRow};
+    /// # fn run(row: &SnowflakeRow) -> Result<()> {
+    /// let name: &str = row.get_ref("NAME")?;
+    /// let note: Option<&str> = row.get_ref("NOTE")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_ref<'a, T: SnowflakeDecodeRef<'a>>(&'a self, column_name: &str) -> Result<T> {
+        let index = self.index_of(column_name)?;
+        let value = self.value(index);
+        let column_type = self.columns.column_type(index);
+        T::try_decode_ref(value, column_type).map_err(|e| {
+            e.with_column(
+                self.columns.name(index),
+                index,
+                column_type.snowflake_type(),
+                std::any::type_name::<T>(),
+                value,
+            )
+        })
+    }
+
+    /// Returns the value of the column exactly as Snowflake sent it, or `None` for NULL.
+    ///
+    /// This is the wire representation, not a stable serialization: its format depends on the
+    /// column type and on session parameters (e.g. timestamps arrive as epoch seconds, VARIANT
+    /// values as pretty-printed JSON). Use [`SnowflakeRow::get`] for decoded values.
+    pub fn get_raw(&self, column_name: &str) -> Result<Option<&str>> {
+        let index = self.index_of(column_name)?;
+        Ok(self.value(index))
+    }
+
+    /// Consumes the row and returns its values in column order, in the same wire representation
+    /// as [`SnowflakeRow::get_raw`].
+    pub fn into_inner(self) -> Vec<Option<String>> {
+        self.values()
+            .map(|value| value.map(str::to_string))
+            .collect()
+    }
+
+    /// Decodes the value at `index`, attaching the column to a decode error.
+    pub(crate) fn decode_at<T: SnowflakeDecode>(&self, index: usize) -> Result<T> {
+        let value = self.value(index);
+        let column_type = self.columns.column_type(index);
+        let checked = match &self.columns.nullability {
+            Some(check) => check.check(
+                index,
+                self.columns.name(index),
+                column_type.nullable(),
+                T::DECODES_NULL,
+                std::any::type_name::<T>(),
+            ),
+            None => Ok(()),
+        };
+        let decoded = checked.and_then(|()| T::try_decode_str(value, column_type));
+        decoded.map_err(|e| {
+            e.with_column(
+                self.columns.name(index),
+                index,
+                column_type.snowflake_type(),
+                std::any::type_name::<T>(),
+                value,
+            )
+        })
+    }
+
+    pub(crate) fn index_of(&self, column_name: &str) -> Result<usize> {
+        self.columns
+            .index_of(column_name)
+            .ok_or_else(|| Error::decode(format!("column not found: {}", column_name)))
+    }
+
+    /// Deserializes the row into `T` with serde.
+    ///
+    /// The row is presented as a map from column names to values, so structs, maps and tuples
+    /// can all be targets. Struct fields match columns case-insensitively; use
+    /// `#[serde(rename = "...")]` for fields whose name differs from the column. `Option` fields
+    /// read NULL as `None`, and VARIANT, OBJECT and ARRAY columns can be deserialized into
+    /// `serde_json::Value` or any nested `Deserialize` type.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeRow};
+    /// #[derive(serde::Deserialize)]
+    /// struct Example {
+    ///     id: i64,
+    ///     #[serde(rename = "VALUE")]
+    ///     text: Option<String>,
+    /// }
+    ///
+    /// # fn run(row: &SnowflakeRow) -> Result<()> {
+    /// let example: Example = row.deserialize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        T::deserialize(RowDeserializer::new(self))
+    }
+
+    /// Returns the column names as the server sent them, in the order of the SELECT list.
     pub fn column_names(&self) -> Vec<&str> {
-        self.column_names.iter().map(|(k, _)| k.as_str()).collect()
+        self.columns
+            .names
+            .iter()
+            .map(|name| name.as_str())
+            .collect()
+    }
+
+    /// Iterates over `(column name, value)` pairs in the order of the SELECT list, with values in
+    /// the same wire representation as [`SnowflakeRow::get_raw`].
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::SnowflakeRow;
+    /// fn diff<'a>(left: &'a SnowflakeRow, right: &'a SnowflakeRow) -> Vec<&'a str> {
+    ///     left.iter()
+    ///         .zip(right.iter())
+    ///         .filter(|((_, l), (_, r))| l != r)
+    ///         .map(|((name, _), _)| name)
+    ///         .collect()
+    /// }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> + '_ {
+        self.columns
+            .names
+            .iter()
+            .map(String::as_str)
+            .zip(self.values())
+    }
+}
+
+impl IntoIterator for SnowflakeRow {
+    type Item = (String, Option<String>);
+    type IntoIter = std::iter::Zip<std::vec::IntoIter<String>, std::vec::IntoIter<Option<String>>>;
+
+    /// Consumes the row and yields owned `(column name, value)` pairs; see
+    /// [`SnowflakeRow::iter`].
+    fn into_iter(self) -> Self::IntoIter {
+        let names = self.columns.names.clone();
+        names.into_iter().zip(self.into_inner())
     }
 }
 
+/// Column metadata of a result set, shared by all of its rows.
+///
+/// `names` and `types` keep the server's column order and the names exactly as the server sent
+/// them. `exact` maps each name to the positions of the columns with that name, and `folded`
+/// does the same for uppercased names. Positions are in column order, so duplicate names (e.g.
+/// `SELECT a.id, b.id`) keep every column reachable.
+#[derive(Debug, Clone)]
+pub(crate) struct Columns {
+    names: Vec<String>,
+    types: Vec<SnowflakeColumnType>,
+    exact: HashMap<String, Vec<usize>>,
+    folded: HashMap<String, Vec<usize>>,
+    /// How decoded values are checked against the nullability of their columns.
+    pub(crate) nullability: Option<NullabilityCheck>,
+}
+
+impl Columns {
+    pub(crate) fn new(columns: Vec<(String, SnowflakeColumnType)>) -> Self {
+        let (names, types): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+        let mut exact = HashMap::<_, Vec<_>>::with_capacity(names.len());
+        let mut folded = HashMap::<_, Vec<_>>::with_capacity(names.len());
+        for (i, name) in names.iter().enumerate() {
+            exact.entry(name.clone()).or_default().push(i);
+            folded.entry(name.to_ascii_uppercase()).or_default().push(i);
+        }
+        Self {
+            names,
+            types,
+            exact,
+            folded,
+            nullability: None,
+        }
+    }
+
+    /// Resolves a column name to the first column it refers to. See [`Columns::indices_of`].
+    pub(crate) fn index_of(&self, column_name: &str) -> Option<usize> {
+        self.indices_of(column_name).first().copied()
+    }
+
+    /// Resolves a column name the way Snowflake resolves identifiers: columns with exactly that
+    /// name (a quoted identifier) win, then columns named the uppercased name (an unquoted
+    /// identifier), then columns whose name matches ignoring case.
+    pub(crate) fn indices_of(&self, column_name: &str) -> &[usize] {
+        if let Some(indices) = self.exact.get(column_name) {
+            return indices;
+        }
+        let upper = column_name.to_ascii_uppercase();
+        self.exact
+            .get(&upper)
+            .or_else(|| self.folded.get(&upper))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn index_of_exact(&self, column_name: &str) -> Option<usize> {
+        self.exact
+            .get(column_name)
+            .and_then(|indices| indices.first())
+            .copied()
+    }
+
+    pub(crate) fn name(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    pub(crate) fn column_type(&self, index: usize) -> &SnowflakeColumnType {
+        &self.types[index]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+/// Builds a value from a whole row.
+///
+/// With the `derive` feature, `#[derive(FromRow)]` implements this for structs by decoding each
+/// field with [`SnowflakeDecode`] from the column of the same name.
+pub trait FromRow: Sized {
+    fn from_row(row: &SnowflakeRow) -> Result<Self>;
+}
+
+/// A tuple of values decoded from the columns of a row by position; see
+/// [`SnowflakeRow::get_tuple`].
+pub trait FromTuple: Sized {
+    /// The number of elements of the tuple.
+    const LEN: usize;
+
+    #[doc(hidden)]
+    fn from_tuple(row: &SnowflakeRow) -> Result<Self>;
+}
+
+macro_rules! impl_from_tuple {
+    ($len:literal; $($t:ident $i:tt),+) => {
+        impl<$($t: SnowflakeDecode),+> FromTuple for ($($t,)+) {
+            const LEN: usize = $len;
+
+            fn from_tuple(row: &SnowflakeRow) -> Result<Self> {
+                Ok(($(row.decode_at::<$t>($i)?,)+))
+            }
+        }
+    };
+}
+
+impl_from_tuple!(1; A 0);
+impl_from_tuple!(2; A 0, B 1);
+impl_from_tuple!(3; A 0, B 1, C 2);
+impl_from_tuple!(4; A 0, B 1, C 2, D 3);
+impl_from_tuple!(5; A 0, B 1, C 2, D 3, E 4);
+impl_from_tuple!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+impl_from_tuple!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_from_tuple!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_from_tuple!(9; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_from_tuple!(10; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_from_tuple!(11; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_from_tuple!(12; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// Decodes a single value of a row.
+///
+/// `value` is the cell in Snowflake's wire representation (see [`SnowflakeRow::get_raw`]), or
+/// `None` for NULL. Types that parse from a string can use [`Parsed`] instead of implementing
+/// this trait. To implement it by hand, delegate to an existing impl and report failures with
+/// [`Error::decode`]; [`SnowflakeRow::get`] adds the column name and type to the error:
+///
+/// ```rust
+/// use snowflake_connector_rs::{Error, Result, SnowflakeDecode};
+///
+/// struct CustomerId(String);
+///
+/// impl SnowflakeDecode for CustomerId {
+///     fn try_decode(value: &Option<String>) -> Result<Self> {
+///         let id = String::try_decode(value)?;
+///         if !id.starts_with("C-") {
+///             return Err(Error::decode(format!("'{id}' is not a customer ID")));
+///         }
+///         Ok(CustomerId(id))
+///     }
+/// }
+/// ```
+///
+/// NULL reaches `try_decode` as `None`; `Option<CustomerId>` handles it without any extra code.
+/// Override [`SnowflakeDecode::try_decode_typed`] as well if decoding depends on the column
+/// type.
 pub trait SnowflakeDecode: Sized {
+    /// Whether the type decodes NULL, as `Option` does. With
+    /// [`strict_nullability`](crate::SnowflakeClientConfig::strict_nullability), types that do
+    /// not cannot be decoded from columns that may hold NULL.
+    const DECODES_NULL: bool = false;
+
     fn try_decode(value: &Option<String>) -> Result<Self>;
+
+    /// Decodes a value knowing the type of the column it came from.
+    ///
+    /// The default implementation ignores the column type and calls
+    /// [`SnowflakeDecode::try_decode`]; override it for types whose decoding depends on the
+    /// column metadata.
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        let _ = column_type;
+        Self::try_decode(value)
+    }
+
+    /// Decodes a value borrowed from its row, knowing the type of the column it came from.
+    ///
+    /// [`SnowflakeRow::get`] and the other row accessors call this method. The default
+    /// implementation copies the value and calls [`SnowflakeDecode::try_decode_typed`]; the
+    /// implementations in this crate override it to decode without the copy.
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_typed(&value.map(str::to_string), column_type)
+    }
 }
 
-impl SnowflakeDecode for u64 {
-    fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
+/// Implements [`SnowflakeDecode`] for types that decode from the value alone, with `$parse`
+/// taking the non-NULL value as `&str`.
+macro_rules! impl_decode_from_str {
+    ($($(#[$meta:meta])* $t:ty => $parse:expr;)+) => {$(
+        $(#[$meta])*
+        impl SnowflakeDecode for $t {
+            fn try_decode(value: &Option<String>) -> Result<Self> {
+                Self::try_decode_str(value.as_deref(), &SnowflakeColumnType::variant())
+            }
+
+            fn try_decode_str(value: Option<&str>, _: &SnowflakeColumnType) -> Result<Self> {
+                let parse: fn(&str) -> Result<Self> = $parse;
+                parse(unwrap_str(value)?)
+            }
+        }
+    )+};
+}
+pub(crate) use impl_decode_from_str;
+
+impl_decode_from_str! {
+    u64 => |value| parse_integer(value, "u64");
+    i64 => |value| parse_integer(value, "i64");
+    i32 => |value| parse_integer(value, "i32");
+    i8 => |value| parse_integer(value, "i8");
+    u8 => |value| parse_integer(value, "u8");
+    f64 => |value| {
         value
             .parse()
-            .map_err(|_| Error::Decode(format!("'{value}' is not u64")))
-    }
+            .map_err(|_| Error::decode(format!("'{value}' is not f64")))
+    };
 }
-impl SnowflakeDecode for i64 {
+
+impl SnowflakeDecode for String {
     fn try_decode(value: &Option<String>) -> Result<Self> {
         let value = unwrap(value)?;
-        value
-            .parse()
-            .map_err(|_| Error::Decode(format!("'{value}' is not i64")))
+        Ok(value.to_string())
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    /// Strings stored in semi-structured columns arrive as JSON string literals; they are
+    /// returned without the JSON quoting. Other semi-structured values are returned as JSON text.
+    /// TIMESTAMP_TZ values are returned as RFC 3339 with their offset, e.g.
+    /// `2023-11-15T07:13:20+09:00`, rather than in their two-part wire form.
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        let value = unwrap_str(value)?;
+        if column_type.is_semi_structured() {
+            return Ok(unquote_json_string(value));
+        }
+        if column_type.snowflake_type() == "timestamp_tz" {
+            return timestamp_tz_text(value, column_type);
+        }
+        Ok(value.to_string())
     }
 }
-impl SnowflakeDecode for i32 {
-    fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
+
+/// A TIMESTAMP_TZ value as RFC 3339, as [`String`] and `Cow<str>` decode it.
+fn timestamp_tz_text(value: &str, column_type: &SnowflakeColumnType) -> Result<String> {
+    format_iso8601(value, column_type)
+        .ok_or_else(|| Error::decode(format!("'{value}' is not datetime")))
+}
+
+/// Returns the contents of `value` if it is a JSON string literal, or `value` itself otherwise.
+pub(crate) fn unquote_json_string(value: &str) -> String {
+    borrow_json_string(value).into_owned()
+}
+
+/// Like [`unquote_json_string`], but borrows from `value` unless the literal has escapes.
+fn borrow_json_string(value: &str) -> Cow<'_, str> {
+    if value.starts_with('"') {
+        if let Ok(s) = serde_json::from_str::<&str>(value) {
+            return Cow::Borrowed(s);
+        }
+        if let Ok(s) = serde_json::from_str::<String>(value) {
+            return Cow::Owned(s);
+        }
+    }
+    Cow::Borrowed(value)
+}
+
+/// Decodes a value borrowed from its row, for types that do not need to own the value.
+///
+/// [`SnowflakeRow::get_ref`] uses this trait. Decoding into `&str` or `Cow<str>` avoids the
+/// allocation that [`SnowflakeRow::get`] makes for every `String`.
+pub trait SnowflakeDecodeRef<'a>: Sized {
+    fn try_decode_ref(value: Option<&'a str>, column_type: &SnowflakeColumnType) -> Result<Self>;
+}
+
+/// Strings stored in semi-structured columns are unquoted like [`String`] does; a string
+/// literal with escape sequences cannot be borrowed and fails to decode, so use `Cow<str>` for
+/// such columns. TIMESTAMP_TZ values, which [`String`] renders as RFC 3339, fail to decode too.
+impl<'a> SnowflakeDecodeRef<'a> for &'a str {
+    fn try_decode_ref(value: Option<&'a str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        let value = value.ok_or_else(|| Error::decode("value is null"))?;
+        if column_type.snowflake_type() == "timestamp_tz" {
+            return Err(Error::decode(
+                "TIMESTAMP_TZ values are rendered as RFC 3339 and cannot be borrowed; decode them \
+                 as Cow<str>",
+            ));
+        }
+        if !column_type.is_semi_structured() {
+            return Ok(value);
+        }
+        match borrow_json_string(value) {
+            Cow::Borrowed(s) => Ok(s),
+            Cow::Owned(_) => Err(Error::decode(format!(
+                "'{value}' has escape sequences and cannot be borrowed; decode it as Cow<str>"
+            ))),
+        }
+    }
+}
+
+/// Borrows the value, allocating only for semi-structured string literals with escapes and for
+/// TIMESTAMP_TZ values, which are rendered as RFC 3339 like [`String`] does.
+impl<'a> SnowflakeDecodeRef<'a> for Cow<'a, str> {
+    fn try_decode_ref(value: Option<&'a str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        let value = value.ok_or_else(|| Error::decode("value is null"))?;
+        if column_type.is_semi_structured() {
+            Ok(borrow_json_string(value))
+        } else if column_type.snowflake_type() == "timestamp_tz" {
+            timestamp_tz_text(value, column_type).map(Cow::Owned)
+        } else {
+            Ok(Cow::Borrowed(value))
+        }
+    }
+}
+
+impl<'a, T: SnowflakeDecodeRef<'a>> SnowflakeDecodeRef<'a> for Option<T> {
+    fn try_decode_ref(value: Option<&'a str>, column_type: &SnowflakeColumnType) -> Result<Self> {
         value
-            .parse()
-            .map_err(|_| Error::Decode(format!("'{value}' is not i32")))
+            .map(|value| T::try_decode_ref(Some(value), column_type))
+            .transpose()
     }
 }
 
-impl SnowflakeDecode for f64 {
+impl_decode_from_str! {
+    bool => parse_bool;
+}
+
+/// Parses a boolean the way `TO_BOOLEAN` does: BOOLEAN columns arrive as `1`/`0`, while text
+/// from SHOW commands, `RESULT_SCAN` or VARIANT values uses the case-insensitive spellings.
+/// Any other integer is true unless it is zero, as `TO_BOOLEAN` of a number is.
+pub(crate) fn parse_bool(value: &str) -> Result<bool> {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(digits.bytes().any(|b| b != b'0'));
+    }
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "t" | "yes" | "y" | "on" => Ok(true),
+        "false" | "f" | "no" | "n" | "off" => Ok(false),
+        _ => Err(Error::decode(format!("'{value}' is not bool"))),
+    }
+}
+
+impl_decode_from_str! {
+    /// TIMESTAMP_TZ values decode to their wall-clock time at the stored offset, like a cast to
+    /// TIMESTAMP_NTZ; decode into `DateTime<FixedOffset>` to keep the offset.
+    NaiveDateTime => |value| {
+        if let Some(v) = parse_timestamp(value) {
+            return Ok(v);
+        }
+        if let Some(v) = parse_timestamp_tz(value) {
+            return Ok(v.naive_local());
+        }
+        if let Ok(v) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+            return Ok(v);
+        }
+        Err(Error::decode(format!("'{value}' is not datetime")))
+    };
+    /// TIMESTAMP_TZ values keep their stored offset; TIMESTAMP_NTZ and TIMESTAMP_LTZ values are
+    /// read as UTC.
+    DateTime<FixedOffset> => |value| {
+        parse_timestamp_tz(value)
+            .or_else(|| parse_timestamp(value).map(|v| v.and_utc().fixed_offset()))
+            .ok_or_else(|| Error::decode(format!("'{value}' is not datetime")))
+    };
+    /// TIMESTAMP_TZ values are converted to UTC; TIMESTAMP_NTZ values are read as UTC.
+    DateTime<Utc> => |value| {
+        parse_timestamp_tz(value)
+            .map(|v| v.to_utc())
+            .or_else(|| parse_timestamp(value).map(|v| v.and_utc()))
+            .ok_or_else(|| Error::decode(format!("'{value}' is not datetime")))
+    };
+    chrono::NaiveDate => |value| {
+        parse_date(value).ok_or_else(|| Error::decode(format!("'{value}' is not Date type")))
+    };
+    chrono::NaiveTime => |value| {
+        parse_time(value).ok_or_else(|| Error::decode(format!("'{value}' is not time")))
+    };
+}
+
+impl SnowflakeDecode for serde_json::Value {
     fn try_decode(value: &Option<String>) -> Result<Self> {
         let value = unwrap(value)?;
-        value
-            .parse()
-            .map_err(|_| Error::Decode(format!("'{value}' is not f64")))
+        serde_json::from_str(value).map_err(|_| Error::decode(format!("'{value}' is not json")))
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    /// Only VARIANT, OBJECT and ARRAY columns decode into JSON; use `PARSE_JSON` in the query to
+    /// decode JSON stored in other columns.
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Json::try_decode_str(value, column_type).map(|Json(v)| v)
     }
 }
 
-impl SnowflakeDecode for i8 {
+/// Decodes a VARIANT, OBJECT or ARRAY column into any `T: DeserializeOwned`.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Json, Result, SnowflakeRow};
+/// #[derive(serde::Deserialize)]
+/// struct Address {
+///     city: String,
+///     zip: Option<String>,
+/// }
+///
+/// # fn run(row: &SnowflakeRow) -> Result<()> {
+/// let Json(address) = row.get::<Json<Address>>("ADDRESS")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Decoding a column of any other type fails with [`Error::Decode`], while JSON that does not
+/// match `T` fails with [`Error::Json`].
+///
+/// The other way, a `Json<T: Serialize>` is bound as JSON, e.g. by
+/// [`SnowflakeSession::insert_into`](crate::SnowflakeSession::insert_into).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> SnowflakeDecode for Json<T> {
     fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
-        value
-            .parse()
-            .map_err(|_| Error::Decode(format!("'{value}' is not i8")))
+        parse_json(unwrap(value)?).map(Json)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        if !column_type.is_semi_structured() {
+            return Err(Error::decode(format!(
+                "column of type {} is not semi-structured (VARIANT, OBJECT or ARRAY)",
+                column_type.snowflake_type()
+            )));
+        }
+        parse_json(unwrap_str(value)?).map(Json)
     }
 }
 
-impl SnowflakeDecode for String {
+fn parse_json<T: DeserializeOwned>(value: &str) -> Result<T> {
+    serde_json::from_str(value).map_err(|e| Error::Json(e, value.to_string()))
+}
+
+/// Decodes a value with `T`'s [`FromStr`] impl, for newtypes and enums that already parse from
+/// strings.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Parsed, Result, SnowflakeRow};
+/// # fn run(row: &SnowflakeRow) -> Result<()> {
+/// let Parsed(address) = row.get::<Parsed<std::net::IpAddr>>("CLIENT_IP")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Strings in VARIANT, OBJECT and ARRAY columns are unquoted first, like [`String`]. The
+/// message of `T::Err` becomes part of the decode error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Parsed<T>(pub T);
+
+impl<T> SnowflakeDecode for Parsed<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
     fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
-        Ok(value.to_string())
+        Self::try_decode_typed(value, &SnowflakeColumnType::new("text", None))
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        let value = <Cow<str>>::try_decode_ref(value, column_type)?;
+        value.parse().map(Parsed).map_err(|e| {
+            Error::decode(format!(
+                "'{value}' is not a valid {}: {e}",
+                short_type_name(std::any::type_name::<T>())
+            ))
+        })
     }
 }
 
-impl SnowflakeDecode for bool {
+/// Decodes an ARRAY column, converting each element with `T`'s [`SnowflakeDecode`] impl.
+///
+/// Elements are handed to `T` as the JSON text of a VARIANT value, so strings, numbers,
+/// booleans, nested arrays (`Vec<Vec<_>>`) and objects (via [`Json`]) all decode. A JSON `null`
+/// element is a NULL value and fails to decode unless `T` is an `Option`.
+///
+/// A BINARY column decodes into a `Vec<u8>` only.
+impl<T: SnowflakeDecode + 'static> SnowflakeDecode for Vec<T> {
     fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
-        if let Ok(v) = value.parse::<u16>() {
-            return Ok(v > 0);
+        decode_json_array(unwrap(value)?)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        if column_type.snowflake_type() == "binary" {
+            return decode_binary(unwrap_str(value)?);
         }
-        if let Ok(v) = value.parse::<bool>() {
-            return Ok(v);
+        if !column_type.is_semi_structured() {
+            return Err(Error::decode(format!(
+                "column of type {} is not an ARRAY",
+                column_type.snowflake_type()
+            )));
         }
-        Err(Error::Decode(format!("'{value}' is not bool")))
+        decode_json_array(unwrap_str(value)?)
+    }
+}
+
+/// Decodes the bytes of a BINARY value, which arrives as hex, into the one `Vec<T>` they are:
+/// a `Vec<u8>`.
+fn decode_binary<T: 'static>(value: &str) -> Result<Vec<T>> {
+    let bytes: Box<dyn Any> = Box::new(parse_hex(value)?);
+    match bytes.downcast::<Vec<T>>() {
+        Ok(bytes) => Ok(*bytes),
+        Err(_) => Err(Error::decode("BINARY columns decode as Vec<u8> only")),
     }
 }
 
-impl SnowflakeDecode for NaiveDateTime {
+/// Decodes a BINARY column, as `Vec<u8>` does.
+#[cfg(feature = "bytes")]
+impl SnowflakeDecode for bytes::Bytes {
     fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
-        if let Ok(v) = value.parse::<f64>() {
-            let secs = v.trunc() as i64;
-            let nsec = (v.fract() * 1_000_000_000.0) as u32;
-            let dt = NaiveDateTime::from_timestamp_opt(secs, nsec)
-                .ok_or_else(|| Error::Decode(format!("invalid datetime: {}", value)))?;
-            return Ok(dt);
-        }
-        if let Ok(v) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
-            return Ok(v);
+        parse_hex(unwrap(value)?).map(Self::from)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        if column_type.snowflake_type() != "binary" {
+            return Err(Error::decode(format!(
+                "column of type {} is not BINARY",
+                column_type.snowflake_type()
+            )));
         }
-        Err(Error::Decode(format!("'{value}' is not datetime")))
+        parse_hex(unwrap_str(value)?).map(Self::from)
     }
 }
-impl SnowflakeDecode for chrono::NaiveDate {
+
+/// Parses the hex a BINARY value is sent as.
+pub(crate) fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    let not_hex = || {
+        Error::decode(format!(
+            "'{value}' is not hex; set BINARY_OUTPUT_FORMAT to HEX to decode BINARY columns"
+        ))
+    };
+    (0..value.len())
+        .step_by(2)
+        .map(|i| {
+            value
+                .get(i..i + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(not_hex)
+        })
+        .collect()
+}
+
+/// Parses a JSON array and decodes its elements.
+fn decode_json_array<T: SnowflakeDecode>(value: &str) -> Result<Vec<T>> {
+    let elements: Vec<&RawValue> =
+        serde_json::from_str(value).map_err(|e| Error::Json(e, value.to_string()))?;
+    let element_type = SnowflakeColumnType::variant();
+    elements
+        .into_iter()
+        .enumerate()
+        .map(|(i, element)| {
+            decode_json_element(element, &element_type)
+                .map_err(|e| e.decode_context(format_args!("array element {i}")))
+        })
+        .collect()
+}
+
+/// Decodes an OBJECT column, converting each value with `T`'s [`SnowflakeDecode`] impl.
+///
+/// Values are decoded like the elements of an ARRAY (see the impl for `Vec<T>`), so nested
+/// objects decode through [`Json`] or another map. Input that is not a JSON object, or an object
+/// with the same key twice, fails to decode.
+impl<T: SnowflakeDecode, S: BuildHasher + Default> SnowflakeDecode for HashMap<String, T, S> {
     fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
-        let days_since_epoch = value
-            .parse::<u64>()
-            .map_err(|_| Error::Decode(format!("'{value}' is not Date type")))?;
-        NaiveDate::from_ymd_opt(1970, 1, 1)
-            .unwrap_or_default()
-            .checked_add_days(Days::new(days_since_epoch))
-            .ok_or(Error::Decode(format!("'{value}' is not a valid date")))
+        decode_json_object(unwrap(value)?)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        ensure_object_column(column_type)?;
+        decode_json_object(unwrap_str(value)?)
     }
 }
 
-impl SnowflakeDecode for serde_json::Value {
+/// Decodes an OBJECT column into a map sorted by key; see the impl for `HashMap`.
+impl<T: SnowflakeDecode> SnowflakeDecode for BTreeMap<String, T> {
     fn try_decode(value: &Option<String>) -> Result<Self> {
-        let value = unwrap(value)?;
-        serde_json::from_str(value).map_err(|_| Error::Decode(format!("'{value}' is not json")))
+        decode_json_object(unwrap(value)?)
+    }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        Self::try_decode_str(value.as_deref(), column_type)
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        ensure_object_column(column_type)?;
+        decode_json_object(unwrap_str(value)?)
     }
 }
 
+fn ensure_object_column(column_type: &SnowflakeColumnType) -> Result<()> {
+    if !column_type.is_semi_structured() {
+        return Err(Error::decode(format!(
+            "column of type {} is not an OBJECT",
+            column_type.snowflake_type()
+        )));
+    }
+    Ok(())
+}
+
+/// Parses a JSON object and decodes its values, rejecting duplicate keys.
+fn decode_json_object<T, C>(value: &str) -> Result<C>
+where
+    T: SnowflakeDecode,
+    C: FromIterator<(String, T)>,
+{
+    let JsonEntries(entries) = serde_json::from_str(value)
+        .map_err(|e| Error::decode(format!("'{value}' is not a JSON object: {e}")))?;
+    let mut keys = HashSet::with_capacity(entries.len());
+    if let Some((key, _)) = entries.iter().find(|(key, _)| !keys.insert(key.as_str())) {
+        return Err(Error::decode(format!(
+            "duplicate key '{key}' in JSON object"
+        )));
+    }
+    let element_type = SnowflakeColumnType::variant();
+    entries
+        .into_iter()
+        .map(|(key, element)| {
+            let element = decode_json_element(element, &element_type)
+                .map_err(|e| e.decode_context(format_args!("object value '{key}'")))?;
+            Ok((key, element))
+        })
+        .collect()
+}
+
+/// The entries of a JSON object in document order, duplicates included.
+struct JsonEntries<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for JsonEntries<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct EntriesVisitor<'a>(PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for EntriesVisitor<'a> {
+            type Value = JsonEntries<'a>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or_default());
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(JsonEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
+
+/// Decodes an element of an ARRAY or OBJECT value: the element's JSON text is treated as a
+/// VARIANT cell, with JSON `null` mapped to NULL.
+fn decode_json_element<T: SnowflakeDecode>(
+    element: &RawValue,
+    element_type: &SnowflakeColumnType,
+) -> Result<T> {
+    let element = element.get();
+    T::try_decode_str((element != "null").then_some(element), element_type)
+}
+
 impl<T: SnowflakeDecode> SnowflakeDecode for Option<T> {
+    const DECODES_NULL: bool = true;
+
     fn try_decode(value: &Option<String>) -> Result<Self> {
         if value.is_none() {
             return Ok(None);
         }
         T::try_decode(value).map(|v| Some(v))
     }
+
+    fn try_decode_typed(value: &Option<String>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        if value.is_none() {
+            return Ok(None);
+        }
+        T::try_decode_typed(value, column_type).map(|v| Some(v))
+    }
+
+    fn try_decode_str(value: Option<&str>, column_type: &SnowflakeColumnType) -> Result<Self> {
+        value
+            .map(|value| T::try_decode_str(Some(value), column_type))
+            .transpose()
+    }
 }
 
-trait TryGet {
-    fn try_get<T: SnowflakeDecode>(&self) -> Result<T>;
+pub(crate) fn unwrap(value: &Option<String>) -> Result<&String> {
+    value.as_ref().ok_or_else(|| Error::decode("value is null"))
 }
 
-impl TryGet for Option<String> {
-    fn try_get<T: SnowflakeDecode>(&self) -> Result<T> {
-        T::try_decode(self)
-    }
+pub(crate) fn unwrap_str(value: Option<&str>) -> Result<&str> {
+    value.ok_or_else(|| Error::decode("value is null"))
 }
 
-fn unwrap(value: &Option<String>) -> Result<&String> {
-    value
-        .as_ref()
-        .ok_or_else(|| Error::Decode("value is null".into()))
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn text_row(columns: &[(&str, Option<&str>)]) -> SnowflakeRow {
+        SnowflakeRow::new(
+            columns
+                .iter()
+                .map(|(_, value)| value.map(str::to_string))
+                .collect(),
+            Arc::new(Columns::new(
+                columns
+                    .iter()
+                    .map(|(name, _)| (name.to_string(), SnowflakeColumnType::new("text", None)))
+                    .collect(),
+            )),
+        )
+    }
+
+    /// A row of `(name, Snowflake type, value)` columns.
+    pub(crate) fn typed_row(columns: &[(&str, &str, Option<&str>)]) -> SnowflakeRow {
+        SnowflakeRow::new(
+            columns
+                .iter()
+                .map(|(_, _, value)| value.map(str::to_string))
+                .collect(),
+            Arc::new(Columns::new(
+                columns
+                    .iter()
+                    .map(|(name, ty, _)| (name.to_string(), SnowflakeColumnType::new(ty, None)))
+                    .collect(),
+            )),
+        )
+    }
+
+    #[test]
+    fn test_decode_semi_structured() -> Result<()> {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Address {
+            city: String,
+        }
+
+        let row = typed_row(&[
+            ("OBJ", "object", Some("{\n  \"city\": \"Tokyo\"\n}")),
+            ("STR", "variant", Some("\"hello\"")),
+            ("ARR", "array", Some("[1, 2]")),
+            ("NUM", "fixed", Some("1")),
+            ("NUL", "variant", None),
+        ]);
+
+        assert_eq!(
+            row.get::<Json<Address>>("OBJ")?,
+            Json(Address {
+                city: "Tokyo".into()
+            })
+        );
+        assert_eq!(row.get::<String>("STR")?, "hello");
+        assert_eq!(row.get::<String>("ARR")?, "[1, 2]");
+        assert_eq!(row.get::<Json<String>>("STR")?, Json("hello".to_string()));
+        assert_eq!(
+            row.get::<serde_json::Value>("ARR")?,
+            serde_json::json!([1, 2])
+        );
+        assert_eq!(row.get::<Option<Json<Address>>>("NUL")?, None);
+
+        assert!(matches!(
+            row.get::<serde_json::Value>("NUM"),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            row.get::<Json<Address>>("NUM"),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            row.get::<Json<Address>>("ARR"),
+            Err(Error::Json(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_array() -> Result<()> {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Item {
+            id: i64,
+        }
+
+        let row = typed_row(&[
+            (
+                "NUMS",
+                "array",
+                Some("[\n  1,\n  -2,\n  18446744073709551615\n]"),
+            ),
+            ("FLOATS", "array", Some("[1.5, 1e3]")),
+            ("STRS", "array", Some(r#"["a", "\"quoted\"", ""]"#)),
+            ("NULLS", "array", Some("[1, null]")),
+            ("OBJS", "array", Some(r#"[{"id": 1}, {"id": 2}]"#)),
+            ("NESTED", "array", Some("[[1], [], [2, 3]]")),
+            ("EMPTY", "array", Some("[]")),
+            ("TEXT", "text", Some("[1]")),
+        ]);
+
+        assert_eq!(
+            row.get::<Vec<i64>>("NUMS").unwrap_err().to_string(),
+            "decode error: column 'NUMS' (index 0, type array) as Vec<i64>: \
+             array element 2: '18446744073709551615' is not i64"
+        );
+        assert_eq!(
+            row.get::<Vec<u64>>("NUMS").unwrap_err().to_string(),
+            "decode error: column 'NUMS' (index 0, type array) as Vec<u64>: \
+             array element 1: '-2' is not u64"
+        );
+        assert_eq!(row.get::<Vec<f64>>("FLOATS")?, vec![1.5, 1000.0]);
+        assert_eq!(row.get::<Vec<String>>("STRS")?, vec!["a", "\"quoted\"", ""]);
+        assert_eq!(row.get::<Vec<Option<i64>>>("NULLS")?, vec![Some(1), None]);
+        assert!(row.get::<Vec<i64>>("NULLS").is_err());
+        assert_eq!(
+            row.get::<Vec<Json<Item>>>("OBJS")?,
+            vec![Json(Item { id: 1 }), Json(Item { id: 2 })]
+        );
+        assert_eq!(
+            row.get::<Vec<Vec<i32>>>("NESTED")?,
+            vec![vec![1], vec![], vec![2, 3]]
+        );
+        assert_eq!(row.get::<Vec<i64>>("EMPTY")?, Vec::<i64>::new());
+        assert!(matches!(row.get::<Vec<i64>>("TEXT"), Err(Error::Decode(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_binary() -> Result<()> {
+        let row = typed_row(&[
+            ("HASH", "binary", Some("0FA0ff")),
+            ("EMPTY", "binary", Some("")),
+            ("ODD", "binary", Some("0FA")),
+            ("BASE64", "binary", Some("D6A=")),
+        ]);
+
+        assert_eq!(row.get::<Vec<u8>>("HASH")?, [0x0f, 0xa0, 0xff]);
+        assert_eq!(
+            row.get::<Vec<i64>>("HASH").unwrap_err().to_string(),
+            "decode error: column 'HASH' (index 0, type binary) as Vec<i64>: BINARY columns \
+             decode as Vec<u8> only"
+        );
+        assert_eq!(row.get::<Vec<u8>>("EMPTY")?, Vec::<u8>::new());
+        assert!(row.get::<Vec<u8>>("ODD").is_err());
+        #[cfg(feature = "bytes")]
+        {
+            assert_eq!(row.get::<bytes::Bytes>("HASH")?, [0x0f, 0xa0, 0xff][..]);
+            assert!(row.get::<bytes::Bytes>("ODD").is_err());
+        }
+        assert_eq!(
+            row.get::<Vec<u8>>("BASE64").unwrap_err().to_string(),
+            "decode error: column 'BASE64' (index 3, type binary) as Vec<u8>: 'D6A=' is not \
+             hex; set BINARY_OUTPUT_FORMAT to HEX to decode BINARY columns"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_object_map() -> Result<()> {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Item {
+            id: i64,
+        }
+
+        let row = typed_row(&[
+            (
+                "PROPS",
+                "object",
+                Some("{\n  \"color\": \"red\",\n  \"size\": \"L\"\n}"),
+            ),
+            ("NUMS", "object", Some(r#"{"a": 1, "b": null}"#)),
+            (
+                "NESTED",
+                "object",
+                Some(r#"{"x": {"id": 1}, "y": {"id": 2}}"#),
+            ),
+            ("DEEP", "object", Some(r#"{"x": {"a": [1, 2]}}"#)),
+            ("DUP", "object", Some(r#"{"a": 1, "a": 2}"#)),
+            ("ARR", "array", Some("[1, 2]")),
+            ("TEXT", "text", Some(r#"{"a": 1}"#)),
+        ]);
+
+        assert_eq!(
+            row.get::<HashMap<String, String>>("PROPS")?,
+            HashMap::from([
+                ("color".to_string(), "red".to_string()),
+                ("size".to_string(), "L".to_string()),
+            ])
+        );
+        assert_eq!(
+            row.get::<BTreeMap<String, Option<i64>>>("NUMS")?,
+            BTreeMap::from([("a".to_string(), Some(1)), ("b".to_string(), None)])
+        );
+        assert_eq!(
+            row.get::<BTreeMap<String, Json<Item>>>("NESTED")?,
+            BTreeMap::from([
+                ("x".to_string(), Json(Item { id: 1 })),
+                ("y".to_string(), Json(Item { id: 2 })),
+            ])
+        );
+        assert_eq!(
+            row.get::<HashMap<String, HashMap<String, Vec<i64>>>>("DEEP")?["x"]["a"],
+            vec![1, 2]
+        );
+        assert_eq!(
+            row.get::<HashMap<String, i64>>("NUMS")
+                .unwrap_err()
+                .to_string(),
+            "decode error: column 'NUMS' (index 1, type object) as HashMap<String, i64>: \
+             object value 'b': value is null"
+        );
+        assert!(row
+            .get::<HashMap<String, i64>>("DUP")
+            .unwrap_err()
+            .to_string()
+            .ends_with("duplicate key 'a' in JSON object"));
+        assert!(row
+            .get::<BTreeMap<String, i64>>("ARR")
+            .unwrap_err()
+            .to_string()
+            .contains("is not a JSON object"));
+        assert!(row.get::<BTreeMap<String, i64>>("TEXT").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quoted_identifier_lookup() -> Result<()> {
+        let row = text_row(&[
+            ("id", Some("1")),
+            ("ID", Some("2")),
+            ("myColumn", Some("3")),
+            ("Other", Some("4")),
+        ]);
+
+        assert_eq!(row.get::<i64>("id")?, 1);
+        assert_eq!(row.get::<i64>("ID")?, 2);
+        assert_eq!(row.get::<i64>("Id")?, 2);
+        assert_eq!(row.get::<i64>("myColumn")?, 3);
+        assert_eq!(row.get::<i64>("MYCOLUMN")?, 3);
+        assert_eq!(row.get::<i64>("other")?, 4);
+
+        assert_eq!(row.get_exact::<i64>("id")?, 1);
+        assert_eq!(row.get_exact::<i64>("ID")?, 2);
+        assert!(row.get_exact::<i64>("Id").is_err());
+        assert!(row.get_exact::<i64>("mycolumn").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_column_names() -> Result<()> {
+        let row = text_row(&[("ID", Some("1")), ("NAME", Some("a")), ("ID", Some("2"))]);
+
+        assert_eq!(row.get::<i64>("ID")?, 1);
+        assert_eq!(row.get_all::<i64>("ID")?, vec![1, 2]);
+        assert_eq!(row.get_all::<i64>("id")?, vec![1, 2]);
+        assert_eq!(row.get_all::<String>("NAME")?, vec!["a"]);
+        assert!(row.get_all::<i64>("MISSING").is_err());
+
+        assert_eq!(row.columns_named("id"), vec![0, 2]);
+        assert_eq!(row.columns_named("MISSING"), Vec::<usize>::new());
+
+        assert_eq!(row.get_unique::<String>("NAME")?, "a");
+        let err = row.get_unique::<i64>("ID").unwrap_err().to_string();
+        assert!(err.contains("ambiguous"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_aware_accessors() -> Result<()> {
+        let row = text_row(&[("NUL", None), ("EMPTY", Some("")), ("NUM", Some("3"))]);
+
+        assert!(row.is_null("NUL")?);
+        assert!(row.is_null("nul")?);
+        assert!(!row.is_null("EMPTY")?);
+        assert!(!row.is_null("NUM")?);
+        assert!(row.is_null("MISSING").is_err());
+
+        assert_eq!(row.get_or("NUL", 7_i64)?, 7);
+        assert_eq!(row.get_or("NUM", 7_i64)?, 3);
+        assert_eq!(row.get_or("EMPTY", "x".to_string())?, "");
+        assert!(row.get_or("EMPTY", 7_i64).is_err());
+        assert!(row.get_or("MISSING", 7_i64).is_err());
+
+        assert_eq!(row.get_or_default::<String>("NUL")?, "");
+        assert_eq!(row.get_or_default::<i64>("NUM")?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_access() -> Result<()> {
+        let row = typed_row(&[
+            ("TS", "timestamp_ntz", Some("1700000000.123000000")),
+            ("NUL", "text", None),
+            ("OBJ", "object", Some("{\n  \"a\": 1\n}")),
+        ]);
+
+        assert_eq!(row.get_raw("TS")?, Some("1700000000.123000000"));
+        assert_eq!(row.get_raw("NUL")?, None);
+        assert_eq!(row.get_raw("obj")?, Some("{\n  \"a\": 1\n}"));
+        assert!(row.get_raw("MISSING").is_err());
+
+        assert_eq!(
+            row.into_inner(),
+            vec![
+                Some("1700000000.123000000".to_string()),
+                None,
+                Some("{\n  \"a\": 1\n}".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_timestamp_tz() -> Result<()> {
+        let row = typed_row(&[
+            ("PLUS", "timestamp_tz", Some("1700000000.123456789 1980")),
+            ("MINUS", "timestamp_tz", Some("1700000000.000000000 1140")),
+            ("HALF", "timestamp_tz", Some("1700000000.000000000 1770")),
+            ("NTZ", "timestamp_ntz", Some("1700000000.000000000")),
+        ]);
+
+        let plus = row.get::<DateTime<FixedOffset>>("PLUS")?;
+        assert_eq!(plus.to_rfc3339(), "2023-11-15T07:13:20.123456789+09:00");
+        let minus = row.get::<DateTime<FixedOffset>>("MINUS")?;
+        assert_eq!(minus.to_rfc3339(), "2023-11-14T17:13:20-05:00");
+        let half = row.get::<DateTime<FixedOffset>>("HALF")?;
+        assert_eq!(half.to_rfc3339(), "2023-11-15T03:43:20+05:30");
+        let ntz = row.get::<DateTime<FixedOffset>>("NTZ")?;
+        assert_eq!(ntz.to_rfc3339(), "2023-11-14T22:13:20+00:00");
+        assert_eq!(minus, ntz);
+
+        // Naive targets get the wall-clock time at the stored offset.
+        assert_eq!(
+            row.get::<NaiveDateTime>("PLUS")?.to_string(),
+            "2023-11-15 07:13:20.123456789"
+        );
+        assert_eq!(
+            row.get::<NaiveDateTime>("MINUS")?.to_string(),
+            "2023-11-14 17:13:20"
+        );
+        assert_eq!(
+            row.get::<String>("PLUS")?,
+            "2023-11-15T07:13:20.123456789+09:00"
+        );
+        assert_eq!(row.get::<String>("HALF")?, "2023-11-15T03:43:20+05:30");
+        assert_eq!(row.get::<String>("NTZ")?, "1700000000.000000000");
+        assert_eq!(
+            row.get::<DateTime<Utc>>("PLUS")?.to_rfc3339(),
+            "2023-11-14T22:13:20.123456789+00:00"
+        );
+        assert_eq!(row.get::<DateTime<Utc>>("NTZ")?, ntz);
+        assert!(typed_row(&[("BAD", "timestamp_tz", Some("1700000000"))])
+            .get::<String>("BAD")
+            .is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "chrono-tz")]
+    #[test]
+    fn test_get_in_timezone() -> Result<()> {
+        let row = typed_row(&[("TS", "timestamp_tz", Some("1700000000.000000000 1980"))]);
+        let dt = row.get_in_timezone("TS", chrono_tz::America::New_York)?;
+        assert_eq!(dt.to_rfc3339(), "2023-11-14T17:13:20-05:00");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bool() {
+        for value in [
+            "1",
+            "2",
+            "-1",
+            "+7",
+            "18446744073709551616",
+            "true",
+            "TRUE",
+            "True",
+            "t",
+            "T",
+            "yes",
+            "YES",
+            "y",
+            "Y",
+            "on",
+            "ON",
+        ] {
+            assert!(bool::try_decode(&Some(value.into())).unwrap(), "{value}");
+        }
+        for value in [
+            "0", "-0", "000", "false", "FALSE", "False", "f", "F", "no", "NO", "n", "N", "off",
+            "OFF",
+        ] {
+            assert!(!bool::try_decode(&Some(value.into())).unwrap(), "{value}");
+        }
+        for value in ["", "-", "maybe", "truthy", "1.0", "1e3", " true", " 1"] {
+            assert!(bool::try_decode(&Some(value.into())).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_decode_error_context() {
+        let long = "x".repeat(100);
+        let row = typed_row(&[
+            ("ID", "fixed", Some("1")),
+            ("NAME", "text", Some(&long)),
+            ("DAY", "date", None),
+        ]);
+
+        let err = row.get::<i64>("name").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("decode error: column 'NAME' (index 1, type text) as i64: '{long}' is not i64")
+        );
+        let Error::Decode(err) = err else {
+            panic!("expected a decode error");
+        };
+        assert_eq!(err.column_name(), Some("NAME"));
+        assert_eq!(err.column_index(), Some(1));
+        assert_eq!(err.snowflake_type(), Some("text"));
+        assert_eq!(err.rust_type(), Some("i64"));
+        assert_eq!(err.value(), Some(format!("{}...", &long[..64]).as_str()));
+        assert_eq!(err.message(), format!("'{long}' is not i64"));
+
+        let err = row.get::<chrono::NaiveDate>("DAY").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: column 'DAY' (index 2, type date) as NaiveDate: value is null"
+        );
+        let err = row.get::<i64>("MISSING").unwrap_err();
+        assert_eq!(err.to_string(), "decode error: column not found: MISSING");
+    }
+
+    #[test]
+    fn test_get_ref() -> Result<()> {
+        let row = typed_row(&[
+            ("TEXT", "text", Some("hello")),
+            ("NUL", "text", None),
+            ("VAR", "variant", Some("\"plain\"")),
+            ("ESCAPED", "variant", Some(r#""a\"b""#)),
+            ("OBJ", "object", Some("{\n  \"a\": 1\n}")),
+            ("TZ", "timestamp_tz", Some("1700000000.123000000 1980")),
+        ]);
+
+        assert_eq!(row.get_ref::<&str>("TEXT")?, "hello");
+        assert_eq!(row.get_ref::<Option<&str>>("NUL")?, None);
+        assert!(row.get_ref::<&str>("NUL").is_err());
+        assert_eq!(row.get_ref::<&str>("VAR")?, "plain");
+        assert_eq!(row.get_ref::<&str>("OBJ")?, "{\n  \"a\": 1\n}");
+        assert!(row.get_ref::<&str>("ESCAPED").is_err());
+
+        assert!(matches!(
+            row.get_ref::<Cow<str>>("VAR")?,
+            Cow::Borrowed("plain")
+        ));
+        assert!(matches!(
+            row.get_ref::<Cow<str>>("ESCAPED")?,
+            Cow::Owned(s) if s == "a\"b"
+        ));
+
+        let tz = row.get::<String>("TZ")?;
+        assert_eq!(tz, "2023-11-15T07:13:20.123+09:00");
+        assert_eq!(row.get_ref::<Cow<str>>("TZ")?, tz);
+        assert!(row.get_ref::<&str>("TZ").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_parsed() -> Result<()> {
+        let row = typed_row(&[
+            ("IP", "text", Some("127.0.0.1")),
+            ("VAR", "variant", Some("\"::1\"")),
+            ("BAD", "text", Some("localhost")),
+        ]);
+
+        let Parsed(ip) = row.get::<Parsed<std::net::IpAddr>>("IP")?;
+        assert_eq!(ip, std::net::IpAddr::from([127, 0, 0, 1]));
+        let Parsed(ip) = row.get::<Parsed<std::net::IpAddr>>("VAR")?;
+        assert_eq!(ip, "::1".parse::<std::net::IpAddr>().unwrap());
+        assert_eq!(
+            row.get::<Parsed<std::net::IpAddr>>("BAD")
+                .unwrap_err()
+                .to_string(),
+            "decode error: column 'BAD' (index 2, type text) as Parsed<IpAddr>: \
+             'localhost' is not a valid IpAddr: invalid IP address syntax"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_get() -> Result<()> {
+        let row = typed_row(&[
+            ("ID", "fixed", Some("1")),
+            ("NAME", "text", Some("a")),
+            ("NUL", "fixed", None),
+        ]);
+
+        assert_eq!(row.try_get::<i64>("MISSING")?, None);
+        assert_eq!(row.try_get::<Option<i64>>("MISSING")?, None);
+        assert_eq!(row.try_get::<i64>("id")?, Some(1));
+        assert_eq!(
+            row.try_get::<i64>("NAME").unwrap_err().to_string(),
+            "decode error: column 'NAME' (index 1, type text) as i64: 'a' is not i64"
+        );
+        assert_eq!(row.try_get::<Option<i64>>("NUL")?, Some(None));
+        assert_eq!(
+            row.try_get::<i64>("NUL").unwrap_err().to_string(),
+            "decode error: column 'NUL' (index 2, type fixed) as i64: value is null"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter() {
+        let row = text_row(&[("ZETA", Some("z")), ("alpha", None), ("ZETA", Some(""))]);
+
+        assert_eq!(
+            row.iter().collect::<Vec<_>>(),
+            vec![("ZETA", Some("z")), ("alpha", None), ("ZETA", Some(""))]
+        );
+        assert_eq!(
+            row.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("ZETA".to_string(), Some("z".to_string())),
+                ("alpha".to_string(), None),
+                ("ZETA".to_string(), Some(String::new())),
+            ]
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, crate::FromRow)]
+    struct Example {
+        id: i64,
+        #[snowflake(rename = "VALUE")]
+        text: Option<String>,
+        #[snowflake(default)]
+        extra: String,
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_from_row() -> Result<()> {
+        let row = text_row(&[("ID", Some("1")), ("VALUE", None), ("EXTRA", Some("x"))]);
+        assert_eq!(
+            Example::from_row(&row)?,
+            Example {
+                id: 1,
+                text: None,
+                extra: "x".into(),
+            }
+        );
+
+        let row = text_row(&[("ID", Some("1")), ("VALUE", Some("a"))]);
+        assert_eq!(
+            Example::from_row(&row)?,
+            Example {
+                id: 1,
+                text: Some("a".into()),
+                extra: String::new(),
+            }
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_from_row_missing_column() {
+        let row = text_row(&[("ID", Some("1"))]);
+        let err = Example::from_row(&row).unwrap_err();
+        assert_eq!(err.to_string(), "decode error: column not found: VALUE");
+    }
+
+    #[test]
+    fn test_column_names_preserve_server_order() {
+        let names = ["ZETA", "alpha", "Mid", "B", "a_1"];
+        let row = text_row(&names.map(|name| (name, Some(name))));
+
+        assert_eq!(row.column_names(), names);
+        for name in names {
+            assert_eq!(row.get::<String>(name).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn test_get_tuple() -> Result<()> {
+        let row = typed_row(&[
+            ("ID", "fixed", Some("7")),
+            ("NAME", "text", Some("seven")),
+            ("CREATED", "timestamp_ntz", Some("1700000000.123000000")),
+            ("NOTE", "text", None),
+        ]);
+
+        let (id, name, created, note): (i64, String, NaiveDateTime, Option<String>) =
+            row.get_tuple()?;
+        assert_eq!((id, name.as_str(), note), (7, "seven", None));
+        assert_eq!(created.and_utc().timestamp_millis(), 1_700_000_000_123);
+
+        let err = row.get_tuple::<(i64, i64, String, String)>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: column 'NAME' (index 1, type text) as i64: 'seven' is not i64"
+        );
+        let err = row.get_tuple::<(i64, String)>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: a row of 4 columns does not decode into a tuple of 2"
+        );
+        Ok(())
+    }
 }
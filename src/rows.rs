@@ -0,0 +1,252 @@
+//! Helpers that work on a whole result set rather than a single row.
+
+use std::sync::Arc;
+
+use crate::{row::Columns, Result, SnowflakeDecode, SnowflakeRow};
+
+/// Column extraction over the rows of a result.
+///
+/// Column names are resolved once per result rather than once per row, and decode errors carry
+/// the position of the failing row ([`DecodeError::row_index`](crate::DecodeError::row_index)).
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, RowsExt, SnowflakeRow};
+/// # fn run(rows: &[SnowflakeRow]) -> Result<()> {
+/// let ids: Vec<i64> = rows.column("ID")?;
+/// let pairs: Vec<(i64, Option<String>)> = rows.columns(("ID", "VALUE"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait RowsExt {
+    /// Decodes one column of every row.
+    fn column<T: SnowflakeDecode>(&self, column_name: &str) -> Result<Vec<T>>;
+
+    /// Decodes several columns of every row into a tuple, e.g. `(i64, String)` from
+    /// `("ID", "VALUE")`. Tuples of up to eight elements are supported.
+    fn columns<T: FromColumns>(&self, column_names: T::Names<'_>) -> Result<Vec<T>>;
+
+    /// Resolves column names once and returns an accessor that decodes them from each row;
+    /// see [`RowAccessor`].
+    fn accessor<'n, T: FromColumns>(
+        &self,
+        column_names: T::Names<'n>,
+    ) -> Result<RowAccessor<'n, T>>;
+}
+
+impl RowsExt for [SnowflakeRow] {
+    fn column<T: SnowflakeDecode>(&self, column_name: &str) -> Result<Vec<T>> {
+        let values = self.columns::<(T,)>((column_name,))?;
+        Ok(values.into_iter().map(|(value,)| value).collect())
+    }
+
+    fn columns<T: FromColumns>(&self, column_names: T::Names<'_>) -> Result<Vec<T>> {
+        let mut resolved = None;
+        self.iter()
+            .enumerate()
+            .map(|(i, row)| {
+                // Rows of one result share their column metadata; rows from different results
+                // are resolved again.
+                let indices = match &resolved {
+                    Some((columns, indices)) if Arc::ptr_eq(columns, &row.columns) => indices,
+                    _ => {
+                        let indices = T::resolve(row, &column_names)?;
+                        &resolved.insert((Arc::clone(&row.columns), indices)).1
+                    }
+                };
+                T::decode(row, indices).map_err(|e| e.with_row(i))
+            })
+            .collect()
+    }
+
+    fn accessor<'n, T: FromColumns>(
+        &self,
+        column_names: T::Names<'n>,
+    ) -> Result<RowAccessor<'n, T>> {
+        match self.first() {
+            Some(row) => RowAccessor::new(row, column_names),
+            None => Ok(RowAccessor {
+                column_names,
+                resolved: None,
+            }),
+        }
+    }
+}
+
+/// Decodes a tuple of columns from rows, with the column names resolved up front.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, RowsExt, SnowflakeRow};
+/// # fn run(rows: &[SnowflakeRow]) -> Result<()> {
+/// let accessor = rows.accessor::<(i64, String)>(("ID", "VALUE"))?;
+/// for row in rows {
+///     let (id, value) = accessor.get(row)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The names are resolved against the first row when the accessor is created. Rows from another
+/// result, whose columns may differ, are still decoded correctly, but their names are resolved
+/// on every call.
+pub struct RowAccessor<'n, T: FromColumns> {
+    column_names: T::Names<'n>,
+    resolved: Option<(Arc<Columns>, T::Indices)>,
+}
+
+impl<'n, T: FromColumns> RowAccessor<'n, T> {
+    /// Creates an accessor resolving `column_names` against the columns of `row`.
+    pub fn new(row: &SnowflakeRow, column_names: T::Names<'n>) -> Result<Self> {
+        let indices = T::resolve(row, &column_names)?;
+        Ok(Self {
+            column_names,
+            resolved: Some((Arc::clone(&row.columns), indices)),
+        })
+    }
+
+    /// Decodes the columns from `row`.
+    pub fn get(&self, row: &SnowflakeRow) -> Result<T> {
+        match &self.resolved {
+            Some((columns, indices)) if Arc::ptr_eq(columns, &row.columns) => {
+                T::decode(row, indices)
+            }
+            _ => T::decode(row, &T::resolve(row, &self.column_names)?),
+        }
+    }
+}
+
+/// A tuple of values decoded from named columns; see [`RowsExt::columns`].
+pub trait FromColumns: Sized {
+    /// A tuple of column names with one name per element.
+    type Names<'a>;
+
+    #[doc(hidden)]
+    type Indices;
+
+    #[doc(hidden)]
+    fn resolve(row: &SnowflakeRow, column_names: &Self::Names<'_>) -> Result<Self::Indices>;
+
+    #[doc(hidden)]
+    fn decode(row: &SnowflakeRow, indices: &Self::Indices) -> Result<Self>;
+}
+
+macro_rules! impl_from_columns {
+    ($len:literal; $($t:ident $i:tt),+) => {
+        impl<$($t: SnowflakeDecode),+> FromColumns for ($($t,)+) {
+            type Names<'a> = ($(impl_from_columns!(@name $t),)+);
+            type Indices = [usize; $len];
+
+            fn resolve(row: &SnowflakeRow, column_names: &Self::Names<'_>) -> Result<Self::Indices> {
+                Ok([$(row.index_of(column_names.$i)?),+])
+            }
+
+            fn decode(row: &SnowflakeRow, indices: &Self::Indices) -> Result<Self> {
+                Ok(($(row.decode_at::<$t>(indices[$i])?,)+))
+            }
+        }
+    };
+    (@name $t:ident) => { &'a str };
+}
+
+impl_from_columns!(1; A 0);
+impl_from_columns!(2; A 0, B 1);
+impl_from_columns!(3; A 0, B 1, C 2);
+impl_from_columns!(4; A 0, B 1, C 2, D 3);
+impl_from_columns!(5; A 0, B 1, C 2, D 3, E 4);
+impl_from_columns!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+impl_from_columns!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_from_columns!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{types::SnowflakeColumnType, Error};
+
+    fn rows(names: &[&str], values: &[&[Option<&str>]]) -> Vec<SnowflakeRow> {
+        let columns = Arc::new(Columns::new(
+            names
+                .iter()
+                .map(|name| (name.to_string(), SnowflakeColumnType::new("text", None)))
+                .collect(),
+        ));
+        values
+            .iter()
+            .map(|row| {
+                SnowflakeRow::new(
+                    row.iter().map(|v| v.map(str::to_string)).collect(),
+                    Arc::clone(&columns),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_column() -> Result<()> {
+        let rows = rows(
+            &["ID", "VALUE"],
+            &[
+                &[Some("1"), Some("a")],
+                &[Some("2"), None],
+                &[Some("x"), None],
+            ],
+        );
+
+        assert_eq!(rows[..2].column::<i64>("id")?, vec![1, 2]);
+        assert_eq!(
+            rows.column::<Option<String>>("VALUE")?,
+            vec![Some("a".to_string()), None, None]
+        );
+        assert_eq!(
+            rows[..2].columns::<(i64, Option<String>)>(("ID", "VALUE"))?,
+            vec![(1, Some("a".to_string())), (2, None)]
+        );
+        assert_eq!(rows[..0].column::<i64>("MISSING")?, Vec::<i64>::new());
+        assert!(rows.column::<i64>("MISSING").is_err());
+
+        let err = rows.column::<i64>("ID").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "decode error: row 2, column 'ID' (index 0, type text) as i64: 'x' is not i64"
+        );
+        let Error::Decode(err) = err else {
+            panic!("expected a decode error");
+        };
+        assert_eq!(err.row_index(), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns_from_different_results() -> Result<()> {
+        let mut mixed = rows(&["ID", "VALUE"], &[&[Some("1"), Some("a")]]);
+        mixed.extend(rows(&["VALUE", "ID"], &[&[Some("b"), Some("2")]]));
+
+        assert_eq!(
+            mixed.columns::<(String, i64)>(("VALUE", "ID"))?,
+            vec![("a".to_string(), 1), ("b".to_string(), 2)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_accessor() -> Result<()> {
+        let result = rows(
+            &["ID", "VALUE"],
+            &[&[Some("1"), Some("a")], &[Some("2"), None]],
+        );
+        let other = rows(&["VALUE", "ID"], &[&[Some("b"), Some("3")]]);
+
+        let accessor = result.accessor::<(i64, Option<String>)>(("id", "value"))?;
+        assert_eq!(accessor.get(&result[0])?, (1, Some("a".to_string())));
+        assert_eq!(accessor.get(&result[1])?, (2, None));
+        assert_eq!(accessor.get(&other[0])?, (3, Some("b".to_string())));
+        assert!(result.accessor::<(i64,)>(("MISSING",)).is_err());
+
+        let empty = result[..0].accessor::<(i64,)>(("MISSING",))?;
+        assert!(empty.get(&result[0]).is_err());
+
+        let index = result[0].column_index("value")?;
+        assert_eq!(index, 1);
+        assert_eq!(result[0].get_index::<String>(index)?, "a");
+        assert!(result[0].get_index::<String>(2).is_err());
+        Ok(())
+    }
+}
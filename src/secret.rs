@@ -0,0 +1,104 @@
+//! Credentials that are wiped from memory when dropped and left out of `Debug` output.
+
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+/// A secret string, such as a password or a token. Its memory is zeroed when it is dropped,
+/// and `Debug` shows `***` in its place.
+///
+/// Copies made to send the secret, e.g. into the body of the login request, are outside its
+/// reach and are not zeroed.
+#[derive(Clone, Default)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    /// The secret itself.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self::new(secret.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+/// Secret bytes, such as the passphrase of a private key, zeroed and left out of `Debug`
+/// output as a [`SecretString`] is.
+#[derive(Clone, Default)]
+pub struct SecretBytes(Zeroizing<Vec<u8>>);
+
+impl SecretBytes {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    /// The secret itself.
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(secret: Vec<u8>) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(secret: &[u8]) -> Self {
+        Self::new(secret.to_vec())
+    }
+}
+
+impl From<String> for SecretBytes {
+    fn from(secret: String) -> Self {
+        Self::new(secret.into_bytes())
+    }
+}
+
+impl From<&str> for SecretBytes {
+    fn from(secret: &str) -> Self {
+        Self::new(secret.as_bytes().to_vec())
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_not_shown() {
+        let password = SecretString::from("hunter2");
+        assert_eq!(password.expose_secret(), "hunter2");
+        assert_eq!(format!("{password:?}"), r#""***""#);
+
+        let passphrase = SecretBytes::from(b"pem-secret".to_vec());
+        assert_eq!(passphrase.expose_secret(), b"pem-secret");
+        assert_eq!(format!("{passphrase:?}"), r#""***""#);
+    }
+}
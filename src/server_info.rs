@@ -0,0 +1,127 @@
+//! The version of Snowflake a session is connected to, and the session parameters Snowflake
+//! reports for it.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Mutex, PoisonError},
+};
+
+use serde_json::Value;
+
+/// A release of Snowflake, e.g. `8.12.1`, ordered by its components to compare releases.
+///
+/// ```rust
+/// # use snowflake_connector_rs::ServerVersion;
+/// let version = ServerVersion::parse("8.12.1").unwrap();
+/// assert!(version >= ServerVersion::new(8, 5, 0));
+/// assert_eq!(version.to_string(), "8.12.1");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ServerVersion {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses a version as Snowflake reports it, e.g. by `CURRENT_VERSION()`. Components after
+    /// the third are ignored, as are letters after the digits of a component, and missing ones
+    /// are taken to be 0. `None` if the major version is not a number.
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut components = version.trim().split('.').map(|component| {
+            let digits = component
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(component, |end| &component[..end]);
+            digits.parse::<u32>().ok()
+        });
+        let major = components.next().flatten()?;
+        let minor = components.next().flatten().unwrap_or(0);
+        let patch = components.next().flatten().unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A session parameter as responses report it.
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct Parameter {
+    pub(crate) name: String,
+    pub(crate) value: Value,
+}
+
+/// The session parameters Snowflake reported at login, updated with those later responses
+/// report, shared by the clones of a session.
+#[derive(Debug, Default)]
+pub(crate) struct ServerParameters(Mutex<HashMap<String, Value>>);
+
+impl ServerParameters {
+    pub(crate) fn update(&self, parameters: impl IntoIterator<Item = Parameter>) {
+        let mut current = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        for Parameter { name, value } in parameters {
+            current.insert(name.to_ascii_uppercase(), value);
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<Value> {
+        let current = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        current.get(&name.to_ascii_uppercase()).cloned()
+    }
+
+    pub(crate) fn snapshot(&self) -> HashMap<String, Value> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_server_version() {
+        let parse = ServerVersion::parse;
+        assert_eq!(parse("8.12.1"), Some(ServerVersion::new(8, 12, 1)));
+        assert_eq!(parse(" 9.1.0b2 "), Some(ServerVersion::new(9, 1, 0)));
+        assert_eq!(parse("8.40"), Some(ServerVersion::new(8, 40, 0)));
+        assert_eq!(parse("8.1.2.3"), Some(ServerVersion::new(8, 1, 2)));
+        assert_eq!(parse("unknown"), None);
+        assert!(parse("8.9.9") < parse("8.10.0"));
+    }
+
+    #[test]
+    fn test_update_server_parameters() {
+        let parameters = ServerParameters::default();
+        let parameter = |name: &str, value| Parameter {
+            name: name.into(),
+            value,
+        };
+        parameters.update([
+            parameter("TIMEZONE", json!("UTC")),
+            parameter("AUTOCOMMIT", json!(true)),
+        ]);
+        parameters.update([parameter("timezone", json!("Asia/Tokyo"))]);
+
+        let snapshot = parameters.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot["TIMEZONE"], "Asia/Tokyo");
+        assert_eq!(snapshot["AUTOCOMMIT"], true);
+    }
+}
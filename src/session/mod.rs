@@ -1,18 +1,515 @@
+mod token;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use http::HeaderMap;
+use serde::{de::DeserializeOwned, Deserialize};
+
+pub(crate) use self::token::SessionToken;
 use crate::{
-    query::{query, QueryRequest},
-    Result, SnowflakeRow,
+    auth::renew_session,
+    chunk::{download_raw, ChunkDownloadConfig, ChunkSet},
+    metrics::ConnectorMetrics,
+    metrics::RetryKind,
+    nullability::NullabilityLint,
+    query::{query_lazy, query_raw, query_result, Polling, QueryRequest, RequestId},
+    query_tag::QueryTagger,
+    server_info::{Parameter, ServerParameters, ServerVersion},
+    sql_api::{self, SqlApi},
+    statement_log::StatementLog,
+    stats::StatsRecorder,
+    stream::{ResultLimits, TypedRowStream},
+    trace::{span, Instrument},
+    transaction::TransactionState,
+    transport::HttpClient,
+    Error, FromRow, FromTuple, QueryResult, QueryResultSet, QueryStats, Result, RowStream,
 };
+
+/// A session logged in to Snowflake, created with
+/// [`SnowflakeClient::create_session`](crate::SnowflakeClient::create_session).
+///
+/// A session is `Send + Sync` and cheap to clone, so it can be shared by tasks. Clones are the
+/// same Snowflake session: they share its token, renewed once for all of them when it expires,
+/// the HTTP connections, and the state the statements leave on the server, such as the current
+/// database and schema, session variables, temporary tables and an open transaction. The one
+/// thing each clone keeps to itself is [`SnowflakeSession::last_query_stats`].
+///
+/// Statements of clones can run concurrently, but Snowflake runs the statements of a session
+/// independently of each other: a statement that depends on another, such as a `USE` or a
+/// statement in a transaction, must wait for it to finish. Statements needing state of their
+/// own need sessions of their own.
 pub struct SnowflakeSession {
-    pub(super) http: reqwest::Client,
+    pub(super) http: HttpClient,
     pub(super) account: String,
-    pub(super) session_token: String,
+    pub(super) base_url: String,
+    pub(super) token: Arc<SessionToken>,
+    /// Set when statements run with the SQL API, without a session token.
+    pub(super) sql_api: Option<Arc<SqlApi>>,
+    pub(super) server_version: Option<ServerVersion>,
+    pub(super) server_parameters: Arc<ServerParameters>,
     pub(super) polling_interval: Option<std::time::Duration>,
-    pub(super) max_polling_attempts: Option<usize>
+    pub(super) max_polling_attempts: Option<usize>,
+    pub(super) statement_timeout: Option<std::time::Duration>,
+    pub(super) chunk_download: ChunkDownloadConfig,
+    pub(super) result_limits: ResultLimits,
+    pub(super) last_query_stats: Mutex<Option<StatsRecorder>>,
+    /// The variables set with [`SnowflakeSession::set_variable`], in upper case.
+    pub(super) variables: Arc<Mutex<Vec<String>>>,
+    pub(super) transaction: Arc<TransactionState>,
+    pub(super) metrics: Arc<dyn ConnectorMetrics>,
+    pub(super) statement_log: Option<StatementLog>,
+    pub(crate) query_tagger: Option<Arc<dyn QueryTagger>>,
+    pub(crate) strict_nullability: bool,
+    pub(crate) nullability_lint: Option<Arc<dyn NullabilityLint>>,
+    pub(super) warehouse_resume: Option<WarehouseResume>,
+    #[cfg(feature = "tracing")]
+    pub(super) trace_sql: bool,
+}
+
+/// The first wait before sending a statement again while its warehouse resumes, and the
+/// longest.
+const WAREHOUSE_RESUME_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_WAREHOUSE_RESUME_BACKOFF: Duration = Duration::from_secs(16);
+
+/// How statements are sent again while their warehouse resumes; see
+/// [`retry_on_warehouse_resume`](crate::SnowflakeClientConfig::retry_on_warehouse_resume).
+#[derive(Debug, Clone)]
+pub(crate) struct WarehouseResume {
+    pub(crate) max_wait: Duration,
+    /// The warehouse of the configuration, to name when the error of the statement does not.
+    pub(crate) warehouse: Option<String>,
+}
+
+/// Shows the session with only the start and length of its token.
+impl fmt::Debug for SnowflakeSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnowflakeSession")
+            .field("account", &self.account)
+            .field(
+                "session_token",
+                &redact_token(self.token.session_token().expose_secret()),
+            )
+            .field("polling_interval", &self.polling_interval)
+            .field("max_polling_attempts", &self.max_polling_attempts)
+            .field("statement_timeout", &self.statement_timeout)
+            .field("chunk_download", &self.chunk_download)
+            .field("result_limits", &self.result_limits)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Clones share everything but the statistics of the last query, which start out empty.
+impl Clone for SnowflakeSession {
+    fn clone(&self) -> Self {
+        Self {
+            http: self.http.clone(),
+            account: self.account.clone(),
+            base_url: self.base_url.clone(),
+            token: Arc::clone(&self.token),
+            sql_api: self.sql_api.clone(),
+            server_version: self.server_version,
+            server_parameters: Arc::clone(&self.server_parameters),
+            polling_interval: self.polling_interval,
+            max_polling_attempts: self.max_polling_attempts,
+            statement_timeout: self.statement_timeout,
+            chunk_download: self.chunk_download.clone(),
+            result_limits: self.result_limits,
+            last_query_stats: Mutex::new(None),
+            variables: Arc::clone(&self.variables),
+            transaction: Arc::clone(&self.transaction),
+            metrics: Arc::clone(&self.metrics),
+            statement_log: self.statement_log.clone(),
+            query_tagger: self.query_tagger.clone(),
+            strict_nullability: self.strict_nullability,
+            nullability_lint: self.nullability_lint.clone(),
+            warehouse_resume: self.warehouse_resume.clone(),
+            #[cfg(feature = "tracing")]
+            trace_sql: self.trace_sql,
+        }
+    }
+}
+
+/// The chunks at `urls` of a result returned by [`SnowflakeSession::query_raw`], with the
+/// headers from its `chunkHeaders` or `qrmk`.
+pub(crate) fn raw_chunk_set(data: &serde_json::Value, urls: Vec<String>) -> Result<ChunkSet> {
+    let headers = Option::<HashMap<String, String>>::deserialize(&data["chunkHeaders"])
+        .map_err(|e| Error::Json(e, data["chunkHeaders"].to_string()))?
+        .unwrap_or_default();
+    ChunkSet::new(
+        urls,
+        vec![],
+        HeaderMap::try_from(&headers)?,
+        data["qrmk"].as_str(),
+    )
+}
+
+/// Shows the first characters of a token, enough to tell tokens apart, and its length.
+fn redact_token(token: &str) -> String {
+    let prefix = token.chars().take(4).collect::<String>();
+    format!("{prefix}*** ({} chars)", token.chars().count())
 }
 
 impl SnowflakeSession {
-    pub async fn query<Q: Into<QueryRequest>>(&self, request: Q) -> Result<Vec<SnowflakeRow>> {
-        let rows = query(&self.http, &self.account, request, &self.session_token, self.polling_interval, self.max_polling_attempts).await?;
-        Ok(rows)
+    pub async fn query<Q: Into<QueryRequest>>(&self, request: Q) -> Result<QueryResult> {
+        self.query_lazy(request).await?.fetch_all().await
+    }
+
+    /// Runs a query and returns its rows as a [`RowStream`], which downloads the result chunks
+    /// as they are read instead of holding the whole result in memory. At most
+    /// [`chunk_prefetch`](crate::SnowflakeClientConfig::chunk_prefetch) chunks are downloaded
+    /// ahead of the reader.
+    ///
+    /// If a chunk still fails after
+    /// [`max_chunk_download_attempts`](crate::SnowflakeClientConfig::max_chunk_download_attempts),
+    /// the stream yields [`Error::ChunkFailed`](crate::Error::ChunkFailed) with the number of
+    /// rows already returned.
+    pub async fn query_stream<Q: Into<QueryRequest>>(&self, request: Q) -> Result<RowStream> {
+        Ok(self.query_lazy(request).await?.stream())
+    }
+
+    /// Runs a query and returns its result without downloading the result chunks, so that its
+    /// size can be checked first. The rows are read with [`QueryResultSet::fetch_all`] or
+    /// [`QueryResultSet::stream`].
+    pub async fn query_lazy<Q: Into<QueryRequest>>(&self, request: Q) -> Result<QueryResultSet> {
+        let request = self.tag(request.into());
+        let span = span!(
+            "snowflake.query",
+            query_id = tracing::field::Empty,
+            statement_type = tracing::field::Empty,
+            row_count = tracing::field::Empty,
+            chunk_count = tracing::field::Empty,
+            total_bytes = tracing::field::Empty,
+            sql = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        if self.trace_sql {
+            span.record("sql", request.sql_text.as_str());
+        }
+        *self.last_stats() = None;
+        let polling = self.polling(&request);
+        self.metrics.on_query_start();
+        let start = Instant::now();
+        let statement = &request;
+        let result = match &self.sql_api {
+            Some(api) => {
+                self.resuming(|request_id| {
+                    sql_api::query_lazy(
+                        &self.http,
+                        api,
+                        statement,
+                        request_id,
+                        polling,
+                        self.chunk_download.clone(),
+                    )
+                })
+                .instrument(span)
+                .await
+            }
+            None => {
+                self.resuming(|request_id| {
+                    self.renewing(move || {
+                        query_lazy(
+                            &self.http,
+                            &self.base_url,
+                            statement,
+                            request_id,
+                            &self.token,
+                            polling,
+                            self.chunk_download.clone(),
+                        )
+                    })
+                })
+                .instrument(span)
+                .await
+            }
+        };
+        let result = result.map_err(|e| e.into_statement_timeout(start.elapsed()));
+        self.metrics
+            .on_query_finish(start.elapsed(), result.as_ref().err());
+        if let Some(log) = &self.statement_log {
+            match &result {
+                Ok(result) => log.log(&request, Some(&result.stats().query_id), true),
+                Err(e) => log.log(&request, e.query_id(), false),
+            }
+        }
+        let mut result = result?;
+        *result.stream.result_limits_mut() = self.result_limits;
+        self.check_nullability(request.strict_nullability, &mut result);
+        *self.last_stats() = Some(result.stream.stats_recorder().clone());
+        self.server_parameters
+            .update(std::mem::take(&mut result.parameters));
+        Ok(result)
+    }
+
+    /// Runs a query and returns the `data` of its final response, once the query has finished,
+    /// as parsed JSON, without reading the rows from it.
+    ///
+    /// This is a low-level API, an escape hatch for statements whose results the connector does
+    /// not model: the shape of the data is Snowflake's and may change between releases. A
+    /// failed query fails as with [`SnowflakeSession::query`], and an expired session token is
+    /// renewed the same way. The chunks of the result are downloaded with
+    /// [`SnowflakeSession::fetch_raw_chunk`]. Not supported with the SQL API.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let data = session.query_raw("SHOW WAREHOUSES").await?;
+    /// for column in data["rowtype"].as_array().into_iter().flatten() {
+    ///     println!("{}", column["name"]);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_raw<Q: Into<QueryRequest>>(&self, request: Q) -> Result<serde_json::Value> {
+        if self.sql_api.is_some() {
+            return Err(Error::Unsupported("query_raw with the SQL API".into()));
+        }
+        let request = self.tag(request.into());
+        let polling = self.polling(&request);
+        self.metrics.on_query_start();
+        let start = Instant::now();
+        let statement = &request;
+        let result = self
+            .resuming(|request_id| {
+                self.renewing(move || {
+                    let (http, base_url, token) = (&self.http, &self.base_url, &self.token);
+                    query_raw(http, base_url, statement, request_id, token, polling)
+                })
+            })
+            .instrument(span!("snowflake.query"))
+            .await
+            .map_err(|e| e.into_statement_timeout(start.elapsed()));
+        self.metrics
+            .on_query_finish(start.elapsed(), result.as_ref().err());
+        if let Some(log) = &self.statement_log {
+            match &result {
+                Ok(data) => log.log(&request, data["queryId"].as_str(), true),
+                Err(e) => log.log(&request, e.query_id(), false),
+            }
+        }
+        let data = result?;
+        if let Ok(parameters) = Vec::<Parameter>::deserialize(&data["parameters"]) {
+            self.server_parameters.update(parameters);
+        }
+        Ok(data)
+    }
+
+    /// Downloads chunk `index` of a result returned by [`SnowflakeSession::query_raw`], with
+    /// the URL in its `chunks` and the headers from its `chunkHeaders` or `qrmk`, and returns
+    /// the body decompressed. For a JSON result, that is the rows of the chunk as JSON arrays
+    /// separated by commas, without enclosing brackets.
+    ///
+    /// Chunk URLs expire some time after the query; they are not refreshed here.
+    pub async fn fetch_raw_chunk(&self, data: &serde_json::Value, index: usize) -> Result<Vec<u8>> {
+        let url = data["chunks"][index]["url"]
+            .as_str()
+            .ok_or_else(|| Error::Communication(format!("the result has no chunk {index}")))?;
+        let chunks = raw_chunk_set(data, vec![url.to_string()])?;
+        download_raw(&self.chunk_download, &chunks, 0).await
+    }
+
+    /// How a query is waited for, with its statement timeout or the session's.
+    fn polling(&self, request: &QueryRequest) -> Polling {
+        Polling {
+            interval: self.polling_interval,
+            max_attempts: self.max_polling_attempts,
+            deadline: request
+                .parameters
+                .statement_timeout
+                .or(self.statement_timeout),
+        }
+    }
+
+    /// Fetches the result of a finished query of this session again, without downloading its
+    /// chunks yet.
+    pub(crate) async fn query_result(&self, query_id: &str) -> Result<QueryResultSet> {
+        let mut result = match &self.sql_api {
+            Some(api) => {
+                sql_api::query_result(&self.http, api, query_id, self.chunk_download.clone())
+                    .await?
+            }
+            None => {
+                self.renewing(|| {
+                    query_result(
+                        &self.http,
+                        &self.base_url,
+                        query_id,
+                        &self.token,
+                        self.chunk_download.clone(),
+                    )
+                })
+                .await?
+            }
+        };
+        *result.stream.result_limits_mut() = self.result_limits;
+        self.check_nullability(None, &mut result);
+        Ok(result)
+    }
+
+    /// Runs a request, and once more if the session token had expired, after renewing it.
+    async fn renewing<T, F, Fut>(&self, request: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let session_token = self.token.session_token();
+        match request().await {
+            Err(Error::SessionExpired { .. }) => {
+                self.renew_expired(session_token.expose_secret()).await?;
+                request().await
+            }
+            result => result,
+        }
+    }
+
+    /// Runs a statement, and again with the same request ID while its warehouse resumes, if
+    /// [`retry_on_warehouse_resume`](crate::SnowflakeClientConfig::retry_on_warehouse_resume)
+    /// is on, waiting twice as long each time.
+    async fn resuming<T, F, Fut>(&self, statement: F) -> Result<T>
+    where
+        F: Fn(RequestId) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut request_id = RequestId::new();
+        let Some(resume) = &self.warehouse_resume else {
+            return statement(request_id).await;
+        };
+        let start = Instant::now();
+        let mut backoff = WAREHOUSE_RESUME_BACKOFF;
+        loop {
+            let error = match statement(request_id).await {
+                Err(e) if e.is_warehouse_resuming() => e,
+                result => return result,
+            };
+            let waited = start.elapsed();
+            if waited + backoff > resume.max_wait {
+                return Err(Error::WarehouseUnavailable {
+                    warehouse: error.warehouse_name().or_else(|| resume.warehouse.clone()),
+                    waited,
+                    source: Box::new(error),
+                });
+            }
+            self.metrics.on_retry(RetryKind::WarehouseResume);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_WAREHOUSE_RESUME_BACKOFF);
+            request_id = request_id.retry();
+        }
+    }
+
+    /// Renews the session token now rather than when it expires. The clones of the session
+    /// use the new token as well.
+    ///
+    /// Statements renew the token themselves when it has expired, so there is rarely a need to
+    /// call this. Sessions of the SQL API have no session token, and nothing is done.
+    pub async fn renew(&self) -> Result<()> {
+        match self.sql_api {
+            Some(_) => Ok(()),
+            None => {
+                self.renew_expired(self.token.session_token().expose_secret())
+                    .await
+            }
+        }
+    }
+
+    async fn renew_expired(&self, session_token: &str) -> Result<()> {
+        self.token
+            .renew(session_token, |tokens| async move {
+                renew_session(&self.http, &self.base_url, &tokens).await
+            })
+            .instrument(span!("snowflake.renew", account = %self.account))
+            .await
+    }
+
+    /// Returns the statistics of the last query that succeeded on this session, with the
+    /// download measurements of its result so far, or `None` if the last query failed or none
+    /// has been run.
+    pub fn last_query_stats(&self) -> Option<QueryStats> {
+        self.last_stats().as_ref().map(StatsRecorder::snapshot)
+    }
+
+    /// The version of Snowflake the session is connected to, as the login response reported
+    /// it. `None` if it did not, and for sessions of the SQL API, which do not log in.
+    pub fn server_version(&self) -> Option<ServerVersion> {
+        self.server_version
+    }
+
+    /// The session parameters Snowflake reported at login, by upper-case name, kept up to date
+    /// with the changes the responses to later statements of the session or its clones report,
+    /// e.g. after an `ALTER SESSION`. Empty for sessions of the SQL API.
+    pub fn server_parameters(&self) -> HashMap<String, serde_json::Value> {
+        self.server_parameters.snapshot()
+    }
+
+    fn last_stats(&self) -> MutexGuard<'_, Option<StatsRecorder>> {
+        self.last_query_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs a query and deserializes every row into `T`. See [`SnowflakeRow::deserialize`] for
+    /// how columns are mapped.
+    pub async fn query_as<T: DeserializeOwned>(
+        &self,
+        request: impl Into<QueryRequest>,
+    ) -> Result<Vec<T>> {
+        let rows = self.query(request).await?;
+        rows.iter().map(|row| row.deserialize()).collect()
+    }
+
+    /// Runs a query and builds a `T` from every row with [`FromRow`].
+    pub async fn query_typed<T: FromRow>(
+        &self,
+        request: impl Into<QueryRequest>,
+    ) -> Result<Vec<T>> {
+        let rows = self.query(request).await?;
+        rows.iter().map(T::from_row).collect()
+    }
+
+    /// Runs a query and decodes the columns of every row by position into a tuple, as
+    /// [`SnowflakeRow::get_tuple`](crate::SnowflakeRow::get_tuple) does.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let names: Vec<(i64, String)> = session.query_tuples("SELECT ID, NAME FROM users").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_tuples<T: FromTuple>(
+        &self,
+        request: impl Into<QueryRequest>,
+    ) -> Result<Vec<T>> {
+        let rows = self.query(request).await?;
+        rows.iter()
+            .enumerate()
+            .map(|(i, row)| row.get_tuple().map_err(|e| e.with_row(i)))
+            .collect()
+    }
+
+    /// Runs a query and deserializes its rows into `T` as [`SnowflakeSession::query_as`] does,
+    /// but a chunk at a time as the chunks are downloaded. See [`TypedRowStream`].
+    pub async fn query_as_stream<T: DeserializeOwned>(
+        &self,
+        request: impl Into<QueryRequest>,
+    ) -> Result<TypedRowStream<T>> {
+        let rows = self.query_stream(request).await?;
+        Ok(TypedRowStream::new(rows, |row| row.deserialize()))
+    }
+
+    /// Runs a query and builds a `T` from its rows with [`FromRow`] as
+    /// [`SnowflakeSession::query_typed`] does, but a chunk at a time as the chunks are
+    /// downloaded. See [`TypedRowStream`].
+    pub async fn query_typed_stream<T: FromRow>(
+        &self,
+        request: impl Into<QueryRequest>,
+    ) -> Result<TypedRowStream<T>> {
+        let rows = self.query_stream(request).await?;
+        Ok(TypedRowStream::new(rows, T::from_row))
     }
 }
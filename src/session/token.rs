@@ -0,0 +1,95 @@
+//! The session token shared by the clones of a session.
+
+use std::{
+    future::Future,
+    sync::{PoisonError, RwLock},
+};
+
+use crate::{auth::SessionTokens, Result, SecretString};
+
+/// The current tokens of a session. When the session token expires, the first request to
+/// notice renews it and the requests failing with it at the same time wait for that renewal
+/// rather than starting their own.
+pub(crate) struct SessionToken {
+    tokens: RwLock<SessionTokens>,
+    /// Held while the tokens are renewed.
+    renewal: tokio::sync::Mutex<()>,
+}
+
+impl SessionToken {
+    pub(crate) fn new(tokens: SessionTokens) -> Self {
+        Self {
+            tokens: RwLock::new(tokens),
+            renewal: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// The session token to send with a request.
+    pub(crate) fn session_token(&self) -> SecretString {
+        self.current().session
+    }
+
+    fn current(&self) -> SessionTokens {
+        self.tokens
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Renews the tokens with `renew` unless the session token is no longer `expired`, i.e.
+    /// another request has renewed it in the meantime.
+    pub(crate) async fn renew<F, Fut>(&self, expired: &str, renew: F) -> Result<()>
+    where
+        F: FnOnce(SessionTokens) -> Fut,
+        Fut: Future<Output = Result<SessionTokens>>,
+    {
+        let _renewal = self.renewal.lock().await;
+        let current = self.current();
+        if current.session.expose_secret() != expired {
+            return Ok(());
+        }
+        let renewed = renew(current).await?;
+        *self.tokens.write().unwrap_or_else(PoisonError::into_inner) = renewed;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_renewed_once() -> Result<()> {
+        let token = Arc::new(SessionToken::new(SessionTokens {
+            session: "session-1".into(),
+            master: "master".into(),
+        }));
+        let renewals = Arc::new(AtomicUsize::new(0));
+        let tasks = (0..12).map(|_| {
+            let (token, renewals) = (Arc::clone(&token), Arc::clone(&renewals));
+            tokio::spawn(async move {
+                token
+                    .renew("session-1", |tokens| async move {
+                        let renewal = renewals.fetch_add(1, Ordering::SeqCst) + 2;
+                        tokio::task::yield_now().await;
+                        Ok(SessionTokens {
+                            session: format!("session-{renewal}").into(),
+                            ..tokens
+                        })
+                    })
+                    .await
+            })
+        });
+        for task in tasks.collect::<Vec<_>>() {
+            task.await.unwrap()?;
+        }
+        assert_eq!(renewals.load(Ordering::SeqCst), 1);
+        assert_eq!(token.session_token().expose_secret(), "session-2");
+        Ok(())
+    }
+}
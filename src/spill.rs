@@ -0,0 +1,335 @@
+//! Results written to disk, for results larger than memory that are read more than once.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{row::Columns, values::RowValues, Error, Result, RowStream, SnowflakeRow};
+
+/// Where and how much [`QueryResultSet::spill`](crate::QueryResultSet::spill) writes to disk.
+#[derive(Debug, Clone)]
+pub struct SpillConfig {
+    /// The directory in which each spilled result gets its own subdirectory. Defaults to the
+    /// system's temporary directory.
+    pub directory: PathBuf,
+
+    /// The most bytes a spilled result may take on disk. Defaults to no limit.
+    pub max_disk_bytes: Option<u64>,
+}
+
+impl Default for SpillConfig {
+    fn default() -> Self {
+        Self {
+            directory: std::env::temp_dir(),
+            max_disk_bytes: None,
+        }
+    }
+}
+
+/// A query result whose chunks have been downloaded to disk.
+///
+/// Each chunk is kept in its own file, in the parsed form, and read back one chunk at a time by
+/// [`SpilledResult::rows`], as many times as needed. The files are deleted when the result is
+/// dropped; use [`SpilledResult::close`] to delete them at a known point and see any error.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeSession, SpillConfig};
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let config = SpillConfig {
+///     max_disk_bytes: Some(50 << 30),
+///     ..Default::default()
+/// };
+/// let result = session.query_lazy("SELECT * FROM events").await?;
+/// let result = result.spill(&config).await?;
+/// let mut total = 0;
+/// for row in result.rows() {
+///     total += row?.get::<i64>("AMOUNT")?;
+/// }
+/// for row in result.rows() {
+///     let share = row?.get::<i64>("AMOUNT")? as f64 / total as f64;
+/// }
+/// result.close()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SpilledResult {
+    directory: PathBuf,
+    columns: Arc<Columns>,
+    /// The row count of each chunk file.
+    chunks: Vec<usize>,
+    disk_usage: u64,
+    closed: bool,
+}
+
+/// Downloads the chunks of `stream` into a new subdirectory of the spill directory. The
+/// subdirectory is removed again if this fails.
+pub(crate) async fn spill(mut stream: RowStream, config: &SpillConfig) -> Result<SpilledResult> {
+    let directory = config
+        .directory
+        .join(format!("snowflake-result-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).map_err(|e| with_path(e, &directory))?;
+    let mut result = SpilledResult {
+        directory,
+        columns: Arc::clone(stream.columns()),
+        chunks: vec![],
+        disk_usage: 0,
+        closed: false,
+    };
+    while let Some(values) = stream.next_values().await {
+        let values = values?;
+        let disk_usage = result.disk_usage + values.encoded_len();
+        if let Some(limit) = config.max_disk_bytes {
+            if disk_usage > limit {
+                return Err(Error::SpillLimitExceeded(limit));
+            }
+        }
+        let path = result.chunk_path(result.chunks.len());
+        let rows = values.len();
+        tokio::task::spawn_blocking(move || write_chunk(&path, &values)).await??;
+        result.chunks.push(rows);
+        result.disk_usage = disk_usage;
+    }
+    Ok(result)
+}
+
+impl SpilledResult {
+    /// The number of rows.
+    pub fn len(&self) -> usize {
+        self.chunks.iter().sum()
+    }
+
+    /// Whether the result has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the column names of the result.
+    pub fn column_names(&self) -> Vec<&str> {
+        (0..self.columns.len())
+            .map(|i| self.columns.name(i))
+            .collect()
+    }
+
+    /// The directory holding the chunk files.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The bytes the chunk files take on disk.
+    pub fn disk_usage(&self) -> u64 {
+        self.disk_usage
+    }
+
+    /// Reads the rows back from disk, in order. Only one chunk is held in memory at a time, apart
+    /// from the chunks of rows kept by the caller. A chunk that cannot be read yields an
+    /// [`Error::IO`] naming its file, after which the iterator ends.
+    pub fn rows(&self) -> SpilledRows<'_> {
+        SpilledRows {
+            result: self,
+            chunk: 0,
+            values: Arc::default(),
+            next: 0,
+            failed: false,
+        }
+    }
+
+    /// Deletes the chunk files, reporting a failure to do so, which dropping the result ignores.
+    pub fn close(mut self) -> Result<()> {
+        self.closed = true;
+        std::fs::remove_dir_all(&self.directory).map_err(|e| with_path(e, &self.directory))?;
+        Ok(())
+    }
+
+    fn chunk_path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("chunk-{index}.bin"))
+    }
+}
+
+impl Drop for SpilledResult {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = std::fs::remove_dir_all(&self.directory);
+        }
+    }
+}
+
+/// The rows of a [`SpilledResult`], read from disk; see [`SpilledResult::rows`].
+pub struct SpilledRows<'a> {
+    result: &'a SpilledResult,
+    /// The index of the next chunk file to read.
+    chunk: usize,
+    values: Arc<RowValues>,
+    next: usize,
+    failed: bool,
+}
+
+impl Iterator for SpilledRows<'_> {
+    type Item = Result<SnowflakeRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.next < self.values.len() {
+                let values = Arc::clone(&self.values);
+                let columns = Arc::clone(&self.result.columns);
+                self.next += 1;
+                return Some(Ok(SnowflakeRow::at(values, self.next - 1, columns)));
+            }
+            if self.failed || self.chunk == self.result.chunks.len() {
+                return None;
+            }
+            match read_chunk(&self.result.chunk_path(self.chunk)) {
+                Ok(values) => {
+                    self.values = Arc::new(values);
+                    self.next = 0;
+                    self.chunk += 1;
+                }
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+    }
+}
+
+fn write_chunk(path: &Path, values: &RowValues) -> io::Result<()> {
+    let write = || {
+        let mut writer = BufWriter::new(File::create(path)?);
+        values.write_to(&mut writer)?;
+        writer.flush()
+    };
+    write().map_err(|e| with_path(e, path))
+}
+
+fn read_chunk(path: &Path) -> io::Result<RowValues> {
+    let read = || RowValues::read_from(&mut BufReader::new(File::open(path)?));
+    read().map_err(|e| with_path(e, path))
+}
+
+/// Adds the path an IO error came from to its message.
+pub(crate) fn with_path(error: io::Error, path: &Path) -> io::Error {
+    io::Error::new(error.kind(), format!("{}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{stream::tests::stream, QueryResultSet};
+
+    /// A spill directory of its own, removed with everything in it when dropped.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("spill-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir(&path).unwrap();
+            Self(path)
+        }
+
+        fn config(&self, max_disk_bytes: Option<u64>) -> SpillConfig {
+            SpillConfig {
+                directory: self.0.clone(),
+                max_disk_bytes,
+            }
+        }
+
+        fn entries(&self) -> usize {
+            std::fs::read_dir(&self.0).unwrap().count()
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A result with the given inline rows followed by `chunks` chunks of one row each.
+    fn result_set(row_set: &[&str], chunks: usize) -> QueryResultSet {
+        let (stream, _) = stream(row_set, chunks);
+        QueryResultSet {
+            total_rows: row_set.len() + chunks,
+            compressed_size: 0,
+            stream,
+            transfer: None,
+            parameters: vec![],
+        }
+    }
+
+    fn values(result: &SpilledResult) -> Result<Vec<String>> {
+        result
+            .rows()
+            .map(|row| row?.get::<String>("VALUE"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_spill_and_read_back() -> Result<()> {
+        let dir = TestDir::new();
+        let result = result_set(&["a", "b"], 3).spill(&dir.config(None)).await?;
+        assert_eq!(result.len(), 5);
+        assert_eq!(result.column_names(), vec!["VALUE"]);
+        assert!(result.directory().starts_with(&dir.0));
+
+        let files = std::fs::read_dir(result.directory())?
+            .map(|entry| Ok(entry?.metadata()?.len()))
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(files.len(), 4);
+        assert_eq!(files.iter().sum::<u64>(), result.disk_usage());
+
+        // Rows can be read any number of times.
+        let expected = vec!["a", "b", "0", "1", "2"];
+        assert_eq!(values(&result)?, expected);
+        assert_eq!(values(&result)?, expected);
+
+        result.close()?;
+        assert_eq!(dir.entries(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spill_cleanup() -> Result<()> {
+        let dir = TestDir::new();
+        let result = result_set(&["a"], 2).spill(&dir.config(None)).await?;
+        assert_eq!(dir.entries(), 1);
+        drop(result);
+        assert_eq!(dir.entries(), 0);
+
+        // The files written before the budget ran out are removed.
+        let err = result_set(&["a"], 5)
+            .spill(&dir.config(Some(100)))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::SpillLimitExceeded(100)));
+        assert_eq!(dir.entries(), 0);
+
+        let empty = result_set(&[], 0).spill(&dir.config(Some(0))).await?;
+        assert!(empty.is_empty());
+        assert_eq!(values(&empty)?, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spill_read_error() -> Result<()> {
+        let dir = TestDir::new();
+        let result = result_set(&["a"], 2).spill(&dir.config(None)).await?;
+        let missing = result.chunk_path(1);
+        std::fs::remove_file(&missing)?;
+
+        let mut rows = result.rows();
+        assert_eq!(rows.next().unwrap()?.get::<String>("VALUE")?, "a");
+        let err = rows.next().unwrap().unwrap_err();
+        assert!(matches!(&err, Error::IO(e) if e.kind() == io::ErrorKind::NotFound));
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(source.to_string().contains(&missing.display().to_string()));
+        assert!(rows.next().is_none());
+
+        result.close()?;
+        assert_eq!(dir.entries(), 0);
+        Ok(())
+    }
+}
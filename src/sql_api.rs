@@ -0,0 +1,637 @@
+//! Running statements with Snowflake's SQL API, `/api/v2/statements`, rather than the endpoints
+//! of the drivers: a statement is submitted, polled by its handle while it runs, and its result
+//! read in partitions, which are downloaded as the chunks of a driver result are.
+
+use std::{sync::Arc, time::Instant};
+
+use http::{
+    header::{ACCEPT, AUTHORIZATION},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use serde_json::{json, Map, Value};
+use tokio::time::sleep;
+
+use crate::{
+    auth::{session_parameters, KeyPairJwt},
+    bind::Bindings,
+    chunk::{decode_body, ChunkDownloadConfig, ChunkFetcher, ChunkSet},
+    query::{timeout_seconds, Polling, RequestId, DEFAULT_POLLING_INTERVAL},
+    result_set::QueryResultSet,
+    row::Columns,
+    stats::{QueryStats, StatsRecorder},
+    stream::RowStream,
+    trace::{span, Instrument, Span},
+    transport::{send_accepting, HttpClient, Reply},
+    types::SnowflakeColumnType,
+    values::RowValues,
+    Error, QueryRequest, Result, SecretString, SnowflakeAuthMethod, SnowflakeClientConfig,
+    TimeoutPhase,
+};
+
+/// The statuses whose bodies describe the statement: still running (202), failed (422) or
+/// cancelled for running too long (408).
+const STATEMENT_STATUSES: [StatusCode; 3] = [
+    StatusCode::ACCEPTED,
+    StatusCode::UNPROCESSABLE_ENTITY,
+    StatusCode::REQUEST_TIMEOUT,
+];
+
+/// Which API of Snowflake a session runs its statements with; see
+/// [`SnowflakeClientConfig::query_api`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryApi {
+    /// The endpoints the Snowflake drivers use, in a session created at login. Every feature of
+    /// the connector works with them.
+    #[default]
+    Driver,
+
+    /// The public SQL API, `/api/v2/statements`, for networks that only let it through.
+    ///
+    /// No session is created: each statement runs on its own, with the warehouse, database,
+    /// schema and role of the config, so temporary tables and stages, variables, transactions
+    /// and `USE` statements do not carry over to the next statement. PUT and GET are not
+    /// supported, and so neither are the uploads, downloads, bulk loads and unloads built on
+    /// them. It authenticates with [`SnowflakeAuthMethod::KeyPair`] or
+    /// [`SnowflakeAuthMethod::OAuth`]; a password fails
+    /// [`SnowflakeClient::create_session`](crate::SnowflakeClient::create_session).
+    SqlApi,
+}
+
+/// The SQL API of an account, with the credentials and context each statement is sent with.
+pub(crate) struct SqlApi {
+    /// `https://<account>.snowflakecomputing.com/api/v2/statements`.
+    statements_url: String,
+    auth: ApiAuth,
+    /// The warehouse, database, schema and role fields of each request.
+    context: Map<String, Value>,
+    parameters: Map<String, Value>,
+}
+
+enum ApiAuth {
+    KeyPair(KeyPairJwt),
+    OAuth(SecretString),
+}
+
+impl SqlApi {
+    pub(crate) fn new(
+        username: &str,
+        auth: &SnowflakeAuthMethod,
+        config: &SnowflakeClientConfig,
+    ) -> Result<Self> {
+        let auth = match auth {
+            SnowflakeAuthMethod::Password(_) => {
+                return Err(Error::Unsupported(
+                    "the SQL API does not accept passwords; use a key pair or OAuth".into(),
+                ))
+            }
+            SnowflakeAuthMethod::KeyPair {
+                encrypted_pem,
+                password,
+            } => ApiAuth::KeyPair(KeyPairJwt::new(
+                encrypted_pem,
+                password,
+                username,
+                &config.account,
+            )?),
+            SnowflakeAuthMethod::OAuth(token) => ApiAuth::OAuth(token.clone()),
+        };
+        let mut context = Map::new();
+        for (name, value) in [
+            ("warehouse", &config.warehouse),
+            ("database", &config.database),
+            ("schema", &config.schema),
+            ("role", &config.role),
+        ] {
+            if let Some(value) = value {
+                context.insert(name.into(), json!(value));
+            }
+        }
+        let parameters = session_parameters(config)
+            .into_iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            .collect();
+        Ok(Self {
+            statements_url: format!("{}/api/v2/statements", config.base_url()),
+            auth,
+            context,
+            parameters,
+        })
+    }
+
+    /// The headers of each request: the token, its type, and that the response is JSON.
+    fn headers(&self) -> Result<HeaderMap> {
+        let (token, token_type) = match &self.auth {
+            ApiAuth::KeyPair(jwt) => (jwt.token()?, "KEYPAIR_JWT"),
+            ApiAuth::OAuth(token) => (token.expose_secret().to_string(), "OAUTH"),
+        };
+        let mut headers = HeaderMap::new();
+        let mut authorization = HeaderValue::try_from(format!("Bearer {token}"))?;
+        authorization.set_sensitive(true);
+        headers.insert(AUTHORIZATION, authorization);
+        headers.insert(
+            "X-Snowflake-Authorization-Token-Type",
+            HeaderValue::from_static(token_type),
+        );
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        Ok(headers)
+    }
+
+    /// The body submitting a statement.
+    fn body(&self, request: &QueryRequest) -> Value {
+        let mut body = self.context.clone();
+        body.insert("statement".into(), json!(request.sql_text));
+        if let Some(bindings) = &request.bindings {
+            body.insert("bindings".into(), bindings_json(bindings));
+        }
+        let statement = &request.parameters;
+        if let Some(timeout) = statement.statement_timeout {
+            body.insert("timeout".into(), json!(timeout_seconds(timeout)));
+        }
+        let mut parameters = self.parameters.clone();
+        if let Some(size) = statement.result_chunk_size_mb {
+            parameters.insert("client_result_chunk_size".into(), json!(size));
+        }
+        if let Some(rows) = statement.rows_per_result_set {
+            parameters.insert("rows_per_resultset".into(), json!(rows));
+        }
+        if let Some(tag) = &statement.query_tag {
+            parameters.insert("query_tag".into(), json!(tag));
+        }
+        if !parameters.is_empty() {
+            body.insert("parameters".into(), Value::Object(parameters));
+        }
+        Value::Object(body)
+    }
+
+    /// The URL of a partition of the result of a statement.
+    fn partition_url(&self, handle: &str, partition: usize) -> String {
+        format!("{}/{handle}?partition={partition}", self.statements_url)
+    }
+
+    /// The partitions of a result after the first, which comes with the response, with the
+    /// headers to download them with.
+    fn partitions(&self, handle: &str, row_counts: Vec<usize>) -> Result<ChunkSet> {
+        let urls = (1..=row_counts.len())
+            .map(|partition| self.partition_url(handle, partition))
+            .collect();
+        ChunkSet::new(urls, row_counts, self.headers()?, None)
+    }
+}
+
+/// Bindings as the SQL API takes them: like those of the drivers, but with one value rather
+/// than a list of one for a statement run once.
+fn bindings_json(bindings: &Bindings) -> Value {
+    let bindings = bindings.iter().map(|(position, binding)| {
+        let value = match binding.value.as_slice() {
+            [value] => json!(value),
+            values => json!(values),
+        };
+        let binding = json!({ "type": binding.bind_type, "value": value });
+        (position.clone(), binding)
+    });
+    Value::Object(bindings.collect())
+}
+
+/// Runs a statement and returns its result without downloading the partitions after the first.
+pub(crate) async fn query_lazy(
+    http: &HttpClient,
+    api: &Arc<SqlApi>,
+    request: &QueryRequest,
+    request_id: RequestId,
+    polling: Polling,
+    chunk_download: ChunkDownloadConfig,
+) -> Result<QueryResultSet> {
+    let mut url = format!("{}?requestId={}", api.statements_url, request_id.id);
+    // With `retry`, Snowflake does not run a statement again that it ran for the same request ID.
+    if request_id.retry_count > 0 {
+        url.push_str("&retry=true");
+    }
+    let request_id = request_id.id;
+    let start = Instant::now();
+    let stats = StatsRecorder::new(start);
+    let mut reply = send_accepting(
+        http,
+        http.post(url)
+            .headers(api.headers()?)
+            .json(&api.body(request)),
+        &STATEMENT_STATUSES,
+    )
+    .await?;
+
+    let polling_interval = polling.interval.unwrap_or(DEFAULT_POLLING_INTERVAL);
+    let mut attempts = 0;
+    let response = loop {
+        let response = parse_reply(&reply)?;
+        let handle = match (reply.status(), &response.statement_handle) {
+            (StatusCode::ACCEPTED, Some(handle)) => handle.clone(),
+            _ => break response,
+        };
+        if polling.expired(attempts, start.elapsed()) {
+            return Err(Error::Timeout {
+                phase: TimeoutPhase::Polling,
+                elapsed: start.elapsed(),
+                query_id: Some(handle),
+            });
+        }
+        let url = format!("{}/{handle}?requestId={request_id}", api.statements_url);
+        attempts += 1;
+        reply = async {
+            sleep(polling_interval).await;
+            let request = http.get(url).headers(api.headers()?);
+            send_accepting(http, request, &STATEMENT_STATUSES).await
+        }
+        .instrument(span!("snowflake.poll", attempt = attempts))
+        .await?;
+    };
+
+    let span = Span::current();
+    if let Some(handle) = &response.statement_handle {
+        span.record("query_id", handle.as_str());
+    }
+    let result = result_set(http, api, response, stats, chunk_download)?;
+    span.record("row_count", result.total_rows());
+    span.record("chunk_count", result.chunk_count());
+    span.record("total_bytes", result.approx_compressed_size());
+    Ok(result)
+}
+
+/// Fetches the result of a finished statement again by its handle, to read it from the start
+/// or from where a reader stopped, without downloading the partitions after the first yet.
+pub(crate) async fn query_result(
+    http: &HttpClient,
+    api: &Arc<SqlApi>,
+    handle: &str,
+    chunk_download: ChunkDownloadConfig,
+) -> Result<QueryResultSet> {
+    let stats = StatsRecorder::new(Instant::now());
+    let url = format!("{}/{handle}", api.statements_url);
+    let request = http.get(url).headers(api.headers()?);
+    let reply = send_accepting(http, request, &STATEMENT_STATUSES).await?;
+    let response = parse_reply(&reply)?;
+    if reply.status() == StatusCode::ACCEPTED {
+        return Err(Error::Communication(format!(
+            "statement {handle} is still running"
+        )));
+    }
+    result_set(http, api, response, stats, chunk_download)
+}
+
+/// Builds the result of a statement, whose partitions after the first are downloaded with a
+/// fresh token once the token they were requested with has expired.
+fn result_set(
+    http: &HttpClient,
+    api: &Arc<SqlApi>,
+    response: StatementResponse,
+    stats: StatsRecorder,
+    chunk_download: ChunkDownloadConfig,
+) -> Result<QueryResultSet> {
+    let http = http.clone();
+    let api = Arc::clone(api);
+    response.into_result_set(stats.clone(), |handle, row_counts| {
+        let row_counts = row_counts.to_vec();
+        let chunks = api.partitions(handle, row_counts.clone())?;
+        let handle = handle.to_string();
+        // The partitions are downloaded from Snowflake itself rather than from storage.
+        let chunk_download = ChunkDownloadConfig {
+            client: http.client().clone(),
+            ..chunk_download
+        };
+        let fetcher =
+            ChunkFetcher::parsing(chunks, chunk_download, stats, Arc::new(parse_partition));
+        Ok(fetcher.with_refresh(move || {
+            let (api, handle, row_counts) = (Arc::clone(&api), handle.clone(), row_counts.clone());
+            Box::pin(async move { api.partitions(&handle, row_counts) })
+        }))
+    })
+}
+
+/// Parses the response to a statement or a poll, failing with the error of a statement that
+/// failed.
+fn parse_reply(reply: &Reply) -> Result<StatementResponse> {
+    let response: StatementResponse = reply.parse()?;
+    match reply.status() {
+        StatusCode::OK | StatusCode::ACCEPTED => Ok(response),
+        _ => Err(response.into_error()),
+    }
+}
+
+/// Decodes a partition after the first, whose rows are in its `data`.
+fn parse_partition(body: &[u8], content_encoding: Option<&str>) -> Result<RowValues> {
+    #[derive(serde::Deserialize)]
+    struct Partition {
+        data: RowValues,
+    }
+
+    let mut buf = Vec::with_capacity(body.len());
+    decode_body(body, content_encoding, &mut buf)?;
+    let text = String::from_utf8(buf)?;
+    let partition: Partition = serde_json::from_str(&text).map_err(|e| Error::Json(e, text))?;
+    Ok(partition.data)
+}
+
+/// The response to a statement, or to a poll of one.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatementResponse {
+    code: Option<String>,
+    sql_state: Option<String>,
+    message: Option<String>,
+    statement_handle: Option<String>,
+    result_set_meta_data: Option<ResultSetMetaData>,
+    /// The rows of the first partition.
+    data: Option<RowValues>,
+    stats: Option<DmlStats>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultSetMetaData {
+    num_rows: Option<usize>,
+    format: Option<String>,
+    #[serde(default)]
+    partition_info: Vec<PartitionInfo>,
+    #[serde(default)]
+    row_type: Vec<RowType>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartitionInfo {
+    row_count: usize,
+    #[serde(default)]
+    uncompressed_size: u64,
+    #[serde(default)]
+    compressed_size: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct RowType {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: String,
+    scale: Option<i64>,
+    #[serde(default = "nullable_by_default")]
+    nullable: bool,
+}
+
+fn nullable_by_default() -> bool {
+    true
+}
+
+/// The rows a DML statement changed.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DmlStats {
+    num_rows_inserted: Option<u64>,
+    num_rows_updated: Option<u64>,
+    num_rows_deleted: Option<u64>,
+    num_duplicate_rows_updated: Option<u64>,
+}
+
+impl StatementResponse {
+    /// The error of a failed statement: the error for its code, as [`Error::from_code`]
+    /// classifies them, or [`Error::Communication`] with its message without a numeric code.
+    fn into_error(self) -> Error {
+        let message = self.message.unwrap_or_default();
+        let Some(Ok(code)) = self.code.as_deref().map(str::parse) else {
+            return Error::Communication(message);
+        };
+        Error::from_code(
+            code,
+            self.sql_state.unwrap_or_default(),
+            message,
+            self.statement_handle,
+        )
+    }
+
+    /// Builds the result of a statement, with `fetcher` creating the fetcher of the partitions
+    /// after the first from the statement handle and their row counts, and records its
+    /// statistics in `stats`.
+    fn into_result_set(
+        self,
+        stats: StatsRecorder,
+        fetcher: impl FnOnce(&str, &[usize]) -> Result<ChunkFetcher>,
+    ) -> Result<QueryResultSet> {
+        let meta = self.result_set_meta_data.ok_or_else(|| {
+            Error::Communication("the statement response has no result set metadata".into())
+        })?;
+        if let Some(format) = meta.format.filter(|format| format != "jsonv2") {
+            return Err(Error::UnsupportedFormat(format));
+        }
+        let handle = self.statement_handle.unwrap_or_default();
+        let columns = meta
+            .row_type
+            .into_iter()
+            .map(|row_type| {
+                let column_type = SnowflakeColumnType::new(&row_type.data_type, row_type.scale)
+                    .with_nullable(row_type.nullable);
+                (row_type.name, column_type)
+            })
+            .collect();
+        let row_set = self.data.unwrap_or_default();
+        let partitions = meta.partition_info.get(1..).unwrap_or_default();
+        let row_counts = partitions.iter().map(|p| p.row_count).collect::<Vec<_>>();
+        let compressed_size = partitions.iter().map(|p| p.compressed_size).sum();
+        let uncompressed_size = partitions.iter().map(|p| p.uncompressed_size).sum();
+        let dml = self.stats.as_ref();
+        stats.record_response(QueryStats {
+            query_id: handle.clone(),
+            total_rows: meta.num_rows,
+            returned_rows: Some(row_set.len()),
+            rows_inserted: dml.and_then(|dml| dml.num_rows_inserted),
+            rows_updated: dml.and_then(|dml| dml.num_rows_updated),
+            rows_deleted: dml.and_then(|dml| dml.num_rows_deleted),
+            dml_duplicates: dml.and_then(|dml| dml.num_duplicate_rows_updated),
+            chunk_count: partitions.len(),
+            compressed_bytes: compressed_size,
+            uncompressed_bytes: uncompressed_size,
+            ..Default::default()
+        });
+        let total_rows = meta
+            .num_rows
+            .unwrap_or(row_set.len() + row_counts.iter().sum::<usize>());
+        let fetcher = fetcher(&handle, &row_counts)?;
+        Ok(QueryResultSet {
+            total_rows,
+            compressed_size,
+            stream: RowStream::new(
+                Arc::new(Columns::new(columns)),
+                row_set,
+                fetcher,
+                meta.num_rows,
+                uncompressed_size,
+                stats,
+            ),
+            transfer: None,
+            parameters: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::{bind::BindValue, chunk::tests::fake_fetcher, GeoOutputFormat};
+
+    fn sql_api(auth: SnowflakeAuthMethod) -> Result<SqlApi> {
+        let config = SnowflakeClientConfig {
+            account: "myaccount".into(),
+            warehouse: Some("WH".into()),
+            role: Some("ANALYST".into()),
+            geo_output_format: Some(GeoOutputFormat::GeoJson),
+            default_statement_timeout: Some(std::time::Duration::from_secs(900)),
+            result_chunk_size_mb: Some(64),
+            query_api: QueryApi::SqlApi,
+            ..Default::default()
+        };
+        SqlApi::new("user", &auth, &config)
+    }
+
+    #[test]
+    fn test_statement_request() -> Result<()> {
+        let api = sql_api(SnowflakeAuthMethod::OAuth("oauth-token".into()))?;
+        let headers = api.headers()?;
+        assert_eq!(headers[AUTHORIZATION], "Bearer oauth-token");
+        assert_eq!(headers["X-Snowflake-Authorization-Token-Type"], "OAUTH");
+
+        let mut request = QueryRequest::from("INSERT INTO t VALUES (?, ?)")
+            .statement_timeout(std::time::Duration::from_millis(1500))
+            .rows_per_result_set(1000)
+            .query_tag("nightly");
+        let text = |value: &str| BindValue {
+            bind_type: crate::bind::BindType::Text,
+            value: Some(value.into()),
+        };
+        request.bindings = Some(Bindings::from([
+            ("1".to_string(), crate::bind::Binding::many(vec![text("a")])),
+            (
+                "2".to_string(),
+                crate::bind::Binding::many(vec![text("b"), text("c")]),
+            ),
+        ]));
+        assert_eq!(
+            api.body(&request),
+            json!({
+                "statement": "INSERT INTO t VALUES (?, ?)",
+                "warehouse": "WH",
+                "role": "ANALYST",
+                "bindings": {
+                    "1": {"type": "TEXT", "value": "a"},
+                    "2": {"type": "TEXT", "value": ["b", "c"]},
+                },
+                "timeout": 2,
+                "parameters": {
+                    "geography_output_format": "GeoJSON",
+                    "geometry_output_format": "GeoJSON",
+                    "statement_timeout_in_seconds": 900,
+                    "client_result_chunk_size": 64,
+                    "rows_per_resultset": 1000,
+                    "query_tag": "nightly",
+                },
+            })
+        );
+        assert_eq!(
+            api.partition_url("01b2-handle", 3),
+            "https://myaccount.snowflakecomputing.com/api/v2/statements/01b2-handle?partition=3"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_authentication() -> Result<()> {
+        let key_pair = SnowflakeAuthMethod::KeyPair {
+            encrypted_pem: include_str!("auth/test_snowflake_key.p8").into(),
+            password: b"12345".to_vec().into(),
+        };
+        let headers = sql_api(key_pair)?.headers()?;
+        assert!(headers[AUTHORIZATION]
+            .to_str()
+            .unwrap()
+            .starts_with("Bearer ey"));
+        assert_eq!(
+            headers["X-Snowflake-Authorization-Token-Type"],
+            "KEYPAIR_JWT"
+        );
+
+        let password = sql_api(SnowflakeAuthMethod::Password("secret".into()));
+        assert!(matches!(password, Err(Error::Unsupported(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_errors() {
+        let response = |body: &str| serde_json::from_str::<StatementResponse>(body).unwrap();
+        let error = response(
+            r#"{"code":"002003","sqlState":"02000","message":"SQL compilation error: Object 'T' does not exist.","statementHandle":"01b2-handle"}"#,
+        )
+        .into_error();
+        assert!(matches!(
+            &error,
+            Error::Sql { code: 2003, sqlstate, query_id: Some(handle), .. }
+                if sqlstate == "02000" && handle == "01b2-handle"
+        ));
+
+        let error = response(r#"{"code":"390114","message":"Authentication token has expired."}"#)
+            .into_error();
+        assert!(matches!(
+            error,
+            Error::AuthTokenExpired { code: 390114, .. }
+        ));
+
+        let error = response(r#"{"message":"Unable to parse the request."}"#).into_error();
+        assert!(matches!(error, Error::Communication(message) if message.contains("parse")));
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_result() -> Result<()> {
+        let response: StatementResponse = serde_json::from_str(
+            r#"{"code":"090001","sqlState":"00000","message":"Statement executed successfully.","statementHandle":"01b2-handle","resultSetMetaData":{"numRows":3,"format":"jsonv2","partitionInfo":[{"rowCount":1,"uncompressedSize":10},{"rowCount":1,"uncompressedSize":12,"compressedSize":30},{"rowCount":1,"uncompressedSize":12,"compressedSize":31}],"rowType":[{"name":"VALUE","type":"text","scale":null,"nullable":true}]},"data":[["a"]]}"#,
+        )
+        .unwrap();
+        let stats = StatsRecorder::default();
+        let result = response.into_result_set(stats.clone(), |handle, row_counts| {
+            assert_eq!((handle, row_counts), ("01b2-handle", &[1, 1][..]));
+            Ok(fake_fetcher(row_counts.len(), 2).0)
+        })?;
+        assert_eq!(result.total_rows(), 3);
+        assert_eq!(result.chunk_count(), 2);
+        assert_eq!(result.approx_compressed_size(), 61);
+        let values = result
+            .fetch_all()
+            .await?
+            .iter()
+            .map(|row| row.get::<String>("VALUE"))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(values, ["a", "0", "1"]);
+
+        let stats = stats.snapshot();
+        assert_eq!(stats.query_id, "01b2-handle");
+        assert_eq!((stats.total_rows, stats.returned_rows), (Some(3), Some(1)));
+        assert_eq!(stats.uncompressed_bytes, 24);
+
+        let response: StatementResponse = serde_json::from_str(
+            r#"{"statementHandle":"01b2-handle","resultSetMetaData":{"format":"arrowv1"}}"#,
+        )
+        .unwrap();
+        let result = response.into_result_set(StatsRecorder::default(), |_, _| unreachable!());
+        assert!(matches!(result, Err(Error::UnsupportedFormat(format)) if format == "arrowv1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_partition() -> Result<()> {
+        let body = br#"{"data":[["1",null],["2","b"]]}"#;
+        let expected = RowValues::from_rows(vec![
+            vec![Some("1".to_string()), None],
+            vec![Some("2".to_string()), Some("b".to_string())],
+        ]);
+        assert_eq!(parse_partition(body, None)?, expected);
+
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        gzip.write_all(body)?;
+        assert_eq!(parse_partition(&gzip.finish()?, Some("gzip"))?, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+//! Creating, listing and cleaning up stages, the file areas that bulk loads and unloads go
+//! through.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    catalog::{optional_text, parse_object_name, quote_object_parts},
+    Error, FromRow, Result, SnowflakeRow, SnowflakeSession,
+};
+
+/// A file on a stage, as listed by [`SnowflakeSession::list_stage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StagedFile {
+    /// The path of the file, starting with the stage, e.g. `load_stage/2024/data_0.csv.gz`.
+    pub name: String,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// The MD5 digest of the file, in lowercase hex; `None` where the storage service does not
+    /// report one, e.g. for objects an external stage's bucket got uploaded in parts.
+    pub md5: Option<String>,
+    /// When the file was last written.
+    pub last_modified: DateTime<Utc>,
+}
+
+/// The outcome for one file of [`SnowflakeSession::remove_from_stage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RemoveResult {
+    /// The path of the file, starting with the stage.
+    pub name: String,
+    /// Whether the file was removed.
+    pub removed: bool,
+}
+
+impl SnowflakeSession {
+    /// Creates a temporary stage, which is dropped when the session ends, and returns its
+    /// quoted, fully qualified name, e.g. `"DB"."PUBLIC"."LOAD_STAGE"`, to refer to it as
+    /// `@"DB"."PUBLIC"."LOAD_STAGE"`.
+    ///
+    /// `name` is written as in SQL, optionally qualified with its schema and database; an
+    /// unqualified name is created in the current schema. `file_format` holds the options of
+    /// the stage's `FILE_FORMAT`, e.g. `TYPE = CSV FIELD_OPTIONALLY_ENCLOSED_BY = '"'`; `None`
+    /// keeps Snowflake's default.
+    pub async fn create_temp_stage(&self, name: &str, file_format: Option<&str>) -> Result<String> {
+        let mut parts = parse_object_name(name)?;
+        let mut sql = format!("CREATE TEMPORARY STAGE {}", quote_object_parts(&parts));
+        if let Some(file_format) = file_format {
+            sql.push_str(&format!(" FILE_FORMAT = ({file_format})"));
+        }
+        self.query(sql).await?;
+        if parts.len() < 3 {
+            let rows = self
+                .query("SELECT CURRENT_DATABASE(), CURRENT_SCHEMA()")
+                .await?;
+            let row = rows
+                .first()
+                .ok_or_else(|| Error::decode("the current schema query returned no rows"))?;
+            let current = [row.get_index::<String>(0)?, row.get_index::<String>(1)?];
+            parts.splice(0..0, current[..3 - parts.len()].iter().cloned());
+        }
+        Ok(quote_object_parts(&parts))
+    }
+
+    /// Lists the files under a stage path written as in SQL, e.g. `@load_stage/2024/` or
+    /// `@~`. A path with spaces or other characters SQL cannot take unquoted, e.g.
+    /// `@load_stage/my files/`, is quoted; a path already in single quotes is kept as it is.
+    pub async fn list_stage(&self, path: &str) -> Result<Vec<StagedFile>> {
+        self.query_typed(list_statement(path, None)).await
+    }
+
+    /// Lists the files under a stage path, as [`SnowflakeSession::list_stage`] does, whose
+    /// paths match the regular expression `pattern`, e.g. `.*[.]csv[.]gz`.
+    pub async fn list_stage_matching(&self, path: &str, pattern: &str) -> Result<Vec<StagedFile>> {
+        self.query_typed(list_statement(path, Some(pattern))).await
+    }
+
+    /// Removes the files under a stage path written as in SQL, e.g. `@load_stage/2024/`, and
+    /// returns what happened to each. The path is quoted as for
+    /// [`SnowflakeSession::list_stage`].
+    pub async fn remove_from_stage(&self, path: &str) -> Result<Vec<RemoveResult>> {
+        self.query_typed(remove_statement(path)).await
+    }
+}
+
+fn remove_statement(path: &str) -> String {
+    format!("REMOVE {}", stage_location(path))
+}
+
+fn list_statement(path: &str, pattern: Option<&str>) -> String {
+    let mut sql = format!("LIST {}", stage_location(path));
+    if let Some(pattern) = pattern {
+        sql.push_str(&format!(" PATTERN = '{}'", pattern.replace('\'', "''")));
+    }
+    sql
+}
+
+/// A stage path as SQL takes it: in single quotes if it has characters that cannot stand
+/// unquoted. A path that is already one string literal is kept as it is; anything else
+/// starting with a quote is quoted whole, so it cannot end the literal early.
+fn stage_location(path: &str) -> String {
+    let path = path.trim();
+    let unquoted = |c: char| c.is_ascii_alphanumeric() || "_$./@~%-=\"".contains(c);
+    if is_string_literal(path) || path.chars().all(unquoted) {
+        return path.to_string();
+    }
+    format!("'{}'", path.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// Whether `text` is one single-quoted string literal, with its quotes doubled.
+fn is_string_literal(text: &str) -> bool {
+    match text
+        .strip_prefix('\'')
+        .and_then(|text| text.strip_suffix('\''))
+    {
+        Some(inner) => {
+            let inner = inner.replace("''", "");
+            !inner.contains('\'') && !inner.ends_with('\\')
+        }
+        None => false,
+    }
+}
+
+/// Parses the `last_modified` of a `LIST` result, e.g. `Wed, 5 Jun 2024 12:34:56 GMT`. Some
+/// external stages name the zone `UTC`, which RFC 2822 does not know.
+fn parse_last_modified(value: &str) -> Result<DateTime<Utc>> {
+    let rfc2822 = match value.strip_suffix(" UTC") {
+        Some(time) => format!("{time} GMT"),
+        None => value.to_string(),
+    };
+    DateTime::parse_from_rfc2822(&rfc2822)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| Error::decode(format!("invalid last_modified {value:?}: {e}")))
+}
+
+/// The `md5` of a `LIST` result, if it is one. External stages report the ETag of the object
+/// instead, quoted on some, which is not the MD5 digest of objects uploaded in parts.
+fn parse_md5(value: Option<String>) -> Option<String> {
+    let value = value?;
+    let digest = value.trim_matches('"');
+    let is_md5 = digest.len() == 32 && digest.chars().all(|c| c.is_ascii_hexdigit());
+    is_md5.then(|| digest.to_ascii_lowercase())
+}
+
+impl FromRow for StagedFile {
+    fn from_row(row: &SnowflakeRow) -> Result<Self> {
+        Ok(Self {
+            name: row.get("name")?,
+            size: row.get("size")?,
+            md5: parse_md5(optional_text(row, "md5")?),
+            last_modified: parse_last_modified(&row.get::<String>("last_modified")?)?,
+        })
+    }
+}
+
+impl FromRow for RemoveResult {
+    fn from_row(row: &SnowflakeRow) -> Result<Self> {
+        let result: String = row.get("result")?;
+        Ok(Self {
+            name: row.get("name")?,
+            removed: result.eq_ignore_ascii_case("removed"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::row::tests::typed_row;
+
+    #[test]
+    fn test_list_rows() -> Result<()> {
+        let file = StagedFile::from_row(&typed_row(&[
+            ("name", "text", Some("load_stage/2024/data_0.csv.gz")),
+            ("size", "fixed", Some("1048576")),
+            ("md5", "text", Some("0f343b0931126a20f133d67c2b018a3b")),
+            (
+                "last_modified",
+                "text",
+                Some("Wed, 5 Jun 2024 12:34:56 GMT"),
+            ),
+        ]))?;
+        assert_eq!(file.name, "load_stage/2024/data_0.csv.gz");
+        assert_eq!(file.size, 1_048_576);
+        assert_eq!(
+            file.md5.as_deref(),
+            Some("0f343b0931126a20f133d67c2b018a3b")
+        );
+        assert_eq!(
+            file.last_modified,
+            Utc.with_ymd_and_hms(2024, 6, 5, 12, 34, 56).unwrap()
+        );
+
+        let file = StagedFile::from_row(&typed_row(&[
+            ("name", "text", Some("s3://bucket/data.parquet")),
+            ("size", "fixed", Some("10")),
+            ("md5", "text", None),
+            (
+                "last_modified",
+                "text",
+                Some("Mon, 30 Dec 2024 01:02:03 GMT"),
+            ),
+        ]))?;
+        assert_eq!(file.md5, None);
+
+        let file = StagedFile::from_row(&typed_row(&[
+            ("name", "text", Some("gcs://bucket/part-0.csv")),
+            ("size", "fixed", Some("10")),
+            ("md5", "text", Some("\"0F343B0931126A20F133D67C2B018A3B\"")),
+            (
+                "last_modified",
+                "text",
+                Some("Fri, 7 Jun 2024 08:00:00 UTC"),
+            ),
+        ]))?;
+        assert_eq!(
+            file.md5.as_deref(),
+            Some("0f343b0931126a20f133d67c2b018a3b")
+        );
+        assert_eq!(
+            file.last_modified,
+            Utc.with_ymd_and_hms(2024, 6, 7, 8, 0, 0).unwrap()
+        );
+        let multipart = Some("\"d41d8cd98f00b204e9800998ecf8427e-3\"".to_string());
+        assert_eq!(parse_md5(multipart), None);
+
+        let invalid = StagedFile::from_row(&typed_row(&[
+            ("name", "text", Some("a")),
+            ("size", "fixed", Some("1")),
+            ("md5", "text", None),
+            ("last_modified", "text", Some("2024-06-05")),
+        ]));
+        assert!(matches!(invalid, Err(Error::Decode(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_statement() {
+        assert_eq!(
+            list_statement("@load_stage/2024/", None),
+            "LIST @load_stage/2024/"
+        );
+        assert_eq!(list_statement(" @~ ", None), "LIST @~");
+        assert_eq!(
+            list_statement(r#"@"DB"."PUBLIC"."My-Stage"/a=1/"#, None),
+            r#"LIST @"DB"."PUBLIC"."My-Stage"/a=1/"#
+        );
+        assert_eq!(
+            list_statement("@load_stage/it's a dir\\/", Some(".*'s[.]csv")),
+            r"LIST '@load_stage/it''s a dir\\/' PATTERN = '.*''s[.]csv'"
+        );
+        assert_eq!(
+            list_statement("'@load_stage/my files/'", None),
+            "LIST '@load_stage/my files/'"
+        );
+        assert_eq!(
+            list_statement("'@s/'; DROP TABLE t; --'", None),
+            "LIST '''@s/''; DROP TABLE t; --'''"
+        );
+    }
+
+    #[test]
+    fn test_remove_statement() {
+        assert_eq!(
+            remove_statement("@load_stage/2024/"),
+            "REMOVE @load_stage/2024/"
+        );
+        assert_eq!(
+            remove_statement("@load_stage/it's a dir/"),
+            "REMOVE '@load_stage/it''s a dir/'"
+        );
+        assert_eq!(
+            remove_statement("@load_stage/x; DROP TABLE t"),
+            "REMOVE '@load_stage/x; DROP TABLE t'"
+        );
+    }
+
+    #[test]
+    fn test_remove_rows() -> Result<()> {
+        let result = |status| {
+            RemoveResult::from_row(&typed_row(&[
+                ("name", "text", Some("load_stage/data_0.csv.gz")),
+                ("result", "text", Some(status)),
+            ]))
+        };
+        assert!(result("removed")?.removed);
+        assert!(!result("not removed")?.removed);
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! Logging of the statements a session sends, with their literals redacted.
+
+use std::{fmt, sync::Arc};
+
+use crate::{bind::in_placeholder_order, QueryRequest};
+
+/// Receives each statement a session sends, with its literals redacted, e.g. to write it to an
+/// application log. Set it with
+/// [`SnowflakeClientConfig::statement_logger`](crate::SnowflakeClientConfig::statement_logger).
+///
+/// ```rust
+/// # use snowflake_connector_rs::{LoggedStatement, StatementLogger};
+/// struct PrintStatements;
+///
+/// impl StatementLogger for PrintStatements {
+///     fn log_statement(&self, statement: &LoggedStatement<'_>) {
+///         println!("{} {}", statement.query_id.unwrap_or("-"), statement.sql);
+///     }
+/// }
+/// ```
+pub trait StatementLogger: Send + Sync {
+    /// Called once for every statement, after the server has answered it.
+    fn log_statement(&self, statement: &LoggedStatement<'_>);
+}
+
+impl fmt::Debug for dyn StatementLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StatementLogger")
+    }
+}
+
+/// A statement as given to a [`StatementLogger`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct LoggedStatement<'a> {
+    /// The statement with its literals replaced by `?`, as [`redact_sql`] does, and cut to
+    /// [`SnowflakeClientConfig::max_logged_statement_len`](crate::SnowflakeClientConfig::max_logged_statement_len)
+    /// characters.
+    pub sql: &'a str,
+
+    /// The ID of the query, to find it in `QUERY_HISTORY`; `None` if the statement failed
+    /// before the server assigned one.
+    pub query_id: Option<&'a str>,
+
+    /// Whether the statement succeeded.
+    pub succeeded: bool,
+
+    /// The number of values bound to the statement: one for each placeholder, or for each
+    /// placeholder and row of a statement run for many rows. The values themselves are never
+    /// logged.
+    pub bind_count: usize,
+
+    /// The types the values of each placeholder are bound as, in the order of the
+    /// placeholders, e.g. `["FIXED", "TEXT"]`; empty for a statement without bindings.
+    pub bind_types: &'a [&'static str],
+}
+
+/// A [`StatementLogger`] with the length statements are cut to.
+#[derive(Clone)]
+pub(crate) struct StatementLog {
+    pub(crate) logger: Arc<dyn StatementLogger>,
+    pub(crate) max_len: usize,
+}
+
+impl StatementLog {
+    pub(crate) fn log(&self, request: &QueryRequest, query_id: Option<&str>, succeeded: bool) {
+        let mut sql = redact_sql(&request.sql_text);
+        if let Some((end, _)) = sql.char_indices().nth(self.max_len) {
+            sql.truncate(end);
+            sql.push_str("...");
+        }
+        let bindings = request
+            .bindings
+            .iter()
+            .flat_map(in_placeholder_order)
+            .collect::<Vec<_>>();
+        let bind_count = bindings.iter().map(|binding| binding.value.len()).sum();
+        let bind_types = bindings
+            .iter()
+            .map(|binding| binding.bind_type.name())
+            .collect::<Vec<_>>();
+        self.logger.log_statement(&LoggedStatement {
+            sql: &sql,
+            query_id,
+            succeeded,
+            bind_count,
+            bind_types: &bind_types,
+        });
+    }
+}
+
+/// Replaces the literals of a statement with `?` and removes its comments, so that it can be
+/// logged without the values it holds.
+///
+/// Strings in single quotes or `$$`, and numbers, are literals; identifiers, including quoted
+/// ones, and keywords are kept. This is a tokenizer rather than a parser, so it does not know
+/// where a statement uses a value, but a literal never survives it.
+///
+/// ```rust
+/// # use snowflake_connector_rs::redact_sql;
+/// assert_eq!(
+///     redact_sql("SELECT * FROM users_2024 WHERE email = 'ann@example.com' AND age > 30"),
+///     "SELECT * FROM users_2024 WHERE email = ? AND age > ?"
+/// );
+/// ```
+pub fn redact_sql(sql: &str) -> String {
+    let mut redacted = String::with_capacity(sql.len());
+    let mut chars = sql.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let rest = &sql[start..];
+        match c {
+            '\'' => {
+                skip_string(&mut chars);
+                redacted.push('?');
+            }
+            '$' if rest.starts_with("$$") => {
+                chars.next();
+                match rest[2..].find("$$") {
+                    Some(end) => {
+                        let end = start + 2 + end + 2;
+                        while chars.next_if(|&(i, _)| i < end).is_some() {}
+                    }
+                    None => while chars.next().is_some() {},
+                }
+                redacted.push('?');
+            }
+            '"' => {
+                redacted.push(c);
+                for (_, c) in chars.by_ref() {
+                    redacted.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '-' if rest.starts_with("--") => skip_line(&mut chars, &mut redacted),
+            '/' if rest.starts_with("//") => skip_line(&mut chars, &mut redacted),
+            '/' if rest.starts_with("/*") => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                redacted.push(' ');
+            }
+            c if c.is_ascii_digit() => {
+                while chars
+                    .next_if(|&(i, c)| {
+                        c.is_ascii_alphanumeric()
+                            || c == '.'
+                            || (matches!(c, '+' | '-') && sql[..i].ends_with(['e', 'E']))
+                    })
+                    .is_some()
+                {}
+                redacted.push('?');
+            }
+            c if is_identifier_char(c) => {
+                redacted.push(c);
+                while let Some((_, c)) = chars.next_if(|&(_, c)| is_identifier_char(c)) {
+                    redacted.push(c);
+                }
+            }
+            c => redacted.push(c),
+        }
+    }
+    redacted
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Skips the rest of a string in single quotes, with its `''` and backslash escapes.
+fn skip_string(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>) {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' if chars.next_if(|&(_, c)| c == '\'').is_none() => return,
+            _ => {}
+        }
+    }
+}
+
+/// Skips a comment up to the end of its line, keeping the line break.
+fn skip_line(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>, redacted: &mut String) {
+    for (_, c) in chars.by_ref() {
+        if c == '\n' {
+            redacted.push(c);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sql() {
+        let cases = [
+            (
+                "INSERT INTO t VALUES ('it''s', 'a\\'b', 1.5e-3, -42)",
+                "INSERT INTO t VALUES (?, ?, ?, -?)",
+            ),
+            (
+                "SELECT $$secret $ text$$, col1 FROM \"Table 'x' 1\"",
+                "SELECT ?, col1 FROM \"Table 'x' 1\"",
+            ),
+            (
+                "SELECT a -- ssn 123-45-6789\nFROM t /* token 'abc' */ WHERE b = X'0F'",
+                "SELECT a \nFROM t   WHERE b = X?",
+            ),
+            ("SELECT 'unterminated", "SELECT ?"),
+            ("SELECT $$unterminated", "SELECT ?"),
+            ("SELECT /*/ 1 */ 2", "SELECT   ?"),
+            ("select ü_2, 10 // note", "select ü_2, ? "),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(redact_sql(sql), expected, "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_statement_log() -> crate::Result<()> {
+        type Logged = (String, Option<String>, bool, usize, Vec<&'static str>);
+        struct Last(std::sync::Mutex<Option<Logged>>);
+
+        impl StatementLogger for Last {
+            fn log_statement(&self, statement: &LoggedStatement<'_>) {
+                *self.0.lock().unwrap() = Some((
+                    statement.sql.to_string(),
+                    statement.query_id.map(str::to_string),
+                    statement.succeeded,
+                    statement.bind_count,
+                    statement.bind_types.to_vec(),
+                ));
+            }
+        }
+
+        let logger = Arc::new(Last(Default::default()));
+        let log = StatementLog {
+            logger: logger.clone(),
+            max_len: 20,
+        };
+        log.log(
+            &"SELECT name FROM people WHERE id = 7".into(),
+            Some("01b0"),
+            true,
+        );
+        assert_eq!(
+            logger.0.lock().unwrap().clone(),
+            Some((
+                "SELECT name FROM peo...".to_string(),
+                Some("01b0".to_string()),
+                true,
+                0,
+                vec![]
+            ))
+        );
+        log.log(&"SELECT 'x'".into(), None, false);
+        assert_eq!(
+            logger.0.lock().unwrap().clone(),
+            Some(("SELECT ?".to_string(), None, false, 0, vec![]))
+        );
+
+        // Bound values are logged as their types, in the order of the placeholders, and
+        // counted.
+        let mut request = QueryRequest::from(format!("SELECT {}", ["?"; 11].join(", ")));
+        for value in 1..=10 {
+            request = request.bind(&value)?;
+        }
+        log.log(&request.bind("x")?, None, true);
+        let (_, _, _, bind_count, bind_types) = logger.0.lock().unwrap().clone().unwrap();
+        assert_eq!(bind_count, 11);
+        assert_eq!(bind_types[..10], ["FIXED"; 10]);
+        assert_eq!(bind_types[10], "TEXT");
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Statistics of a query, from its response and measured while it runs.
+
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Statistics of a query: what the query response reports about the statement and its result,
+/// and what the client measured while running the query and downloading the result.
+///
+/// Returned by [`QueryResultSet::stats`](crate::QueryResultSet::stats),
+/// [`RowStream::stats`](crate::RowStream::stats) and
+/// [`SnowflakeSession::last_query_stats`](crate::SnowflakeSession::last_query_stats). The
+/// download measurements grow as the chunks are read, so a copy taken before the result is read
+/// does not include them.
+///
+/// Fields the response leaves out for some statements are `None` rather than zero, e.g. the
+/// DML counts of a `SELECT`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QueryStats {
+    /// The ID of the query.
+    pub query_id: String,
+
+    /// The type of the statement, as Snowflake numbers them.
+    pub statement_type_id: Option<i64>,
+
+    /// The rows in the result.
+    pub total_rows: Option<usize>,
+
+    /// The rows sent with the query response rather than in chunks.
+    pub returned_rows: Option<usize>,
+
+    /// The rows inserted by a DML statement.
+    pub rows_inserted: Option<u64>,
+
+    /// The rows updated by a DML statement.
+    pub rows_updated: Option<u64>,
+
+    /// The rows deleted by a DML statement.
+    pub rows_deleted: Option<u64>,
+
+    /// The target rows a DML statement matched more than once.
+    pub dml_duplicates: Option<u64>,
+
+    /// The number of chunks of the result.
+    pub chunk_count: usize,
+
+    /// The compressed size of the chunks, as reported by the server.
+    pub compressed_bytes: u64,
+
+    /// The uncompressed size of the chunks, as reported by the server.
+    pub uncompressed_bytes: u64,
+
+    /// The time from sending the query until its result arrived, including polling.
+    pub execution_time: Duration,
+
+    /// The time from sending the query until the first chunk was read; `None` until then, and
+    /// for results without chunks.
+    pub time_to_first_chunk: Option<Duration>,
+
+    /// The bytes of chunk data downloaded so far.
+    pub downloaded_bytes: u64,
+}
+
+/// Collects the [`QueryStats`] of a query, shared by its result, its chunk downloads and the
+/// session that ran it.
+#[derive(Debug, Clone)]
+pub(crate) struct StatsRecorder(Arc<Mutex<Recorded>>);
+
+#[derive(Debug)]
+struct Recorded {
+    sent: Instant,
+    stats: QueryStats,
+}
+
+impl StatsRecorder {
+    /// Starts recording for a query sent at `sent`.
+    pub(crate) fn new(sent: Instant) -> Self {
+        Self(Arc::new(Mutex::new(Recorded {
+            sent,
+            stats: QueryStats::default(),
+        })))
+    }
+
+    /// Records the statistics of the query response, which has just arrived.
+    pub(crate) fn record_response(&self, stats: QueryStats) {
+        self.update(|recorded| {
+            recorded.stats = QueryStats {
+                execution_time: recorded.sent.elapsed(),
+                time_to_first_chunk: recorded.stats.time_to_first_chunk,
+                downloaded_bytes: recorded.stats.downloaded_bytes,
+                ..stats
+            }
+        });
+    }
+
+    /// Records a downloaded chunk body of `bytes` bytes.
+    pub(crate) fn record_download(&self, bytes: u64) {
+        self.update(|recorded| recorded.stats.downloaded_bytes += bytes);
+    }
+
+    /// Records that a chunk has been read, which only counts for the first one.
+    pub(crate) fn record_chunk_read(&self) {
+        self.update(|recorded| {
+            if recorded.stats.time_to_first_chunk.is_none() {
+                recorded.stats.time_to_first_chunk = Some(recorded.sent.elapsed());
+            }
+        });
+    }
+
+    /// Returns the statistics recorded so far.
+    pub(crate) fn snapshot(&self) -> QueryStats {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .stats
+            .clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Recorded)) {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
+
+impl Default for StatsRecorder {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
@@ -0,0 +1,571 @@
+//! Row-by-row reading of query results, downloading chunks as they are needed.
+
+use std::sync::Arc;
+
+use crate::{
+    chunk::ChunkFetcher, row::Columns, stats::StatsRecorder, values::RowValues, Error, QueryStats,
+    Result, ResultLimit, ResultMetadata, SnowflakeRow,
+};
+
+/// The rows of a query result, read in order without holding the whole result in memory.
+///
+/// The rows sent with the query response come first; chunk downloads start once they have been
+/// read, with a bounded number of chunks downloaded ahead. A chunk that cannot be downloaded
+/// after retrying yields [`Error::ChunkFailed`](crate::Error::ChunkFailed), and a result with
+/// fewer or more rows than its metadata announced ends with
+/// [`Error::IncompleteResult`](crate::Error::IncompleteResult), as does a chunk with fewer or
+/// more rows than announced. A result larger than the
+/// configured limits yields [`Error::ResultTooLarge`](crate::Error::ResultTooLarge), before its
+/// chunks are downloaded if the result metadata shows it. Returned by
+/// [`SnowflakeSession::query_stream`](crate::SnowflakeSession::query_stream).
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeSession};
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let mut rows = session.query_stream("SELECT * FROM large_table").await?;
+/// while let Some(row) = rows.next_row().await {
+///     let id: i64 = row?.get("ID")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct RowStream {
+    columns: Arc<Columns>,
+    /// The chunk being read, and the index of its next row.
+    values: Arc<RowValues>,
+    next: usize,
+    fetcher: ChunkFetcher,
+    /// The index of the chunk being read; `None` for the rows sent with the query response.
+    chunk_index: Option<usize>,
+    /// The rows of the next chunk skipped when it arrives, to start reading in the middle of it.
+    skip: usize,
+    rows_delivered: usize,
+    /// The row count announced in the result metadata, until it has been checked.
+    total: Option<usize>,
+    limits: ResultLimits,
+    /// The uncompressed size of the chunks announced in the result metadata, until it has been
+    /// checked against the limits.
+    chunk_bytes: Option<u64>,
+    stats: StatsRecorder,
+}
+
+/// The most rows and chunk bytes a result may have.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ResultLimits {
+    pub(crate) max_rows: Option<usize>,
+    pub(crate) max_bytes: Option<u64>,
+}
+
+impl RowStream {
+    pub(crate) fn new(
+        columns: Arc<Columns>,
+        row_set: RowValues,
+        fetcher: ChunkFetcher,
+        total: Option<usize>,
+        chunk_bytes: u64,
+        stats: StatsRecorder,
+    ) -> Self {
+        Self {
+            columns,
+            values: Arc::new(row_set),
+            next: 0,
+            fetcher,
+            chunk_index: None,
+            skip: 0,
+            rows_delivered: 0,
+            total,
+            limits: ResultLimits::default(),
+            chunk_bytes: Some(chunk_bytes),
+            stats,
+        }
+    }
+
+    /// Returns the next row, or `None` once every row has been returned.
+    pub async fn next_row(&mut self) -> Option<Result<SnowflakeRow>> {
+        if let Some(e) = self.check_announced_size() {
+            return Some(Err(e));
+        }
+        loop {
+            if self.next < self.values.len() {
+                let row = self.row(self.next);
+                self.next += 1;
+                self.rows_delivered += 1;
+                return Some(Ok(row));
+            }
+            if let Err(e) = self.next_chunk().await? {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    /// Returns the rest of the current chunk, or the next chunk if it has been read, or `None`
+    /// once every row has been returned.
+    pub async fn next_batch(&mut self) -> Option<Result<Vec<SnowflakeRow>>> {
+        self.next_rows(usize::MAX).await
+    }
+
+    /// Returns at most `max` rows of the rest of the current chunk, or of the next chunk if it
+    /// has been read, or `None` once every row has been returned.
+    pub(crate) async fn next_rows(&mut self, max: usize) -> Option<Result<Vec<SnowflakeRow>>> {
+        if let Err(e) = self.fill().await? {
+            return Some(Err(e));
+        }
+        let end = self.values.len().min(self.next.saturating_add(max));
+        let rows = (self.next..end)
+            .map(|index| self.row(index))
+            .collect::<Vec<_>>();
+        self.next = end;
+        self.rows_delivered += rows.len();
+        Some(Ok(rows))
+    }
+
+    /// Where the next row is: the index of its part of the result, where part 0 holds the rows
+    /// sent with the query response and part `n` is chunk `n - 1`, and its offset in the part.
+    pub(crate) fn position(&self) -> (usize, usize) {
+        let part = self.chunk_index.map_or(0, |index| index + 1);
+        match self.next == self.values.len() {
+            true => (part + 1, self.skip),
+            false => (part, self.next),
+        }
+    }
+
+    /// Starts reading at the row at `offset` of `part` of the result, numbered as by
+    /// [`RowStream::position`], before any row has been read. The chunks before it are not
+    /// downloaded.
+    pub(crate) fn seek(&mut self, part: usize, offset: usize) -> Result<()> {
+        let invalid =
+            || Error::InvalidCursor(format!("row {offset} of part {part} is not in the result"));
+        if part == 0 {
+            if offset > self.values.len() {
+                return Err(invalid());
+            }
+            self.next = offset;
+            self.rows_delivered = offset;
+            return Ok(());
+        }
+        // The position after the last row is the start of the part after the last chunk.
+        let parts = self.fetcher.chunk_count() + 1;
+        if part > parts || (part == parts && offset > 0) {
+            return Err(invalid());
+        }
+        match self.fetcher.skip_to(part - 1) {
+            Some(skipped) => self.rows_delivered = self.values.len() + skipped,
+            // Without the row counts of the chunks skipped, the total cannot be checked.
+            None => self.total = None,
+        }
+        self.values = Arc::default();
+        self.next = 0;
+        self.chunk_index = part.checked_sub(2);
+        self.skip = offset;
+        Ok(())
+    }
+
+    /// Returns the values of the rest of the current chunk, or of the next chunk if it has been
+    /// read, or `None` once every row has been returned.
+    pub(crate) async fn next_values(&mut self) -> Option<Result<Arc<RowValues>>> {
+        if let Err(e) = self.fill().await? {
+            return Some(Err(e));
+        }
+        let values = if self.next == 0 {
+            Arc::clone(&self.values)
+        } else {
+            let rows = (self.next..self.values.len())
+                .map(|row| self.row(row).into_inner())
+                .collect();
+            Arc::new(RowValues::from_rows(rows))
+        };
+        self.rows_delivered += self.values.len() - self.next;
+        self.next = self.values.len();
+        Some(Ok(values))
+    }
+
+    /// Decodes the rest of the current chunk, or the next chunk if it has been read, with
+    /// `decode`, or returns `None` once every row has been returned. The rows are decoded where
+    /// they are stored rather than collected first. A row that fails to decode fails the batch,
+    /// with its position in the result and in its chunk.
+    pub(crate) async fn next_decoded<T>(
+        &mut self,
+        decode: impl Fn(&SnowflakeRow) -> Result<T>,
+    ) -> Option<Result<Vec<T>>> {
+        if let Err(e) = self.fill().await? {
+            return Some(Err(e));
+        }
+        let start = self.next;
+        let decoded = (start..self.values.len())
+            .map(|index| {
+                decode(&self.row(index)).map_err(|e| {
+                    let e = e.with_row(self.rows_delivered + index - start);
+                    match self.chunk_index {
+                        Some(chunk_index) => e.with_chunk(chunk_index, index),
+                        None => e,
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>>>();
+        self.rows_delivered += self.values.len() - start;
+        self.next = self.values.len();
+        Some(decoded)
+    }
+
+    pub(crate) fn set_chunk_prefetch(&mut self, prefetch: usize) {
+        self.fetcher.set_prefetch(prefetch);
+    }
+
+    pub(crate) fn set_chunks_ordered(&mut self, ordered: bool) {
+        self.fetcher.set_ordered(ordered);
+    }
+
+    pub(crate) fn result_limits_mut(&mut self) -> &mut ResultLimits {
+        &mut self.limits
+    }
+
+    pub(crate) fn columns_mut(&mut self) -> &mut Columns {
+        Arc::make_mut(&mut self.columns)
+    }
+
+    pub(crate) fn columns(&self) -> &Arc<Columns> {
+        &self.columns
+    }
+
+    /// Returns the column names of the result.
+    pub fn column_names(&self) -> Vec<&str> {
+        (0..self.columns.len())
+            .map(|i| self.columns.name(i))
+            .collect()
+    }
+
+    /// Returns the columns, query ID, statement type and statistics of the result, with the
+    /// download measurements so far.
+    pub fn metadata(&self) -> ResultMetadata {
+        ResultMetadata::new(Arc::clone(&self.columns), self.stats.snapshot())
+    }
+
+    /// Returns the statistics of the query, with the download measurements so far.
+    pub fn stats(&self) -> QueryStats {
+        self.stats.snapshot()
+    }
+
+    pub(crate) fn stats_recorder(&self) -> &StatsRecorder {
+        &self.stats
+    }
+
+    /// The number of chunks of the result.
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.fetcher.chunk_count()
+    }
+
+    /// Reads chunks until there is a row left to return, or returns `None` once every row has
+    /// been returned.
+    async fn fill(&mut self) -> Option<Result<()>> {
+        if let Some(e) = self.check_announced_size() {
+            return Some(Err(e));
+        }
+        while self.next == self.values.len() {
+            if let Err(e) = self.next_chunk().await? {
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(()))
+    }
+
+    async fn next_chunk(&mut self) -> Option<Result<()>> {
+        let Some(chunk) = self.fetcher.next_chunk().await else {
+            return self.check_total().map(Err);
+        };
+        Some(match chunk {
+            Ok(values) => {
+                if let Some(max_rows) = self.limits.max_rows {
+                    if self.rows_delivered + values.len() > max_rows {
+                        return Some(Err(self.too_large(ResultLimit::Rows(max_rows))));
+                    }
+                }
+                if self.skip > values.len() {
+                    return Some(Err(Error::InvalidCursor(format!(
+                        "chunk {} has {} rows, fewer than the {} to skip",
+                        self.fetcher.returned_index().unwrap_or_default(),
+                        values.len(),
+                        self.skip
+                    ))));
+                }
+                self.values = Arc::new(values);
+                self.next = std::mem::take(&mut self.skip);
+                self.rows_delivered += self.next;
+                self.chunk_index = self.fetcher.returned_index();
+                self.stats.record_chunk_read();
+                Ok(())
+            }
+            Err(failed) => Err(failed.into_error(self.rows_delivered)),
+        })
+    }
+
+    /// Compares the announced size of the result with the limits, before the first row is read.
+    fn check_announced_size(&mut self) -> Option<Error> {
+        let chunk_bytes = self.chunk_bytes.take()?;
+        let limit = match (self.limits, self.total) {
+            (
+                ResultLimits {
+                    max_rows: Some(max_rows),
+                    ..
+                },
+                Some(total),
+            ) if total > max_rows => ResultLimit::Rows(max_rows),
+            (
+                ResultLimits {
+                    max_bytes: Some(max_bytes),
+                    ..
+                },
+                _,
+            ) if chunk_bytes > max_bytes => ResultLimit::Bytes(max_bytes),
+            _ => return None,
+        };
+        Some(self.too_large(limit))
+    }
+
+    /// Ends the stream for exceeding `limit`, without downloading any further chunks.
+    fn too_large(&mut self, limit: ResultLimit) -> Error {
+        self.fetcher.stop();
+        self.values = Arc::default();
+        self.next = 0;
+        self.total = None;
+        Error::ResultTooLarge {
+            rows_so_far: self.rows_delivered,
+            limit,
+        }
+    }
+
+    /// Compares the number of rows read with the announced total, once the last chunk is read.
+    fn check_total(&mut self) -> Option<Error> {
+        let expected = self.total.take()?;
+        (expected != self.rows_delivered).then_some(Error::IncompleteResult {
+            chunk_index: None,
+            expected,
+            received: self.rows_delivered,
+        })
+    }
+
+    fn row(&self, index: usize) -> SnowflakeRow {
+        SnowflakeRow::at(Arc::clone(&self.values), index, Arc::clone(&self.columns))
+    }
+}
+
+/// The rows of a query result decoded into `T`, a chunk at a time. Returned by
+/// [`SnowflakeSession::query_as_stream`](crate::SnowflakeSession::query_as_stream) and
+/// [`SnowflakeSession::query_typed_stream`](crate::SnowflakeSession::query_typed_stream).
+///
+/// Each chunk is decoded as it is read, straight from the downloaded values, so apart from the
+/// chunks downloaded ahead only one chunk of `T`s is held at a time. A row that cannot be decoded
+/// yields an [`Error::Decode`](crate::Error::Decode) with its
+/// [`chunk_index`](crate::DecodeError::chunk_index) and
+/// [`chunk_row_offset`](crate::DecodeError::chunk_row_offset), and the rest of its chunk is
+/// skipped.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{Result, SnowflakeSession};
+/// #[derive(serde::Deserialize)]
+/// struct Event {
+///     #[serde(rename = "ID")]
+///     id: i64,
+/// }
+///
+/// # async fn run(session: &SnowflakeSession) -> Result<()> {
+/// let mut events = session.query_as_stream::<Event>("SELECT id FROM events").await?;
+/// while let Some(batch) = events.next_batch().await {
+///     for event in batch? {
+///         println!("{}", event.id);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct TypedRowStream<T> {
+    rows: RowStream,
+    decode: fn(&SnowflakeRow) -> Result<T>,
+}
+
+impl<T> TypedRowStream<T> {
+    pub(crate) fn new(rows: RowStream, decode: fn(&SnowflakeRow) -> Result<T>) -> Self {
+        Self { rows, decode }
+    }
+
+    /// Returns the decoded rest of the current chunk, or the next chunk if it has been read, or
+    /// `None` once every row has been returned.
+    pub async fn next_batch(&mut self) -> Option<Result<Vec<T>>> {
+        self.rows.next_decoded(self.decode).await
+    }
+
+    /// Returns the column names of the result.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.rows.column_names()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::{chunk::tests::fake_fetcher, types::SnowflakeColumnType};
+
+    /// A stream of a `VALUE` text column with the given inline rows followed by `chunks` chunks
+    /// of one row each, and the count of the chunk downloads started.
+    pub(crate) fn stream(
+        row_set: &[&str],
+        chunks: usize,
+    ) -> (RowStream, Arc<std::sync::atomic::AtomicUsize>) {
+        let columns = Columns::new(vec![(
+            "VALUE".to_string(),
+            SnowflakeColumnType::new("text", None),
+        )]);
+        let row_set =
+            RowValues::from_rows(row_set.iter().map(|v| vec![Some(v.to_string())]).collect());
+        let (fetcher, started) = fake_fetcher(chunks, 2);
+        let total = row_set.len() + chunks;
+        (
+            RowStream::new(
+                Arc::new(columns),
+                row_set,
+                fetcher,
+                Some(total),
+                0,
+                StatsRecorder::default(),
+            ),
+            started,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_next_row() -> Result<()> {
+        let (mut rows, started) = stream(&["a", "b"], 3);
+        assert_eq!(rows.column_names(), vec!["VALUE"]);
+
+        let mut values = vec![];
+        for _ in 0..2 {
+            values.push(rows.next_row().await.unwrap()?.get::<String>("VALUE")?);
+        }
+        // The inline rows are read before any chunk is downloaded.
+        assert_eq!(started.load(Ordering::SeqCst), 0);
+        while let Some(row) = rows.next_row().await {
+            values.push(row?.get::<String>("VALUE")?);
+        }
+        assert_eq!(values, vec!["a", "b", "0", "1", "2"]);
+        assert!(rows.next_row().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_next_batch() -> Result<()> {
+        let (mut rows, _) = stream(&["a", "b"], 2);
+        let first = rows.next_row().await.unwrap()?;
+        assert_eq!(first.get::<String>("VALUE")?, "a");
+
+        let mut batches = vec![];
+        while let Some(batch) = rows.next_batch().await {
+            let batch = batch?
+                .iter()
+                .map(|row| row.get::<String>("VALUE"))
+                .collect::<Result<Vec<_>>>()?;
+            batches.push(batch);
+        }
+        assert_eq!(batches, vec![vec!["b"], vec!["0"], vec!["1"]]);
+
+        let (mut empty, _) = stream(&[], 0);
+        assert!(empty.next_batch().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_result_limits() -> Result<()> {
+        let too_large = |rows_so_far, limit| {
+            move |e: &Error| {
+                matches!(e, Error::ResultTooLarge { rows_so_far: r, limit: l }
+                    if *r == rows_so_far && *l == limit)
+            }
+        };
+
+        // The announced size is checked before anything is read or downloaded.
+        let (mut rows, started) = stream(&["a", "b"], 3);
+        rows.result_limits_mut().max_rows = Some(4);
+        let err = rows.next_row().await.unwrap().unwrap_err();
+        assert!(too_large(0, ResultLimit::Rows(4))(&err));
+        assert!(rows.next_row().await.is_none());
+        assert_eq!(started.load(Ordering::SeqCst), 0);
+
+        let (fetcher, started) = fake_fetcher(3, 2);
+        let columns = Arc::new(Columns::new(vec![]));
+        let mut rows = RowStream::new(
+            columns,
+            RowValues::default(),
+            fetcher,
+            Some(3),
+            300,
+            StatsRecorder::default(),
+        );
+        rows.result_limits_mut().max_bytes = Some(299);
+        let err = rows.next_batch().await.unwrap().unwrap_err();
+        assert!(too_large(0, ResultLimit::Bytes(299))(&err));
+        assert!(rows.next_batch().await.is_none());
+        assert_eq!(started.load(Ordering::SeqCst), 0);
+
+        // Without a row count in the metadata, the rows are counted as the chunks arrive.
+        let (mut rows, _) = stream(&["a", "b"], 3);
+        rows.total = None;
+        rows.result_limits_mut().max_rows = Some(3);
+        let mut values = vec![];
+        let err = loop {
+            match rows.next_row().await.unwrap() {
+                Ok(row) => values.push(row.get::<String>("VALUE")?),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(values, vec!["a", "b", "0"]);
+        assert!(too_large(3, ResultLimit::Rows(3))(&err));
+        assert!(rows.next_row().await.is_none());
+
+        let (mut rows, _) = stream(&["a", "b"], 3);
+        *rows.result_limits_mut() = ResultLimits {
+            max_rows: Some(5),
+            max_bytes: Some(0),
+        };
+        let mut count = 0;
+        while let Some(row) = rows.next_row().await {
+            row?;
+            count += 1;
+        }
+        assert_eq!(count, 5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_typed_stream() -> Result<()> {
+        fn decode(row: &SnowflakeRow) -> Result<i64> {
+            match row.get::<i64>("VALUE")? {
+                1 => Err(Error::decode("bad value")),
+                value => Ok(value),
+            }
+        }
+
+        let (rows, _) = stream(&["7", "8"], 1);
+        let mut typed = TypedRowStream::new(rows, decode);
+        assert_eq!(typed.column_names(), vec!["VALUE"]);
+        let mut batches = vec![];
+        while let Some(batch) = typed.next_batch().await {
+            batches.push(batch?);
+        }
+        assert_eq!(batches, vec![vec![7, 8], vec![0]]);
+
+        // The failing row is located by its chunk; the stream goes on with the next chunk.
+        let (rows, _) = stream(&["7"], 3);
+        let mut typed = TypedRowStream::new(rows, decode);
+        assert_eq!(typed.next_batch().await.unwrap()?, vec![7]);
+        assert_eq!(typed.next_batch().await.unwrap()?, vec![0]);
+        let Err(Error::Decode(e)) = typed.next_batch().await.unwrap() else {
+            panic!("expected a decode error");
+        };
+        assert_eq!((e.row_index(), e.chunk_index()), (Some(2), Some(1)));
+        assert_eq!(e.chunk_row_offset(), Some(0));
+        assert_eq!(e.to_string(), "row 2, chunk 1 row 0, bad value");
+        assert_eq!(typed.next_batch().await.unwrap()?, vec![2]);
+        assert!(typed.next_batch().await.is_none());
+        Ok(())
+    }
+}
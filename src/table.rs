@@ -0,0 +1,229 @@
+//! Plain-text table rendering of query results.
+
+use std::fmt;
+
+use crate::{row::parse_bool, temporal::format_iso8601, types::SnowflakeColumnType, SnowflakeRow};
+
+const DEFAULT_MAX_WIDTH: usize = 40;
+const ELLIPSIS: &str = "...";
+
+/// Renders rows as an ASCII table with [`Table`]'s default settings.
+pub fn format_table(rows: &[SnowflakeRow]) -> String {
+    Table::new(rows).to_string()
+}
+
+/// A [`Display`](fmt::Display) wrapper that renders rows as an ASCII table.
+///
+/// Columns appear in the order of the SELECT list. NUMBER and FLOAT columns are right-aligned,
+/// dates and timestamps are shown as ISO-8601, VARIANT, OBJECT and ARRAY values as compact JSON,
+/// and NULL as `NULL` (an empty string stays empty). Cells longer than
+/// [`Table::max_width`] characters are truncated with `...`.
+///
+/// ```rust
+/// # use snowflake_connector_rs::{SnowflakeRow, Table};
+/// # fn run(rows: &[SnowflakeRow]) {
+/// println!("{}", Table::new(rows).max_width(20));
+/// # }
+/// ```
+///
+/// ```text
+/// +----+-------+
+/// | ID | VALUE |
+/// +----+-------+
+/// |  1 | hello |
+/// |  2 | NULL  |
+/// +----+-------+
+/// ```
+#[derive(Debug, Clone)]
+pub struct Table<'a> {
+    rows: &'a [SnowflakeRow],
+    column_names: Vec<&'a str>,
+    max_width: usize,
+}
+
+impl<'a> Table<'a> {
+    pub fn new(rows: &'a [SnowflakeRow]) -> Self {
+        Self {
+            rows,
+            column_names: vec![],
+            max_width: DEFAULT_MAX_WIDTH,
+        }
+    }
+
+    /// Sets the maximum width of a cell in characters. Defaults to 40.
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Sets the header to print when there are no rows to take the column names from. Without
+    /// rows or column names, the table renders as an empty string.
+    pub fn column_names(mut self, column_names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.column_names = column_names.into_iter().collect();
+        self
+    }
+
+    fn render(&self) -> (Vec<String>, Vec<bool>, Vec<Vec<String>>) {
+        let Some(first) = self.rows.first() else {
+            let header = self
+                .column_names
+                .iter()
+                .map(|name| self.truncate(name.to_string()))
+                .collect();
+            return (header, vec![false; self.column_names.len()], vec![]);
+        };
+        let columns = &first.columns;
+        let header = (0..columns.len())
+            .map(|i| self.truncate(columns.name(i).to_string()))
+            .collect();
+        let numeric = (0..columns.len())
+            .map(|i| matches!(columns.column_type(i).snowflake_type(), "fixed" | "real"))
+            .collect();
+        let body = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.values()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        let cell = match value {
+                            Some(value) => display_value(value, columns.column_type(i)),
+                            None => "NULL".to_string(),
+                        };
+                        self.truncate(cell)
+                    })
+                    .collect()
+            })
+            .collect();
+        (header, numeric, body)
+    }
+
+    fn truncate(&self, cell: String) -> String {
+        if cell.chars().count() <= self.max_width {
+            return cell;
+        }
+        if self.max_width <= ELLIPSIS.len() {
+            return cell.chars().take(self.max_width).collect();
+        }
+        let mut truncated = cell
+            .chars()
+            .take(self.max_width - ELLIPSIS.len())
+            .collect::<String>();
+        truncated.push_str(ELLIPSIS);
+        truncated
+    }
+}
+
+impl fmt::Display for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (header, numeric, body) = self.render();
+        if header.is_empty() {
+            return Ok(());
+        }
+        let mut widths = header.iter().map(|h| h.chars().count()).collect::<Vec<_>>();
+        for row in &body {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let separator = widths
+            .iter()
+            .map(|&w| "-".repeat(w + 2))
+            .collect::<Vec<_>>()
+            .join("+");
+        let separator = format!("+{separator}+");
+        let write_line = |f: &mut fmt::Formatter<'_>, cells: &[String]| {
+            write!(f, "|")?;
+            for ((cell, &width), &right) in cells.iter().zip(&widths).zip(&numeric) {
+                if right {
+                    write!(f, " {cell:>width$} |")?;
+                } else {
+                    write!(f, " {cell:<width$} |")?;
+                }
+            }
+            writeln!(f)
+        };
+
+        writeln!(f, "{separator}")?;
+        write_line(f, &header)?;
+        writeln!(f, "{separator}")?;
+        if !body.is_empty() {
+            for row in &body {
+                write_line(f, row)?;
+            }
+            writeln!(f, "{separator}")?;
+        }
+        Ok(())
+    }
+}
+
+fn display_value(value: &str, column_type: &SnowflakeColumnType) -> String {
+    let display = match column_type.snowflake_type() {
+        "boolean" => parse_bool(value).ok().map(|v| v.to_string()),
+        _ if column_type.is_semi_structured() => serde_json::from_str::<serde_json::Value>(value)
+            .ok()
+            .map(|v| v.to_string()),
+        _ => format_iso8601(value, column_type),
+    };
+    display
+        .unwrap_or_else(|| value.to_string())
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::row::tests::typed_row;
+
+    #[test]
+    fn test_format_table() {
+        let rows = vec![
+            typed_row(&[
+                ("ID", "fixed", Some("1")),
+                ("VALUE", "text", Some("hello")),
+                ("DAY", "date", Some("18262")),
+                ("DATA", "variant", Some("{\n  \"a\": 1\n}")),
+            ]),
+            typed_row(&[
+                ("ID", "fixed", Some("20")),
+                ("VALUE", "text", Some("")),
+                ("DAY", "date", None),
+                ("DATA", "variant", Some("\"a long value\"")),
+            ]),
+        ];
+
+        assert_eq!(
+            format_table(&rows),
+            "\
++----+-------+------------+----------------+
+| ID | VALUE | DAY        | DATA           |
++----+-------+------------+----------------+
+|  1 | hello | 2020-01-01 | {\"a\":1}        |
+| 20 |       | NULL       | \"a long value\" |
++----+-------+------------+----------------+
+"
+        );
+        assert_eq!(
+            Table::new(&rows[1..]).max_width(8).to_string(),
+            "\
++----+-------+------+----------+
+| ID | VALUE | DAY  | DATA     |
++----+-------+------+----------+
+| 20 |       | NULL | \"a lo... |
++----+-------+------+----------+
+"
+        );
+    }
+
+    #[test]
+    fn test_format_empty_table() {
+        assert_eq!(format_table(&[]), "");
+        assert_eq!(
+            Table::new(&[]).column_names(["ID", "VALUE"]).to_string(),
+            "+----+-------+\n| ID | VALUE |\n+----+-------+\n"
+        );
+    }
+}
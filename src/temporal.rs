@@ -0,0 +1,277 @@
+//! Parsing of the wire representation of DATE, TIME and TIMESTAMP values.
+//!
+//! Snowflake sends dates as days since the epoch, times as seconds since midnight and
+//! timestamps as seconds since the epoch, the latter two with a decimal fraction whose length is
+//! the column scale. The values are parsed exactly here, without going through `f64`.
+
+use chrono::{DateTime, Days, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat};
+
+use crate::types::SnowflakeColumnType;
+
+/// Seconds and nanoseconds since an origin; `nanos` is always non-negative, so `-1.5` is
+/// `{ secs: -2, nanos: 500_000_000 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScaledSeconds {
+    pub(crate) secs: i64,
+    pub(crate) nanos: u32,
+}
+
+impl ScaledSeconds {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if int.is_empty() || frac.len() > 9 {
+            return None;
+        }
+        if !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let secs: i64 = int.parse().ok()?;
+        let nanos: u32 = if frac.is_empty() {
+            0
+        } else {
+            frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32)
+        };
+        if !negative {
+            return Some(Self { secs, nanos });
+        }
+        if nanos == 0 {
+            return Some(Self { secs: -secs, nanos });
+        }
+        Some(Self {
+            secs: -secs - 1,
+            nanos: 1_000_000_000 - nanos,
+        })
+    }
+}
+
+/// Parses a DATE value into days since 1970-01-01.
+pub(crate) fn parse_days(value: &str) -> Option<i64> {
+    value.parse().ok()
+}
+
+/// Parses a TIMESTAMP_TZ value into the instant and the time zone offset in seconds. The wire
+/// form is seconds since the epoch (UTC) and the offset in minutes biased by 1440, separated by
+/// a space, e.g. `1719851930.123456789 1980` for +09:00.
+pub(crate) fn parse_zoned(value: &str) -> Option<(ScaledSeconds, i32)> {
+    let (epoch, offset) = value.split_once(' ')?;
+    let instant = ScaledSeconds::parse(epoch)?;
+    let offset_minutes = offset.parse::<i32>().ok()? - 1440;
+    Some((instant, offset_minutes * 60))
+}
+
+/// Parses a DATE value (days since 1970-01-01).
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    let days = parse_days(value)?;
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    if days >= 0 {
+        epoch.checked_add_days(Days::new(days as u64))
+    } else {
+        epoch.checked_sub_days(Days::new(days.unsigned_abs()))
+    }
+}
+
+/// Parses a TIME value (seconds since midnight).
+pub(crate) fn parse_time(value: &str) -> Option<NaiveTime> {
+    let ScaledSeconds { secs, nanos } = ScaledSeconds::parse(value)?;
+    NaiveTime::from_num_seconds_from_midnight_opt(u32::try_from(secs).ok()?, nanos)
+}
+
+/// Parses a TIMESTAMP_NTZ or TIMESTAMP_LTZ value (seconds since the epoch).
+pub(crate) fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    let ScaledSeconds { secs, nanos } = ScaledSeconds::parse(value)?;
+    DateTime::from_timestamp(secs, nanos).map(|dt| dt.naive_utc())
+}
+
+/// Parses a TIMESTAMP_TZ value; see [`parse_zoned`].
+pub(crate) fn parse_timestamp_tz(value: &str) -> Option<DateTime<FixedOffset>> {
+    let (ScaledSeconds { secs, nanos }, offset) = parse_zoned(value)?;
+    let offset = FixedOffset::east_opt(offset)?;
+    DateTime::from_timestamp(secs, nanos).map(|dt| dt.with_timezone(&offset))
+}
+
+/// Renders a DATE, TIME or TIMESTAMP_* value as ISO-8601; TIMESTAMP_LTZ is rendered in UTC.
+/// Returns `None` for other column types and unparsable values.
+pub(crate) fn format_iso8601(value: &str, column_type: &SnowflakeColumnType) -> Option<String> {
+    match column_type.snowflake_type() {
+        "date" => parse_date(value).map(|v| v.to_string()),
+        "time" => parse_time(value).map(|v| v.format("%H:%M:%S%.f").to_string()),
+        "timestamp_ntz" => {
+            parse_timestamp(value).map(|v| v.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+        }
+        "timestamp_ltz" => {
+            parse_timestamp(value).map(|v| v.and_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+        "timestamp_tz" => {
+            parse_timestamp_tz(value).map(|v| v.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(feature = "chrono-tz")]
+impl crate::SnowflakeRow {
+    /// Decodes a TIMESTAMP column as a `DateTime<FixedOffset>` and converts it into the named
+    /// time zone, e.g. `chrono_tz::America::New_York`.
+    pub fn get_in_timezone(
+        &self,
+        column_name: &str,
+        tz: chrono_tz::Tz,
+    ) -> crate::Result<DateTime<chrono_tz::Tz>> {
+        self.get::<DateTime<FixedOffset>>(column_name)
+            .map(|dt| dt.with_timezone(&tz))
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use time::{Date, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+    use super::{parse_days, parse_zoned, ScaledSeconds};
+    use crate::{
+        row::{impl_decode_from_str, unwrap_str},
+        types::SnowflakeColumnType,
+        Error, Result, SnowflakeDecode,
+    };
+
+    /// Julian day number of 1970-01-01.
+    const UNIX_EPOCH_JULIAN_DAY: i64 = 2_440_588;
+
+    fn offset_date_time(instant: ScaledSeconds) -> Option<OffsetDateTime> {
+        let nanos = i128::from(instant.secs) * 1_000_000_000 + i128::from(instant.nanos);
+        OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()
+    }
+
+    fn zoned_date_time(value: &str) -> Option<OffsetDateTime> {
+        let (instant, offset) = parse_zoned(value)?;
+        let offset = UtcOffset::from_whole_seconds(offset).ok()?;
+        offset_date_time(instant).map(|dt| dt.to_offset(offset))
+    }
+
+    impl_decode_from_str! {
+        Date => |value| {
+            parse_days(value)
+                .and_then(|days| i32::try_from(days + UNIX_EPOCH_JULIAN_DAY).ok())
+                .and_then(|julian_day| Date::from_julian_day(julian_day).ok())
+                .ok_or_else(|| Error::decode(format!("'{value}' is not Date type")))
+        };
+        Time => |value| {
+            ScaledSeconds::parse(value)
+                .filter(|t| (0..86_400).contains(&t.secs))
+                .and_then(|t| {
+                    Time::from_hms_nano(
+                        (t.secs / 3600) as u8,
+                        (t.secs / 60 % 60) as u8,
+                        (t.secs % 60) as u8,
+                        t.nanos,
+                    )
+                    .ok()
+                })
+                .ok_or_else(|| Error::decode(format!("'{value}' is not time")))
+        };
+        /// TIMESTAMP_TZ values decode to their wall-clock time at the stored offset.
+        PrimitiveDateTime => |value| {
+            zoned_date_time(value)
+                .or_else(|| ScaledSeconds::parse(value).and_then(offset_date_time))
+                .map(|dt| PrimitiveDateTime::new(dt.date(), dt.time()))
+                .ok_or_else(|| Error::decode(format!("'{value}' is not datetime")))
+        };
+        /// TIMESTAMP_TZ values keep their stored offset; TIMESTAMP_NTZ and TIMESTAMP_LTZ values
+        /// are read as UTC.
+        OffsetDateTime => |value| {
+            zoned_date_time(value)
+                .or_else(|| ScaledSeconds::parse(value).and_then(offset_date_time))
+                .ok_or_else(|| Error::decode(format!("'{value}' is not datetime")))
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scaled_seconds() {
+        let parse = |v| ScaledSeconds::parse(v).map(|s| (s.secs, s.nanos));
+        assert_eq!(parse("1700000000.123456789"), Some((1700000000, 123456789)));
+        assert_eq!(parse("1700000000.5"), Some((1700000000, 500000000)));
+        assert_eq!(parse("12"), Some((12, 0)));
+        assert_eq!(parse("-1.500000000"), Some((-2, 500000000)));
+        assert_eq!(parse("-3.000"), Some((-3, 0)));
+        assert_eq!(parse("1.1234567890"), None);
+        assert_eq!(parse("1e3"), None);
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("-.5"), None);
+    }
+
+    #[test]
+    fn test_parse_temporal() {
+        assert_eq!(parse_date("18262"), NaiveDate::from_ymd_opt(2020, 1, 1));
+        assert_eq!(parse_date("-1"), NaiveDate::from_ymd_opt(1969, 12, 31));
+        assert_eq!(
+            parse_time("45296.123456789"),
+            NaiveTime::from_hms_nano_opt(12, 34, 56, 123456789)
+        );
+        assert_eq!(parse_time("86400"), None);
+        assert_eq!(
+            parse_timestamp_tz("1700000000.000000000 1980").map(|dt| dt.to_rfc3339()),
+            Some("2023-11-15T07:13:20+09:00".to_string())
+        );
+        assert_eq!(
+            parse_timestamp("-0.000000001"),
+            NaiveDate::from_ymd_opt(1969, 12, 31)
+                .and_then(|d| d.and_hms_nano_opt(23, 59, 59, 999999999))
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_decode_time_crate() -> crate::Result<()> {
+        use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+        use crate::SnowflakeDecode;
+
+        let decode = |v: &str| Some(v.to_string());
+        assert_eq!(
+            Date::try_decode(&decode("18262"))?,
+            Date::from_calendar_date(2020, Month::January, 1).unwrap()
+        );
+        assert_eq!(
+            Date::try_decode(&decode("-1"))?,
+            Date::from_calendar_date(1969, Month::December, 31).unwrap()
+        );
+        assert_eq!(
+            Time::try_decode(&decode("45296.123456789"))?,
+            Time::from_hms_nano(12, 34, 56, 123456789).unwrap()
+        );
+        assert!(Time::try_decode(&decode("86400")).is_err());
+
+        let expected = PrimitiveDateTime::new(
+            Date::from_calendar_date(2023, Month::November, 14).unwrap(),
+            Time::from_hms_nano(22, 13, 20, 123000000).unwrap(),
+        );
+        assert_eq!(
+            PrimitiveDateTime::try_decode(&decode("1700000000.123000000"))?,
+            expected
+        );
+        assert_eq!(
+            OffsetDateTime::try_decode(&decode("1700000000.123000000"))?,
+            expected.assume_utc()
+        );
+
+        let zoned = OffsetDateTime::try_decode(&decode("1700000000.123000000 1980"))?;
+        assert_eq!(zoned, expected.assume_utc());
+        assert_eq!(zoned.offset(), UtcOffset::from_hms(9, 0, 0).unwrap());
+        assert_eq!(
+            PrimitiveDateTime::try_decode(&decode("1700000000.123000000 1980"))?,
+            PrimitiveDateTime::new(
+                Date::from_calendar_date(2023, Month::November, 15).unwrap(),
+                Time::from_hms_nano(7, 13, 20, 123000000).unwrap(),
+            )
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+//! Autocommit and explicit transactions of a session.
+
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{trace::warn_event, Error, Result, SnowflakeSession};
+
+/// The transaction state of a session, shared by its clones.
+pub(crate) struct TransactionState {
+    autocommit: AtomicBool,
+    /// Whether [`SnowflakeSession::run_in_transaction`] has a transaction open.
+    open: AtomicBool,
+}
+
+impl Default for TransactionState {
+    fn default() -> Self {
+        Self {
+            autocommit: AtomicBool::new(true),
+            open: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Marks a transaction of [`SnowflakeSession::run_in_transaction`] open until dropped, so
+/// that it is marked closed however the method ends, even if its future is dropped.
+struct OpenTransaction<'a>(&'a AtomicBool);
+
+impl<'a> OpenTransaction<'a> {
+    /// Marks the transaction open, or returns `None` if one already is.
+    fn open(open: &'a AtomicBool) -> Option<Self> {
+        (!open.swap(true, Ordering::SeqCst)).then_some(Self(open))
+    }
+}
+
+impl Drop for OpenTransaction<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl SnowflakeSession {
+    /// Turns `AUTOCOMMIT` on or off for the session and its clones. With autocommit off, a DML
+    /// statement starts a transaction that lasts until `COMMIT` or `ROLLBACK`.
+    pub async fn set_autocommit(&self, autocommit: bool) -> Result<()> {
+        let value = if autocommit { "TRUE" } else { "FALSE" };
+        self.query(format!("ALTER SESSION SET AUTOCOMMIT = {value}"))
+            .await?;
+        self.transaction
+            .autocommit
+            .store(autocommit, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Whether autocommit is on, as last set with [`SnowflakeSession::set_autocommit`]. Taken
+    /// to be on until then, Snowflake's default, even if the user or account sets `AUTOCOMMIT`
+    /// otherwise.
+    pub fn autocommit(&self) -> bool {
+        self.transaction.autocommit.load(Ordering::SeqCst)
+    }
+
+    /// Whether the session has a transaction open, by asking Snowflake, so that transactions
+    /// started by statements directly count too, e.g. before a pooled session is handed to its
+    /// next user.
+    pub async fn in_transaction(&self) -> Result<bool> {
+        let rows = self
+            .query("SELECT CURRENT_TRANSACTION() AS TRANSACTION_ID")
+            .await?;
+        let id = match rows.first() {
+            Some(row) => row.get::<Option<String>>("TRANSACTION_ID")?,
+            None => None,
+        };
+        Ok(id.is_some())
+    }
+
+    /// Runs the statements of `f` in a transaction: begins it, runs `f` with a clone of the
+    /// session, which shares the transaction, and commits if `f` succeeds. If `f` fails, the
+    /// transaction is rolled back before its error is returned; if the commit fails, it is
+    /// rolled back as well. A rollback that fails is logged as a warning with the `tracing`
+    /// feature, and the error that caused it is returned.
+    ///
+    /// If the returned future is dropped before it finishes, e.g. by a timeout, the transaction
+    /// is left open in Snowflake until the session ends or the next `COMMIT` or `ROLLBACK`, but
+    /// another one can be run.
+    ///
+    /// Fails with [`Error::Unsupported`] while a transaction of this method is already open on
+    /// the session or a clone of it, as Snowflake does not nest transactions.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// session
+    ///     .run_in_transaction(|txn| async move {
+    ///         txn.query("UPDATE accounts SET balance = balance - 10 WHERE id = 1")
+    ///             .await?;
+    ///         txn.query("UPDATE accounts SET balance = balance + 10 WHERE id = 2")
+    ///             .await?;
+    ///         Ok::<_, snowflake_connector_rs::Error>(())
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_in_transaction<T, E, F, Fut>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(SnowflakeSession) -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: From<Error>,
+    {
+        let Some(_open) = OpenTransaction::open(&self.transaction.open) else {
+            return Err(Error::Unsupported("nested transactions".into()).into());
+        };
+        self.transact(f).await
+    }
+
+    async fn transact<T, E, F, Fut>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(SnowflakeSession) -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: From<Error>,
+    {
+        self.query("BEGIN TRANSACTION").await?;
+        let value = match f(self.clone()).await {
+            Ok(value) => value,
+            Err(e) => {
+                self.rollback().await;
+                return Err(e);
+            }
+        };
+        if let Err(e) = self.query("COMMIT").await {
+            self.rollback().await;
+            return Err(e.into());
+        }
+        Ok(value)
+    }
+
+    /// Rolls back the open transaction, only logging a failure: the error that made the
+    /// transaction roll back matters more.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn rollback(&self) {
+        if let Err(e) = self.query("ROLLBACK").await {
+            warn_event!(error = %e, "rollback failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{metrics::NoMetrics, tests::session};
+
+    #[tokio::test]
+    async fn test_transaction_state() {
+        // An account that makes an invalid URL fails every statement before anything is sent.
+        let session = session("not an account", Arc::new(NoMetrics));
+        assert!(session.set_autocommit(false).await.is_err());
+        assert!(session.autocommit());
+
+        let result = session
+            .run_in_transaction(|_| async { Ok::<_, Error>(()) })
+            .await;
+        assert!(result.is_err());
+        assert!(!session.transaction.open.load(Ordering::SeqCst));
+
+        session.transaction.open.store(true, Ordering::SeqCst);
+        let nested = session
+            .clone()
+            .run_in_transaction(|_| async { Ok::<_, Error>(()) })
+            .await;
+        assert!(matches!(nested, Err(Error::Unsupported(_))));
+    }
+}
@@ -0,0 +1,268 @@
+//! Client-side encryption of staged files, as Snowflake's other connectors do it.
+//!
+//! Each file is encrypted with AES-CBC under a random key of its own, and that key is wrapped
+//! with AES-ECB under the query stage master key Snowflake sends with the PUT or GET statement.
+//! The wrapped key, the IV and a description of the master key go along as metadata of the file.
+
+use aes::{
+    cipher::{
+        block_padding::Pkcs7, generic_array::GenericArray, BlockCipher, BlockDecrypt,
+        BlockDecryptMut, BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit,
+    },
+    Aes128, Aes192, Aes256,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+/// The AES block size, which is also the size of the IV.
+const BLOCK_SIZE: usize = 16;
+
+/// The master key a file is encrypted for, from the response to a PUT or GET statement.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EncryptionMaterial {
+    query_stage_master_key: String,
+    query_id: String,
+    smk_id: i64,
+}
+
+/// Shows the material without its key.
+impl std::fmt::Debug for EncryptionMaterial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptionMaterial")
+            .field("query_id", &self.query_id)
+            .field("smk_id", &self.smk_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The metadata an encrypted file is stored with.
+pub(crate) struct FileEncryption {
+    /// The file key wrapped with the master key, in base64.
+    pub(crate) key: String,
+    /// The IV, in base64.
+    pub(crate) iv: String,
+    /// The description of the master key, as JSON.
+    pub(crate) matdesc: String,
+}
+
+impl FileEncryption {
+    /// The `encryptiondata` metadata of a file on Azure or Google Cloud Storage.
+    pub(crate) fn encryption_data(&self) -> String {
+        serde_json::json!({
+            "EncryptionMode": "FullBlob",
+            "WrappedContentKey": {
+                "KeyId": "symmKey1",
+                "EncryptedKey": self.key,
+                "Algorithm": "AES_CBC_256",
+            },
+            "EncryptionAgent": {
+                "Protocol": "1.0",
+                "EncryptionAlgorithm": "AES_CBC_256",
+            },
+            "ContentEncryptionIV": self.iv,
+            "KeyWrappingMetadata": {"EncryptionLibrary": "Java 5.3.0"},
+        })
+        .to_string()
+    }
+
+    /// Reads the metadata of a file on Azure or Google Cloud Storage from its
+    /// `encryptiondata` and `matdesc`.
+    pub(crate) fn from_encryption_data(encryption_data: &str, matdesc: String) -> Result<Self> {
+        let invalid = || Error::Communication("invalid encryption metadata of a file".into());
+        let data: serde_json::Value = serde_json::from_str(encryption_data)
+            .map_err(|e| Error::Json(e, encryption_data.to_string()))?;
+        let text = |value: &serde_json::Value| value.as_str().map(str::to_string);
+        Ok(Self {
+            key: text(&data["WrappedContentKey"]["EncryptedKey"]).ok_or_else(invalid)?,
+            iv: text(&data["ContentEncryptionIV"]).ok_or_else(invalid)?,
+            matdesc,
+        })
+    }
+}
+
+/// Encrypts `data` for the master key of `material`, with a key as long as the master key, and
+/// returns it with the metadata to store it with.
+pub(crate) fn encrypt(
+    material: &EncryptionMaterial,
+    data: &[u8],
+) -> Result<(Vec<u8>, FileEncryption)> {
+    let master_key = STANDARD
+        .decode(&material.query_stage_master_key)
+        .map_err(|e| Error::Communication(format!("invalid query stage master key: {e}")))?;
+    let mut file_key = vec![0; master_key.len()];
+    let mut iv = [0; BLOCK_SIZE];
+    rand::thread_rng().fill_bytes(&mut file_key);
+    rand::thread_rng().fill_bytes(&mut iv);
+    let (data, wrapped_key) = match master_key.len() {
+        16 => encrypt_with::<Aes128>(&master_key, &file_key, &iv, data),
+        24 => encrypt_with::<Aes192>(&master_key, &file_key, &iv, data),
+        32 => encrypt_with::<Aes256>(&master_key, &file_key, &iv, data),
+        len => {
+            return Err(Error::Communication(format!(
+                "invalid query stage master key of {len} bytes"
+            )))
+        }
+    };
+    let matdesc = serde_json::json!({
+        "queryId": material.query_id,
+        "smkId": material.smk_id.to_string(),
+        "keySize": (master_key.len() * 8).to_string(),
+    });
+    let encryption = FileEncryption {
+        key: STANDARD.encode(wrapped_key),
+        iv: STANDARD.encode(iv),
+        matdesc: matdesc.to_string(),
+    };
+    Ok((data, encryption))
+}
+
+/// Encrypts `data` with `file_key` in CBC mode and wraps `file_key`, padded, with `master_key`
+/// in ECB mode.
+fn encrypt_with<C>(master_key: &[u8], file_key: &[u8], iv: &[u8], data: &[u8]) -> (Vec<u8>, Vec<u8>)
+where
+    C: BlockCipher + BlockEncrypt + KeyInit,
+{
+    // The keys and the IV have the lengths the cipher takes, so creating it cannot fail.
+    let data = cbc::Encryptor::<C>::new_from_slices(file_key, iv)
+        .expect("the file key and IV fit the cipher")
+        .encrypt_padded_vec_mut::<Pkcs7>(data);
+    let cipher = C::new_from_slice(master_key).expect("the master key fits the cipher");
+    let mut wrapped_key = pad(file_key);
+    for block in wrapped_key.chunks_exact_mut(BLOCK_SIZE) {
+        cipher.encrypt_block(GenericArray::from_mut_slice(block));
+    }
+    (data, wrapped_key)
+}
+
+/// Decrypts a file downloaded from a stage with the master key of `material` and the metadata
+/// the file was stored with.
+pub(crate) fn decrypt(
+    material: &EncryptionMaterial,
+    data: &[u8],
+    encryption: &FileEncryption,
+) -> Result<Vec<u8>> {
+    let decode = |value: &str, what: &str| {
+        STANDARD
+            .decode(value)
+            .map_err(|e| Error::Communication(format!("invalid {what}: {e}")))
+    };
+    let master_key = decode(&material.query_stage_master_key, "query stage master key")?;
+    let wrapped_key = decode(&encryption.key, "file key")?;
+    let iv = decode(&encryption.iv, "IV")?;
+    if wrapped_key.is_empty() || wrapped_key.len() % BLOCK_SIZE != 0 || iv.len() != BLOCK_SIZE {
+        return Err(Error::Communication(
+            "invalid encryption metadata of a file".into(),
+        ));
+    }
+    let decrypted = match master_key.len() {
+        16 => decrypt_with::<Aes128>(&master_key, wrapped_key, &iv, data),
+        24 => decrypt_with::<Aes192>(&master_key, wrapped_key, &iv, data),
+        32 => decrypt_with::<Aes256>(&master_key, wrapped_key, &iv, data),
+        len => {
+            return Err(Error::Communication(format!(
+                "invalid query stage master key of {len} bytes"
+            )))
+        }
+    };
+    decrypted.ok_or_else(|| Error::Communication("failed to decrypt a downloaded file".into()))
+}
+
+/// Unwraps the file key with `master_key` in ECB mode and decrypts `data` with it in CBC mode,
+/// or returns `None` where a padding is invalid, e.g. because the keys do not match.
+fn decrypt_with<C>(
+    master_key: &[u8],
+    mut wrapped_key: Vec<u8>,
+    iv: &[u8],
+    data: &[u8],
+) -> Option<Vec<u8>>
+where
+    C: BlockCipher + BlockDecrypt + KeyInit,
+{
+    let cipher = C::new_from_slice(master_key).expect("the master key fits the cipher");
+    for block in wrapped_key.chunks_exact_mut(BLOCK_SIZE) {
+        cipher.decrypt_block(GenericArray::from_mut_slice(block));
+    }
+    let file_key = unpad(&wrapped_key)?;
+    cbc::Decryptor::<C>::new_from_slices(file_key, iv)
+        .ok()?
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .ok()
+}
+
+/// Removes the PKCS #7 padding of `data`.
+fn unpad(data: &[u8]) -> Option<&[u8]> {
+    let padding = *data.last()? as usize;
+    let valid = (1..=BLOCK_SIZE).contains(&padding)
+        && padding <= data.len()
+        && data[data.len() - padding..]
+            .iter()
+            .all(|&byte| byte as usize == padding);
+    valid.then(|| &data[..data.len() - padding])
+}
+
+/// Pads `data` to whole blocks as PKCS #7 does.
+fn pad(data: &[u8]) -> Vec<u8> {
+    let padding = BLOCK_SIZE - data.len() % BLOCK_SIZE;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + padding, padding as u8);
+    padded
+}
+
+/// The SHA-256 digest of a file, in base64, which Snowflake keeps as the `sfc-digest` metadata
+/// of a staged file to tell whether it has changed.
+pub(crate) fn digest(data: &[u8]) -> String {
+    STANDARD.encode(Sha256::digest(data))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn material(master_key: &[u8]) -> EncryptionMaterial {
+        EncryptionMaterial {
+            query_stage_master_key: STANDARD.encode(master_key),
+            query_id: "01b0-query".to_string(),
+            smk_id: 1234,
+        }
+    }
+
+    #[test]
+    fn test_encrypt() -> Result<()> {
+        let master_key = [7; 16];
+        let data = b"1,hello\r\n2,world\r\n";
+        let (encrypted, file) = encrypt(&material(&master_key), data)?;
+        assert_eq!(encrypted.len(), 32);
+        assert_eq!(STANDARD.decode(&file.key).unwrap().len(), 32);
+        assert_eq!(decrypt(&material(&master_key), &encrypted, &file)?, data);
+        assert!(decrypt(&material(&[8; 16]), &encrypted, &file).is_err());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&file.matdesc).unwrap(),
+            serde_json::json!({"queryId": "01b0-query", "smkId": "1234", "keySize": "128"})
+        );
+        let encryption_data: serde_json::Value =
+            serde_json::from_str(&file.encryption_data()).unwrap();
+        assert_eq!(
+            encryption_data["WrappedContentKey"]["EncryptedKey"],
+            file.key
+        );
+        assert_eq!(encryption_data["ContentEncryptionIV"], file.iv);
+        let read = FileEncryption::from_encryption_data(&file.encryption_data(), file.matdesc)?;
+        assert_eq!((read.key, read.iv), (file.key, file.iv));
+
+        let (encrypted, file) = encrypt(&material(&[7; 32]), data)?;
+        assert_eq!(encrypted.len(), 32);
+        assert_eq!(decrypt(&material(&[7; 32]), &encrypted, &file)?, data);
+        assert!(encrypt(&material(&[7; 10]), data).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_digest() {
+        assert_eq!(digest(b""), "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=");
+    }
+}
@@ -0,0 +1,951 @@
+//! Uploading local files to stages with PUT, and downloading staged files with GET.
+//!
+//! Snowflake only checks a PUT or GET statement and answers with where the stage keeps its files
+//! and short-lived credentials to reach them; the client compresses, encrypts and uploads the
+//! files itself, or downloads and decrypts them, straight from the stage's storage service.
+
+mod crypto;
+mod storage;
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Deserialize;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{spill::with_path, transport::HttpClient, Error, Result, SnowflakeSession};
+use crypto::{decrypt, digest, encrypt, EncryptionMaterial};
+use storage::{DownloadedFile, StageInfo, StoredFile};
+
+/// The most files transferred at once when Snowflake does not say.
+const DEFAULT_PARALLEL: usize = 4;
+
+/// File extensions of the compressed formats Snowflake reads, which are not compressed again.
+/// Parquet and ORC files compress their own columns.
+const COMPRESSED_EXTENSIONS: [(&str, &str); 8] = [
+    ("gz", "gzip"),
+    ("bz2", "bz2"),
+    ("br", "brotli"),
+    ("zst", "zstd"),
+    ("deflate", "deflate"),
+    ("raw_deflate", "raw_deflate"),
+    ("parquet", "parquet"),
+    ("orc", "orc"),
+];
+
+/// What a PUT or GET statement asks the client to transfer, from the response to it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Transfer {
+    /// `UPLOAD` for PUT, `DOWNLOAD` for GET.
+    command: String,
+    #[serde(default)]
+    auto_compress: bool,
+    /// The compression of the files of a PUT, e.g. `auto_detect` to tell it from their
+    /// extensions, or `none`.
+    source_compression: Option<String>,
+    /// Whether a PUT replaces staged files of the same name.
+    #[serde(default)]
+    overwrite: bool,
+    parallel: Option<usize>,
+    stage_info: StageInfo,
+    encryption_material: Option<EncryptionMaterials>,
+    /// The files a GET downloads, by their path under the stage's location.
+    #[serde(rename = "src_locations", default)]
+    src_locations: Vec<String>,
+    /// The presigned URLs of the files a GET downloads from a GCS stage, in order.
+    #[serde(default)]
+    presigned_urls: Vec<Option<String>>,
+}
+
+/// The master key of a PUT, or those of the files of a GET, in order.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EncryptionMaterials {
+    One(EncryptionMaterial),
+    Many(Vec<Option<EncryptionMaterial>>),
+}
+
+impl EncryptionMaterials {
+    fn upload(&self) -> Option<&EncryptionMaterial> {
+        match self {
+            EncryptionMaterials::One(material) => Some(material),
+            EncryptionMaterials::Many(materials) => materials.first()?.as_ref(),
+        }
+    }
+
+    /// The master key of the file at `index` of a GET.
+    fn download(&self, index: usize) -> Option<&EncryptionMaterial> {
+        match self {
+            EncryptionMaterials::One(material) => Some(material),
+            EncryptionMaterials::Many(materials) => materials.get(index)?.as_ref(),
+        }
+    }
+}
+
+/// How [`SnowflakeSession::put_with_options`] uploads files, the options of a `PUT` statement.
+#[derive(Debug, Clone)]
+pub struct PutOptions {
+    /// Whether files that are not compressed are compressed with gzip before they are
+    /// uploaded, `AUTO_COMPRESS`. Defaults to true.
+    pub auto_compress: bool,
+
+    /// The compression of the local files, `SOURCE_COMPRESSION`. Defaults to
+    /// [`SourceCompression::AutoDetect`].
+    pub source_compression: SourceCompression,
+
+    /// Whether files replace staged files of the same name, `OVERWRITE`. Otherwise a file
+    /// whose name is on the stage already is not uploaded, and reported as
+    /// [`TransferStatus::Skipped`]. Defaults to false.
+    pub overwrite: bool,
+
+    /// The most files uploaded at once, `PARALLEL`, from 1 to 99. Defaults to what Snowflake
+    /// chooses.
+    pub parallel: Option<usize>,
+}
+
+impl Default for PutOptions {
+    fn default() -> Self {
+        Self {
+            auto_compress: true,
+            source_compression: SourceCompression::AutoDetect,
+            overwrite: false,
+            parallel: None,
+        }
+    }
+}
+
+/// The compression of the files of a PUT, `SOURCE_COMPRESSION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceCompression {
+    /// Told from the extension of each file, e.g. `.gz` for gzip; files with other extensions
+    /// are taken not to be compressed.
+    AutoDetect,
+    None,
+    Gzip,
+    Bz2,
+    Brotli,
+    Zstd,
+    Deflate,
+    RawDeflate,
+}
+
+impl SourceCompression {
+    fn as_sql(self) -> &'static str {
+        match self {
+            SourceCompression::AutoDetect => "AUTO_DETECT",
+            SourceCompression::None => "NONE",
+            SourceCompression::Gzip => "GZIP",
+            SourceCompression::Bz2 => "BZ2",
+            SourceCompression::Brotli => "BROTLI",
+            SourceCompression::Zstd => "ZSTD",
+            SourceCompression::Deflate => "DEFLATE",
+            SourceCompression::RawDeflate => "RAW_DEFLATE",
+        }
+    }
+}
+
+/// How [`SnowflakeSession::get_with_options`] downloads files, the options of a `GET`
+/// statement.
+#[derive(Debug, Clone, Default)]
+pub struct GetOptions {
+    /// The most files downloaded at once, `PARALLEL`, from 1 to 99. Defaults to what Snowflake
+    /// chooses.
+    pub parallel: Option<usize>,
+
+    /// A regular expression the paths of the files to download must match, `PATTERN`, e.g.
+    /// `.*[.]csv[.]gz`. Defaults to every file under the stage path.
+    pub pattern: Option<String>,
+}
+
+/// What became of a file of a PUT or GET.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransferStatus {
+    Uploaded,
+    Downloaded,
+    /// Not uploaded, as a file of its name is on the stage and [`PutOptions::overwrite`] is
+    /// off.
+    Skipped,
+    /// The transfer of the file failed; the message says why.
+    Error,
+}
+
+/// A file of [`SnowflakeSession::put`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PutResult {
+    /// The name of the local file.
+    pub source: String,
+    /// The name of the file on the stage, e.g. with `.gz` added where it was compressed.
+    pub target: String,
+    pub source_size: u64,
+    /// The size of the file as uploaded, before encryption; 0 for a file that was not.
+    pub target_size: u64,
+    /// The compression of the local file, e.g. `none` or `gzip`.
+    pub source_compression: String,
+    /// The compression of the file on the stage.
+    pub target_compression: String,
+    pub status: TransferStatus,
+    /// Why the file was skipped or failed; empty for an uploaded file.
+    pub message: String,
+}
+
+/// A file of [`SnowflakeSession::get`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GetResult {
+    /// The path of the file on the stage, under the stage path it was downloaded from.
+    pub file: String,
+    /// The local file it was written to.
+    pub path: PathBuf,
+    /// The size of the local file, in bytes; 0 for a file that failed.
+    pub size: u64,
+    pub status: TransferStatus,
+    /// Why the file failed; empty for a downloaded file.
+    pub message: String,
+}
+
+/// The results of the files of a PUT or GET, in order, each with the error that failed it.
+type Outcomes<T> = Vec<(T, Option<Error>)>;
+
+/// The results of the files of a PUT or GET, or the error of the first file that failed, for
+/// callers that need every file.
+pub(crate) fn all_transferred<T>(outcomes: Outcomes<T>) -> Result<Vec<T>> {
+    outcomes
+        .into_iter()
+        .map(|(result, error)| match error {
+            Some(e) => Err(e),
+            None => Ok(result),
+        })
+        .collect()
+}
+
+fn results<T>(outcomes: Outcomes<T>) -> Vec<T> {
+    outcomes.into_iter().map(|(result, _)| result).collect()
+}
+
+impl SnowflakeSession {
+    /// Uploads local files to a stage, as the `PUT` command of SnowSQL does, and returns what
+    /// became of each, by file name.
+    ///
+    /// `local_path` names a file, or files with `*` and `?` wildcards in the file name, e.g.
+    /// `exports/orders_*.csv`. `stage_path` is the stage and path to upload into, written as in
+    /// SQL, e.g. `@load_stage/2024/`. Unless they are compressed already, the files are
+    /// compressed with gzip, and they are encrypted where the stage asks for it. Several files
+    /// are uploaded at once, and files already on the stage are skipped; see
+    /// [`SnowflakeSession::put_with_options`] to change that.
+    ///
+    /// A file that fails to upload does not stop the others: it is reported with
+    /// [`TransferStatus::Error`] and the reason in [`PutResult::message`].
+    pub async fn put(
+        &self,
+        local_path: impl AsRef<Path>,
+        stage_path: &str,
+    ) -> Result<Vec<PutResult>> {
+        self.put_with_options(local_path, stage_path, &PutOptions::default())
+            .await
+    }
+
+    /// Uploads local files to a stage as [`SnowflakeSession::put`] does, with the given
+    /// options.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{PutOptions, Result, SnowflakeSession, SourceCompression, TransferStatus};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let options = PutOptions {
+    ///     auto_compress: false,
+    ///     source_compression: SourceCompression::Gzip,
+    ///     parallel: Some(8),
+    ///     ..Default::default()
+    /// };
+    /// let results = session
+    ///     .put_with_options("exports/*.csv.gz", "@load_stage", &options)
+    ///     .await?;
+    /// for file in results.iter().filter(|file| file.status == TransferStatus::Error) {
+    ///     eprintln!("{} failed: {}", file.source, file.message);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn put_with_options(
+        &self,
+        local_path: impl AsRef<Path>,
+        stage_path: &str,
+        options: &PutOptions,
+    ) -> Result<Vec<PutResult>> {
+        let outcomes = self
+            .put_files(local_path.as_ref(), stage_path, options)
+            .await?;
+        Ok(results(outcomes))
+    }
+
+    /// Uploads files as [`SnowflakeSession::put_with_options`] does, with the error of each
+    /// file that failed.
+    pub(crate) async fn put_files(
+        &self,
+        local_path: &Path,
+        stage_path: &str,
+        options: &PutOptions,
+    ) -> Result<Outcomes<PutResult>> {
+        let files = expand_local_path(local_path)?;
+        if files.is_empty() {
+            return Err(Error::IO(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no file matches {}", local_path.display()),
+            )));
+        }
+        let transfer = self
+            .transfer(put_statement(local_path, stage_path, options), "UPLOAD")
+            .await?;
+        Ok(upload(self.http.clone(), transfer, files).await)
+    }
+
+    /// Downloads the files under a stage path written as in SQL, e.g. `@unload_stage/2024/`,
+    /// into a local directory, as the `GET` command of SnowSQL does, and returns what became of
+    /// each. The directory is created if it does not exist, and files of the same name in it
+    /// are overwritten. The files are decrypted where they were encrypted, but kept as
+    /// compressed as they were staged. Several files are downloaded at once.
+    ///
+    /// A file that fails to download does not stop the others: it is reported with
+    /// [`TransferStatus::Error`] and the reason in [`GetResult::message`].
+    pub async fn get(
+        &self,
+        stage_path: &str,
+        local_directory: impl AsRef<Path>,
+    ) -> Result<Vec<GetResult>> {
+        self.get_with_options(stage_path, local_directory, &GetOptions::default())
+            .await
+    }
+
+    /// Downloads files as [`SnowflakeSession::get`] does, with the given options.
+    pub async fn get_with_options(
+        &self,
+        stage_path: &str,
+        local_directory: impl AsRef<Path>,
+        options: &GetOptions,
+    ) -> Result<Vec<GetResult>> {
+        let outcomes = self
+            .get_files(stage_path, local_directory.as_ref(), options)
+            .await?;
+        Ok(results(outcomes))
+    }
+
+    /// Downloads files as [`SnowflakeSession::get_with_options`] does, with the error of each
+    /// file that failed.
+    pub(crate) async fn get_files(
+        &self,
+        stage_path: &str,
+        local_directory: &Path,
+        options: &GetOptions,
+    ) -> Result<Outcomes<GetResult>> {
+        std::fs::create_dir_all(local_directory).map_err(|e| with_path(e, local_directory))?;
+        let sql = get_statement(stage_path, local_directory, options);
+        let transfer = self.transfer(sql, "DOWNLOAD").await?;
+        Ok(download(self.http.clone(), transfer, local_directory.to_path_buf()).await)
+    }
+
+    /// Runs a PUT or GET statement and returns what it asks the client to transfer.
+    async fn transfer(&self, sql: String, command: &str) -> Result<Arc<Transfer>> {
+        let transfer = self
+            .query_lazy(sql)
+            .await?
+            .transfer
+            .filter(|transfer| transfer.command == command)
+            .ok_or_else(|| {
+                Error::Communication(format!("the response has no {command} instructions"))
+            })?;
+        Ok(Arc::new(transfer))
+    }
+}
+
+/// The PUT statement of `local_path`, with the options that differ from Snowflake's defaults.
+fn put_statement(local_path: &Path, stage_path: &str, options: &PutOptions) -> String {
+    let mut sql = format!("PUT {} {stage_path}", file_uri(local_path));
+    if !options.auto_compress {
+        sql.push_str(" AUTO_COMPRESS = FALSE");
+    }
+    if options.source_compression != SourceCompression::AutoDetect {
+        sql.push_str(" SOURCE_COMPRESSION = ");
+        sql.push_str(options.source_compression.as_sql());
+    }
+    if options.overwrite {
+        sql.push_str(" OVERWRITE = TRUE");
+    }
+    if let Some(parallel) = options.parallel {
+        sql.push_str(&format!(" PARALLEL = {}", parallel.clamp(1, 99)));
+    }
+    sql
+}
+
+/// The GET statement of the files under `stage_path` into `local_directory`.
+fn get_statement(stage_path: &str, local_directory: &Path, options: &GetOptions) -> String {
+    // The directory of a GET statement ends with a slash.
+    let mut sql = format!("GET {stage_path} {}", file_uri(&local_directory.join("")));
+    if let Some(parallel) = options.parallel {
+        sql.push_str(&format!(" PARALLEL = {}", parallel.clamp(1, 99)));
+    }
+    if let Some(pattern) = &options.pattern {
+        sql.push_str(&format!(" PATTERN = '{}'", pattern.replace('\'', "''")));
+    }
+    sql
+}
+
+/// Uploads `files` as `transfer` says, at most as many at once as it allows.
+async fn upload(
+    http: HttpClient,
+    transfer: Arc<Transfer>,
+    files: Vec<PathBuf>,
+) -> Outcomes<PutResult> {
+    let permits = Arc::new(Semaphore::new(
+        transfer.parallel.unwrap_or(DEFAULT_PARALLEL).max(1),
+    ));
+    let mut tasks = JoinSet::new();
+    for (index, file) in files.into_iter().enumerate() {
+        let (http, transfer, permits) = (http.clone(), Arc::clone(&transfer), Arc::clone(&permits));
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let target = upload_target(&file_name(&file), &transfer);
+            let outcome = match upload_file(&http, &transfer, file.clone(), &target).await {
+                Ok(result) => (result, None),
+                Err(e) => {
+                    let mut result = target.result(&file, TransferStatus::Error);
+                    result.message = e.to_string();
+                    (result, Some(e))
+                }
+            };
+            (index, outcome)
+        });
+    }
+    let mut outcomes = Vec::new();
+    while let Some(outcome) = tasks.join_next().await {
+        match outcome {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+/// Uploads a file, unless a file of its name is on the stage and the statement does not
+/// overwrite it.
+async fn upload_file(
+    http: &HttpClient,
+    transfer: &Arc<Transfer>,
+    file: PathBuf,
+    target: &UploadTarget,
+) -> Result<PutResult> {
+    if !transfer.overwrite && transfer.stage_info.exists(http, &target.name).await? {
+        let mut result = target.result(&file, TransferStatus::Skipped);
+        result.message = format!("{} is on the stage already", target.name);
+        return Ok(result);
+    }
+    let prepared = Arc::clone(transfer);
+    let (result, stored) =
+        tokio::task::spawn_blocking(move || prepare_upload(&file, &prepared)).await??;
+    transfer
+        .stage_info
+        .upload(http, &result.target, stored)
+        .await?;
+    Ok(result)
+}
+
+/// Downloads the files `transfer` names into `directory`, at most as many at once as it allows.
+async fn download(
+    http: HttpClient,
+    transfer: Arc<Transfer>,
+    directory: PathBuf,
+) -> Outcomes<GetResult> {
+    let permits = Arc::new(Semaphore::new(
+        transfer.parallel.unwrap_or(DEFAULT_PARALLEL).max(1),
+    ));
+    let directory = Arc::new(directory);
+    let mut tasks = JoinSet::new();
+    for index in 0..transfer.src_locations.len() {
+        let (http, transfer, permits) = (http.clone(), Arc::clone(&transfer), Arc::clone(&permits));
+        let directory = Arc::clone(&directory);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let outcome = match download_file(&http, &transfer, index, &directory).await {
+                Ok(result) => (result, None),
+                Err(e) => {
+                    let file = transfer.src_locations[index].clone();
+                    let result = GetResult {
+                        path: directory.join(local_name(&file)),
+                        file,
+                        size: 0,
+                        status: TransferStatus::Error,
+                        message: e.to_string(),
+                    };
+                    (result, Some(e))
+                }
+            };
+            (index, outcome)
+        });
+    }
+    let mut outcomes = Vec::new();
+    while let Some(outcome) = tasks.join_next().await {
+        match outcome {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+/// Downloads the file at `index` of a GET into `directory`.
+async fn download_file(
+    http: &HttpClient,
+    transfer: &Arc<Transfer>,
+    index: usize,
+    directory: &Arc<PathBuf>,
+) -> Result<GetResult> {
+    let file = &transfer.src_locations[index];
+    let presigned_url = transfer
+        .presigned_urls
+        .get(index)
+        .and_then(Option::as_deref);
+    let downloaded = transfer
+        .stage_info
+        .download(http, file, presigned_url)
+        .await?;
+    let (written, directory) = (Arc::clone(transfer), Arc::clone(directory));
+    tokio::task::spawn_blocking(move || write_download(&written, index, downloaded, &directory))
+        .await?
+}
+
+/// Decrypts the file at `index` of a GET where it was encrypted, and writes it into
+/// `directory` under its file name.
+fn write_download(
+    transfer: &Transfer,
+    index: usize,
+    downloaded: DownloadedFile,
+    directory: &Path,
+) -> Result<GetResult> {
+    let file = transfer.src_locations[index].clone();
+    let data = match &downloaded.encryption {
+        Some(encryption) => {
+            let material = transfer
+                .encryption_material
+                .as_ref()
+                .and_then(|materials| materials.download(index))
+                .ok_or_else(|| {
+                    Error::Communication(format!("no key to decrypt the staged file {file}"))
+                })?;
+            decrypt(material, &downloaded.data, encryption)?
+        }
+        None => downloaded.data,
+    };
+    let path = directory.join(local_name(&file));
+    std::fs::write(&path, &data).map_err(|e| with_path(e, &path))?;
+    Ok(GetResult {
+        file,
+        path,
+        size: data.len() as u64,
+        status: TransferStatus::Downloaded,
+        message: String::new(),
+    })
+}
+
+/// The name a staged file is downloaded as: the last part of its path.
+fn local_name(file: &str) -> &str {
+    file.rsplit('/').next().unwrap_or(file)
+}
+
+/// The name and compression a file of a PUT is stored with.
+struct UploadTarget {
+    name: String,
+    /// The compression of the local file, if it is compressed.
+    source_compression: Option<String>,
+    /// Whether the file is compressed with gzip before it is uploaded.
+    compress: bool,
+}
+
+impl UploadTarget {
+    fn compression(&self) -> &str {
+        match (self.compress, &self.source_compression) {
+            (true, _) => "gzip",
+            (false, Some(compression)) => compression,
+            (false, None) => "none",
+        }
+    }
+
+    /// The result of a file that was not uploaded.
+    fn result(&self, path: &Path, status: TransferStatus) -> PutResult {
+        PutResult {
+            source: file_name(path),
+            target: self.name.clone(),
+            source_size: std::fs::metadata(path).map_or(0, |metadata| metadata.len()),
+            target_size: 0,
+            source_compression: self
+                .source_compression
+                .clone()
+                .unwrap_or_else(|| "none".into()),
+            target_compression: self.compression().to_string(),
+            status,
+            message: String::new(),
+        }
+    }
+}
+
+/// How the local file `source` is stored: compressed with gzip unless it is compressed
+/// already, by its extension or the `SOURCE_COMPRESSION` of the statement, or the statement
+/// says not to.
+fn upload_target(source: &str, transfer: &Transfer) -> UploadTarget {
+    let stated = transfer
+        .source_compression
+        .as_deref()
+        .map(str::to_ascii_lowercase);
+    let source_compression = match stated.as_deref() {
+        None | Some("auto_detect") => compression_of(source).map(str::to_string),
+        Some("none") => None,
+        Some(_) => stated,
+    };
+    let compress = source_compression.is_none() && transfer.auto_compress;
+    UploadTarget {
+        name: match compress {
+            true => format!("{source}.gz"),
+            false => source.to_string(),
+        },
+        source_compression,
+        compress,
+    }
+}
+
+/// Reads a local file, compresses it as [`upload_target`] says, and encrypts it where the
+/// stage asks for it.
+fn prepare_upload(path: &Path, transfer: &Transfer) -> Result<(PutResult, StoredFile)> {
+    let data = std::fs::read(path).map_err(|e| with_path(e, path))?;
+    let source_size = data.len() as u64;
+    let source = file_name(path);
+    let target = upload_target(&source, transfer);
+    let data = match target.compress {
+        true => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&data)?;
+            encoder.finish()?
+        }
+        false => data,
+    };
+    let result = PutResult {
+        source_size,
+        target_size: data.len() as u64,
+        source,
+        target_compression: target.compression().to_string(),
+        source_compression: target.source_compression.unwrap_or_else(|| "none".into()),
+        target: target.name,
+        status: TransferStatus::Uploaded,
+        message: String::new(),
+    };
+    let digest = digest(&data);
+    let material = transfer
+        .encryption_material
+        .as_ref()
+        .and_then(EncryptionMaterials::upload);
+    let stored = match material {
+        Some(material) => {
+            let (data, encryption) = encrypt(material, &data)?;
+            StoredFile {
+                data,
+                digest,
+                encryption: Some(encryption),
+            }
+        }
+        None => StoredFile {
+            data,
+            digest,
+            encryption: None,
+        },
+    };
+    Ok((result, stored))
+}
+
+/// The compression of a file, told from its extension.
+fn compression_of(name: &str) -> Option<&'static str> {
+    let extension = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    COMPRESSED_EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, compression)| *compression)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The files a local path names: the file itself, or the files matching the wildcards of its
+/// file name, sorted by name.
+fn expand_local_path(path: &Path) -> Result<Vec<PathBuf>> {
+    let pattern = file_name(path);
+    if !pattern.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(directory).map_err(|e| with_path(e, directory))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && wildcard_match(&pattern, &name) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `name` matches `pattern`, in which `*` stands for any characters and `?` for one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (
+        pattern.chars().collect::<Vec<_>>(),
+        name.chars().collect::<Vec<_>>(),
+    );
+    // The position after the last `*` in the pattern, and the position in the name it matched up
+    // to, to backtrack to.
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// The `file://` URI of a local path in a PUT or GET statement, quoted.
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let path = if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    };
+    format!(
+        "'file://{}'",
+        path.replace('\\', "\\\\").replace('\'', "\\'")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("orders_*.csv", "orders_2024.csv"));
+        assert!(wildcard_match("orders_*.csv", "orders_.csv"));
+        assert!(wildcard_match("*", "anything"));
+        assert!(wildcard_match("data_?.csv", "data_1.csv"));
+        assert!(wildcard_match("*a*b", "xxaxxab"));
+        assert!(!wildcard_match("orders_*.csv", "orders_2024.csv.gz"));
+        assert!(!wildcard_match("data_?.csv", "data_10.csv"));
+        assert!(!wildcard_match("data.csv", "data_csv"));
+    }
+
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(
+            file_uri(Path::new("/tmp/it's here/data.csv")),
+            r"'file:///tmp/it\'s here/data.csv'"
+        );
+        assert_eq!(compression_of("data.csv.GZ"), Some("gzip"));
+        assert_eq!(compression_of("data.csv"), None);
+        assert_eq!(compression_of("data_0.parquet"), Some("parquet"));
+    }
+
+    #[test]
+    fn test_transfer_statements() {
+        let path = Path::new("/tmp/data.csv.gz");
+        assert_eq!(
+            put_statement(path, "@stage", &PutOptions::default()),
+            "PUT 'file:///tmp/data.csv.gz' @stage"
+        );
+        let options = PutOptions {
+            auto_compress: false,
+            source_compression: SourceCompression::Gzip,
+            overwrite: true,
+            parallel: Some(200),
+        };
+        assert_eq!(
+            put_statement(path, "@stage/2024/", &options),
+            "PUT 'file:///tmp/data.csv.gz' @stage/2024/ AUTO_COMPRESS = FALSE \
+             SOURCE_COMPRESSION = GZIP OVERWRITE = TRUE PARALLEL = 99"
+        );
+        let options = GetOptions {
+            parallel: Some(0),
+            pattern: Some(".*'s[.]csv".into()),
+        };
+        assert_eq!(
+            get_statement("@stage/", Path::new("/tmp/out"), &options),
+            "GET @stage/ 'file:///tmp/out/' PARALLEL = 1 PATTERN = '.*''s[.]csv'"
+        );
+    }
+
+    #[test]
+    fn test_upload_target() {
+        let transfer = |json: serde_json::Value| -> Transfer {
+            let mut transfer = serde_json::json!({
+                "command": "UPLOAD",
+                "stageInfo": {"locationType": "S3", "location": "bucket/stage/"},
+            });
+            transfer
+                .as_object_mut()
+                .unwrap()
+                .extend(json.as_object().unwrap().clone());
+            serde_json::from_value(transfer).unwrap()
+        };
+        let target = |source: &str, transfer: &Transfer| {
+            let target = upload_target(source, transfer);
+            (
+                target.name.clone(),
+                target.source_compression.clone(),
+                target.compression().to_string(),
+            )
+        };
+        let auto = transfer(serde_json::json!({"autoCompress": true}));
+        assert_eq!(
+            target("data.csv", &auto),
+            ("data.csv.gz".into(), None, "gzip".into())
+        );
+        assert_eq!(
+            target("data.csv.bz2", &auto),
+            ("data.csv.bz2".into(), Some("bz2".into()), "bz2".into())
+        );
+
+        let stated =
+            transfer(serde_json::json!({"autoCompress": true, "sourceCompression": "GZIP"}));
+        assert_eq!(
+            target("data", &stated),
+            ("data".into(), Some("gzip".into()), "gzip".into())
+        );
+        let none =
+            transfer(serde_json::json!({"autoCompress": false, "sourceCompression": "none"}));
+        assert_eq!(
+            target("data.csv.gz", &none),
+            ("data.csv.gz".into(), None, "none".into())
+        );
+    }
+
+    #[test]
+    fn test_prepare_upload() -> Result<()> {
+        let transfer: Transfer = serde_json::from_value(serde_json::json!({
+            "command": "UPLOAD",
+            "src_locations": ["/tmp/data.csv"],
+            "autoCompress": true,
+            "parallel": 4,
+            "stageInfo": {"locationType": "S3", "location": "bucket/stage/", "region": "us-west-2"},
+            "encryptionMaterial": {
+                "queryStageMasterKey": "AAAAAAAAAAAAAAAAAAAAAA==",
+                "queryId": "01b0",
+                "smkId": 42,
+            },
+        }))
+        .unwrap();
+        let directory =
+            std::env::temp_dir().join(format!("snowflake-put-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("data.csv");
+        std::fs::write(&path, "1,a\n".repeat(100))?;
+        std::fs::write(directory.join("data.csv.gz"), "")?;
+
+        let (result, stored) = prepare_upload(&path, &transfer)?;
+        assert_eq!(
+            (result.source.as_str(), result.target.as_str()),
+            ("data.csv", "data.csv.gz")
+        );
+        assert_eq!(
+            (
+                result.source_compression.as_str(),
+                result.target_compression.as_str()
+            ),
+            ("none", "gzip")
+        );
+        assert_eq!(result.source_size, 400);
+        assert!(result.target_size < 400);
+        assert_eq!(result.status, TransferStatus::Uploaded);
+        assert!(stored.encryption.is_some());
+        assert_eq!(stored.data.len() as u64, (result.target_size / 16 + 1) * 16);
+
+        let (result, stored) = prepare_upload(&directory.join("data.csv.gz"), &transfer)?;
+        assert_eq!(result.target, "data.csv.gz");
+        assert_eq!(result.target_compression, "gzip");
+        assert_eq!(stored.data.len(), 16);
+
+        assert_eq!(
+            expand_local_path(&directory.join("data.*"))?,
+            [directory.join("data.csv"), directory.join("data.csv.gz")]
+        );
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_download() -> Result<()> {
+        let master_key = [3; 16];
+        let transfer: Transfer = serde_json::from_value(serde_json::json!({
+            "command": "DOWNLOAD",
+            "src_locations": ["unload/data_0_0_0.csv.gz", "unload/data_0_1_0.csv.gz"],
+            "stageInfo": {"locationType": "S3", "location": "bucket/stage/", "region": "us-west-2"},
+            "encryptionMaterial": [
+                null,
+                {"queryStageMasterKey": "AwMDAwMDAwMDAwMDAwMDAw==", "queryId": "01b0", "smkId": 42},
+            ],
+        }))
+        .unwrap();
+        let directory =
+            std::env::temp_dir().join(format!("snowflake-get-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory)?;
+
+        let plain = DownloadedFile {
+            data: b"1,a\n".to_vec(),
+            encryption: None,
+        };
+        let result = write_download(&transfer, 0, plain, &directory)?;
+        assert_eq!(result.file, "unload/data_0_0_0.csv.gz");
+        assert_eq!(result.path, directory.join("data_0_0_0.csv.gz"));
+        assert_eq!(std::fs::read(&result.path)?, b"1,a\n");
+
+        let encrypted = || {
+            let (data, encryption) =
+                encrypt(&crypto::tests::material(&master_key), b"2,b\n").unwrap();
+            DownloadedFile {
+                data,
+                encryption: Some(encryption),
+            }
+        };
+        let result = write_download(&transfer, 1, encrypted(), &directory)?;
+        assert_eq!(
+            (result.size, std::fs::read(&result.path)?),
+            (4, b"2,b\n".to_vec())
+        );
+        // The first file has no key.
+        assert!(write_download(&transfer, 0, encrypted(), &directory).is_err());
+        std::fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,648 @@
+//! Requests to Snowflake whose failures carry the request and response they came from.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    HeaderMap, Method, StatusCode,
+};
+use reqwest::{Client, ClientBuilder, IntoUrl, RequestBuilder, Url};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::time::sleep;
+
+use crate::{
+    metrics::{ConnectorMetrics, RetryKind},
+    trace::debug_event,
+    Error, Result, TimeoutPhase,
+};
+
+/// The most bytes of a response body kept in an [`Error::HttpResponse`].
+const BODY_EXCERPT_LEN: usize = 1024;
+
+/// The wait before the first retry of a rate-limited request without `Retry-After`, doubled for
+/// each further retry.
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The HTTP client for requests to Snowflake, which retries rate-limited requests.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
+    client: Client,
+    rate_limit: RateLimitConfig,
+    metrics: Arc<dyn ConnectorMetrics>,
+    /// Whether errors keep the whole body of a response rather than its start.
+    full_bodies: bool,
+}
+
+/// How rate-limited requests are retried; see the matching fields of
+/// [`SnowflakeClientConfig`](crate::SnowflakeClientConfig).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RateLimitConfig {
+    pub(crate) max_retries: usize,
+    pub(crate) max_wait: Duration,
+}
+
+/// How the connections of an HTTP client are kept for reuse, e.g.
+/// [`SnowflakeClientConfig::api_pool`](crate::SnowflakeClientConfig::api_pool) or
+/// [`SnowflakeClientConfig::chunk_download_pool`](crate::SnowflakeClientConfig::chunk_download_pool).
+/// Fields left unset take the defaults documented where the config is used.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionPoolConfig {
+    /// The most idle connections kept open to each host.
+    pub max_idle_per_host: Option<usize>,
+    /// How long a connection is kept open without being used.
+    pub idle_timeout: Option<Duration>,
+    /// How long a connection is idle before TCP keepalive probes are sent on it, which keep
+    /// firewalls and NAT gateways from dropping a connection that waits in the pool.
+    pub tcp_keepalive: Option<Duration>,
+    /// Uses HTTP/1.1 only, never HTTP/2, for hosts that serve parallel downloads better over
+    /// several connections than over one. Off by default; HTTP/2 is used where the TLS
+    /// connection negotiates it.
+    pub http1_only: bool,
+}
+
+impl ConnectionPoolConfig {
+    /// Applies the pool settings to a client, with the defaults for the ones left unset.
+    pub(crate) fn apply(
+        &self,
+        mut builder: ClientBuilder,
+        default_max_idle_per_host: usize,
+        default_idle_timeout: Duration,
+        default_tcp_keepalive: Option<Duration>,
+    ) -> ClientBuilder {
+        builder = builder
+            .pool_max_idle_per_host(self.max_idle_per_host.unwrap_or(default_max_idle_per_host))
+            .pool_idle_timeout(self.idle_timeout.unwrap_or(default_idle_timeout))
+            .tcp_keepalive(self.tcp_keepalive.or(default_tcp_keepalive));
+        if self.http1_only {
+            builder = builder.http1_only();
+        }
+        builder
+    }
+}
+
+impl HttpClient {
+    pub(crate) fn new(
+        client: Client,
+        rate_limit: RateLimitConfig,
+        metrics: Arc<dyn ConnectorMetrics>,
+        full_bodies: bool,
+    ) -> Self {
+        Self {
+            client,
+            rate_limit,
+            metrics,
+            full_bodies,
+        }
+    }
+
+    /// The underlying client, for requests that are not sent to Snowflake.
+    pub(crate) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub(crate) fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub(crate) fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+}
+
+/// A successful response, with the request it answers.
+pub(crate) struct Reply {
+    method: Method,
+    url: Url,
+    status: StatusCode,
+    content_type: Option<String>,
+    pub(crate) body: String,
+    full_body: bool,
+}
+
+impl Reply {
+    pub(crate) fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Parses the body, failing with an [`Error::HttpResponse`] that shows the body if it is not
+    /// what was expected, e.g. a JSON body of an unexpected shape. An empty body fails with
+    /// [`Error::EmptyResponse`], and one whose content type is not JSON, such as an HTML page,
+    /// with [`Error::NotJson`].
+    pub(crate) fn parse<'a, T: Deserialize<'a>>(&'a self) -> Result<T> {
+        self.parse_showing(&self.body)
+    }
+
+    /// Parses the body like [`Reply::parse`], but leaves the body out of the error, for
+    /// responses that hold credentials.
+    pub(crate) fn parse_secret<'a, T: Deserialize<'a>>(&'a self) -> Result<T> {
+        self.parse_showing("")
+    }
+
+    fn parse_showing<'a, T: Deserialize<'a>>(&'a self, body: &str) -> Result<T> {
+        if self.body.trim().is_empty() {
+            return Err(Error::EmptyResponse {
+                url: redact_url(self.url.as_str()),
+                status: self.status,
+            });
+        }
+        if let Some(content_type) = self.content_type.as_ref().filter(|c| !is_json(c)) {
+            return Err(Error::NotJson {
+                url: redact_url(self.url.as_str()),
+                status: self.status,
+                content_type: content_type.clone(),
+                body: excerpt(body.trim().to_string(), self.full_body),
+            });
+        }
+        serde_json::from_str(&self.body).map_err(|e| {
+            http_response_error(
+                self.method.clone(),
+                self.url.as_str(),
+                self.status,
+                body,
+                Some(e),
+                self.full_body,
+            )
+        })
+    }
+}
+
+/// Sends a request and reads the body of its response, failing for an error status.
+///
+/// A request refused with 429 Too Many Requests is sent again after the wait its `Retry-After`
+/// header asks for, or a backoff without one, at most `max_wait`, until it has been retried
+/// `max_retries` times; then it fails with [`Error::RateLimited`].
+pub(crate) async fn send(http: &HttpClient, request: RequestBuilder) -> Result<Reply> {
+    send_accepting(http, request, &[]).await
+}
+
+/// Sends a request as [`send`] does, but returns the reply to it for the error statuses in
+/// `accepted` too, for APIs that describe an error in a body of their own, checked with
+/// [`Reply::status`].
+pub(crate) async fn send_accepting(
+    http: &HttpClient,
+    request: RequestBuilder,
+    accepted: &[StatusCode],
+) -> Result<Reply> {
+    let (method, url, start, response) = execute(http, request).await?;
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response
+        .text()
+        .await
+        .map_err(|e| request_error(e, start.elapsed()))?;
+    if !status.is_success() && !accepted.contains(&status) {
+        return Err(http_response_error(
+            method,
+            url.as_str(),
+            status,
+            &body,
+            None,
+            http.full_bodies,
+        ));
+    }
+    Ok(Reply {
+        method,
+        url,
+        status,
+        content_type,
+        body,
+        full_body: http.full_bodies,
+    })
+}
+
+/// Sends a request as [`send`] does, and returns the headers and the bytes of the body of its
+/// response, e.g. of a file downloaded from a stage.
+pub(crate) async fn download(
+    http: &HttpClient,
+    request: RequestBuilder,
+) -> Result<(HeaderMap, Vec<u8>)> {
+    let (method, url, start, response) = execute(http, request).await?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| request_error(e, start.elapsed()))?;
+    if !status.is_success() {
+        return Err(http_response_error(
+            method,
+            url.as_str(),
+            status,
+            &String::from_utf8_lossy(&body),
+            None,
+            http.full_bodies,
+        ));
+    }
+    Ok((headers, body.to_vec()))
+}
+
+/// Sends a request, retrying it while it is rate limited, and returns its response with the
+/// method and URL of the request and when it was first sent.
+async fn execute(
+    http: &HttpClient,
+    request: RequestBuilder,
+) -> Result<(Method, Url, Instant, reqwest::Response)> {
+    let mut request = request
+        .build()
+        .map_err(|e| request_error(e, Duration::ZERO))?;
+    let (method, url) = (request.method().clone(), request.url().clone());
+    let start = Instant::now();
+    let mut retries = 0;
+    loop {
+        // Requests with streaming bodies cannot be cloned, and are not retried.
+        let retry = request.try_clone();
+        let response = http
+            .client
+            .execute(request)
+            .await
+            .map_err(|e| request_error(e, start.elapsed()))?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok((method, url, start, response));
+        }
+        let Some(retry) = retry.filter(|_| retries < http.rate_limit.max_retries) else {
+            return Err(Error::RateLimited {
+                method,
+                url: redact_url(url.as_str()),
+                retries,
+            });
+        };
+        let backoff = RATE_LIMIT_BACKOFF.saturating_mul(1 << retries.min(16));
+        let wait = retry_after(response.headers())
+            .unwrap_or(backoff)
+            .min(http.rate_limit.max_wait);
+        retries += 1;
+        debug_event!(retries, ?wait, "rate limited, retrying");
+        http.metrics.on_retry(RetryKind::RateLimited);
+        sleep(wait).await;
+        request = retry;
+    }
+}
+
+/// Whether a content type is JSON, such as `application/json` or `application/problem+json`.
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.eq_ignore_ascii_case("application/json")
+        || essence.to_ascii_lowercase().ends_with("+json")
+}
+
+/// The wait a `Retry-After` header asks for, given either in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Turns a timeout of the HTTP client into an [`Error::Timeout`] after `elapsed`. Other errors
+/// are returned without the query string of their URL, as [`redact_url`] does.
+pub(crate) fn request_error(mut error: reqwest::Error, elapsed: Duration) -> Error {
+    if !error.is_timeout() {
+        if let Some(url) = error.url_mut() {
+            url.set_query(None);
+            url.set_fragment(None);
+        }
+        return error.into();
+    }
+    let phase = match error.is_connect() {
+        true => TimeoutPhase::Connect,
+        false => TimeoutPhase::Request,
+    };
+    Error::Timeout {
+        phase,
+        elapsed,
+        query_id: None,
+    }
+}
+
+/// Creates an [`Error::HttpResponse`], leaving out the query string of `url` as
+/// [`redact_url`] does. A JSON `body` is parsed again without the shape that was expected, for
+/// its top-level `code` and `message` and to be pretty-printed. Unless `full_body` is set, only
+/// the start of the body is kept.
+pub(crate) fn http_response_error(
+    method: Method,
+    url: &str,
+    status: StatusCode,
+    body: &str,
+    parse_error: Option<serde_json::Error>,
+    full_body: bool,
+) -> Error {
+    let url = redact_url(url);
+    let json = serde_json::from_str::<Value>(body).ok();
+    let field = |name| json.as_ref()?.get(name);
+    let code = field("code").and_then(|code| match code {
+        Value::String(code) => code.parse().ok(),
+        code => code.as_u64()?.try_into().ok(),
+    });
+    let message = field("message").and_then(Value::as_str).map(str::to_string);
+    let body = match &json {
+        Some(json) => serde_json::to_string_pretty(json).unwrap_or_default(),
+        None => body.trim().to_string(),
+    };
+    Error::HttpResponse {
+        method,
+        url,
+        status,
+        code,
+        message,
+        body: excerpt(body, full_body),
+        parse_error,
+    }
+}
+
+/// Returns the start of a response body for an error, or all of it with `full_body`.
+fn excerpt(body: String, full_body: bool) -> String {
+    match body.char_indices().find(|(i, _)| *i >= BODY_EXCERPT_LEN) {
+        Some((end, _)) if !full_body => format!("{}...", &body[..end]),
+        _ => body,
+    }
+}
+
+/// Returns `url` without its query string and fragment, which may hold credentials such as the
+/// signature of a presigned URL, for errors and logs.
+pub(crate) fn redact_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            url.set_query(None);
+            url.set_fragment(None);
+            url.to_string()
+        }
+        Err(_) => url.split(['?', '#']).next().unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::metrics::tests::CountingMetrics;
+
+    /// Serves each of `responses` on a connection of its own, in order, and returns the URL to
+    /// request.
+    fn serve(responses: Vec<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/session?token=secret",
+            listener.local_addr().unwrap()
+        );
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let _ = stream.read(&mut [0; 4096]);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: {}\r\n{headers}\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn client(max_retries: usize, metrics: Arc<CountingMetrics>) -> HttpClient {
+        let rate_limit = RateLimitConfig {
+            max_retries,
+            max_wait: Duration::from_millis(20),
+        };
+        HttpClient::new(Client::new(), rate_limit, metrics, false)
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_retries() -> Result<()> {
+        let too_many = |retry_after: &str| {
+            response(
+                "429 Too Many Requests",
+                &format!("Retry-After: {retry_after}\r\n"),
+                "",
+            )
+        };
+        let url = serve(vec![
+            too_many("0"),
+            too_many("Wed, 21 Oct 2015 07:28:00 GMT"),
+            too_many("3600"),
+            response("200 OK", "", "{}"),
+        ]);
+        let metrics = Arc::new(CountingMetrics::default());
+        let http = client(3, metrics.clone());
+        let reply = send(&http, http.get(&url)).await?;
+        assert_eq!(reply.body, "{}");
+        assert_eq!(metrics.retries(RetryKind::RateLimited), 3);
+
+        let url = serve(vec![too_many("0"), too_many("0")]);
+        let metrics = Arc::new(CountingMetrics::default());
+        let http = client(1, metrics.clone());
+        let Err(error) = send(&http, http.get(&url)).await else {
+            panic!("expected the request to fail");
+        };
+        let Error::RateLimited {
+            method,
+            url,
+            retries,
+        } = &error
+        else {
+            panic!("expected a rate limit error, got {error:?}");
+        };
+        assert_eq!((method, *retries), (&Method::GET, 1));
+        assert!(!url.contains("secret"));
+        assert!(error.is_retryable());
+        assert_eq!(metrics.retries(RetryKind::RateLimited), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_and_non_json_responses() -> Result<()> {
+        let page = "<html><body>Sign in to the guest network</body></html>";
+        let url = serve(vec![
+            response("200 OK", "", ""),
+            response("200 OK", "Content-Type: text/html; charset=utf-8\r\n", page),
+            response("200 OK", "Content-Type: text/plain\r\n", "maintenance"),
+            response("200 OK", "Content-Type: application/json\r\n", "{}"),
+        ]);
+        let http = client(0, Arc::new(CountingMetrics::default()));
+        let parse = |reply: Reply| reply.parse::<Value>();
+
+        let error = parse(send(&http, http.get(&url)).await?).unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::EmptyResponse { status: StatusCode::OK, url } if !url.contains("secret")
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("Snowflake returned an empty response from http://127.0.0.1:"));
+        assert!(error
+            .to_string()
+            .ends_with("/session (status 200 OK); check your account identifier"));
+
+        let error = parse(send(&http, http.get(&url)).await?).unwrap_err();
+        assert!(matches!(&error, Error::NotJson { body, .. } if body == page));
+        assert!(error
+            .to_string()
+            .starts_with("received HTML instead of JSON from http://"));
+        assert!(error.to_string().ends_with(&format!(
+            "you may be behind a captive portal or proxy: {page}"
+        )));
+
+        let error = parse(send(&http, http.get(&url)).await?).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("received text/plain instead of JSON"));
+
+        assert_eq!(
+            parse(send(&http, http.get(&url)).await?)?,
+            serde_json::json!({})
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_accepted_error_statuses() -> Result<()> {
+        let json = "Content-Type: application/json\r\n";
+        let body = r#"{"code":"002003","message":"SQL compilation error"}"#;
+        let url = serve(vec![
+            response("422 Unprocessable Entity", json, body),
+            response("422 Unprocessable Entity", json, body),
+        ]);
+        let http = client(0, Arc::new(CountingMetrics::default()));
+
+        let accepted = [StatusCode::UNPROCESSABLE_ENTITY];
+        let reply = send_accepting(&http, http.get(&url), &accepted).await?;
+        assert_eq!(reply.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(reply.parse::<Value>()?["code"], "002003");
+
+        let error = send(&http, http.get(&url)).await.err();
+        assert!(matches!(
+            error,
+            Some(Error::HttpResponse {
+                status: StatusCode::UNPROCESSABLE_ENTITY,
+                code: Some(2003),
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unexpected_json_response() {
+        #[derive(Debug, Deserialize)]
+        struct Expected {
+            #[allow(dead_code)]
+            data: Vec<u32>,
+        }
+        let body = format!(
+            r#"{{"code":"390400","message":"Snowflake is under maintenance.","success":false,"data":{{"notice":"{}"}}}}"#,
+            "x".repeat(2000)
+        );
+        let error = |full_body| {
+            http_response_error(
+                Method::POST,
+                "https://acct.snowflakecomputing.com/queries/v1/query-request",
+                StatusCode::OK,
+                &body,
+                serde_json::from_str::<Expected>(&body).err(),
+                full_body,
+            )
+        };
+
+        let excerpt = error(false);
+        let Error::HttpResponse {
+            code,
+            message,
+            body: excerpt_body,
+            ..
+        } = &excerpt
+        else {
+            panic!("expected an HTTP response error");
+        };
+        assert_eq!(*code, Some(390400));
+        assert_eq!(message.as_deref(), Some("Snowflake is under maintenance."));
+        assert!(excerpt_body.starts_with("{\n  \"code\": \"390400\",\n"));
+        assert!(excerpt_body.ends_with("..."));
+        assert!(excerpt.to_string().starts_with(
+            "POST https://acct.snowflakecomputing.com/queries/v1/query-request returned 200 OK \
+             with a body that could not be parsed (code 390400: Snowflake is under maintenance.): {"
+        ));
+
+        let Error::HttpResponse { body, .. } = error(true) else {
+            panic!("expected an HTTP response error");
+        };
+        assert!(body.contains(&"x".repeat(2000)) && !body.ends_with("..."));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let retry_after = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, value.parse().unwrap());
+            super::retry_after(&headers)
+        };
+        assert_eq!(retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let wait = retry_after(&later.to_rfc2822()).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90));
+        assert_eq!(retry_after("soon"), None);
+        assert_eq!(super::retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_http_response_error() {
+        let error = http_response_error(
+            Method::GET,
+            "https://acct.blob.core.windows.net/results/data_0?sv=2020-08-04&sig=secret",
+            StatusCode::FORBIDDEN,
+            "<Error><Code>AuthenticationFailed</Code></Error>\n",
+            None,
+            false,
+        );
+        assert_eq!(
+            error.to_string(),
+            "GET https://acct.blob.core.windows.net/results/data_0 returned 403 Forbidden: \
+             <Error><Code>AuthenticationFailed</Code></Error>"
+        );
+
+        let page = format!("<html>{}</html>", "é".repeat(1000));
+        let error = http_response_error(
+            Method::POST,
+            "https://acct.snowflakecomputing.com/queries/v1/query-request?requestId=1",
+            StatusCode::OK,
+            &page,
+            serde_json::from_str::<serde_json::Value>(&page).err(),
+            false,
+        );
+        let Error::HttpResponse { url, body, .. } = &error else {
+            panic!("expected an HTTP response error");
+        };
+        assert_eq!(
+            url,
+            "https://acct.snowflakecomputing.com/queries/v1/query-request"
+        );
+        assert!(body.starts_with("<html>é") && body.ends_with("é..."));
+        assert_eq!(body.len(), BODY_EXCERPT_LEN + 3);
+        assert!(error.to_string().starts_with(
+            "POST https://acct.snowflakecomputing.com/queries/v1/query-request returned 200 OK \
+             with a body that could not be parsed: <html>"
+        ));
+        let source = std::error::Error::source(&error).map(ToString::to_string);
+        assert_eq!(source.as_deref(), Some("expected value at line 1 column 1"));
+    }
+}
@@ -1 +1,57 @@
+/// Type information of a result column, taken from the `rowtype` metadata of a query response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnowflakeColumnType {
+    pub(crate) snowflake_type: String,
+    pub(crate) scale: Option<i64>,
+    pub(crate) nullable: bool,
+}
 
+impl SnowflakeColumnType {
+    /// Creates the type of a column, e.g. for a row made by
+    /// [`SnowflakeRow::from_values`](crate::SnowflakeRow::from_values). `snowflake_type` is the
+    /// name Snowflake uses in result metadata, such as `fixed`, `real`, `text`, `boolean`,
+    /// `date` or `timestamp_ntz`, and `scale` that of a `fixed` column.
+    pub fn new(snowflake_type: &str, scale: Option<i64>) -> Self {
+        Self {
+            snowflake_type: snowflake_type.to_ascii_lowercase(),
+            scale,
+            nullable: true,
+        }
+    }
+
+    /// Sets whether the column may hold NULL, which [`SnowflakeColumnType::new`] assumes it
+    /// may.
+    pub fn with_nullable(self, nullable: bool) -> Self {
+        Self { nullable, ..self }
+    }
+
+    /// The type of values nested in semi-structured data.
+    pub(crate) fn variant() -> Self {
+        Self::new("variant", None)
+    }
+
+    /// Lowercase Snowflake type name as sent by the server, e.g. `fixed`, `text` or `variant`.
+    pub fn snowflake_type(&self) -> &str {
+        &self.snowflake_type
+    }
+
+    /// Scale of a `fixed` (NUMBER) column.
+    pub fn scale(&self) -> Option<i64> {
+        self.scale
+    }
+
+    /// Whether the column may hold NULL, as the result metadata says.
+    pub fn nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// Whether the column holds semi-structured data (`VARIANT`, `OBJECT` or `ARRAY`).
+    pub fn is_semi_structured(&self) -> bool {
+        matches!(self.snowflake_type.as_str(), "variant" | "object" | "array")
+    }
+
+    /// Whether the column holds geospatial data (`GEOGRAPHY` or `GEOMETRY`).
+    pub fn is_geospatial(&self) -> bool {
+        matches!(self.snowflake_type.as_str(), "geography" | "geometry")
+    }
+}
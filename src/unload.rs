@@ -0,0 +1,296 @@
+//! Unloading the result of a query into local files through a stage, with `COPY INTO` and
+//! `GET`.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    catalog::{parse_object_name, quote_object_parts},
+    transfer::all_transferred,
+    GetOptions, GetResult, Result, SnowflakeRow, SnowflakeSession,
+};
+
+/// How [`SnowflakeSession::unload`] writes the files.
+#[derive(Debug, Clone)]
+pub struct UnloadOptions {
+    /// The format of the files. Defaults to [`UnloadFormat::Csv`].
+    pub format: UnloadFormat,
+
+    /// The compression of the files. Defaults to [`UnloadCompression::Auto`].
+    pub compression: UnloadCompression,
+
+    /// Whether the files keep the names of the columns: on the first line of a CSV file, and
+    /// as the names of the columns of a parquet file rather than `_COL_0`, `_COL_1` and so on.
+    /// Defaults to true.
+    pub header: bool,
+
+    /// The most bytes Snowflake writes into one file; a larger result is unloaded into several
+    /// files. Defaults to Snowflake's 16 MB.
+    pub max_file_size: Option<u64>,
+
+    /// The most files downloaded at once. Defaults to what Snowflake chooses.
+    pub parallel: Option<usize>,
+}
+
+impl Default for UnloadOptions {
+    fn default() -> Self {
+        Self {
+            format: UnloadFormat::Csv,
+            compression: UnloadCompression::Auto,
+            header: true,
+            max_file_size: None,
+            parallel: None,
+        }
+    }
+}
+
+/// The format of the files of [`SnowflakeSession::unload`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnloadFormat {
+    /// Comma-separated values, enclosed in double quotes where they need to be. NULL is written
+    /// as `\N`.
+    Csv,
+    Parquet,
+}
+
+/// The `COMPRESSION` of the files of [`SnowflakeSession::unload`]. CSV files take any but
+/// [`Snappy`](UnloadCompression::Snappy) and [`Lzo`](UnloadCompression::Lzo); parquet files
+/// only those, [`Auto`](UnloadCompression::Auto) and [`None`](UnloadCompression::None).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnloadCompression {
+    /// gzip for CSV, Snappy for parquet.
+    Auto,
+    None,
+    Gzip,
+    Bz2,
+    Brotli,
+    Zstd,
+    Deflate,
+    RawDeflate,
+    Snappy,
+    Lzo,
+}
+
+impl UnloadCompression {
+    fn as_sql(self) -> &'static str {
+        match self {
+            UnloadCompression::Auto => "AUTO",
+            UnloadCompression::None => "NONE",
+            UnloadCompression::Gzip => "GZIP",
+            UnloadCompression::Bz2 => "BZ2",
+            UnloadCompression::Brotli => "BROTLI",
+            UnloadCompression::Zstd => "ZSTD",
+            UnloadCompression::Deflate => "DEFLATE",
+            UnloadCompression::RawDeflate => "RAW_DEFLATE",
+            UnloadCompression::Snappy => "SNAPPY",
+            UnloadCompression::Lzo => "LZO",
+        }
+    }
+}
+
+/// A file written by [`SnowflakeSession::unload`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct UnloadedFile {
+    /// The local file.
+    pub path: PathBuf,
+    /// The rows in the file.
+    pub rows: u64,
+    /// The size of the file, in bytes.
+    pub size: u64,
+}
+
+impl SnowflakeSession {
+    /// Unloads the result of a query, or a whole table or view, into files in a local
+    /// directory, through a temporary stage.
+    ///
+    /// `query_or_table` is the name of a table or view, written as in SQL, e.g.
+    /// `sales.public.orders`, or else a query. Its rows are written to the stage with one
+    /// `COPY INTO` statement, in files of the format and compression of `options`, and
+    /// downloaded into `local_directory` with [`SnowflakeSession::get`]; the directory is
+    /// created if it does not exist. Then the stage is removed, also when the unload fails.
+    ///
+    /// The files are returned in the order Snowflake lists them, with the rows in each. An
+    /// empty result writes no files.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession, UnloadFormat, UnloadOptions};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// let options = UnloadOptions {
+    ///     format: UnloadFormat::Parquet,
+    ///     ..Default::default()
+    /// };
+    /// let query = "SELECT * FROM orders WHERE ordered_at >= '2024-01-01'";
+    /// for file in session.unload(query, "exports/orders", &options).await? {
+    ///     println!("{}: {} rows", file.path.display(), file.rows);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn unload(
+        &self,
+        query_or_table: &str,
+        local_directory: impl AsRef<Path>,
+        options: &UnloadOptions,
+    ) -> Result<Vec<UnloadedFile>> {
+        let source = match parse_object_name(query_or_table) {
+            Ok(table) => quote_object_parts(&table),
+            Err(_) => format!("({query_or_table})"),
+        };
+        let stage = format!("SNOWFLAKE_UNLOAD_{}", uuid::Uuid::new_v4().simple()).to_uppercase();
+        let stage = self.create_temp_stage(&stage, None).await?;
+        let result = self
+            .unload_through(&source, &stage, local_directory.as_ref(), options)
+            .await;
+        let removed = self.remove_from_stage(&format!("@{stage}")).await;
+        let dropped = self.query(format!("DROP STAGE IF EXISTS {stage}")).await;
+        let files = result?;
+        removed?;
+        dropped?;
+        Ok(files)
+    }
+
+    async fn unload_through(
+        &self,
+        source: &str,
+        stage: &str,
+        local_directory: &Path,
+        options: &UnloadOptions,
+    ) -> Result<Vec<UnloadedFile>> {
+        let results = self.query(copy_statement(source, stage, options)).await?;
+        if results.iter().all(|row| row_count(row).ok() == Some(0)) {
+            return Ok(vec![]);
+        }
+        let get = GetOptions {
+            parallel: options.parallel,
+            ..Default::default()
+        };
+        let downloaded = self
+            .get_files(&format!("@{stage}/"), local_directory, &get)
+            .await?;
+        let downloaded = all_transferred(downloaded)?;
+        unloaded_files(&downloaded, &results)
+    }
+}
+
+/// The `COPY INTO` statement that writes the rows of `source` into files on `stage`.
+fn copy_statement(source: &str, stage: &str, options: &UnloadOptions) -> String {
+    let format = match options.format {
+        UnloadFormat::Csv => "TYPE = CSV FIELD_OPTIONALLY_ENCLOSED_BY = '\"'",
+        UnloadFormat::Parquet => "TYPE = PARQUET",
+    };
+    let mut sql = format!(
+        "COPY INTO @{stage}/data_ FROM {source} FILE_FORMAT = ({format} COMPRESSION = {}) \
+         HEADER = {} DETAILED_OUTPUT = TRUE",
+        options.compression.as_sql(),
+        match options.header {
+            true => "TRUE",
+            false => "FALSE",
+        }
+    );
+    if let Some(max_file_size) = options.max_file_size {
+        sql.push_str(&format!(" MAX_FILE_SIZE = {max_file_size}"));
+    }
+    sql
+}
+
+fn row_count(row: &SnowflakeRow) -> Result<u64> {
+    row.get("row_count")
+}
+
+/// Matches the downloaded files to the rows of the detailed output of `COPY INTO`, which names
+/// each file it wrote and its rows.
+fn unloaded_files(downloaded: &[GetResult], results: &[SnowflakeRow]) -> Result<Vec<UnloadedFile>> {
+    let mut files = Vec::with_capacity(downloaded.len());
+    for file in downloaded {
+        let name = file.file.rsplit('/').next().unwrap_or(&file.file);
+        let mut rows = 0;
+        for row in results {
+            let written: String = row.get("file_name")?;
+            if written.rsplit('/').next() == Some(name) {
+                rows = row_count(row)?;
+                break;
+            }
+        }
+        files.push(UnloadedFile {
+            path: file.path.clone(),
+            rows,
+            size: file.size,
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SnowflakeColumnType, TransferStatus};
+
+    #[test]
+    fn test_copy_statement() {
+        let options = UnloadOptions {
+            max_file_size: Some(1 << 20),
+            ..Default::default()
+        };
+        assert_eq!(
+            copy_statement("\"DB\".\"PUBLIC\".\"ORDERS\"", "\"STAGE\"", &options),
+            "COPY INTO @\"STAGE\"/data_ FROM \"DB\".\"PUBLIC\".\"ORDERS\" FILE_FORMAT = \
+             (TYPE = CSV FIELD_OPTIONALLY_ENCLOSED_BY = '\"' COMPRESSION = AUTO) HEADER = TRUE \
+             DETAILED_OUTPUT = TRUE MAX_FILE_SIZE = 1048576"
+        );
+        let options = UnloadOptions {
+            format: UnloadFormat::Parquet,
+            compression: UnloadCompression::Snappy,
+            header: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            copy_statement("(SELECT 1)", "\"STAGE\"", &options),
+            "COPY INTO @\"STAGE\"/data_ FROM (SELECT 1) FILE_FORMAT = \
+             (TYPE = PARQUET COMPRESSION = SNAPPY) HEADER = FALSE DETAILED_OUTPUT = TRUE"
+        );
+    }
+
+    #[test]
+    fn test_unloaded_files() -> Result<()> {
+        let columns = [
+            ("FILE_NAME", SnowflakeColumnType::new("text", None)),
+            ("FILE_SIZE", SnowflakeColumnType::new("fixed", Some(0))),
+            ("ROW_COUNT", SnowflakeColumnType::new("fixed", Some(0))),
+        ];
+        let row = |name: &str, rows: &str| {
+            SnowflakeRow::from_values(
+                columns.clone(),
+                vec![
+                    Some(name.to_string()),
+                    Some("100".to_string()),
+                    Some(rows.to_string()),
+                ],
+            )
+        };
+        let results = [
+            row("data_0_0_0.csv.gz", "10"),
+            row("data_0_1_0.csv.gz", "5"),
+        ];
+        let downloaded = |file: &str| GetResult {
+            file: file.to_string(),
+            path: PathBuf::from("/tmp/out").join(file),
+            size: 120,
+            status: TransferStatus::Downloaded,
+            message: String::new(),
+        };
+        let files = unloaded_files(
+            &[
+                downloaded("data_0_1_0.csv.gz"),
+                downloaded("data_0_0_0.csv.gz"),
+            ],
+            &results,
+        )?;
+        assert_eq!(
+            files.iter().map(|file| file.rows).collect::<Vec<_>>(),
+            [5, 10]
+        );
+        assert_eq!(files[0].path, PathBuf::from("/tmp/out/data_0_1_0.csv.gz"));
+        assert_eq!(files[0].size, 120);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+//! Compact storage of the values of a result.
+//!
+//! Results can have hundreds of millions of values, so rather than a `String` per value, the
+//! values of a chunk are kept in one string with the position of each value in it. Rows of the
+//! chunk share this storage through an `Arc`.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+
+/// The size of the lengths at the start of the binary form of [`RowValues`].
+const HEADER_LEN: usize = 24;
+
+/// The range a NULL is written as in the binary form, which no value can have.
+const NULL_CELL: (u32, u32) = (u32::MAX, 0);
+
+/// The most cells or rows allocated for ahead of reading them, against corrupt lengths.
+const MAX_PREALLOCATED: usize = 1 << 20;
+
+/// The values of consecutive rows.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct RowValues {
+    text: String,
+    /// The range of each value in `text`, row after row; `None` for NULL.
+    cells: Vec<Option<(u32, u32)>>,
+    /// The index in `cells` of the first value of each row.
+    row_starts: Vec<usize>,
+}
+
+impl RowValues {
+    /// Stores rows of owned values.
+    ///
+    /// # Panics
+    ///
+    /// If the values add up to more than 4 GiB.
+    pub(crate) fn from_rows(rows: Vec<Vec<Option<String>>>) -> Self {
+        let mut values = Self::default();
+        for row in rows {
+            values.start_row();
+            for value in row {
+                values
+                    .push(value.as_deref())
+                    .expect("row values exceed 4 GiB");
+            }
+        }
+        values
+    }
+
+    /// The number of rows.
+    pub(crate) fn len(&self) -> usize {
+        self.row_starts.len()
+    }
+
+    /// The number of values in row `row`.
+    pub(crate) fn row_len(&self, row: usize) -> usize {
+        self.row(row).len()
+    }
+
+    /// The value at `column` of row `row`, or `None` for NULL.
+    pub(crate) fn get(&self, row: usize, column: usize) -> Option<&str> {
+        self.row(row)[column].map(|(start, end)| &self.text[start as usize..end as usize])
+    }
+
+    /// The number of bytes [`RowValues::write_to`] writes.
+    pub(crate) fn encoded_len(&self) -> u64 {
+        (HEADER_LEN + self.text.len() + 8 * self.cells.len() + 8 * self.row_starts.len()) as u64
+    }
+
+    /// Writes the values in a binary form read back by [`RowValues::read_from`]: the lengths of
+    /// the text, cells and rows, then the text, the cell ranges and the row starts, all integers
+    /// little-endian.
+    pub(crate) fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        for len in [self.text.len(), self.cells.len(), self.row_starts.len()] {
+            w.write_all(&(len as u64).to_le_bytes())?;
+        }
+        w.write_all(self.text.as_bytes())?;
+        for cell in &self.cells {
+            let (start, end) = cell.unwrap_or(NULL_CELL);
+            w.write_all(&start.to_le_bytes())?;
+            w.write_all(&end.to_le_bytes())?;
+        }
+        for start in &self.row_starts {
+            w.write_all(&(*start as u64).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads values written by [`RowValues::write_to`], checking that they are consistent.
+    pub(crate) fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut lens = [0; 3];
+        for len in &mut lens {
+            *len = usize::try_from(read_u64(r)?).map_err(|_| invalid("length out of range"))?;
+        }
+        let [text_len, cell_count, row_count] = lens;
+
+        let mut text = vec![];
+        r.by_ref().take(text_len as u64).read_to_end(&mut text)?;
+        if text.len() != text_len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let text = String::from_utf8(text).map_err(|_| invalid("values are not UTF-8"))?;
+
+        let mut cells = Vec::with_capacity(cell_count.min(MAX_PREALLOCATED));
+        for _ in 0..cell_count {
+            let mut buf = [0; 8];
+            r.read_exact(&mut buf)?;
+            let start = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+            let end = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+            if (start, end) == NULL_CELL {
+                cells.push(None);
+            } else if text.get(start as usize..end as usize).is_some() {
+                cells.push(Some((start, end)));
+            } else {
+                return Err(invalid("value out of range"));
+            }
+        }
+
+        let mut row_starts = Vec::with_capacity(row_count.min(MAX_PREALLOCATED));
+        for _ in 0..row_count {
+            let start = read_u64(r)? as usize;
+            if start > cell_count || row_starts.last().is_some_and(|last| start < *last) {
+                return Err(invalid("row out of range"));
+            }
+            row_starts.push(start);
+        }
+
+        Ok(Self {
+            text,
+            cells,
+            row_starts,
+        })
+    }
+
+    fn row(&self, row: usize) -> &[Option<(u32, u32)>] {
+        let start = self.row_starts[row];
+        let end = self
+            .row_starts
+            .get(row + 1)
+            .copied()
+            .unwrap_or(self.cells.len());
+        &self.cells[start..end]
+    }
+
+    fn start_row(&mut self) {
+        self.row_starts.push(self.cells.len());
+    }
+
+    fn push(&mut self, value: Option<&str>) -> Result<(), &'static str> {
+        let cell = match value {
+            Some(value) => {
+                let start = self.text.len();
+                self.text.push_str(value);
+                match (u32::try_from(start), u32::try_from(self.text.len())) {
+                    (Ok(start), Ok(end)) => Some((start, end)),
+                    _ => return Err("row values exceed 4 GiB"),
+                }
+            }
+            None => None,
+        };
+        self.cells.push(cell);
+        Ok(())
+    }
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Reads the JSON form of a rowset or chunk: an array of rows, each an array of strings and
+/// `null`s.
+impl<'de> de::Deserialize<'de> for RowValues {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(RowsVisitor)
+    }
+}
+
+struct RowsVisitor;
+
+impl<'de> Visitor<'de> for RowsVisitor {
+    type Value = RowValues;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of rows")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = RowValues::default();
+        while seq.next_element_seed(RowSeed(&mut values))?.is_some() {}
+        Ok(values)
+    }
+}
+
+struct RowSeed<'a>(&'a mut RowValues);
+
+impl<'de> DeserializeSeed<'de> for RowSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RowSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of values")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        self.0.start_row();
+        while seq.next_element_seed(ValueSeed(self.0))?.is_some() {}
+        Ok(())
+    }
+}
+
+struct ValueSeed<'a>(&'a mut RowValues);
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_option(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ValueSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string or null")
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<(), E> {
+        self.0.push(None).map_err(E::custom)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_str(self)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<(), E> {
+        self.0.push(Some(value)).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_row_values() {
+        let values: RowValues =
+            serde_json::from_str(r#"[["1", null, "a\"b"], [], ["", "é"]]"#).unwrap();
+        let mut buf = vec![];
+        values.write_to(&mut buf).unwrap();
+        assert_eq!(buf.len() as u64, values.encoded_len());
+        assert_eq!(RowValues::read_from(&mut buf.as_slice()).unwrap(), values);
+
+        let empty = RowValues::default();
+        let mut buf = vec![];
+        empty.write_to(&mut buf).unwrap();
+        assert_eq!(RowValues::read_from(&mut buf.as_slice()).unwrap(), empty);
+
+        let mut truncated = vec![];
+        values.write_to(&mut truncated).unwrap();
+        truncated.pop();
+        assert!(RowValues::read_from(&mut truncated.as_slice()).is_err());
+
+        // A cell pointing into the middle of "é".
+        let mut buf = vec![];
+        RowValues::from_rows(vec![vec![Some("é".into())]])
+            .write_to(&mut buf)
+            .unwrap();
+        buf[HEADER_LEN + 2..HEADER_LEN + 6].copy_from_slice(&1u32.to_le_bytes());
+        let err = RowValues::read_from(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_parse_row_values() {
+        let values: RowValues =
+            serde_json::from_str(r#"[["1", null, "a\"b"], [], ["", "é"]]"#).unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(
+            (0..3).map(|i| values.get(0, i)).collect::<Vec<_>>(),
+            vec![Some("1"), None, Some("a\"b")]
+        );
+        assert_eq!(values.row_len(1), 0);
+        assert_eq!(values.get(2, 0), Some(""));
+        assert_eq!(values.get(2, 1), Some("é"));
+        assert_eq!(
+            values,
+            RowValues::from_rows(vec![
+                vec![Some("1".into()), None, Some("a\"b".into())],
+                vec![],
+                vec![Some("".into()), Some("é".into())],
+            ])
+        );
+
+        assert!(serde_json::from_str::<RowValues>(r#"[["1", 2]]"#).is_err());
+        assert!(serde_json::from_str::<RowValues>(r#"["1"]"#).is_err());
+        assert_eq!(serde_json::from_str::<RowValues>("[]").unwrap().len(), 0);
+    }
+}
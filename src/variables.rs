@@ -0,0 +1,131 @@
+//! Session variables, set with `SET` and read in statements as `$name`.
+
+use std::sync::PoisonError;
+
+use serde::Serialize;
+
+use crate::{bind::to_bind_value, Error, Result, SnowflakeDecode, SnowflakeSession};
+
+impl SnowflakeSession {
+    /// Sets a session variable, read in later statements as `$name`. The value is serialized
+    /// with serde, as [`QueryRequest::bind`](crate::QueryRequest::bind) serializes it, and
+    /// written as the literal of the type it would be bound with: e.g. a string as a quoted
+    /// string, a sequence or a map, even an empty one, as `PARSE_JSON` of its JSON, and bytes
+    /// or a date wrapped in [`Bind`](crate::Bind) as `BINARY` or `DATE`.
+    ///
+    /// Variable names are identifiers made of letters, digits, `_` and `$`, not starting with a
+    /// digit, and are case-insensitive. The names set here are tracked on the session, shared
+    /// with its clones, so that [`SnowflakeSession::unset_variables`] can reset them.
+    ///
+    /// ```rust
+    /// # use snowflake_connector_rs::{Result, SnowflakeSession};
+    /// # async fn run(session: &SnowflakeSession) -> Result<()> {
+    /// session.set_variable("min_date", "2024-01-01").await?;
+    /// let rows = session
+    ///     .query("SELECT * FROM events WHERE created_at >= $min_date")
+    ///     .await?;
+    /// let min_date: String = session.get_variable("min_date").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_variable<T: Serialize + ?Sized>(&self, name: &str, value: &T) -> Result<()> {
+        let name = variable_name(name)?;
+        let value = to_bind_value(value)?.to_sql_literal();
+        self.query(format!("SET {name} = {value}")).await?;
+        let mut names = self.tracked_variables();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        Ok(())
+    }
+
+    /// Unsets a session variable.
+    pub async fn unset_variable(&self, name: &str) -> Result<()> {
+        let name = variable_name(name)?;
+        self.query(format!("UNSET {name}")).await?;
+        self.tracked_variables().retain(|set| *set != name);
+        Ok(())
+    }
+
+    /// Reads a session variable as a `T`. Fails with an [`Error::Sql`] if it is not set.
+    pub async fn get_variable<T: SnowflakeDecode>(&self, name: &str) -> Result<T> {
+        let name = variable_name(name)?;
+        let rows = self.query(format!("SELECT ${name} AS VALUE")).await?;
+        match rows.first() {
+            Some(row) => row.get("VALUE"),
+            None => Err(Error::Communication(format!("no value of ${name}"))),
+        }
+    }
+
+    /// The names of the variables set with [`SnowflakeSession::set_variable`] on the session or
+    /// its clones and not unset since, in upper case. Variables set by statements directly are
+    /// not included.
+    pub fn variable_names(&self) -> Vec<String> {
+        self.tracked_variables().clone()
+    }
+
+    /// Unsets every variable in [`SnowflakeSession::variable_names`], e.g. before a pooled
+    /// session is handed to its next user.
+    pub async fn unset_variables(&self) -> Result<()> {
+        let names = self.variable_names();
+        if names.is_empty() {
+            return Ok(());
+        }
+        self.query(format!("UNSET ({})", names.join(", "))).await?;
+        self.tracked_variables()
+            .retain(|name| !names.contains(name));
+        Ok(())
+    }
+
+    fn tracked_variables(&self) -> std::sync::MutexGuard<'_, Vec<String>> {
+        self.variables
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A variable name as Snowflake stores it, in upper case, or an error if it cannot be written
+/// as `$name`.
+fn variable_name(name: &str) -> Result<String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    match valid {
+        true => Ok(name.to_ascii_uppercase()),
+        false => Err(Error::InvalidIdentifier(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{metrics::NoMetrics, tests::session};
+
+    #[test]
+    fn test_variable_names() {
+        assert_eq!(variable_name("min_date").unwrap(), "MIN_DATE");
+        assert_eq!(variable_name("_v$2").unwrap(), "_V$2");
+        for name in ["", "2x", "a b", "x;DROP", "\"x\""] {
+            assert!(matches!(
+                variable_name(name),
+                Err(Error::InvalidIdentifier(invalid)) if invalid == name
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_set_is_not_tracked() {
+        // An account that makes an invalid URL fails the statement before anything is sent.
+        let session = session("not an account", Arc::new(NoMetrics));
+        assert!(session
+            .set_variable("min_date", "2024-01-01")
+            .await
+            .is_err());
+        assert!(session.variable_names().is_empty());
+        assert!(session.unset_variables().await.is_ok());
+    }
+}
@@ -1,4 +1,8 @@
-use snowflake_connector_rs::{Result, SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use snowflake_connector_rs::{
+    Bind, DmlStats, GeoOutputFormat, Json, QueryRequest, Result, SnowflakeAuthMethod,
+    SnowflakeClient, SnowflakeClientConfig, StatementType, Wkt,
+};
 
 #[tokio::test]
 async fn test_decode_naive_date() -> Result<()> {
@@ -20,6 +24,47 @@ async fn test_decode_naive_date() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_decode_bool() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT TRUE AS native, 'TRUE' AS upper, 'off' AS off, TO_VARIANT(FALSE)::STRING AS variant";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert!(rows[0].get::<bool>("NATIVE")?);
+    assert!(rows[0].get::<bool>("UPPER")?);
+    assert!(!rows[0].get::<bool>("OFF")?);
+    assert!(!rows[0].get::<bool>("VARIANT")?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_scientific_notation() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT SUM(v) AS total, AVG(v) AS average FROM (SELECT 1234567000000::FLOAT AS v UNION ALL SELECT 1.5::FLOAT)";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert!(rows[0].get::<i64>("TOTAL").is_err());
+    assert_eq!(rows[0].get::<f64>("TOTAL")?, 1234567000001.5);
+    assert!(rows[0].get::<i64>("AVERAGE").is_err());
+
+    let query = "SELECT 1234567000000::FLOAT AS v";
+    let rows = session.query(query).await?;
+    assert_eq!(rows[0].get::<i64>("V")?, 1234567000000);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_basic_operations() -> Result<()> {
     // Connect to Snowflake
@@ -53,7 +98,542 @@ async fn test_basic_operations() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_column_order() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT 1 AS zeta, 2 AS alpha, 3 AS mid, 4 AS b, 5 AS a_1";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        rows[0].column_names(),
+        vec!["ZETA", "ALPHA", "MID", "B", "A_1"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_result_metadata() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let result = session
+        .query("SELECT 1 AS ID, 'a' AS NAME WHERE FALSE")
+        .await?;
+
+    // Assert
+    assert!(result.is_empty());
+    assert!(!result.query_id().is_empty());
+    assert_eq!(result.statement_type(), Some(StatementType::Select));
+    let columns = result.columns();
+    assert_eq!(
+        columns
+            .iter()
+            .map(|column| column.name())
+            .collect::<Vec<_>>(),
+        ["ID", "NAME"]
+    );
+    assert_eq!(columns[1].column_type().snowflake_type(), "text");
+    assert_eq!(result.stats().query_id, result.query_id());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_raw() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let data = session.query_raw("SELECT 1 AS ONE").await?;
+
+    // Assert
+    assert!(data["queryId"].as_str().is_some_and(|id| !id.is_empty()));
+    assert_eq!(data["rowtype"][0]["name"], "ONE");
+    assert_eq!(data["rowset"][0][0], "1");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_as() -> Result<()> {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Example {
+        id: i64,
+        value: Option<String>,
+        #[serde(rename = "DATA")]
+        payload: serde_json::Value,
+    }
+
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT 1 AS id, NULL AS value, PARSE_JSON('{\"a\": [1, 2]}') AS data";
+    let rows = session.query_as::<Example>(query).await?;
+
+    // Assert
+    assert_eq!(
+        rows,
+        vec![Example {
+            id: 1,
+            value: None,
+            payload: serde_json::json!({"a": [1, 2]}),
+        }]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_semi_structured() -> Result<()> {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Address {
+        city: String,
+    }
+
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query =
+        "SELECT PARSE_JSON('{\"city\": \"Tokyo\"}') AS obj, TO_VARIANT('hello') AS str, 1 AS num";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        rows[0].get::<Json<Address>>("OBJ")?.0,
+        Address {
+            city: "Tokyo".into()
+        }
+    );
+    assert_eq!(rows[0].get::<String>("STR")?, "hello");
+    assert!(rows[0].get::<Json<Address>>("NUM").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_array() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT ARRAY_AGG(id) WITHIN GROUP (ORDER BY id) AS ids, PARSE_JSON('[\"a\", null, \"c\"]')::ARRAY AS strs FROM (SELECT SEQ4() AS id FROM TABLE(GENERATOR(ROWCOUNT => 3)))";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<Vec<i64>>("IDS")?, vec![0, 1, 2]);
+    assert_eq!(
+        rows[0].get::<Vec<Option<String>>>("STRS")?,
+        vec![Some("a".to_string()), None, Some("c".to_string())]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_decode_geography() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT TO_GEOGRAPHY('POINT(-122.35 37.55)') AS location";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        rows[0].get::<Wkt>("LOCATION")?,
+        Wkt("POINT(-122.35 37.55)".to_string())
+    );
+    #[cfg(feature = "geo")]
+    assert_eq!(
+        rows[0].get::<geo_types::Geometry<f64>>("LOCATION")?,
+        geo_types::Geometry::Point(geo_types::Point::new(-122.35, 37.55))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_geo_output_format() -> Result<()> {
+    // Arrange
+    let client = connect_with(|config| config.geo_output_format = Some(GeoOutputFormat::Wkt))?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT TO_GEOGRAPHY('POINT(-122.35 37.55)') AS location";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows[0].get::<String>("LOCATION")?, "POINT(-122.35 37.55)");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_quoted_identifiers() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = r#"SELECT 1 AS "id", 2 AS ID, 3 AS "myColumn""#;
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows[0].column_names(), vec!["id", "ID", "myColumn"]);
+    assert_eq!(rows[0].get::<i64>("id")?, 1);
+    assert_eq!(rows[0].get::<i64>("ID")?, 2);
+    assert_eq!(rows[0].get::<i64>("myColumn")?, 3);
+    assert_eq!(rows[0].get::<i64>("MYCOLUMN")?, 3);
+    assert!(rows[0].get_exact::<i64>("MYCOLUMN").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_duplicate_column_names() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT a.id, b.id FROM (SELECT 1 AS id) a JOIN (SELECT 2 AS id) b";
+    let rows = session.query(query).await?;
+
+    // Assert
+    assert_eq!(rows[0].get::<i64>("ID")?, 1);
+    assert_eq!(rows[0].get_all::<i64>("ID")?, vec![1, 2]);
+    assert_eq!(rows[0].columns_named("ID"), vec![0, 1]);
+    assert!(rows[0].get_unique::<i64>("ID").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_variables() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    session.set_variable("min_date", "it's 2024-01-01").await?;
+    session.set_variable("row_limit", &10).await?;
+
+    // Assert
+    let text: String = session.get_variable("MIN_DATE").await?;
+    assert_eq!(text, "it's 2024-01-01");
+    let rows = session.query("SELECT $row_limit * 2 AS DOUBLED").await?;
+    assert_eq!(rows[0].get::<i64>("DOUBLED")?, 20);
+    assert_eq!(session.variable_names(), ["MIN_DATE", "ROW_LIMIT"]);
+
+    session.unset_variables().await?;
+    assert!(session.variable_names().is_empty());
+    assert!(session.get_variable::<i64>("row_limit").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bind_json() -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Event {
+        id: usize,
+        payload: Option<Json<serde_json::Value>>,
+    }
+
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+    session
+        .query("CREATE TEMPORARY TABLE json_events (id NUMBER, payload VARIANT)")
+        .await?;
+    let payloads = [
+        Some(serde_json::json!({"user": {"name": "it's \"me\"", "tags": ["a", "b"]}})),
+        Some(serde_json::json!([1, 2.5, null, {"deep": [true]}])),
+        Some(serde_json::json!({"city": "東京 🗼", "escaped": "\\u0041"})),
+        Some(serde_json::json!("plain")),
+        Some(serde_json::Value::Null),
+        None,
+    ];
+    let events = payloads.iter().enumerate().map(|(id, payload)| Event {
+        id,
+        payload: payload.clone().map(Json),
+    });
+
+    // Act
+    let inserted = session
+        .insert_into("json_events")
+        .values(events)
+        .execute()
+        .await?;
+    let rows = session
+        .query("SELECT payload FROM json_events ORDER BY id")
+        .await?;
+
+    // Assert
+    assert_eq!(inserted, payloads.len() as u64);
+    let selected = rows
+        .iter()
+        .map(|row| row.get::<Option<serde_json::Value>>("PAYLOAD"))
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(selected, payloads);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bind_binary() -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Blob {
+        id: usize,
+        data: Option<Vec<u8>>,
+    }
+
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+    session
+        .query("CREATE TEMPORARY TABLE blobs (id NUMBER, data BINARY)")
+        .await?;
+    let data = [
+        Some(vec![0x00, 0x0f, 0xa0, 0xff]),
+        Some((0..=255).collect()),
+        Some(vec![]),
+        None,
+    ];
+    let blobs = data.iter().enumerate().map(|(id, data)| Blob {
+        id,
+        data: data.clone(),
+    });
+
+    // Act
+    let inserted = session
+        .insert_into("blobs")
+        .values(blobs)
+        .rows_per_statement(3)
+        .execute()
+        .await?;
+    let rows = session.query("SELECT data FROM blobs ORDER BY id").await?;
+
+    // Assert
+    assert_eq!(inserted, data.len() as u64);
+    let selected = rows
+        .iter()
+        .map(|row| row.get::<Option<Vec<u8>>>("DATA"))
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(selected, data);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bind_temporal() -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Moment {
+        id: usize,
+        day: Bind<NaiveDate>,
+        at: Bind<NaiveTime>,
+        ntz: Bind<NaiveDateTime>,
+        ltz: Bind<DateTime<Utc>>,
+        tz: Bind<DateTime<FixedOffset>>,
+    }
+
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+    session.query("ALTER SESSION SET TIMEZONE = 'UTC'").await?;
+    session
+        .query(
+            "CREATE TEMPORARY TABLE moments (id NUMBER, day DATE, at TIME(9), \
+             ntz TIMESTAMP_NTZ(9), ltz TIMESTAMP_LTZ(9), tz TIMESTAMP_TZ(9))",
+        )
+        .await?;
+    let datetimes = [
+        "2024-02-29T23:59:59.123456789+09:00",
+        "1969-12-31T23:59:59.5-05:30",
+        "1900-01-01T00:00:00.000000001+00:00",
+    ]
+    .map(|text| DateTime::parse_from_rfc3339(text).unwrap());
+    let moments = datetimes.iter().enumerate().map(|(id, datetime)| Moment {
+        id,
+        day: Bind(datetime.date_naive()),
+        at: Bind(datetime.time()),
+        ntz: Bind(datetime.naive_local()),
+        ltz: Bind(datetime.to_utc()),
+        tz: Bind(*datetime),
+    });
+
+    // Act
+    session
+        .insert_into("moments")
+        .values(moments)
+        .execute()
+        .await?;
+    let rows = session
+        .query("SELECT day, at, ntz, ltz, tz FROM moments ORDER BY id")
+        .await?;
+
+    // Assert
+    assert_eq!(rows.len(), datetimes.len());
+    for (row, datetime) in rows.iter().zip(datetimes) {
+        assert_eq!(row.get::<NaiveDate>("DAY")?, datetime.date_naive());
+        assert_eq!(row.get::<NaiveTime>("AT")?, datetime.time());
+        assert_eq!(row.get::<NaiveDateTime>("NTZ")?, datetime.naive_local());
+        assert_eq!(row.get::<DateTime<Utc>>("LTZ")?, datetime.to_utc());
+        let tz = row.get::<DateTime<FixedOffset>>("TZ")?;
+        assert_eq!(tz, datetime);
+        assert_eq!(tz.offset(), datetime.offset());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bind_list() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+    session
+        .query("CREATE TEMPORARY TABLE list_events AS SELECT SEQ4() AS id FROM TABLE(GENERATOR(ROWCOUNT => 200))")
+        .await?;
+    let sql = "SELECT COUNT(*) AS n FROM list_events WHERE MOD(id, 2) = ? AND id IN (:ids)";
+    let count = |ids: Vec<i64>| {
+        let request = QueryRequest::from(sql)
+            .bind(&0)
+            .and_then(|request| request.bind_list("ids", ids));
+        let session = session.clone();
+        async move { session.query(request?).await?[0].get::<i64>("N") }
+    };
+
+    // Act
+    let expanded = count(vec![0, 1, 2, 3, 4]).await?;
+    let flattened = count((0..150).collect()).await?;
+    let empty = count(vec![]).await?;
+
+    // Assert
+    assert_eq!(expanded, 3);
+    assert_eq!(flattened, 75);
+    assert_eq!(empty, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_in_transaction() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+    session
+        .query("CREATE TEMPORARY TABLE transfers (amount NUMBER)")
+        .await?;
+
+    // Act
+    let failed = session
+        .run_in_transaction(|txn| async move {
+            txn.query("INSERT INTO transfers VALUES (10)").await?;
+            txn.query("INSERT INTO no_such_table VALUES (10)").await?;
+            Ok::<_, snowflake_connector_rs::Error>(())
+        })
+        .await;
+    session
+        .run_in_transaction(|txn| async move {
+            txn.query("INSERT INTO transfers VALUES (20)").await?;
+            Ok::<_, snowflake_connector_rs::Error>(())
+        })
+        .await?;
+
+    // Assert
+    assert!(failed.is_err());
+    assert!(!session.in_transaction().await?);
+    let rows = session
+        .query("SELECT SUM(amount) AS TOTAL FROM transfers")
+        .await?;
+    assert_eq!(rows[0].get::<i64>("TOTAL")?, 20);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dml_stats() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+    for table in ["dml_target", "dml_small", "dml_large"] {
+        session
+            .query(format!(
+                "CREATE TEMPORARY TABLE {table} (id NUMBER, v TEXT)"
+            ))
+            .await?;
+    }
+
+    // Act
+    let insert = session
+        .execute_dml("INSERT INTO dml_target VALUES (1, 'a'), (2, 'b'), (3, 'c')")
+        .await?;
+    let update = session
+        .execute_dml("UPDATE dml_target SET v = 'z' WHERE id > 1")
+        .await?;
+    let merge = session
+        .execute_dml(
+            "MERGE INTO dml_target t USING (SELECT 1 AS id UNION ALL SELECT 4) s ON t.id = s.id \
+             WHEN MATCHED THEN DELETE WHEN NOT MATCHED THEN INSERT VALUES (s.id, 'new')",
+        )
+        .await?;
+    let multi = session
+        .execute_dml(
+            "INSERT ALL WHEN id < 3 THEN INTO dml_small ELSE INTO dml_large \
+             SELECT id, v FROM dml_target",
+        )
+        .await?;
+    let delete = session.execute_dml("DELETE FROM dml_target").await?;
+    let select = session.execute_dml("SELECT 1").await;
+
+    // Assert
+    assert_eq!(insert, DmlStats::Insert { inserted: 3 });
+    assert!(matches!(update, DmlStats::Update { updated: 2, .. }));
+    assert_eq!(
+        merge,
+        DmlStats::Merge {
+            inserted: 1,
+            updated: 0,
+            deleted: 1
+        }
+    );
+    let DmlStats::MultiTableInsert(tables) = multi else {
+        panic!("expected a multi-table insert: {multi:?}");
+    };
+    assert_eq!(
+        tables.iter().map(|(_, count)| count).collect::<Vec<_>>(),
+        [&1, &2]
+    );
+    assert_eq!(delete, DmlStats::Delete { deleted: 3 });
+    assert!(select.is_err());
+
+    Ok(())
+}
+
 fn connect() -> Result<SnowflakeClient> {
+    connect_with(|_| {})
+}
+
+fn connect_with(configure: impl FnOnce(&mut SnowflakeClientConfig)) -> Result<SnowflakeClient> {
     let username = std::env::var("SNOWFLAKE_USERNAME").expect("set SNOWFLAKE_USERNAME for testing");
     let password = std::env::var("SNOWFLAKE_PASSWORD").expect("set SNOWFLAKE_PASSWORD for testing");
     let account = std::env::var("SNOWFLAKE_ACCOUNT").expect("set SNOWFLAKE_ACCOUNT for testing");
@@ -63,16 +643,20 @@ fn connect() -> Result<SnowflakeClient> {
     let database = std::env::var("SNOWFLAKE_DATABASE").ok();
     let schema = std::env::var("SNOWFLAKE_SCHEMA").ok();
 
+    let mut config = SnowflakeClientConfig {
+        account,
+        warehouse,
+        database,
+        schema,
+        role,
+        ..Default::default()
+    };
+    configure(&mut config);
+
     let client = SnowflakeClient::new(
         &username,
-        SnowflakeAuthMethod::Password(password),
-        SnowflakeClientConfig {
-            account,
-            warehouse,
-            database,
-            schema,
-            role,
-        },
+        SnowflakeAuthMethod::Password(password.into()),
+        config,
     )?;
 
     Ok(client)
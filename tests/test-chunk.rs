@@ -1,4 +1,6 @@
-use snowflake_connector_rs::{Result, SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig};
+use snowflake_connector_rs::{
+    QueryRequest, Result, SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig,
+};
 
 #[tokio::test]
 async fn test_download_chunked_results() -> Result<()> {
@@ -14,13 +16,14 @@ async fn test_download_chunked_results() -> Result<()> {
 
     let client = SnowflakeClient::new(
         &username,
-        SnowflakeAuthMethod::Password(password),
+        SnowflakeAuthMethod::Password(password.into()),
         SnowflakeClientConfig {
             account,
             warehouse,
             database,
             schema,
             role,
+            ..Default::default()
         },
     )?;
 
@@ -39,3 +42,86 @@ async fn test_download_chunked_results() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_stream_chunked_results() -> Result<()> {
+    // Arrange
+    let username = std::env::var("SNOWFLAKE_USERNAME").expect("set SNOWFLAKE_USERNAME for testing");
+    let password = std::env::var("SNOWFLAKE_PASSWORD").expect("set SNOWFLAKE_PASSWORD for testing");
+    let account = std::env::var("SNOWFLAKE_ACCOUNT").expect("set SNOWFLAKE_ACCOUNT for testing");
+
+    let role = std::env::var("SNOWFLAKE_ROLE").ok();
+    let warehouse = std::env::var("SNOWFLAKE_WAREHOUSE").ok();
+    let database = std::env::var("SNOWFLAKE_DATABASE").ok();
+    let schema = std::env::var("SNOWFLAKE_SCHEMA").ok();
+
+    let client = SnowflakeClient::new(
+        &username,
+        SnowflakeAuthMethod::Password(password.into()),
+        SnowflakeClientConfig {
+            account,
+            warehouse,
+            database,
+            schema,
+            role,
+            ..Default::default()
+        },
+    )?;
+
+    // Act
+    let session = client.create_session().await?;
+    let query =
+        "SELECT SEQ8() AS SEQ, RANDSTR(1000, RANDOM()) AS RAND FROM TABLE(GENERATOR(ROWCOUNT=>10000)) ORDER BY SEQ";
+    let mut rows = session.query_stream(query).await?;
+    let mut seqs = Vec::with_capacity(10000);
+    while let Some(row) = rows.next_row().await {
+        seqs.push(row?.get::<u64>("SEQ")?);
+    }
+
+    // Assert
+    assert_eq!(seqs, (0..10000).collect::<Vec<_>>());
+    assert!(rows.column_names().contains(&"RAND"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_result_chunk_size() -> Result<()> {
+    // Arrange
+    let username = std::env::var("SNOWFLAKE_USERNAME").expect("set SNOWFLAKE_USERNAME for testing");
+    let password = std::env::var("SNOWFLAKE_PASSWORD").expect("set SNOWFLAKE_PASSWORD for testing");
+    let account = std::env::var("SNOWFLAKE_ACCOUNT").expect("set SNOWFLAKE_ACCOUNT for testing");
+
+    let role = std::env::var("SNOWFLAKE_ROLE").ok();
+    let warehouse = std::env::var("SNOWFLAKE_WAREHOUSE").ok();
+    let database = std::env::var("SNOWFLAKE_DATABASE").ok();
+    let schema = std::env::var("SNOWFLAKE_SCHEMA").ok();
+
+    let client = SnowflakeClient::new(
+        &username,
+        SnowflakeAuthMethod::Password(password.into()),
+        SnowflakeClientConfig {
+            account,
+            warehouse,
+            database,
+            schema,
+            role,
+            result_chunk_size_mb: Some(160),
+            ..Default::default()
+        },
+    )?;
+
+    // Act
+    let session = client.create_session().await?;
+    let sql = "SELECT SEQ8() AS SEQ, RANDSTR(1000, RANDOM()) AS RAND FROM TABLE(GENERATOR(ROWCOUNT=>500000))";
+    let large = session.query_lazy(sql).await?;
+    let small = session
+        .query_lazy(QueryRequest::from(sql).result_chunk_size_mb(48))
+        .await?;
+
+    // Assert
+    assert_eq!(large.total_rows(), small.total_rows());
+    assert!(small.chunk_count() > large.chunk_count());
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use snowflake_connector_rs::{Result, SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig};
+
+#[tokio::test]
+async fn test_concurrent_queries_on_clones() -> Result<()> {
+    // Arrange
+    let username = std::env::var("SNOWFLAKE_USERNAME").expect("set SNOWFLAKE_USERNAME for testing");
+    let password = std::env::var("SNOWFLAKE_PASSWORD").expect("set SNOWFLAKE_PASSWORD for testing");
+    let account = std::env::var("SNOWFLAKE_ACCOUNT").expect("set SNOWFLAKE_ACCOUNT for testing");
+
+    let role = std::env::var("SNOWFLAKE_ROLE").ok();
+    let warehouse = std::env::var("SNOWFLAKE_WAREHOUSE").ok();
+    let database = std::env::var("SNOWFLAKE_DATABASE").ok();
+    let schema = std::env::var("SNOWFLAKE_SCHEMA").ok();
+
+    let client = SnowflakeClient::new(
+        &username,
+        SnowflakeAuthMethod::Password(password.into()),
+        SnowflakeClientConfig {
+            account,
+            warehouse,
+            database,
+            schema,
+            role,
+            ..Default::default()
+        },
+    )?;
+    let session = client.create_session().await?;
+
+    // Act: a dozen queries on clones of the session, with the session token renewed while
+    // they run.
+    let tasks = (0..12u64)
+        .map(|i| {
+            let session = session.clone();
+            tokio::spawn(async move {
+                let rows = session
+                    .query(format!("SELECT {i} AS N, SYSTEM$WAIT(1) AS WAITED"))
+                    .await?;
+                rows[0].get::<u64>("N")
+            })
+        })
+        .collect::<Vec<_>>();
+    session.renew().await?;
+    let mut results = vec![];
+    for task in tasks {
+        results.push(task.await.unwrap()?);
+    }
+
+    // Assert
+    assert_eq!(results, (0..12).collect::<Vec<_>>());
+    let rows = session.query("SELECT 1 AS ONE").await?;
+    assert_eq!(rows[0].get::<u64>("ONE")?, 1);
+    Ok(())
+}
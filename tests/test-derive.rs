@@ -0,0 +1,91 @@
+#![cfg(feature = "derive")]
+
+use snowflake_connector_rs::{
+    FromRow, Result, SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig,
+};
+
+#[derive(Debug, PartialEq, FromRow)]
+struct Example {
+    id: i64,
+    #[snowflake(rename = "VALUE")]
+    text: Option<String>,
+    #[snowflake(default)]
+    extra: i64,
+}
+
+#[tokio::test]
+async fn test_query_typed() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let query = "SELECT 1 AS id, 'hello' AS value, 2 AS extra";
+    let rows = session.query_typed::<Example>(query).await?;
+
+    // Assert
+    assert_eq!(
+        rows,
+        vec![Example {
+            id: 1,
+            text: Some("hello".into()),
+            extra: 2,
+        }]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_typed_missing_columns() -> Result<()> {
+    // Arrange
+    let client = connect()?;
+    let session = client.create_session().await?;
+
+    // Act
+    let with_default = session
+        .query_typed::<Example>("SELECT 1 AS id, NULL AS value")
+        .await?;
+    let without_required = session
+        .query_typed::<Example>("SELECT 'hello' AS value")
+        .await;
+
+    // Assert
+    assert_eq!(
+        with_default,
+        vec![Example {
+            id: 1,
+            text: None,
+            extra: 0,
+        }]
+    );
+    assert!(without_required.is_err());
+
+    Ok(())
+}
+
+fn connect() -> Result<SnowflakeClient> {
+    let username = std::env::var("SNOWFLAKE_USERNAME").expect("set SNOWFLAKE_USERNAME for testing");
+    let password = std::env::var("SNOWFLAKE_PASSWORD").expect("set SNOWFLAKE_PASSWORD for testing");
+    let account = std::env::var("SNOWFLAKE_ACCOUNT").expect("set SNOWFLAKE_ACCOUNT for testing");
+
+    let role = std::env::var("SNOWFLAKE_ROLE").ok();
+    let warehouse = std::env::var("SNOWFLAKE_WAREHOUSE").ok();
+    let database = std::env::var("SNOWFLAKE_DATABASE").ok();
+    let schema = std::env::var("SNOWFLAKE_SCHEMA").ok();
+
+    let client = SnowflakeClient::new(
+        &username,
+        SnowflakeAuthMethod::Password(password.into()),
+        SnowflakeClientConfig {
+            account,
+            warehouse,
+            database,
+            schema,
+            role,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(client)
+}
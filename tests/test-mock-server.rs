@@ -0,0 +1,495 @@
+//! Runs the client against the mock server of the `test-util` feature, without an account.
+#![cfg(feature = "test-util")]
+
+use std::time::Duration;
+
+use snowflake_connector_rs::{
+    Bind, Error, MockResponse, MockServer, QueryRequest, Result, ServerVersion,
+    SnowflakeAuthMethod, SnowflakeClient, SnowflakeClientConfig, SnowflakeColumnType,
+    SnowflakeSession, StatementType, TimeoutPhase,
+};
+
+fn number() -> SnowflakeColumnType {
+    SnowflakeColumnType::new("fixed", Some(0))
+}
+
+fn rows(values: impl IntoIterator<Item = i64>) -> Vec<Vec<Option<String>>> {
+    values
+        .into_iter()
+        .map(|value| vec![Some(value.to_string())])
+        .collect()
+}
+
+async fn session(config: SnowflakeClientConfig) -> Result<SnowflakeSession> {
+    let client = SnowflakeClient::new(
+        "USER",
+        SnowflakeAuthMethod::Password("PASSWORD".into()),
+        config,
+    )?;
+    client.create_session().await
+}
+
+#[tokio::test]
+async fn test_mock_rows_and_chunks() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .query(
+            "SELECT N FROM numbers",
+            MockResponse::rows([("N", number())], rows(1..=2))
+                .with_chunk(rows(3..=5))
+                .with_chunk(rows(6..=6)),
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let result = session.query("SELECT N FROM numbers").await?;
+
+    // Assert
+    let values = result
+        .iter()
+        .map(|row| row.get::<i64>("N"))
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(values, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(result.statement_type(), Some(StatementType::Select));
+    assert_eq!(result.stats().chunk_count, 2);
+    assert_eq!(server.statements(), ["SELECT N FROM numbers"]);
+    assert_eq!(server.logins(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_polling() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .query(
+            "CALL slow()",
+            MockResponse::rows([("N", number())], rows([7])).running(3),
+        )
+        .query(
+            "CALL slower()",
+            MockResponse::rows([("N", number())], rows([7])).running(10),
+        )
+        .start()
+        .await?;
+    let session = session(SnowflakeClientConfig {
+        polling_interval: Some(Duration::from_millis(1)),
+        max_polling_attempts: Some(5),
+        ..server.client_config()
+    })
+    .await?;
+
+    // Act
+    let finished = session.query("CALL slow()").await?;
+    let timed_out = session.query("CALL slower()").await;
+
+    // Assert
+    assert_eq!(finished[0].get::<i64>("N")?, 7);
+    assert!(matches!(
+        timed_out,
+        Err(Error::Timeout {
+            phase: TimeoutPhase::Polling,
+            query_id: Some(_),
+            ..
+        })
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_errors() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .query(
+            "SELECT * FROM missing",
+            MockResponse::error(2003, "42S02", "Object 'MISSING' does not exist"),
+        )
+        .query("SELECT 1", MockResponse::http(200, "{not json"))
+        .query(
+            "SELECT 2",
+            MockResponse::http(503, "<html>down</html>").with_header("Content-Type", "text/html"),
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let sql = session.query("SELECT * FROM missing").await;
+    let malformed = session.query("SELECT 1").await;
+    let unavailable = session.query("SELECT 2").await;
+    let unscripted = session.query("SELECT 3").await;
+
+    // Assert
+    assert!(matches!(
+        sql,
+        Err(Error::Sql { code: 2003, ref sqlstate, query_id: Some(_), .. }) if sqlstate == "42S02"
+    ));
+    assert!(matches!(malformed, Err(Error::HttpResponse { .. })));
+    assert!(unavailable.is_err_and(|e| e.is_retryable()));
+    assert!(matches!(
+        unscripted,
+        Err(Error::Sql { ref message, .. }) if message == "no mock answer for: SELECT 3"
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_token_expiry() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .query("SELECT 1", MockResponse::rows([("N", number())], rows([1])))
+        .expire_token_after(2)
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    for _ in 0..5 {
+        session.query("SELECT 1").await?;
+    }
+
+    // Assert
+    assert_eq!(server.statements().len(), 5);
+    assert_eq!(server.logins(), 1);
+    assert_eq!(server.renewals(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_rate_limited() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .query_sequence(
+            "SELECT 1",
+            [
+                MockResponse::http(429, "").with_header("Retry-After", "0"),
+                MockResponse::rows([("N", number())], rows([1])),
+            ],
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let result = session.query("SELECT 1").await?;
+
+    // Assert
+    assert_eq!(result[0].get::<i64>("N")?, 1);
+    assert_eq!(server.statements(), ["SELECT 1", "SELECT 1"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_server_info() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .server_version("8.12.1")
+        .parameter("TIMEZONE", "America/Los_Angeles")
+        .parameter("AUTOCOMMIT", true)
+        .query(
+            "ALTER SESSION SET TIMEZONE = 'UTC'",
+            MockResponse::rows([("status", SnowflakeColumnType::new("text", None))], vec![])
+                .with_parameter("TIMEZONE", "UTC"),
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let version = session.server_version();
+    let at_login = session.server_parameters();
+    session
+        .clone()
+        .query("ALTER SESSION SET TIMEZONE = 'UTC'")
+        .await?;
+
+    // Assert
+    assert_eq!(version, Some(ServerVersion::new(8, 12, 1)));
+    assert!(version >= ServerVersion::parse("8.5"));
+    assert_eq!(at_login["TIMEZONE"], "America/Los_Angeles");
+    let parameters = session.server_parameters();
+    assert_eq!(parameters["TIMEZONE"], "UTC");
+    assert_eq!(parameters["AUTOCOMMIT"], true);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_list_stage() -> Result<()> {
+    // Arrange
+    let text = || SnowflakeColumnType::new("text", None);
+    let columns = [
+        ("name", text()),
+        ("size", number()),
+        ("md5", text()),
+        ("last_modified", text()),
+    ];
+    let file = |name: &str, md5: Option<&str>| {
+        vec![
+            Some(name.to_string()),
+            Some("1024".to_string()),
+            md5.map(str::to_string),
+            Some("Wed, 5 Jun 2024 12:34:56 GMT".to_string()),
+        ]
+    };
+    let server = MockServer::builder()
+        .query(
+            "LIST '@load_stage/my files/' PATTERN = '.*[.]csv'",
+            MockResponse::rows(
+                columns.clone(),
+                vec![file(
+                    "load_stage/my files/a.csv",
+                    Some("0f343b0931126a20f133d67c2b018a3b"),
+                )],
+            ),
+        )
+        .query(
+            "LIST @ext_stage",
+            MockResponse::rows(
+                columns,
+                vec![file(
+                    "s3://bucket/b.csv",
+                    Some("\"9b2cf535f27731c974343645a3985328-2\""),
+                )],
+            ),
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let internal = session
+        .list_stage_matching("@load_stage/my files/", ".*[.]csv")
+        .await?;
+    let external = session.list_stage("@ext_stage").await?;
+
+    // Assert
+    assert_eq!(internal[0].name, "load_stage/my files/a.csv");
+    assert_eq!(internal[0].size, 1024);
+    assert_eq!(
+        internal[0].md5.as_deref(),
+        Some("0f343b0931126a20f133d67c2b018a3b")
+    );
+    assert_eq!(external[0].name, "s3://bucket/b.csv");
+    assert_eq!(external[0].md5, None);
+    assert_eq!(external[0].last_modified.timestamp(), 1_717_590_896);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_warehouse_resume() -> Result<()> {
+    // Arrange
+    let resuming = || MockResponse::error(608, "57P03", "Warehouse 'LOAD_WH' is resuming.");
+    let server = MockServer::builder()
+        .query_sequence(
+            "SELECT 1",
+            [resuming(), MockResponse::rows([("N", number())], rows([1]))],
+        )
+        .query("SELECT 2", resuming())
+        .start()
+        .await?;
+    let session = session(SnowflakeClientConfig {
+        retry_on_warehouse_resume: true,
+        max_warehouse_resume_wait: Some(Duration::from_millis(1500)),
+        ..server.client_config()
+    })
+    .await?;
+
+    // Act
+    let resumed = session.query("SELECT 1").await?;
+    let unavailable = session.query("SELECT 2").await;
+
+    // Assert
+    assert_eq!(resumed[0].get::<i64>("N")?, 1);
+    assert_eq!(
+        server.statements(),
+        ["SELECT 1", "SELECT 1", "SELECT 2", "SELECT 2"]
+    );
+    let Err(Error::WarehouseUnavailable {
+        warehouse, source, ..
+    }) = unavailable
+    else {
+        panic!("expected the warehouse to be unavailable: {unavailable:?}");
+    };
+    assert_eq!(warehouse.as_deref(), Some("LOAD_WH"));
+    assert_eq!(source.sql_code(), Some(608));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_bind_list() -> Result<()> {
+    // Arrange
+    let server = MockServer::builder()
+        .query(
+            "SELECT N FROM numbers WHERE N IN (?, ?, ?, ?) AND N > ?",
+            MockResponse::rows([("N", number())], rows([2, 3])),
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+    let request = QueryRequest::from("SELECT N FROM numbers WHERE N IN (:ns) AND N > ?")
+        .bind_list("ns", [1, 2, 3])?
+        .bind(&1)?;
+
+    // Act
+    let result = session.query(request).await?;
+
+    // Assert
+    assert_eq!(result.len(), 2);
+    assert_eq!(
+        server.statements(),
+        ["SELECT N FROM numbers WHERE N IN (?, ?, ?, ?) AND N > ?"]
+    );
+    Ok(())
+}
+
+#[cfg(feature = "arrow")]
+#[tokio::test]
+async fn test_mock_record_batches() -> Result<()> {
+    // Arrange
+    let price = SnowflakeColumnType::new("fixed", Some(2));
+    let values = |values: &[(i64, Option<&str>)]| {
+        values
+            .iter()
+            .map(|(n, price)| vec![Some(n.to_string()), price.map(str::to_string)])
+            .collect::<Vec<_>>()
+    };
+    let server = MockServer::builder()
+        .query(
+            "SELECT N, PRICE FROM prices",
+            MockResponse::rows(
+                [("N", number()), ("PRICE", price)],
+                values(&[(1, Some("1.50")), (2, None)]),
+            )
+            .with_chunk(values(&[(3, Some("-0.05")), (4, Some("12")), (5, None)])),
+        )
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let batches = session
+        .query_record_batches("SELECT N, PRICE FROM prices")
+        .await?;
+    let arrow = session.query_arrow("SELECT N, PRICE FROM prices").await?;
+
+    // Assert
+    let rows = batches
+        .iter()
+        .map(|batch| batch.num_rows())
+        .collect::<Vec<_>>();
+    assert_eq!(rows, [2, 3]);
+    let schema = batches[0].schema();
+    assert_eq!(schema.field(0).data_type().to_string(), "Int64");
+    assert_eq!(schema.field(1).data_type().to_string(), "Decimal128(38, 2)");
+    assert_eq!(batches[1].column(1).null_count(), 1);
+    // A result sent as JSON is returned with the same types.
+    assert_eq!(arrow, batches);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_expired_chunk_urls() -> Result<()> {
+    // Arrange
+    let response = MockResponse::rows([("N", number())], rows(1..=1))
+        .with_chunk(rows(2..=3))
+        .with_chunk(rows(4..=4))
+        .with_expired_chunk_urls();
+    let server = MockServer::builder()
+        .query("SELECT N FROM numbers", response.clone())
+        .query("SELECT N FROM expired", response.with_expired_result())
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let result = session.query("SELECT N FROM numbers").await?;
+    let expired = session.query("SELECT N FROM expired").await;
+
+    // Assert
+    let values = result
+        .iter()
+        .map(|row| row.get::<i64>("N"))
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(values, [1, 2, 3, 4]);
+    // The first URL is refused, and fresh URLs cannot be had for the expired result.
+    let Err(Error::ChunkFailed {
+        chunk_index,
+        rows_delivered,
+        source,
+        ..
+    }) = expired
+    else {
+        panic!("expected a failed chunk, got {expired:?}");
+    };
+    assert_eq!((chunk_index, rows_delivered), (0, 1));
+    assert!(matches!(*source, Error::ResultExpired(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_dropped_transaction() -> Result<()> {
+    // Arrange
+    let done = || MockResponse::rows::<&str>([], vec![]);
+    let server = MockServer::builder()
+        .query("BEGIN TRANSACTION", done())
+        .query("COMMIT", done())
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    let pending = session.run_in_transaction(|_| std::future::pending::<Result<()>>());
+    let timed_out = tokio::time::timeout(Duration::from_millis(50), pending).await;
+    let result = session
+        .clone()
+        .run_in_transaction(|_| async { Ok::<_, Error>(7) })
+        .await;
+
+    // Assert
+    assert!(timed_out.is_err());
+    assert_eq!(result?, 7);
+    assert_eq!(
+        server.statements(),
+        ["BEGIN TRANSACTION", "BEGIN TRANSACTION", "COMMIT"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_set_variables() -> Result<()> {
+    // Arrange
+    let done = || MockResponse::rows::<&str>([], vec![]);
+    let statements = [
+        "SET IDS = PARSE_JSON('[1,2]')",
+        "SET NAMES = PARSE_JSON('[]')",
+        r#"SET PAYLOAD = PARSE_JSON('{"kind":"it''s"}')"#,
+        "SET HASH = TO_BINARY('0FA0', 'HEX')",
+    ];
+    let server = statements
+        .iter()
+        .fold(MockServer::builder(), |server, sql| {
+            server.query(*sql, done())
+        })
+        .start()
+        .await?;
+    let session = session(server.client_config()).await?;
+
+    // Act
+    session.set_variable("ids", &vec![1, 2]).await?;
+    session.set_variable("names", &Vec::<String>::new()).await?;
+    session
+        .set_variable("payload", &serde_json::json!({"kind": "it's"}))
+        .await?;
+    session
+        .set_variable("hash", &Bind(vec![0x0f_u8, 0xa0]))
+        .await?;
+
+    // Assert
+    assert_eq!(server.statements(), statements);
+    assert_eq!(
+        session.variable_names(),
+        ["IDS", "NAMES", "PAYLOAD", "HASH"]
+    );
+    Ok(())
+}